@@ -0,0 +1,169 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::mesh::MeshData;
+use crate::xml::{self, Element};
+
+/// Loads an uncompressed AMF file, one `MeshData` per volume. Colors are
+/// resolved per triangle in AMF precedence order: triangle, volume,
+/// material, object; vertex colors are used when none of those are set.
+pub fn load(path: &Path) -> Result<Vec<MeshData>> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    parse(&bytes)
+}
+
+fn parse(bytes: &[u8]) -> Result<Vec<MeshData>> {
+    if bytes.starts_with(b"PK") {
+        bail!("Zip-compressed AMF is not supported, extract the .amf file from the archive first");
+    }
+
+    let root = xml::parse(std::str::from_utf8(bytes).context("AMF document isn't UTF-8")?).context("Invalid XML")?;
+    if root.name != "amf" {
+        bail!("Not an AMF document (root element <{}>)", root.name);
+    }
+
+    let scale = unit_scale(root.attribute("unit").unwrap_or("millimeter"));
+    let materials: HashMap<&str, [f32; 3]> = root
+        .children_named("material")
+        .filter_map(|material| Some((material.attribute("id")?, parse_color(material.child("color")?)?)))
+        .collect();
+
+    let mut parts = Vec::new();
+    for object in root.children_named("object") {
+        let object_id = object.attribute("id").unwrap_or("");
+        let object_color = object.child("color").and_then(parse_color);
+        let Some(mesh) = object.child("mesh") else {
+            continue;
+        };
+
+        let mut positions = Vec::new();
+        let mut vertex_colors = Vec::new();
+        if let Some(vertices) = mesh.child("vertices") {
+            for vertex in vertices.children_named("vertex") {
+                let coordinates = vertex.child("coordinates").context("AMF vertex without coordinates")?;
+                let axis = |name: &str| -> Result<f32> {
+                    let text = coordinates.child_text(name).with_context(|| format!("AMF vertex without <{}>", name))?;
+                    Ok(text.parse::<f32>().with_context(|| format!("Invalid coordinate {:?}", text))? * scale)
+                };
+                positions.push([axis("x")?, axis("y")?, axis("z")?]);
+                vertex_colors.push(vertex.child("color").and_then(parse_color));
+            }
+        }
+
+        for (volume_index, volume) in mesh.children_named("volume").enumerate() {
+            let volume_color = volume
+                .child("color")
+                .and_then(parse_color)
+                .or_else(|| volume.attribute("materialid").and_then(|id| materials.get(id).copied()))
+                .or(object_color);
+
+            let mut triangles = Vec::new();
+            let mut face_colors = Vec::new();
+            for triangle in volume.children_named("triangle") {
+                let corner = |name: &str| -> Result<u32> {
+                    let text = triangle.child_text(name).with_context(|| format!("AMF triangle without <{}>", name))?;
+                    let index: u32 = text.parse().with_context(|| format!("Invalid vertex index {:?}", text))?;
+                    if index as usize >= positions.len() {
+                        bail!("AMF vertex index {} out of range", index);
+                    }
+                    Ok(index)
+                };
+                triangles.push([corner("v1")?, corner("v2")?, corner("v3")?]);
+                face_colors.push(triangle.child("color").and_then(parse_color).or(volume_color));
+            }
+
+            let has_face_colors = face_colors.iter().any(Option::is_some);
+            let has_vertex_colors = vertex_colors.iter().any(Option::is_some);
+            let name = match volume.attribute("materialid") {
+                Some(material) => format!("object {} volume {} (material {})", object_id, volume_index, material),
+                None => format!("object {} volume {}", object_id, volume_index),
+            };
+            parts.push(MeshData {
+                name,
+                positions: positions.clone(),
+                triangles,
+                normals: None,
+                vertex_colors: (has_vertex_colors && !has_face_colors)
+                    .then(|| vertex_colors.iter().map(|color| color.unwrap_or([0.8, 0.8, 0.8])).collect()),
+                face_colors: has_face_colors
+                    .then(|| face_colors.iter().map(|color| color.unwrap_or([0.8, 0.8, 0.8])).collect()),
+                ..Default::default()
+            });
+        }
+    }
+
+    Ok(parts)
+}
+
+fn unit_scale(unit: &str) -> f32 {
+    match unit {
+        "inch" => 25.4,
+        "feet" => 304.8,
+        "meter" => 1000.0,
+        "micron" => 0.001,
+        _ => 1.0,
+    }
+}
+
+// Colors may also be given as formulas, which are not evaluated
+fn parse_color(color: &Element) -> Option<[f32; 3]> {
+    let channel = |name: &str| color.child_text(name)?.parse::<f32>().ok();
+    Some([channel("r")?, channel("g")?, channel("b")?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Square in inches with one volume per material; the second volume's
+    // triangle overrides the material color
+    fn square(second_index: u32) -> String {
+        let vertex = |x: u32, y: u32| format!("<vertex><coordinates><x>{}</x><y>{}</y><z>0</z></coordinates></vertex>", x, y);
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <amf unit="inch">
+                <material id="1"><color><r>1</r><g>0</g><b>0</b></color></material>
+                <material id="2"><color><r>0</r><g>1</g><b>0</b></color></material>
+                <object id="7">
+                    <mesh>
+                        <vertices>{}{}{}{}</vertices>
+                        <volume materialid="1"><triangle><v1>0</v1><v2>1</v2><v3>2</v3></triangle></volume>
+                        <volume materialid="2">
+                            <triangle><v1>0</v1><v2>2</v2><v3>{}</v3><color><r>0</r><g>0</g><b>1</b></color></triangle>
+                        </volume>
+                    </mesh>
+                </object>
+            </amf>"#,
+            vertex(0, 0),
+            vertex(1, 0),
+            vertex(1, 1),
+            vertex(0, 1),
+            second_index
+        )
+    }
+
+    #[test]
+    fn loads_volumes_with_colors() {
+        let parts = parse(square(3).as_bytes()).unwrap();
+        let [first, second] = &parts[..] else { panic!("expected two parts, got {}", parts.len()) };
+        assert_eq!(first.name, "object 7 volume 0 (material 1)");
+        assert_eq!(first.positions[2], [25.4, 25.4, 0.0]);
+        assert_eq!(first.triangles, [[0, 1, 2]]);
+        assert_eq!(first.face_colors, Some(vec![[1.0, 0.0, 0.0]]));
+        assert_eq!(second.triangles, [[0, 2, 3]]);
+        assert_eq!(second.face_colors, Some(vec![[0.0, 0.0, 1.0]]));
+    }
+
+    #[test]
+    fn rejects_out_of_range_vertices() {
+        let error = parse(square(4).as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("AMF vertex index 4 out of range"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_zip_archives() {
+        let error = parse(b"PK\x03\x04").unwrap_err();
+        assert!(error.to_string().contains("Zip-compressed"), "{}", error);
+    }
+}
@@ -0,0 +1,560 @@
+use glam::Vec3;
+use std::collections::{HashMap, HashSet};
+
+use crate::mesh::Mesh;
+
+/// Per-face false-color overlays for inspecting mesh data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Shaded,
+    Components,
+    Quality,
+    Materials,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 4] = [ColorMode::Shaded, ColorMode::Components, ColorMode::Quality, ColorMode::Materials];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorMode::Shaded => "Shaded",
+            ColorMode::Components => "Components",
+            ColorMode::Quality => "Triangle quality",
+            ColorMode::Materials => "Material ID",
+        }
+    }
+
+    /// One color per triangle, or `None` to draw the mesh's own colors.
+    pub fn face_colors(self, mesh: &Mesh, quality: &QualitySettings) -> Option<Vec<[f32; 3]>> {
+        match self {
+            ColorMode::Shaded => None,
+            ColorMode::Components => {
+                let (labels, _) = triangle_components(mesh);
+                Some(labels.into_iter().map(palette_color).collect())
+            }
+            ColorMode::Quality => Some(
+                triangle_quality(mesh, quality.metric)
+                    .into_iter()
+                    .map(|value| heat_color(quality.badness(value)))
+                    .collect(),
+            ),
+            ColorMode::Materials => Some(triangle_materials(mesh).into_iter().map(material_color).collect()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QualityMetric {
+    /// Longest edge relative to the inradius, 1 for an equilateral triangle.
+    AspectRatio,
+    /// Smallest interior angle in degrees.
+    MinAngle,
+    /// Area relative to the mean triangle area.
+    Area,
+}
+
+impl QualityMetric {
+    pub const ALL: [QualityMetric; 3] = [QualityMetric::AspectRatio, QualityMetric::MinAngle, QualityMetric::Area];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            QualityMetric::AspectRatio => "Aspect ratio",
+            QualityMetric::MinAngle => "Min angle",
+            QualityMetric::Area => "Relative area",
+        }
+    }
+
+    pub fn default_threshold(self) -> f32 {
+        match self {
+            QualityMetric::AspectRatio => 5.0,
+            QualityMetric::MinAngle => 20.0,
+            QualityMetric::Area => 0.1,
+        }
+    }
+
+    pub fn threshold_range(self) -> std::ops::RangeInclusive<f32> {
+        match self {
+            QualityMetric::AspectRatio => 1.5..=50.0,
+            QualityMetric::MinAngle => 1.0..=59.0,
+            QualityMetric::Area => 0.001..=0.99,
+        }
+    }
+}
+
+/// Metric and threshold for the triangle quality heatmap. Faces past the
+/// threshold are drawn red, the rest ramp from green (ideal) to yellow.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualitySettings {
+    pub metric: QualityMetric,
+    pub threshold: f32,
+}
+
+impl Default for QualitySettings {
+    fn default() -> Self {
+        Self {
+            metric: QualityMetric::AspectRatio,
+            threshold: QualityMetric::AspectRatio.default_threshold(),
+        }
+    }
+}
+
+impl QualitySettings {
+    /// 0 for an ideal triangle, 1 at the threshold and above 1 past it.
+    pub fn badness(&self, value: f32) -> f32 {
+        let badness = match self.metric {
+            QualityMetric::AspectRatio => (value - 1.0) / (self.threshold - 1.0),
+            QualityMetric::MinAngle => (60.0 - value) / (60.0 - self.threshold),
+            QualityMetric::Area => (1.0 - value) / (1.0 - self.threshold),
+        };
+        if badness.is_nan() {
+            f32::INFINITY
+        } else {
+            badness.max(0.0)
+        }
+    }
+
+    pub fn fails(&self, value: f32) -> bool {
+        self.badness(value) > 1.0
+    }
+}
+
+/// Evaluates `metric` for every triangle. Degenerate triangles produce
+/// infinite aspect ratios and zero angles/areas.
+pub fn triangle_quality(mesh: &Mesh, metric: QualityMetric) -> Vec<f32> {
+    let triangles: Vec<[Vec3; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|tri| [0, 1, 2].map(|i| Vec3::from(mesh.vertices[tri[i] as usize].position)))
+        .collect();
+
+    match metric {
+        QualityMetric::AspectRatio => triangles
+            .iter()
+            .map(|[a, b, c]| {
+                let (ab, bc, ca) = (a.distance(*b), b.distance(*c), c.distance(*a));
+                let area = 0.5 * (*b - *a).cross(*c - *a).length();
+                // Normalized so an equilateral triangle scores exactly 1
+                let ratio = ab.max(bc).max(ca) * (ab + bc + ca) / (4.0 * 3f32.sqrt() * area);
+                if ratio.is_finite() { ratio } else { f32::INFINITY }
+            })
+            .collect(),
+        QualityMetric::MinAngle => triangles
+            .iter()
+            .map(|[a, b, c]| {
+                let angle = |p: Vec3, q: Vec3, r: Vec3| (q - p).angle_between(r - p).to_degrees();
+                let min = angle(*a, *b, *c).min(angle(*b, *c, *a)).min(angle(*c, *a, *b));
+                if min.is_finite() { min } else { 0.0 }
+            })
+            .collect(),
+        QualityMetric::Area => {
+            let areas: Vec<f32> = triangles
+                .iter()
+                .map(|[a, b, c]| 0.5 * (*b - *a).cross(*c - *a).length())
+                .collect();
+            let mean = areas.iter().sum::<f32>() / areas.len().max(1) as f32;
+            areas.iter().map(|area| if mean > 0.0 { area / mean } else { 0.0 }).collect()
+        }
+    }
+}
+
+/// Green → yellow ramp up to the threshold, solid red past it.
+fn heat_color(badness: f32) -> [f32; 3] {
+    if badness > 1.0 {
+        [0.9, 0.1, 0.1]
+    } else {
+        [0.2 + 0.75 * badness, 0.8, 0.2]
+    }
+}
+
+/// Well-separated colors for consecutive indices (golden-ratio hue steps).
+pub fn palette_color(index: u32) -> [f32; 3] {
+    let hue = (index as f32 * 0.618_034).fract();
+    hsv_to_rgb(hue, 0.65, 0.95)
+}
+
+/// Legend color of a material index in the material ID mode; faces without
+/// a material are gray.
+pub fn material_color(material: Option<usize>) -> [f32; 3] {
+    material.map_or([0.35, 0.35, 0.35], |material| palette_color(material as u32))
+}
+
+/// Blue (0) to red (1) ramp for normalized scalar values.
+pub fn ramp_color(t: f32) -> [f32; 3] {
+    hsv_to_rgb((1.0 - t.clamp(0.0, 1.0)) * 0.66, 0.85, 0.95)
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [f32; 3] {
+    let h = hue * 6.0;
+    let c = value * saturation;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = value - c;
+    [r + m, g + m, b + m]
+}
+
+/// Topology of one edge-connected shell.
+#[derive(Debug, Clone, Copy)]
+pub struct ComponentTopology {
+    pub vertices: usize,
+    pub edges: usize,
+    pub faces: usize,
+    pub boundary_edges: usize,
+    /// Edges shared by more than two faces.
+    pub non_manifold_edges: usize,
+    /// Edges whose two faces run along them in the same direction, so the
+    /// faces aren't wound consistently.
+    pub flipped_edges: usize,
+    /// Vertices whose faces don't form a single fan, like the tip shared by
+    /// two cones.
+    pub pinched_vertices: usize,
+    pub euler_characteristic: i64,
+}
+
+impl ComponentTopology {
+    pub fn is_closed(&self) -> bool {
+        self.boundary_edges == 0
+    }
+
+    pub fn is_manifold(&self) -> bool {
+        self.non_manifold_edges == 0
+    }
+
+    pub fn is_consistently_oriented(&self) -> bool {
+        self.flipped_edges == 0
+    }
+
+    /// Genus of a closed, consistently oriented surface whose vertices each
+    /// have a single fan of faces; `None` otherwise, where the Euler
+    /// characteristic doesn't determine one.
+    pub fn genus(&self) -> Option<i64> {
+        if self.is_closed() && self.is_manifold() && self.is_consistently_oriented() && self.pinched_vertices == 0 {
+            Some((2 - self.euler_characteristic) / 2)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TopologyReport {
+    pub vertices: usize,
+    pub edges: usize,
+    pub faces: usize,
+    pub boundary_edges: usize,
+    pub non_manifold_edges: usize,
+    pub euler_characteristic: i64,
+    pub components: Vec<ComponentTopology>,
+}
+
+/// Disjoint-set forest with path halving and union by size.
+pub struct UnionFind {
+    parent: Vec<u32>,
+    size: Vec<u32>,
+}
+
+impl UnionFind {
+    pub fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len as u32).collect(),
+            size: vec![1; len],
+        }
+    }
+
+    pub fn find(&mut self, mut x: u32) -> u32 {
+        while self.parent[x as usize] != x {
+            let grandparent = self.parent[self.parent[x as usize] as usize];
+            self.parent[x as usize] = grandparent;
+            x = grandparent;
+        }
+        x
+    }
+
+    pub fn union(&mut self, a: u32, b: u32) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a as usize] < self.size[b as usize] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b as usize] = a;
+        self.size[a as usize] += self.size[b as usize];
+    }
+}
+
+/// Maps every vertex to a representative sharing its exact position, so that
+/// seams split for normals or colors don't break connectivity.
+pub fn weld_positions(mesh: &Mesh) -> Vec<u32> {
+    let mut first_at: HashMap<[u32; 3], u32> = HashMap::new();
+    mesh.vertices
+        .iter()
+        .enumerate()
+        .map(|(i, vertex)| {
+            let key = vertex.position.map(f32::to_bits);
+            *first_at.entry(key).or_insert(i as u32)
+        })
+        .collect()
+}
+
+/// Welded vertex indices of every complete triangle.
+pub fn welded_triangles(mesh: &Mesh) -> Vec<[u32; 3]> {
+    let weld = weld_positions(mesh);
+    mesh.indices
+        .chunks_exact(3)
+        .map(|tri| [weld[tri[0] as usize], weld[tri[1] as usize], weld[tri[2] as usize]])
+        .collect()
+}
+
+/// Labels each triangle with the index of its edge-connected component;
+/// triangles touching at a single vertex stay apart. Returns the labels and
+/// the number of components.
+pub fn triangle_components(mesh: &Mesh) -> (Vec<u32>, usize) {
+    let triangles = welded_triangles(mesh);
+    let mut sets = UnionFind::new(triangles.len());
+    let mut first_face: HashMap<(u32, u32), u32> = HashMap::new();
+    for (face, tri) in triangles.iter().enumerate() {
+        for i in 0..3 {
+            let (a, b) = (tri[i], tri[(i + 1) % 3]);
+            let other = *first_face.entry((a.min(b), a.max(b))).or_insert(face as u32);
+            sets.union(other, face as u32);
+        }
+    }
+
+    let mut component_of_root: HashMap<u32, u32> = HashMap::new();
+    let labels = (0..triangles.len() as u32)
+        .map(|face| {
+            let root = sets.find(face);
+            let next = component_of_root.len() as u32;
+            *component_of_root.entry(root).or_insert(next)
+        })
+        .collect();
+    (labels, component_of_root.len())
+}
+
+/// Material index of each triangle, from the draw range it's in.
+pub fn triangle_materials(mesh: &Mesh) -> Vec<Option<usize>> {
+    let mut materials = vec![None; mesh.triangle_count()];
+    for range in &mesh.draw_ranges {
+        let faces = (range.start / 3) as usize..((range.start + range.count) / 3) as usize;
+        for material in materials.get_mut(faces).into_iter().flatten() {
+            *material = range.material;
+        }
+    }
+    materials
+}
+
+pub fn topology_report(mesh: &Mesh) -> TopologyReport {
+    let triangles = welded_triangles(mesh);
+    let (labels, component_count) = triangle_components(mesh);
+
+    // Faces on each edge and the component they're in, which is the same for
+    // all of them since faces sharing an edge are connected
+    let mut edge_faces: HashMap<(u32, u32), (u32, u32)> = HashMap::new();
+    // Times each edge is walked in each direction
+    let mut directed_edges: HashMap<(u32, u32), u32> = HashMap::new();
+    // Edge opposite to the vertex in each of its faces, per component; a
+    // vertex touching two shells is counted in both
+    let mut vertex_links: HashMap<(u32, u32), Vec<(u32, u32)>> = HashMap::new();
+    let mut components = vec![
+        ComponentTopology {
+            vertices: 0,
+            edges: 0,
+            faces: 0,
+            boundary_edges: 0,
+            non_manifold_edges: 0,
+            flipped_edges: 0,
+            pinched_vertices: 0,
+            euler_characteristic: 0,
+        };
+        component_count
+    ];
+
+    for (tri, &label) in triangles.iter().zip(&labels) {
+        components[label as usize].faces += 1;
+        for i in 0..3 {
+            let (a, b, c) = (tri[i], tri[(i + 1) % 3], tri[(i + 2) % 3]);
+            edge_faces.entry((a.min(b), a.max(b))).or_insert((0, label)).0 += 1;
+            *directed_edges.entry((a, b)).or_insert(0) += 1;
+            vertex_links.entry((a, label)).or_default().push((b, c));
+        }
+    }
+
+    let mut report = TopologyReport {
+        faces: triangles.len(),
+        vertices: vertex_links.keys().map(|&(vertex, _)| vertex).collect::<HashSet<_>>().len(),
+        edges: edge_faces.len(),
+        ..Default::default()
+    };
+    for (&(_, label), link) in &vertex_links {
+        let component = &mut components[label as usize];
+        component.vertices += 1;
+        if fan_count(link) > 1 {
+            component.pinched_vertices += 1;
+        }
+    }
+    for (&(a, b), &(faces, label)) in &edge_faces {
+        let component = &mut components[label as usize];
+        component.edges += 1;
+        match faces {
+            1 => {
+                component.boundary_edges += 1;
+                report.boundary_edges += 1;
+            }
+            2 => {
+                if directed_edges.get(&(a, b)) != Some(&1) {
+                    component.flipped_edges += 1;
+                }
+            }
+            _ => {
+                component.non_manifold_edges += 1;
+                report.non_manifold_edges += 1;
+            }
+        }
+    }
+    for component in &mut components {
+        component.euler_characteristic =
+            component.vertices as i64 - component.edges as i64 + component.faces as i64;
+    }
+
+    report.euler_characteristic = report.vertices as i64 - report.edges as i64 + report.faces as i64;
+    report.components = components;
+    report
+}
+
+// Number of fans in a vertex's link: groups of its faces joined through the
+// edges they share at the vertex
+fn fan_count(link: &[(u32, u32)]) -> usize {
+    let mut index: HashMap<u32, u32> = HashMap::new();
+    for &(b, c) in link {
+        for vertex in [b, c] {
+            let next = index.len() as u32;
+            index.entry(vertex).or_insert(next);
+        }
+    }
+    let mut sets = UnionFind::new(index.len());
+    for (b, c) in link {
+        sets.union(index[b], index[c]);
+    }
+    let mut roots: Vec<u32> = (0..index.len() as u32).map(|i| sets.find(i)).collect();
+    roots.sort_unstable();
+    roots.dedup();
+    roots.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::MeshData;
+
+    fn mesh(positions: Vec<[f32; 3]>, triangles: Vec<[u32; 3]>) -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.append(MeshData {
+            positions,
+            triangles,
+            ..Default::default()
+        });
+        mesh
+    }
+
+    // Unit cube with outward-facing triangles, two per side
+    fn cube_triangles() -> Vec<[u32; 3]> {
+        vec![
+            [0, 2, 1], [0, 3, 2], [4, 5, 6], [4, 6, 7], [0, 1, 5], [0, 5, 4],
+            [2, 3, 7], [2, 7, 6], [1, 2, 6], [1, 6, 5], [0, 4, 7], [0, 7, 3],
+        ]
+    }
+
+    // Corners counterclockwise around the bottom, then around the top
+    fn cube_positions() -> Vec<[f32; 3]> {
+        (0..8).map(|i| [(i & 1 ^ (i >> 1) & 1) as f32, (i >> 1 & 1) as f32, (i >> 2) as f32]).collect()
+    }
+
+    // Torus as a `rings` by `sides` grid of quads wrapping around both ways
+    fn torus(rings: u32, sides: u32) -> Mesh {
+        let mut positions = Vec::new();
+        for ring in 0..rings {
+            let u = ring as f32 / rings as f32 * std::f32::consts::TAU;
+            for side in 0..sides {
+                let v = side as f32 / sides as f32 * std::f32::consts::TAU;
+                let radius = 2.0 + v.cos();
+                positions.push([radius * u.cos(), v.sin(), radius * u.sin()]);
+            }
+        }
+        let index = |ring: u32, side: u32| (ring % rings) * sides + side % sides;
+        let mut triangles = Vec::new();
+        for ring in 0..rings {
+            for side in 0..sides {
+                let (a, b, c, d) = (index(ring, side), index(ring + 1, side), index(ring + 1, side + 1), index(ring, side + 1));
+                triangles.extend([[a, b, c], [a, c, d]]);
+            }
+        }
+        mesh(positions, triangles)
+    }
+
+    #[test]
+    fn cube_has_genus_zero() {
+        let report = topology_report(&mesh(cube_positions(), cube_triangles()));
+        assert_eq!((report.vertices, report.edges, report.faces), (8, 18, 12));
+        assert_eq!(report.components.len(), 1);
+        assert_eq!(report.components[0].euler_characteristic, 2);
+        assert_eq!(report.components[0].genus(), Some(0));
+    }
+
+    #[test]
+    fn torus_has_genus_one() {
+        let report = topology_report(&torus(8, 6));
+        assert_eq!(report.components.len(), 1);
+        assert_eq!(report.components[0].euler_characteristic, 0);
+        assert_eq!(report.components[0].genus(), Some(1));
+    }
+
+    #[test]
+    fn open_shell_has_no_genus() {
+        let mut triangles = cube_triangles();
+        triangles.truncate(10);
+        let report = topology_report(&mesh(cube_positions(), triangles));
+        assert_eq!(report.components[0].boundary_edges, 4);
+        assert_eq!(report.components[0].genus(), None);
+    }
+
+    #[test]
+    fn flipped_face_has_no_genus() {
+        let mut triangles = cube_triangles();
+        triangles[0].swap(1, 2);
+        let report = topology_report(&mesh(cube_positions(), triangles));
+        assert!(report.components[0].is_closed() && report.components[0].is_manifold());
+        assert_eq!(report.components[0].flipped_edges, 3);
+        assert_eq!(report.components[0].genus(), None);
+    }
+
+    #[test]
+    fn shells_touching_at_a_corner_stay_apart() {
+        // The second cube's first corner is the first cube's far corner
+        let mut positions = cube_positions();
+        positions.extend(cube_positions().into_iter().map(|position| position.map(|value| value + 1.0)));
+        let mut triangles = cube_triangles();
+        triangles.extend(cube_triangles().into_iter().map(|triangle| triangle.map(|index| index + 8)));
+        let mesh = mesh(positions, triangles);
+
+        let (labels, count) = triangle_components(&mesh);
+        assert_eq!(count, 2);
+        assert!(labels[..12].iter().all(|&label| label == 0) && labels[12..].iter().all(|&label| label == 1));
+        let report = topology_report(&mesh);
+        assert_eq!(report.vertices, 15);
+        assert!(report.components.iter().all(|component| component.genus() == Some(0)));
+    }
+
+    #[test]
+    fn counts_fans_around_a_vertex() {
+        // Links of a vertex inside a closed surface and of the tip two cones share
+        assert_eq!(fan_count(&[(1, 2), (2, 3), (3, 1)]), 1);
+        assert_eq!(fan_count(&[(1, 2), (2, 3), (3, 1), (4, 5), (5, 6), (6, 4)]), 2);
+    }
+}
@@ -0,0 +1,364 @@
+use anyhow::{Context, Result};
+use exr::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::capture::read_texture;
+use crate::mesh::Vertex;
+use crate::quantize::QuantizedVertex;
+use crate::shaders::create_shader_module;
+
+pub const NORMAL_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+pub const OBJECT_ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AovFormat {
+    Png,
+    Exr,
+}
+
+/// Which auxiliary passes to write next to each exported beauty image.
+#[derive(Debug, Clone, Copy)]
+pub struct AovSettings {
+    pub normal: bool,
+    pub depth: bool,
+    pub object_id: bool,
+    pub format: AovFormat,
+}
+
+impl Default for AovSettings {
+    fn default() -> Self {
+        Self {
+            normal: false,
+            depth: false,
+            object_id: false,
+            format: AovFormat::Png,
+        }
+    }
+}
+
+impl AovSettings {
+    pub fn any(&self) -> bool {
+        self.normal || self.depth || self.object_id
+    }
+}
+
+/// Pipeline writing world normal + linear view depth and object ID in a single MRT pass.
+pub struct AovPipeline {
+    pipeline: wgpu::RenderPipeline,
+    // Reads `QuantizedVertex` buffers
+    quantized_pipeline: wgpu::RenderPipeline,
+}
+
+impl AovPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        object_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = create_shader_module(device, "AOV Shader", concat!(include_str!("shaders/aov.wgsl"), include_str!("shaders/quantized.wgsl")));
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("AOV Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, object_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label, entry_point, buffers: &[wgpu::VertexBufferLayout]| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point,
+                    buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[
+                        Some(wgpu::ColorTargetState {
+                            format: NORMAL_DEPTH_FORMAT,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        }),
+                        Some(wgpu::ColorTargetState {
+                            format: OBJECT_ID_FORMAT,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        }),
+                    ],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("AOV Pipeline", "vs_main", &[Vertex::desc(), Vertex::morph_desc()]);
+        let quantized_pipeline = create_pipeline("Quantized AOV Pipeline", "vs_quantized", &[QuantizedVertex::desc()]);
+
+        Self { pipeline, quantized_pipeline }
+    }
+
+    /// Starts the AOV pass on `target` with the pipeline set; the caller binds
+    /// the camera at group 0 and each object at group 1 and issues the draws.
+    /// `quantized` picks the pipeline for `QuantizedVertex` buffers.
+    pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder, target: &'a AovTarget, quantized: bool) -> wgpu::RenderPass<'a> {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("AOV Pass"),
+            color_attachments: &[
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &target.normal_depth_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &target.object_id_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &target.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(if quantized { &self.quantized_pipeline } else { &self.pipeline });
+        render_pass
+    }
+}
+
+pub struct AovTarget {
+    width: u32,
+    height: u32,
+    normal_depth_texture: wgpu::Texture,
+    normal_depth_view: wgpu::TextureView,
+    object_id_texture: wgpu::Texture,
+    object_id_view: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+}
+
+impl AovTarget {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let create = |label, format, usage| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let readable = wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC;
+        let normal_depth_texture = create("AOV Normal Depth Texture", NORMAL_DEPTH_FORMAT, readable);
+        let object_id_texture = create("AOV Object ID Texture", OBJECT_ID_FORMAT, readable);
+        let depth_texture = create(
+            "AOV Depth Texture",
+            wgpu::TextureFormat::Depth32Float,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        );
+
+        Self {
+            width,
+            height,
+            normal_depth_view: normal_depth_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            object_id_view: object_id_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            depth_view: depth_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            normal_depth_texture,
+            object_id_texture,
+        }
+    }
+
+    pub fn read(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<AovImage> {
+        let normal_depth = read_texture(device, queue, &self.normal_depth_texture, self.width, self.height, 16)?;
+        let object_id = read_texture(device, queue, &self.object_id_texture, self.width, self.height, 4)?;
+        Ok(AovImage {
+            width: self.width,
+            height: self.height,
+            normal_depth: bytemuck::pod_collect_to_vec(&normal_depth),
+            object_id: bytemuck::pod_collect_to_vec(&object_id),
+        })
+    }
+}
+
+/// CPU copy of the AOV passes; background pixels have zero normal, depth and ID.
+pub struct AovImage {
+    pub width: u32,
+    pub height: u32,
+    pub normal_depth: Vec<[f32; 4]>,
+    pub object_id: Vec<u32>,
+}
+
+impl AovImage {
+    /// Writes the enabled passes as `<base>_normal`, `<base>_depth` and `<base>_id`.
+    pub fn save(&self, base: &Path, settings: &AovSettings) -> Result<Vec<PathBuf>> {
+        let extension = match settings.format {
+            AovFormat::Png => "png",
+            AovFormat::Exr => "exr",
+        };
+        let path_for = |suffix: &str| {
+            let mut name = base.file_name().unwrap_or_default().to_os_string();
+            name.push(format!("_{}.{}", suffix, extension));
+            base.with_file_name(name)
+        };
+
+        let mut written = Vec::new();
+        if settings.normal {
+            let path = path_for("normal");
+            match settings.format {
+                AovFormat::Png => self.save_normal_png(&path)?,
+                AovFormat::Exr => self.save_normal_exr(&path)?,
+            }
+            written.push(path);
+        }
+        if settings.depth {
+            let path = path_for("depth");
+            match settings.format {
+                AovFormat::Png => self.save_depth_png(&path)?,
+                AovFormat::Exr => self.save_depth_exr(&path)?,
+            }
+            written.push(path);
+        }
+        if settings.object_id {
+            let path = path_for("id");
+            match settings.format {
+                AovFormat::Png => self.save_id_png(&path)?,
+                AovFormat::Exr => self.save_id_exr(&path)?,
+            }
+            written.push(path);
+        }
+        Ok(written)
+    }
+
+    fn save_normal_png(&self, path: &Path) -> Result<()> {
+        let data: Vec<u8> = self
+            .normal_depth
+            .iter()
+            .flat_map(|[x, y, z, _]| {
+                if [x, y, z].iter().all(|c| **c == 0.0) {
+                    [0, 0, 0]
+                } else {
+                    [x, y, z].map(|c| ((c * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8)
+                }
+            })
+            .collect();
+        write_png(path, self.width, self.height, png::ColorType::Rgb, png::BitDepth::Eight, &data, &[])
+    }
+
+    /// Linear depth is normalized to the hit range, which is recorded in tEXt chunks.
+    fn save_depth_png(&self, path: &Path) -> Result<()> {
+        let hits = self.normal_depth.iter().map(|p| p[3]).filter(|d| *d > 0.0);
+        let (min, max) = hits.fold((f32::INFINITY, 0.0_f32), |(lo, hi), d| (lo.min(d), hi.max(d)));
+        let range = (max - min).max(f32::EPSILON);
+
+        let data: Vec<u8> = self
+            .normal_depth
+            .iter()
+            .flat_map(|p| {
+                let value = if p[3] > 0.0 {
+                    1 + ((p[3] - min) / range * 65534.0).round() as u16
+                } else {
+                    0
+                };
+                value.to_be_bytes()
+            })
+            .collect();
+        let text = [("depth_min", min.to_string()), ("depth_max", max.to_string())];
+        write_png(path, self.width, self.height, png::ColorType::Grayscale, png::BitDepth::Sixteen, &data, &text)
+    }
+
+    fn save_id_png(&self, path: &Path) -> Result<()> {
+        let data: Vec<u8> = self
+            .object_id
+            .iter()
+            .flat_map(|id| ((*id).min(u16::MAX as u32) as u16).to_be_bytes())
+            .collect();
+        write_png(path, self.width, self.height, png::ColorType::Grayscale, png::BitDepth::Sixteen, &data, &[])
+    }
+
+    fn save_normal_exr(&self, path: &Path) -> Result<()> {
+        let width = self.width as usize;
+        write_rgb_file(path, width, self.height as usize, |x, y| {
+            let [nx, ny, nz, _] = self.normal_depth[y * width + x];
+            (nx, ny, nz)
+        })
+        .with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    fn save_depth_exr(&self, path: &Path) -> Result<()> {
+        let depth: Vec<f32> = self.normal_depth.iter().map(|p| p[3]).collect();
+        self.write_exr_channel(path, AnyChannel::new("Z", FlatSamples::F32(depth)))
+    }
+
+    fn save_id_exr(&self, path: &Path) -> Result<()> {
+        self.write_exr_channel(path, AnyChannel::new("id", FlatSamples::U32(self.object_id.clone())))
+    }
+
+    fn write_exr_channel(&self, path: &Path, channel: AnyChannel<FlatSamples>) -> Result<()> {
+        let layer = Layer::new(
+            (self.width as usize, self.height as usize),
+            LayerAttributes::default(),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(vec![channel].into()),
+        );
+        Image::from_layer(layer)
+            .write()
+            .to_file(path)
+            .with_context(|| format!("Failed to write {:?}", path))
+    }
+}
+
+fn write_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    color: png::ColorType,
+    depth: png::BitDepth,
+    data: &[u8],
+    text: &[(&str, String)],
+) -> Result<()> {
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(color);
+    encoder.set_depth(depth);
+    for (keyword, value) in text {
+        encoder.add_text_chunk(keyword.to_string(), value.clone())?;
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    Ok(())
+}
@@ -1,30 +1,74 @@
 use anyhow::Result;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use winit::{
     event::{Event, WindowEvent},
-    event_loop::EventLoop,
-    window::{Window, WindowBuilder},
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder, WindowLevel},
 };
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use crate::renderer::Renderer;
+use crate::renderer::{Renderer, UiAction};
+use crate::camera::ViewPreset;
+use crate::loader::{BackgroundLoad, ModelSummary};
 use crate::menu::Menu;
+use crate::presentation::PresentationPreset;
+use crate::replay::{KeyAction, Player, Recorder, Replay};
+use crate::session::Session;
+use crate::settings::Settings;
+use crate::view_state::ViewState;
+use crate::watcher::FileWatcher;
 
 pub struct App {
     renderer: Option<Renderer>,
+    // Model being parsed on a background thread, installed once it's done
+    pending_load: Option<BackgroundLoad>,
+    // Polls the loaded model's file, to reload it when it's rewritten
+    model_watcher: Option<FileWatcher>,
+    // Summary of the last model loaded in the background, which a reload is compared with
+    model_summary: Option<(PathBuf, ModelSummary)>,
     menu: Menu,
+    session: Session,
+    modifiers: winit::keyboard::ModifiersState,
+    // Window size to restore when leaving compact mode
+    size_before_compact: Option<winit::dpi::PhysicalSize<u32>>,
+    // Started with --safe-mode: the saved session and settings are neither restored nor overwritten
+    safe_mode: bool,
+    // Applied once the renderer exists, over the restored session
+    preset: Option<PresentationPreset>,
+    // Replay file to record to, with the recording once the renderer exists
+    record_path: Option<PathBuf>,
+    recorder: Option<Recorder>,
+    // Replay being played back, until its last frame
+    player: Option<Player>,
     last_stats_display: Instant,
+    last_scene_redraw: Instant,
+    last_redraw: Instant,
     stats_display_interval: Duration,
     show_detailed_stats: bool,
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
+    pub fn new(safe_mode: bool, preset: Option<PresentationPreset>, record: Option<PathBuf>, replay: Option<PathBuf>) -> Result<Self> {
+        let player = replay.map(|path| Replay::load(&path)).transpose()?.map(Player::new);
         Ok(Self {
             renderer: None,
+            pending_load: None,
+            model_watcher: None,
+            model_summary: None,
             menu: Menu::new()?,
+            session: if safe_mode { Session::default() } else { Session::load() },
+            modifiers: winit::keyboard::ModifiersState::empty(),
+            size_before_compact: None,
+            safe_mode,
+            preset,
+            record_path: record,
+            recorder: None,
+            player,
             last_stats_display: Instant::now(),
+            last_scene_redraw: Instant::now(),
+            last_redraw: Instant::now(),
             stats_display_interval: Duration::from_secs(2), // Show stats every 2 seconds
             show_detailed_stats: false,
         })
@@ -32,15 +76,45 @@ impl App {
 
     pub fn run(mut self) -> Result<()> {
         let event_loop = EventLoop::new()?;
-        let window = Rc::new(WindowBuilder::new()
+        let mut builder = WindowBuilder::new()
             .with_title("DotObjViewer")
             .with_inner_size(winit::dpi::LogicalSize::new(1024.0, 768.0))
             .with_resizable(true)
-            .build(&event_loop)?);
+            // Lets the window turn see-through later; opaque until then
+            .with_transparent(true);
+        if self.session.reopen_on_launch {
+            if let Some([width, height]) = self.session.window_size {
+                builder = builder.with_inner_size(winit::dpi::PhysicalSize::new(width, height));
+            }
+        }
+        let window = Arc::new(builder.build(&event_loop)?);
 
        
         info!("Initializing renderer...");
-        self.renderer = Some(pollster::block_on(Renderer::new(&window))?);
+        let mut renderer = pollster::block_on(Renderer::new(window.clone(), self.safe_mode))?;
+        if !self.safe_mode {
+            let settings = Settings::load();
+            renderer.apply_settings(&settings);
+            self.show_detailed_stats = settings.show_detailed_stats;
+        }
+        renderer.reopen_last_session = self.session.reopen_on_launch;
+        renderer.set_sections(self.session.sections.clone());
+        if self.session.reopen_on_launch {
+            self.restore_session(&mut renderer);
+        }
+        if let Some(preset) = self.preset {
+            renderer.apply_presentation(preset);
+        }
+        if let Some(path) = self.record_path.take() {
+            let size = window.inner_size();
+            info!("Recording a replay to {:?}", path);
+            self.recorder = Some(Recorder::new(path, &renderer, size.width, size.height));
+        }
+        if let Some(player) = &self.player {
+            let (width, height) = player.size();
+            let _ = window.request_inner_size(winit::dpi::PhysicalSize::new(width, height));
+        }
+        self.renderer = Some(renderer);
 
         let window_clone = window.clone();
         let mut app = self;
@@ -57,7 +131,7 @@ impl App {
         &mut self,
         event: Event<()>,
         elwt: &winit::event_loop::EventLoopWindowTarget<()>,
-        window: &Window,
+        window: &Arc<Window>,
     ) -> Result<()> {
         match event {
             Event::WindowEvent {
@@ -66,14 +140,20 @@ impl App {
             } if window_id == window.id() => {
                 if let Some(renderer) = &mut self.renderer {
                     // Pass event to egui
-                    let _ = renderer.egui_winit_state.on_window_event(window, event);
+                    if let Some(egui_winit_state) = &mut renderer.egui_winit_state {
+                        let _ = egui_winit_state.on_window_event(window, event);
+                    }
                     renderer.handle_input(event);
                 }
+                // Keep the UI responsive while scene redraws are limited
+                if !matches!(event, WindowEvent::RedrawRequested) {
+                    window.request_redraw();
+                }
 
                 match event {
                     WindowEvent::CloseRequested => {
                         info!("Window close requested");
-                        elwt.exit();
+                        self.exit(elwt, window);
                     }
                     WindowEvent::Resized(physical_size) => {
                         if let Some(renderer) = &mut self.renderer {
@@ -81,9 +161,24 @@ impl App {
                         }
                     }
                     WindowEvent::RedrawRequested => {
-                        if let Some(renderer) = &mut self.renderer {
-                            match renderer.render(window) {
+                        self.finish_background_load();
+                        self.poll_model_watcher();
+                        let mut ui_actions = Vec::new();
+                        let redraw_scene = self.next_scene_redraw().is_none_or(|due| Instant::now() >= due);
+                        let now = Instant::now();
+                        let delta_time = now.duration_since(self.last_redraw).as_secs_f32();
+                        self.last_redraw = now;
+                        self.advance_camera(delta_time);
+                        // Nothing to draw to while minimized or suspended
+                        if let Some(renderer) = self.renderer.as_mut().filter(|renderer| renderer.can_draw()) {
+                            match renderer.render(window, redraw_scene) {
                                 Ok(_) => {
+                                    if redraw_scene {
+                                        self.last_scene_redraw = Instant::now();
+                                    }
+                                    ui_actions = renderer.take_ui_actions();
+
+
                                     // Display performance stats periodically
                                     let now = Instant::now();
                                     if now.duration_since(self.last_stats_display) >= self.stats_display_interval {
@@ -104,50 +199,76 @@ impl App {
                                         self.last_stats_display = now;
                                     }
                                 }
-                                Err(wgpu::SurfaceError::Lost) => {
+                                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
                                     renderer.resize(window.inner_size());
                                 }
                                 Err(wgpu::SurfaceError::OutOfMemory) => {
-                                    elwt.exit();
+                                    self.exit(elwt, window);
+                                    return Ok(());
                                 }
                                 Err(e) => {
                                     error!("Render error: {:?}", e);
                                 }
                             }
                         }
-                        window.request_redraw();
-                    }
-                    WindowEvent::KeyboardInput { event, .. } => {
-                        if event.state == winit::event::ElementState::Pressed {
-                            match event.logical_key.as_ref() {
-                                winit::keyboard::Key::Character("o") | winit::keyboard::Key::Character("O") => {
-                                    // Check for Ctrl modifier - we'll need to track this separately
-                                    if let Ok(Some(path)) = self.menu.open_file() {
-                                        if let Some(renderer) = &mut self.renderer {
-                                            if let Err(e) = renderer.load_mesh(&path) {
-                                                error!("Failed to load mesh: {}", e);
-                                            } else {
-                                                info!("Successfully loaded OBJ file: {:?}", path);
-                                            }
-                                        }
-                                    }
-                                }
-                                winit::keyboard::Key::Character("w") | winit::keyboard::Key::Character("W") => {
-                                    if let Some(renderer) = &mut self.renderer {
-                                        renderer.toggle_wireframe();
-                                    }
-                                }
-                                winit::keyboard::Key::Character("p") | winit::keyboard::Key::Character("P") => {
-                                    self.show_detailed_stats = !self.show_detailed_stats;
-                                    info!("Detailed performance stats: {}", self.show_detailed_stats);
-                                }
-                                winit::keyboard::Key::Character("q") | winit::keyboard::Key::Character("Q") => {
-                                    info!("Window close requested");
-                                    elwt.exit();
-                                }
-                                _ => {}
+                        for action in ui_actions {
+                            self.handle_ui_action(action, elwt, window);
+                        }
+                        // Keeps polling a background load even while scene redraws are limited
+                        if self.can_draw() && (self.next_scene_redraw().is_none() || self.pending_load.is_some()) {
+                            window.request_redraw();
+                        }
+                    }
+                    WindowEvent::ModifiersChanged(modifiers) => {
+                        self.modifiers = modifiers.state();
+                    }
+                    WindowEvent::KeyboardInput { event, .. }
+                        if event.state == winit::event::ElementState::Pressed =>
+                    {
+                        // Typing into the search box or another text field
+                        if self.renderer.as_ref().is_some_and(|renderer| renderer.wants_keyboard_input()) {
+                            return Ok(());
+                        }
+                        if self.modifiers.control_key() && matches!(event.logical_key.as_ref(), winit::keyboard::Key::Character("f" | "F")) {
+                            if let Some(renderer) = &mut self.renderer {
+                                renderer.open_search();
+                            }
+                            return Ok(());
+                        }
+                        if let winit::keyboard::PhysicalKey::Code(code) = event.physical_key {
+                            if let Some(preset) = ViewPreset::from_numpad(code, self.modifiers.control_key()) {
+                                self.handle_ui_action(UiAction::SetViewPreset(preset), elwt, window);
+                                return Ok(());
+                            }
+                        }
+                        if let winit::keyboard::Key::Character(key) = event.logical_key.as_ref() {
+                            if let Some(action) = KeyAction::from_key(key) {
+                                self.apply_key_action(action);
+                                return Ok(());
                             }
                         }
+                        match event.logical_key.as_ref() {
+                            winit::keyboard::Key::Character("o") | winit::keyboard::Key::Character("O") => {
+                                self.handle_ui_action(UiAction::OpenModel, elwt, window);
+                            }
+                            winit::keyboard::Key::Character("c") | winit::keyboard::Key::Character("C") => {
+                                self.handle_ui_action(UiAction::ExportView, elwt, window);
+                            }
+                            winit::keyboard::Key::Character("i") | winit::keyboard::Key::Character("I") => {
+                                self.handle_ui_action(UiAction::ImportView, elwt, window);
+                            }
+                            winit::keyboard::Key::Character("s") | winit::keyboard::Key::Character("S") => {
+                                self.handle_ui_action(UiAction::SaveScreenshot, elwt, window);
+                            }
+                            winit::keyboard::Key::Character("p") | winit::keyboard::Key::Character("P") => {
+                                self.show_detailed_stats = !self.show_detailed_stats;
+                                info!("Detailed performance stats: {}", self.show_detailed_stats);
+                            }
+                            winit::keyboard::Key::Character("q") | winit::keyboard::Key::Character("Q") => {
+                                self.handle_ui_action(UiAction::Quit, elwt, window);
+                            }
+                            _ => {}
+                        }
                     }
                     _ => {}
                 }
@@ -158,12 +279,428 @@ impl App {
             } => {
                 window.request_redraw();
             }
-            Event::AboutToWait => {
-                window.request_redraw();
+            Event::Suspended => {
+                if let Some(renderer) = &mut self.renderer {
+                    renderer.suspend();
+                }
             }
+            Event::Resumed => {
+                if let Some(renderer) = &mut self.renderer {
+                    renderer.resume(window.clone())?;
+                }
+            }
+            // Idle until the window can be drawn again
+            Event::AboutToWait if !self.can_draw() => elwt.set_control_flow(ControlFlow::Wait),
+            Event::AboutToWait => match self.next_scene_redraw() {
+                Some(due) if Instant::now() < due => elwt.set_control_flow(ControlFlow::WaitUntil(due)),
+                _ => {
+                    elwt.set_control_flow(ControlFlow::Wait);
+                    window.request_redraw();
+                }
+            },
             _ => {}
         }
 
         Ok(())
     }
-} 
\ No newline at end of file
+
+    // Moves the camera for the coming frame, from the replay while one plays
+    // and by the time elapsed otherwise, and records where it ends up
+    fn advance_camera(&mut self, delta_time: f32) {
+        let Some(renderer) = &mut self.renderer else {
+            return;
+        };
+        match self.player.as_mut().map(|player| player.step(renderer)) {
+            Some(Ok(true)) => {}
+            Some(result) => {
+                match result {
+                    Err(e) => error!("Replay stopped: {:#}", e),
+                    Ok(_) => info!("Replay finished"),
+                }
+                self.player = None;
+            }
+            // Long stalls (dialogs, minimized window) shouldn't jump the rotation
+            None => renderer.update_camera(delta_time.min(0.1)),
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_frame(renderer, delta_time);
+        }
+    }
+
+    // Runs the action of a shortcut key, and records it while recording
+    fn apply_key_action(&mut self, action: KeyAction) {
+        if let Some(renderer) = &mut self.renderer {
+            action.apply(renderer);
+        }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_action(action);
+        }
+    }
+
+    fn can_draw(&self) -> bool {
+        self.renderer.as_ref().is_some_and(Renderer::can_draw)
+    }
+
+    /// When the next scene redraw is due, or `None` if scene redraws aren't
+    /// limited.
+    fn next_scene_redraw(&self) -> Option<Instant> {
+        let fps = self.renderer.as_ref()?.scene_fps_limit()?;
+        Some(self.last_scene_redraw + Duration::from_secs_f32(1.0 / fps.max(1) as f32))
+    }
+
+    // Installs the background-loaded model once its thread is done
+    fn finish_background_load(&mut self) {
+        let (Some(load), Some(renderer)) = (&self.pending_load, &mut self.renderer) else {
+            return;
+        };
+        let Some(result) = load.try_finish() else {
+            return;
+        };
+        let path = load.progress.path.clone();
+        let (reload, reparse) = (load.reload, load.reparse);
+        self.pending_load = None;
+        renderer.set_load_progress(None);
+        let result = result.and_then(|parsed| {
+            if reload || reparse {
+                renderer.reload_model(&path, parsed.model)?;
+            } else {
+                renderer.install_model(&path, parsed.model)?;
+            }
+            if !reparse {
+                renderer.record_snapshot(&path, parsed.snapshot);
+            }
+            Ok(parsed.summary)
+        });
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        match result {
+            Ok(summary) if reload => {
+                let previous = self.model_summary.as_ref().filter(|(previous_path, _)| *previous_path == path);
+                let lines = summary.diff(previous.map(|(_, previous)| previous));
+                info!("Reloaded {:?}: {}", path, lines.join(", "));
+                renderer.show_toast(format!("Reloaded {}", name), lines);
+                self.model_summary = Some((path, summary));
+            }
+            Ok(summary) => {
+                info!("Successfully loaded OBJ file: {:?}", path);
+                self.model_summary = Some((path, summary));
+            }
+            // A file caught mid-save gets a toast rather than a dialog; the next write reloads it again
+            Err(e) if reload => {
+                error!("Failed to reload {:?}: {:#}", path, e);
+                renderer.show_toast(format!("Failed to reload {}", name), vec![format!("{:#}", e)]);
+            }
+            Err(e) => {
+                error!("Failed to load mesh: {}", e);
+                let _ = self.menu.show_error("Load Failed", &format!("Failed to load {:?}:\n{}", path, e));
+            }
+        }
+    }
+
+    // Starts reloading the model in the background when its file changed
+    fn poll_model_watcher(&mut self) {
+        let Some(renderer) = self.renderer.as_mut().filter(|_| self.pending_load.is_none()) else {
+            return;
+        };
+        let Some(path) = renderer.watched_model_path().map(Path::to_path_buf) else {
+            self.model_watcher = None;
+            return;
+        };
+        match &mut self.model_watcher {
+            Some(watcher) if watcher.path == path => {
+                if !watcher.poll() {
+                    return;
+                }
+            }
+            _ => {
+                self.model_watcher = Some(FileWatcher::new(&path));
+                return;
+            }
+        }
+        info!("{:?} changed on disk, reloading", path);
+        let mut load = BackgroundLoad::spawn(&path, renderer.terrain_options(), renderer.shading_options());
+        load.reload = true;
+        renderer.set_load_progress(Some(load.progress.clone()));
+        self.pending_load = Some(load);
+    }
+
+    fn handle_ui_action(
+        &mut self,
+        action: UiAction,
+        elwt: &winit::event_loop::EventLoopWindowTarget<()>,
+        window: &Window,
+    ) {
+        if action == UiAction::Quit {
+            info!("Window close requested");
+            self.exit(elwt, window);
+            return;
+        }
+
+        let Some(renderer) = &mut self.renderer else {
+            return;
+        };
+
+        match action {
+            UiAction::OpenModel => {
+                if let Ok(Some(path)) = self.menu.open_file() {
+                    info!("Loading {:?} in the background", path);
+                    let load = BackgroundLoad::spawn(&path, renderer.terrain_options(), renderer.shading_options());
+                    renderer.set_load_progress(Some(load.progress.clone()));
+                    // A load still running is dropped and its result ignored
+                    self.pending_load = Some(load);
+                }
+            }
+            UiAction::CloseModel => {
+                renderer.close_model();
+            }
+            UiAction::ImportView => {
+                if let Ok(Some(path)) = self.menu.open_view_file() {
+                    match ViewState::load(&path) {
+                        Ok(state) => {
+                            renderer.apply_view_state(&state);
+                            info!("Imported camera from {:?}", path);
+                        }
+                        Err(e) => {
+                            error!("Failed to import camera: {}", e);
+                            let _ = self.menu.show_error("Import Failed", &format!("{:#}", e));
+                        }
+                    }
+                }
+            }
+            UiAction::ExportView => {
+                if let Ok(Some(path)) = self.menu.save_view_file() {
+                    match renderer.view_state().save(&path) {
+                        Ok(()) => info!("Exported camera to {:?}", path),
+                        Err(e) => {
+                            error!("Failed to export camera: {}", e);
+                            let _ = self.menu.show_error("Export Failed", &e.to_string());
+                        }
+                    }
+                }
+            }
+            UiAction::ImportSamples => {
+                if let Ok(Some(path)) = self.menu.open_samples_file() {
+                    if let Err(e) = renderer.load_samples(&path) {
+                        error!("Failed to import samples: {:#}", e);
+                        let _ = self.menu.show_error("Import Failed", &format!("Failed to load {:?}:\n{:#}", path, e));
+                    }
+                }
+            }
+            UiAction::LoadMorphTarget => {
+                if let Ok(Some(path)) = self.menu.open_file() {
+                    if let Err(e) = renderer.load_morph_target(&path) {
+                        error!("Failed to load morph target: {:#}", e);
+                        let _ = self.menu.show_error("Morph Target", &format!("Failed to load {:?}:\n{:#}", path, e));
+                    }
+                }
+            }
+            UiAction::LoadEnvironment => {
+                if let Ok(Some(path)) = self.menu.open_environment_file() {
+                    if let Err(e) = renderer.load_environment(&path) {
+                        error!("Failed to load environment: {:#}", e);
+                        let _ = self.menu.show_error("Environment", &format!("Failed to load {:?}:\n{:#}", path, e));
+                    }
+                }
+            }
+            UiAction::LoadSequence => {
+                if let Ok(Some(path)) = self.menu.open_file() {
+                    if let Err(e) = renderer.load_sequence(&path) {
+                        error!("Failed to load sequence: {:#}", e);
+                        let _ = self.menu.show_error("Load Failed", &format!("{:#}", e));
+                    }
+                }
+            }
+            UiAction::CaptureTimeline => {
+                if let Ok(Some(folder)) = self.menu.pick_folder("Render Timeline Frames") {
+                    match renderer.capture_timeline(&folder) {
+                        Ok(paths) => {
+                            info!("Rendered {} timeline frames to {:?}", paths.len(), folder);
+                            let _ = self.menu.show_info(
+                                "Render Complete",
+                                &format!("Rendered {} frames to {}", paths.len(), folder.display()),
+                            );
+                        }
+                        Err(e) => {
+                            error!("Failed to render timeline: {}", e);
+                            let _ = self.menu.show_error("Render Failed", &format!("{:#}", e));
+                        }
+                    }
+                }
+            }
+            UiAction::ExportStandardViews => {
+                if let Ok(Some(folder)) = self.menu.pick_folder("Export Standard Views") {
+                    match renderer.export_standard_views(&folder) {
+                        Ok(paths) => {
+                            info!("Exported {} standard views to {:?}", paths.len(), folder);
+                            let _ = self.menu.show_info(
+                                "Export Complete",
+                                &format!("Exported {} views to {}", paths.len(), folder.display()),
+                            );
+                        }
+                        Err(e) => {
+                            error!("Failed to export standard views: {}", e);
+                            let _ = self.menu.show_error("Export Failed", &format!("{:#}", e));
+                        }
+                    }
+                }
+            }
+            UiAction::ExportBlueprint => {
+                let file_name = renderer
+                    .model_path()
+                    .and_then(|path| path.file_stem())
+                    .map(|stem| format!("{}_blueprint.png", stem.to_string_lossy()))
+                    .unwrap_or_else(|| "blueprint.png".to_string());
+                if let Ok(Some(path)) = self.menu.save_png_file(&file_name) {
+                    match renderer.export_blueprint(&path) {
+                        Ok((width, height)) => info!("Exported {}x{} blueprint to {:?}", width, height, path),
+                        Err(e) => {
+                            error!("Failed to export blueprint: {}", e);
+                            let _ = self.menu.show_error("Export Failed", &format!("{:#}", e));
+                        }
+                    }
+                }
+            }
+            UiAction::ExportParts => {
+                let file_name = renderer
+                    .model_path()
+                    .and_then(|path| path.file_stem())
+                    .map(|stem| format!("{}_parts.csv", stem.to_string_lossy()))
+                    .unwrap_or_else(|| "parts.csv".to_string());
+                if let Ok(Some(path)) = self.menu.save_csv_file(&file_name) {
+                    match renderer.export_parts(&path) {
+                        Ok(()) => info!("Exported parts list to {:?}", path),
+                        Err(e) => {
+                            error!("Failed to export parts list: {}", e);
+                            let _ = self.menu.show_error("Export Failed", &format!("{:#}", e));
+                        }
+                    }
+                }
+            }
+            UiAction::SetViewPreset(preset) => renderer.set_view_preset(preset),
+            UiAction::SaveScreenshot => {
+                let file_name = renderer
+                    .model_path()
+                    .and_then(|path| path.file_stem())
+                    .map(|stem| format!("{}_screenshot.png", stem.to_string_lossy()))
+                    .unwrap_or_else(|| "screenshot.png".to_string());
+                if let Ok(Some(path)) = self.menu.save_png_file(&file_name) {
+                    match renderer.capture_screenshot(&path) {
+                        Ok((width, height)) => info!("Saved {}x{} screenshot to {:?}", width, height, path),
+                        Err(e) => {
+                            error!("Failed to save screenshot: {}", e);
+                            let _ = self.menu.show_error("Screenshot Failed", &format!("{:#}", e));
+                        }
+                    }
+                }
+            }
+            UiAction::ExportObj => {
+                let file_name = renderer
+                    .model_path()
+                    .and_then(|path| path.file_stem())
+                    .map(|stem| format!("{}_export.obj", stem.to_string_lossy()))
+                    .unwrap_or_else(|| "export.obj".to_string());
+                if let Ok(Some(path)) = self.menu.save_obj_file(&file_name) {
+                    match renderer.export_obj(&path) {
+                        Ok(stats) => {
+                            info!("Exported {:?}: {} vertices, {} faces ({} degenerate dropped)", path, stats.vertices, stats.faces, stats.dropped_faces);
+                        }
+                        Err(e) => {
+                            error!("Failed to export OBJ: {}", e);
+                            let _ = self.menu.show_error("Export Failed", &format!("{:#}", e));
+                        }
+                    }
+                }
+            }
+            UiAction::SetWindowTransparent(transparent) => {
+                window.set_decorations(!transparent);
+                window.set_transparent(transparent);
+            }
+            UiAction::SetCompactMode(compact) => {
+                if compact {
+                    self.size_before_compact = Some(window.inner_size());
+                    let _ = window.request_inner_size(winit::dpi::LogicalSize::new(360.0, 300.0));
+                    window.set_window_level(WindowLevel::AlwaysOnTop);
+                } else {
+                    window.set_window_level(WindowLevel::Normal);
+                    if let Some(size) = self.size_before_compact.take() {
+                        let _ = window.request_inner_size(size);
+                    }
+                }
+                info!("Compact mode: {}", compact);
+            }
+            UiAction::ReparseModel => {
+                let Some(path) = renderer.model_path().map(Path::to_path_buf) else {
+                    return;
+                };
+                // Replacing the running load could drop a file being opened
+                if self.pending_load.is_some() {
+                    renderer.show_toast("Still loading".to_string(), vec!["Apply the new options once the model has loaded".to_string()]);
+                    return;
+                }
+                info!("Reloading {:?} in the background with the new options", path);
+                let mut load = BackgroundLoad::spawn(&path, renderer.terrain_options(), renderer.shading_options());
+                load.reparse = true;
+                renderer.set_load_progress(Some(load.progress.clone()));
+                self.pending_load = Some(load);
+            }
+            UiAction::ResetSettings => {
+                renderer.apply_settings(&Settings::default());
+                self.show_detailed_stats = false;
+                info!("Settings reset to defaults");
+            }
+            UiAction::Quit => {}
+        }
+    }
+
+    /// Reloads the models and view recorded by the previous session. Models
+    /// that no longer exist are skipped.
+    fn restore_session(&self, renderer: &mut Renderer) {
+        for path in &self.session.models {
+            if !path.exists() {
+                warn!("Skipping missing model from last session: {:?}", path);
+                continue;
+            }
+            match renderer.load_mesh(path) {
+                Ok(()) => info!("Restored {:?} from last session", path),
+                Err(e) => error!("Failed to restore {:?}: {}", path, e),
+            }
+        }
+        if let Some(view) = &self.session.view {
+            renderer.apply_view_state(view);
+        }
+    }
+
+    fn save_session(&mut self, window: &Window) {
+        let Some(renderer) = self.renderer.as_ref().filter(|_| !self.safe_mode) else {
+            return;
+        };
+
+        let size = self.size_before_compact.unwrap_or_else(|| window.inner_size());
+        self.session.reopen_on_launch = renderer.reopen_last_session;
+        self.session.models = renderer.model_path().map(|path| path.to_path_buf()).into_iter().collect();
+        self.session.view = Some(renderer.view_state());
+        self.session.sections = renderer.sections().to_vec();
+        if size.width > 0 && size.height > 0 {
+            self.session.window_size = Some([size.width, size.height]);
+        }
+        if let Err(e) = self.session.save() {
+            warn!("Failed to save session: {:#}", e);
+        }
+        let settings = Settings {
+            show_detailed_stats: self.show_detailed_stats,
+            ..renderer.settings()
+        };
+        if let Err(e) = settings.save() {
+            warn!("Failed to save settings: {:#}", e);
+        }
+    }
+
+    fn exit(&mut self, elwt: &winit::event_loop::EventLoopWindowTarget<()>, window: &Window) {
+        self.save_session(window);
+        if let Some(recorder) = &self.recorder {
+            if let Err(e) = recorder.finish() {
+                error!("Failed to save replay: {:#}", e);
+            }
+        }
+        elwt.exit();
+    }
+}
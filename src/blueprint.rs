@@ -0,0 +1,61 @@
+use glam::Vec3;
+
+use crate::camera::{Camera, ViewPreset};
+use crate::overlay::Overlay;
+
+const DIMENSION_COLOR: [f32; 4] = [1.0, 0.86, 0.24, 1.0];
+
+/// Views of the blueprint sheet in row order on a 2x2 grid, laid out in
+/// third-angle projection: top above front, right side right of front, and
+/// the isometric view in the spare corner.
+pub const SHEET_VIEWS: [ViewPreset; 4] = [ViewPreset::Top, ViewPreset::Iso, ViewPreset::Front, ViewPreset::Right];
+
+/// Adds dimension lines for the bounding box axes lying in the image plane
+/// of `camera`: horizontal ones below the box, vertical ones to its right.
+pub fn add_dimensions(overlay: &mut Overlay, camera: &Camera, bounds: (Vec3, Vec3)) {
+    let (min, max) = bounds;
+    let forward = (camera.target - camera.position).normalize_or_zero();
+    let right = forward.cross(camera.up).normalize_or_zero();
+    let up = right.cross(forward);
+    let center = (min + max) * 0.5;
+    let half_size = (max - min) * 0.5;
+    let gap = (max - min).length().max(1e-3) * 0.08;
+
+    for (axis, name) in [(Vec3::X, "X"), (Vec3::Y, "Y"), (Vec3::Z, "Z")] {
+        // Side of the box the dimension is drawn on, perpendicular to the axis
+        let outward = if axis.dot(right).abs() > 0.99 {
+            -up
+        } else if axis.dot(up).abs() > 0.99 {
+            right
+        } else {
+            continue;
+        };
+        // Box corner furthest along `outward`, then slid to both ends of the axis
+        let edge = center + outward.signum() * half_size;
+        let start = edge * (Vec3::ONE - axis) + min * axis;
+        let end = edge * (Vec3::ONE - axis) + max * axis;
+        let (line_start, line_end) = (start + outward * gap, end + outward * gap);
+        overlay.line(line_start, line_end, DIMENSION_COLOR);
+        for point in [start, end] {
+            overlay.line(point + outward * (gap * 0.25), point + outward * (gap * 1.25), DIMENSION_COLOR);
+        }
+        let length = (max - min).dot(axis);
+        overlay.label((line_start + line_end) * 0.5, &format!("{} {:.4}", name, length), DIMENSION_COLOR);
+    }
+}
+
+/// Tiles four RGBA8 images of `width` by `height` into one sheet twice as
+/// wide and high, in row order.
+pub fn compose_sheet(views: &[Vec<u8>], width: u32, height: u32) -> Vec<u8> {
+    let row_bytes = width as usize * 4;
+    let mut sheet = vec![0; row_bytes * 2 * height as usize * 2];
+    for (i, pixels) in views.iter().enumerate().take(4) {
+        let (column, row) = (i % 2, i / 2);
+        for y in 0..height as usize {
+            let source = &pixels[y * row_bytes..(y + 1) * row_bytes];
+            let offset = (row * height as usize + y) * row_bytes * 2 + column * row_bytes;
+            sheet[offset..offset + row_bytes].copy_from_slice(source);
+        }
+    }
+    sheet
+}
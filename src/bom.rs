@@ -0,0 +1,70 @@
+use anyhow::{Context, Result};
+use glam::Vec3;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::analysis;
+use crate::mesh::Mesh;
+
+/// One row of the parts list: an OBJ object/group, AMF volume or X3D shape.
+#[derive(Debug, Clone)]
+pub struct Part {
+    pub name: String,
+    pub triangles: usize,
+    /// Axis-aligned bounding box extent.
+    pub size: [f32; 3],
+    /// Enclosed volume; only meaningful when `closed`.
+    pub volume: f32,
+    pub closed: bool,
+}
+
+pub fn parts(mesh: &Mesh) -> Vec<Part> {
+    let weld = analysis::weld_positions(mesh);
+    mesh.draw_ranges
+        .iter()
+        // Ranges that only group others have no geometry of their own
+        .filter(|range| range.count > 0)
+        .map(|range| {
+            let indices = &mesh.indices[range.start as usize..(range.start + range.count) as usize];
+            let mut min = Vec3::splat(f32::INFINITY);
+            let mut max = Vec3::splat(f32::NEG_INFINITY);
+            let mut signed_volume = 0.0;
+            let mut edge_faces: HashMap<(u32, u32), u32> = HashMap::new();
+            for triangle in indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.vertices[triangle[i] as usize].position));
+                min = min.min(a).min(b).min(c);
+                max = max.max(a).max(b).max(c);
+                // Signed tetrahedron volumes against the origin sum to the enclosed volume
+                signed_volume += a.dot(b.cross(c)) / 6.0;
+                for i in 0..3 {
+                    let (p, q) = (weld[triangle[i] as usize], weld[triangle[(i + 1) % 3] as usize]);
+                    *edge_faces.entry((p.min(q), p.max(q))).or_insert(0) += 1;
+                }
+            }
+
+            Part {
+                name: if range.name.is_empty() { "(unnamed)".to_string() } else { range.name.clone() },
+                triangles: indices.len() / 3,
+                size: if indices.is_empty() { [0.0; 3] } else { (max - min).to_array() },
+                volume: signed_volume.abs(),
+                closed: !edge_faces.is_empty() && edge_faces.values().all(|&faces| faces == 2),
+            }
+        })
+        .collect()
+}
+
+pub fn write_csv(path: &Path, parts: &[Part]) -> Result<()> {
+    let mut csv = String::from("name,triangles,size_x,size_y,size_z,volume,closed\n");
+    for part in parts {
+        let name = if part.name.contains([',', '"', '\n']) {
+            format!("\"{}\"", part.name.replace('"', "\"\""))
+        } else {
+            part.name.clone()
+        };
+        let [x, y, z] = part.size;
+        writeln!(csv, "{},{},{},{},{},{},{}", name, part.triangles, x, y, z, part.volume, part.closed)?;
+    }
+    std::fs::write(path, csv).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(())
+}
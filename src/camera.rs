@@ -1,7 +1,202 @@
 use glam::{Mat4, Vec3};
-use winit::event::{MouseButton, WindowEvent};
-use winit::dpi::PhysicalPosition;
+use serde::{Deserialize, Serialize};
 
+// Fraction of the distance each wheel step keeps when zooming in
+const ZOOM_STEP: f32 = 0.9;
+// Orbit distances zooming stays within, just to keep the math finite
+const MIN_DISTANCE: f32 = 1e-5;
+const MAX_DISTANCE: f32 = 1e7;
+
+/// Serializable subset of the camera needed to reproduce a viewpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraState {
+    pub position: [f32; 3],
+    pub target: [f32; 3],
+    pub up: [f32; 3],
+    pub fov_y_degrees: f32,
+    pub near: f32,
+    pub far: f32,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl CameraState {
+    /// Blends the orbit parameters towards `other`; yaw takes the shorter way round.
+    /// The position is recomputed from the orbit once applied.
+    pub fn lerp(&self, other: &CameraState, t: f32) -> CameraState {
+        use std::f32::consts::{PI, TAU};
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        let yaw_delta = (other.yaw - self.yaw + PI).rem_euclid(TAU) - PI;
+        let target = Vec3::from(self.target).lerp(Vec3::from(other.target), t);
+        CameraState {
+            position: Vec3::from(self.position).lerp(Vec3::from(other.position), t).to_array(),
+            target: target.to_array(),
+            up: self.up,
+            fov_y_degrees: mix(self.fov_y_degrees, other.fov_y_degrees),
+            near: mix(self.near, other.near),
+            far: mix(self.far, other.far),
+            distance: mix(self.distance, other.distance),
+            yaw: self.yaw + yaw_delta * t,
+            pitch: mix(self.pitch, other.pitch),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewPreset {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Iso,
+}
+
+impl ViewPreset {
+    pub const ALL: [ViewPreset; 7] = [
+        ViewPreset::Front,
+        ViewPreset::Back,
+        ViewPreset::Left,
+        ViewPreset::Right,
+        ViewPreset::Top,
+        ViewPreset::Bottom,
+        ViewPreset::Iso,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ViewPreset::Front => "front",
+            ViewPreset::Back => "back",
+            ViewPreset::Left => "left",
+            ViewPreset::Right => "right",
+            ViewPreset::Top => "top",
+            ViewPreset::Bottom => "bottom",
+            ViewPreset::Iso => "iso",
+        }
+    }
+
+    /// Preset called `name`, as written by `name()`.
+    pub fn from_name(name: &str) -> Option<ViewPreset> {
+        ViewPreset::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ViewPreset::Front => "Front",
+            ViewPreset::Back => "Back",
+            ViewPreset::Left => "Left",
+            ViewPreset::Right => "Right",
+            ViewPreset::Top => "Top",
+            ViewPreset::Bottom => "Bottom",
+            ViewPreset::Iso => "Isometric",
+        }
+    }
+
+    /// Numpad shortcut, Blender style: 1/3/7 for front/right/top, with Ctrl
+    /// for the opposite side, and 0 for isometric.
+    pub fn shortcut(self) -> &'static str {
+        match self {
+            ViewPreset::Front => "Numpad 1",
+            ViewPreset::Back => "Ctrl+Numpad 1",
+            ViewPreset::Left => "Ctrl+Numpad 3",
+            ViewPreset::Right => "Numpad 3",
+            ViewPreset::Top => "Numpad 7",
+            ViewPreset::Bottom => "Ctrl+Numpad 7",
+            ViewPreset::Iso => "Numpad 0",
+        }
+    }
+
+    pub fn from_numpad(key: winit::keyboard::KeyCode, ctrl: bool) -> Option<ViewPreset> {
+        use winit::keyboard::KeyCode;
+        match (key, ctrl) {
+            (KeyCode::Numpad1, false) => Some(ViewPreset::Front),
+            (KeyCode::Numpad1, true) => Some(ViewPreset::Back),
+            (KeyCode::Numpad3, false) => Some(ViewPreset::Right),
+            (KeyCode::Numpad3, true) => Some(ViewPreset::Left),
+            (KeyCode::Numpad7, false) => Some(ViewPreset::Top),
+            (KeyCode::Numpad7, true) => Some(ViewPreset::Bottom),
+            (KeyCode::Numpad0, _) => Some(ViewPreset::Iso),
+            _ => None,
+        }
+    }
+
+    /// Orbit angles (yaw, pitch) looking at the target from this direction.
+    pub fn yaw_pitch(self) -> (f32, f32) {
+        use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+        // Stay just short of the poles so look_at keeps a valid up vector
+        let pole = FRAC_PI_2 - 0.001;
+        match self {
+            ViewPreset::Front => (0.0, 0.0),
+            ViewPreset::Back => (PI, 0.0),
+            ViewPreset::Left => (-FRAC_PI_2, 0.0),
+            ViewPreset::Right => (FRAC_PI_2, 0.0),
+            ViewPreset::Top => (0.0, pole),
+            ViewPreset::Bottom => (0.0, -pole),
+            ViewPreset::Iso => (FRAC_PI_4, (1.0 / 2.0_f32.sqrt()).atan()),
+        }
+    }
+}
+
+/// Camera sensor formats for setting the field of view as a photographic
+/// focal length. The sensor's height spans the view's height, so renders at
+/// a photo's aspect ratio match its framing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SensorFormat {
+    #[default]
+    FullFrame,
+    ApsC,
+    MicroFourThirds,
+    OneInch,
+    MediumFormat,
+}
+
+impl SensorFormat {
+    pub const ALL: [SensorFormat; 5] = [
+        SensorFormat::FullFrame,
+        SensorFormat::ApsC,
+        SensorFormat::MicroFourThirds,
+        SensorFormat::OneInch,
+        SensorFormat::MediumFormat,
+    ];
+
+    /// Focal lengths offered as one-click presets, in millimetres.
+    pub const FOCAL_LENGTHS: [f32; 6] = [24.0, 35.0, 50.0, 85.0, 135.0, 200.0];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SensorFormat::FullFrame => "Full frame (36 x 24 mm)",
+            SensorFormat::ApsC => "APS-C (23.6 x 15.7 mm)",
+            SensorFormat::MicroFourThirds => "Micro Four Thirds (17.3 x 13 mm)",
+            SensorFormat::OneInch => "1 inch (13.2 x 8.8 mm)",
+            SensorFormat::MediumFormat => "Medium format (44 x 33 mm)",
+        }
+    }
+
+    /// Width and height in millimetres.
+    pub fn size(self) -> (f32, f32) {
+        match self {
+            SensorFormat::FullFrame => (36.0, 24.0),
+            SensorFormat::ApsC => (23.6, 15.7),
+            SensorFormat::MicroFourThirds => (17.3, 13.0),
+            SensorFormat::OneInch => (13.2, 8.8),
+            SensorFormat::MediumFormat => (44.0, 33.0),
+        }
+    }
+
+    /// Vertical field of view, in radians, of a lens of `focal_length` mm.
+    pub fn fov(self, focal_length: f32) -> f32 {
+        2.0 * (self.size().1 * 0.5 / focal_length.max(1e-3)).atan()
+    }
+
+    /// Focal length in mm giving the vertical field of view `fov`, in radians.
+    pub fn focal_length(self, fov: f32) -> f32 {
+        self.size().1 * 0.5 / (fov * 0.5).tan()
+    }
+}
+
+#[derive(Clone)]
 pub struct Camera {
     pub position: Vec3,
     pub target: Vec3,
@@ -15,8 +210,18 @@ pub struct Camera {
     pub distance: f32,
     pub yaw: f32,
     pub pitch: f32,
-    pub is_orbiting: bool,
-    pub last_mouse_pos: Option<PhysicalPosition<f64>>,
+
+    // Continuous rotation around the target, in degrees per second
+    pub auto_rotate: bool,
+    pub auto_rotate_speed: f32,
+
+    // Parallel projection covering what the perspective one shows at the
+    // orbit target; only used for exports
+    pub orthographic: bool,
+
+    // Sub-pixel shift of the image in normalized device coordinates, for
+    // supersampled stills
+    pub jitter: glam::Vec2,
 }
 
 impl Camera {
@@ -33,8 +238,12 @@ impl Camera {
             distance: 5.0,
             yaw: 0.0,
             pitch: 0.0,
-            is_orbiting: false,
-            last_mouse_pos: None,
+
+            auto_rotate: false,
+            auto_rotate_speed: 30.0,
+
+            orthographic: false,
+            jitter: glam::Vec2::ZERO,
         }
     }
 
@@ -43,70 +252,180 @@ impl Camera {
     }
 
     pub fn projection_matrix(&self) -> Mat4 {
-        Mat4::perspective_rh(self.fov, self.aspect_ratio, self.near, self.far)
+        let projection = if self.orthographic {
+            let half_height = self.distance * (self.fov * 0.5).tan();
+            let half_width = half_height * self.aspect_ratio;
+            Mat4::orthographic_rh(-half_width, half_width, -half_height, half_height, self.near, self.far)
+        } else {
+            Mat4::perspective_rh(self.fov, self.aspect_ratio, self.near, self.far)
+        };
+        Mat4::from_translation(self.jitter.extend(0.0)) * projection
     }
 
     pub fn update_position(&mut self) {
+        self.position = self.target + self.orbit_offset();
+    }
+
+    // Camera position relative to the target for the current orbit angles
+    fn orbit_offset(&self) -> Vec3 {
         let x = self.distance * self.pitch.cos() * self.yaw.sin();
         let y = self.distance * self.pitch.sin();
         let z = self.distance * self.pitch.cos() * self.yaw.cos();
-        
-        self.position = Vec3::new(x, y, z);
-    }
-
-    pub fn handle_input(&mut self, event: &WindowEvent) {
-        match event {
-            WindowEvent::MouseInput {
-                button: MouseButton::Left,
-                state: winit::event::ElementState::Pressed,
-                ..
-            } => {
-                self.is_orbiting = true;
-            }
-            WindowEvent::MouseInput {
-                button: MouseButton::Left,
-                state: winit::event::ElementState::Released,
-                ..
-            } => {
-                self.is_orbiting = false;
-                self.last_mouse_pos = None;
-            }
-            WindowEvent::CursorMoved { position, .. } => {
-                if self.is_orbiting {
-                    if let Some(last_pos) = self.last_mouse_pos {
-                        let delta_x = position.x - last_pos.x;
-                        let delta_y = position.y - last_pos.y;
-                        
-                        self.yaw += delta_x as f32 * 0.01;
-                        self.pitch += delta_y as f32 * 0.01;
-                        
-                        // Clamp pitch to prevent gimbal lock
-                        self.pitch = self.pitch.clamp(-1.5, 1.5);
-                        
-                        self.update_position();
-                    }
-                    self.last_mouse_pos = Some(*position);
-                }
-            }
-            WindowEvent::MouseWheel { delta, .. } => {
-                match delta {
-                    winit::event::MouseScrollDelta::LineDelta(_, y) => {
-                        self.distance -= y * 0.5;
-                        self.distance = self.distance.clamp(0.1, 100.0);
-                        self.update_position();
-                    }
-                    winit::event::MouseScrollDelta::PixelDelta(pos) => {
-                        self.distance -= pos.y as f32 * 0.01;
-                        self.distance = self.distance.clamp(0.1, 100.0);
-                        self.update_position();
-                    }
-                }
-            }
-            WindowEvent::Resized(physical_size) => {
-                self.aspect_ratio = physical_size.width as f32 / physical_size.height as f32;
-            }
-            _ => {}
+        Vec3::new(x, y, z)
+    }
+
+    /// Turns the orbit around the target by the given angles in radians.
+    pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
+        self.yaw += delta_yaw;
+        // Clamp pitch to prevent gimbal lock
+        self.pitch = (self.pitch + delta_pitch).clamp(-1.5, 1.5);
+        self.update_position();
+    }
+
+    /// Turns the view direction by the given angles while the camera stays
+    /// put, moving the target instead.
+    pub fn look(&mut self, delta_yaw: f32, delta_pitch: f32) {
+        self.yaw += delta_yaw;
+        self.pitch = (self.pitch + delta_pitch).clamp(-1.5, 1.5);
+        self.target = self.position - self.orbit_offset();
+    }
+
+    /// Moves the camera and its target together.
+    pub fn translate(&mut self, offset: Vec3) {
+        self.position += offset;
+        self.target += offset;
+    }
+
+    /// Moves towards the target by `steps` wheel steps (away for negative
+    /// ones). Each step changes the distance by the same fraction, so zooming
+    /// feels the same on a building as on a screw.
+    pub fn zoom(&mut self, steps: f32) {
+        self.distance = (self.distance * ZOOM_STEP.powf(steps)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+        self.keep_target_in_clip_range();
+        self.update_position();
+    }
+
+    // Scales the clip planes together when the target ends up too close to
+    // either, which fixed planes would cut away on very large or very small
+    // models. Keeping their ratio keeps the depth precision.
+    fn keep_target_in_clip_range(&mut self) {
+        // A model without extent fits at distance zero, with nothing to keep
+        let scale = if self.distance <= 0.0 {
+            return;
+        } else if self.distance > self.far * 0.5 {
+            self.distance / (self.far * 0.5)
+        } else if self.distance < self.near * 10.0 {
+            self.distance / (self.near * 10.0)
+        } else {
+            return;
+        };
+        self.near *= scale;
+        self.far *= scale;
+    }
+
+    /// Corners of the view frustum's cross-section `depth` in front of the
+    /// camera: top left, top right, bottom right, bottom left.
+    pub fn frustum_corners(&self, depth: f32) -> [Vec3; 4] {
+        let forward = (self.target - self.position).normalize_or_zero();
+        let right = forward.cross(self.up).normalize_or_zero();
+        let up = right.cross(forward);
+        let half_height = depth * (self.fov * 0.5).tan();
+        let half_width = half_height * self.aspect_ratio;
+        let center = self.position + forward * depth;
+        [(-1.0, 1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)].map(|(x, y)| center + right * (x * half_width) + up * (y * half_height))
+    }
+
+    /// Copy of this camera looking through `ndc` (a point on screen, -1..1
+    /// with y up) with the field of view narrowed by `magnification`.
+    pub fn magnified(&self, ndc: glam::Vec2, magnification: f32, aspect_ratio: f32) -> Camera {
+        let forward = (self.target - self.position).normalize_or_zero();
+        let right = forward.cross(self.up).normalize_or_zero();
+        let up = right.cross(forward);
+        let half_height = (self.fov * 0.5).tan();
+        let half_width = half_height * self.aspect_ratio;
+        let direction = (forward + right * (ndc.x * half_width) + up * (ndc.y * half_height)).normalize_or_zero();
+        let mut detail = self.clone();
+        detail.target = self.position + direction * self.distance;
+        detail.fov = 2.0 * (half_height / magnification.max(1.0)).atan();
+        detail.aspect_ratio = aspect_ratio;
+        detail
+    }
+
+    /// Copy of this camera moved sideways by `offset` (negative = left eye),
+    /// still converging on the orbit target.
+    pub fn stereo_eye(&self, offset: f32, aspect_ratio: f32) -> Camera {
+        let forward = (self.target - self.position).normalize_or_zero();
+        let right = forward.cross(self.up).normalize_or_zero();
+        let mut eye = self.clone();
+        eye.position += right * offset;
+        eye.aspect_ratio = aspect_ratio;
+        eye
+    }
+
+    pub fn set_view_preset(&mut self, preset: ViewPreset) {
+        let (yaw, pitch) = preset.yaw_pitch();
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self.update_position();
+    }
+
+    /// Orbits round to look at the target from `direction`, keeping the
+    /// distance.
+    pub fn set_view_direction(&mut self, direction: Vec3) {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec3::ZERO {
+            return;
+        }
+        // Same margin from the poles as the presets
+        let pole = std::f32::consts::FRAC_PI_2 - 0.001;
+        self.yaw = direction.x.atan2(direction.z);
+        self.pitch = direction.y.asin().clamp(-pole, pole);
+        self.update_position();
+    }
+
+    /// Puts the camera at `position`, still looking at the target.
+    pub fn set_position(&mut self, position: Vec3) {
+        let offset = position - self.target;
+        if offset.length() < 1e-6 {
+            return;
+        }
+        self.distance = offset.length();
+        self.set_view_direction(offset);
+    }
+
+    /// Orbits about `pivot` from now on: the camera stays where it is and
+    /// turns to face it.
+    pub fn set_pivot(&mut self, pivot: Vec3) {
+        let position = self.position;
+        self.target = pivot;
+        if position.distance(pivot) < 1e-6 {
+            // Right on the pivot, so step back from it along the old view
+            self.update_position();
+        } else {
+            self.set_position(position);
+        }
+    }
+
+    /// Moves the target and the camera together along the view's right and up
+    /// axes. Deltas are in pixels, scaled with the distance so the model
+    /// roughly follows the cursor.
+    pub fn pan(&mut self, delta_x: f32, delta_y: f32) {
+        let forward = (self.target - self.position).normalize_or_zero();
+        let right = forward.cross(self.up).normalize_or_zero();
+        let up = right.cross(forward);
+        let scale = self.distance * (self.fov * 0.5).tan() * 0.0025;
+        self.target += (up * delta_y - right * delta_x) * scale;
+        self.update_position();
+    }
+
+    /// Advances the auto-rotation by `delta_time` seconds. Callers skip it
+    /// while the user drags the camera.
+    pub fn update_auto_rotate(&mut self, delta_time: f32) {
+        if !self.auto_rotate {
+            return;
         }
+        self.yaw = (self.yaw + self.auto_rotate_speed.to_radians() * delta_time).rem_euclid(std::f32::consts::TAU);
+        self.update_position();
     }
 
     pub fn auto_fit_to_model(&mut self, model_bounds: (Vec3, Vec3)) {
@@ -116,6 +435,33 @@ impl Camera {
         
         self.target = center;
         self.distance = size * 2.0;
+        self.keep_target_in_clip_range();
         self.update_position();
     }
-} 
\ No newline at end of file
+
+    pub fn state(&self) -> CameraState {
+        CameraState {
+            position: self.position.to_array(),
+            target: self.target.to_array(),
+            up: self.up.to_array(),
+            fov_y_degrees: self.fov.to_degrees(),
+            near: self.near,
+            far: self.far,
+            distance: self.distance,
+            yaw: self.yaw,
+            pitch: self.pitch,
+        }
+    }
+
+    pub fn apply_state(&mut self, state: &CameraState) {
+        self.position = Vec3::from_array(state.position);
+        self.target = Vec3::from_array(state.target);
+        self.up = Vec3::from_array(state.up);
+        self.fov = state.fov_y_degrees.to_radians();
+        self.near = state.near;
+        self.far = state.far;
+        self.distance = state.distance;
+        self.yaw = state.yaw;
+        self.pitch = state.pitch;
+    }
+}
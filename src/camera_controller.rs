@@ -0,0 +1,261 @@
+use glam::Vec3;
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+use crate::camera::Camera;
+use crate::timeline::CameraPath;
+
+// Radians turned per pixel dragged
+const ROTATE_SPEED: f32 = 0.01;
+const LOOK_SPEED: f32 = 0.005;
+// Flying speed in orbit distances per second
+const FLY_SPEED: f32 = 0.5;
+
+/// Navigation scheme turning window input into camera moves. The camera
+/// only knows its own math; controllers decide which input does what, so
+/// new schemes can be added without touching `Camera`.
+pub trait CameraController {
+    fn kind(&self) -> ControllerKind;
+
+    /// Applies a window event to `camera`. Mouse movement and the wheel are
+    /// scaled by `sensitivity`, 1.0 being the default speed.
+    fn handle_input(&mut self, camera: &mut Camera, event: &WindowEvent, sensitivity: f32);
+
+    /// Advances continuous movement by `delta_time` seconds.
+    fn update(&mut self, _camera: &mut Camera, _delta_time: f32) {}
+
+    /// Whether the camera is being moved, by the user or the controller
+    /// itself. Auto-rotation pauses meanwhile.
+    fn is_moving(&self) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerKind {
+    Orbit,
+    Fly,
+    Turntable,
+    PathPlayback,
+}
+
+impl ControllerKind {
+    pub const ALL: [ControllerKind; 4] = [ControllerKind::Orbit, ControllerKind::Fly, ControllerKind::Turntable, ControllerKind::PathPlayback];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ControllerKind::Orbit => "Orbit",
+            ControllerKind::Fly => "Fly",
+            ControllerKind::Turntable => "Turntable",
+            ControllerKind::PathPlayback => "Camera path playback",
+        }
+    }
+
+    pub fn hint(self) -> &'static str {
+        match self {
+            ControllerKind::Orbit => "Left drag orbits, middle/right drag pans, wheel zooms",
+            ControllerKind::Fly => "Left drag looks around, arrow keys fly, Page Up/Down rise and sink, wheel moves forward",
+            ControllerKind::Turntable => "Left drag spins around the vertical axis, wheel zooms",
+            ControllerKind::PathPlayback => "Plays the timeline's camera path in a loop",
+        }
+    }
+}
+
+// Mouse buttons held and the last cursor position while dragging
+#[derive(Default)]
+struct Drag {
+    rotating: bool,
+    panning: bool,
+    last_position: Option<PhysicalPosition<f64>>,
+}
+
+impl Drag {
+    // Tracks button presses; returns the cursor movement in pixels while a
+    // button is held
+    fn update(&mut self, event: &WindowEvent) -> Option<(f32, f32)> {
+        match event {
+            WindowEvent::MouseInput { button: MouseButton::Left, state, .. } => {
+                self.rotating = *state == ElementState::Pressed;
+                self.last_position = None;
+            }
+            WindowEvent::MouseInput { button: MouseButton::Middle | MouseButton::Right, state, .. } => {
+                self.panning = *state == ElementState::Pressed;
+                self.last_position = None;
+            }
+            WindowEvent::CursorMoved { position, .. } if self.rotating || self.panning => {
+                let delta = self.last_position.map(|last| ((position.x - last.x) as f32, (position.y - last.y) as f32));
+                self.last_position = Some(*position);
+                return delta;
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn is_dragging(&self) -> bool {
+        self.rotating || self.panning
+    }
+}
+
+// Lines scrolled, with pixel deltas from touchpads scaled to match
+fn scroll_lines(delta: &MouseScrollDelta) -> f32 {
+    match delta {
+        MouseScrollDelta::LineDelta(_, y) => *y,
+        MouseScrollDelta::PixelDelta(position) => position.y as f32 * 0.02,
+    }
+}
+
+/// Orbits around the target: left drag rotates, middle or right drag pans,
+/// the wheel zooms.
+#[derive(Default)]
+pub struct OrbitController {
+    drag: Drag,
+}
+
+impl CameraController for OrbitController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Orbit
+    }
+
+    fn handle_input(&mut self, camera: &mut Camera, event: &WindowEvent, sensitivity: f32) {
+        if let WindowEvent::MouseWheel { delta, .. } = event {
+            camera.zoom(scroll_lines(delta) * sensitivity);
+        }
+        if let Some((delta_x, delta_y)) = self.drag.update(event) {
+            let (delta_x, delta_y) = (delta_x * sensitivity, delta_y * sensitivity);
+            if self.drag.panning {
+                camera.pan(delta_x, delta_y);
+            } else {
+                camera.orbit(delta_x * ROTATE_SPEED, delta_y * ROTATE_SPEED);
+            }
+        }
+    }
+
+    fn is_moving(&self) -> bool {
+        self.drag.is_dragging()
+    }
+}
+
+/// Like orbiting, but dragging only spins the model around the vertical
+/// axis and the camera can't be panned, for presenting an object.
+#[derive(Default)]
+pub struct TurntableController {
+    drag: Drag,
+}
+
+impl CameraController for TurntableController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Turntable
+    }
+
+    fn handle_input(&mut self, camera: &mut Camera, event: &WindowEvent, sensitivity: f32) {
+        if let WindowEvent::MouseWheel { delta, .. } = event {
+            camera.zoom(scroll_lines(delta) * sensitivity);
+        }
+        if let Some((delta_x, _)) = self.drag.update(event).filter(|_| self.drag.rotating) {
+            camera.orbit(delta_x * sensitivity * ROTATE_SPEED, 0.0);
+        }
+    }
+
+    fn is_moving(&self) -> bool {
+        self.drag.rotating
+    }
+}
+
+/// First-person flying: left drag turns the view in place, the arrow keys
+/// move along it and Page Up/Down move vertically. Arrow keys stay clear of
+/// the letter shortcuts.
+#[derive(Default)]
+pub struct FlyController {
+    drag: Drag,
+    forward: bool,
+    back: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+}
+
+impl CameraController for FlyController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Fly
+    }
+
+    fn handle_input(&mut self, camera: &mut Camera, event: &WindowEvent, sensitivity: f32) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                let pressed = event.state == ElementState::Pressed;
+                match event.physical_key {
+                    PhysicalKey::Code(KeyCode::ArrowUp) => self.forward = pressed,
+                    PhysicalKey::Code(KeyCode::ArrowDown) => self.back = pressed,
+                    PhysicalKey::Code(KeyCode::ArrowLeft) => self.left = pressed,
+                    PhysicalKey::Code(KeyCode::ArrowRight) => self.right = pressed,
+                    PhysicalKey::Code(KeyCode::PageUp) => self.up = pressed,
+                    PhysicalKey::Code(KeyCode::PageDown) => self.down = pressed,
+                    _ => {}
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let forward = (camera.target - camera.position).normalize_or_zero();
+                camera.translate(forward * scroll_lines(delta) * sensitivity * camera.distance * 0.1);
+            }
+            // Keys released while the window is in the background never arrive
+            WindowEvent::Focused(false) => *self = FlyController::default(),
+            _ => {}
+        }
+        if let Some((delta_x, delta_y)) = self.drag.update(event).filter(|_| self.drag.rotating) {
+            camera.look(-delta_x * sensitivity * LOOK_SPEED, delta_y * sensitivity * LOOK_SPEED);
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, delta_time: f32) {
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+        let forward = (camera.target - camera.position).normalize_or_zero();
+        let right = forward.cross(camera.up).normalize_or_zero();
+        let direction = forward * axis(self.forward, self.back) + right * axis(self.right, self.left) + Vec3::Y * axis(self.up, self.down);
+        if direction != Vec3::ZERO {
+            camera.translate(direction.normalize() * camera.distance * FLY_SPEED * delta_time);
+        }
+    }
+
+    fn is_moving(&self) -> bool {
+        self.drag.is_dragging() || self.forward || self.back || self.left || self.right || self.up || self.down
+    }
+}
+
+/// Moves the camera along a copy of the timeline's camera path, looping at
+/// the timeline's frame rate and ignoring input.
+pub struct PathPlaybackController {
+    path: CameraPath,
+    fps: f32,
+    time: f32,
+}
+
+impl PathPlaybackController {
+    pub fn new(path: CameraPath, fps: f32) -> Self {
+        Self { path, fps, time: 0.0 }
+    }
+}
+
+impl CameraController for PathPlaybackController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::PathPlayback
+    }
+
+    fn handle_input(&mut self, _camera: &mut Camera, _event: &WindowEvent, _sensitivity: f32) {}
+
+    fn update(&mut self, camera: &mut Camera, delta_time: f32) {
+        let (Some((first, _)), Some((last, _))) = (self.path.keys().first(), self.path.keys().last()) else {
+            return;
+        };
+        let frames = (last - first + 1) as f32;
+        self.time = (self.time + delta_time * self.fps.max(1.0)).rem_euclid(frames);
+        if let Some(state) = self.path.sample(first + self.time as u32) {
+            camera.apply_state(&state);
+            camera.update_position();
+        }
+    }
+
+    fn is_moving(&self) -> bool {
+        true
+    }
+}
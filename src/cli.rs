@@ -0,0 +1,159 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::PathBuf;
+
+use crate::aov::{AovFormat, AovSettings};
+use crate::dataset::DatasetOptions;
+use crate::presentation::PresentationPreset;
+
+pub const USAGE: &str = "\
+Usage:
+  dotobjviewer [--safe-mode] [--preset <name>] [--record <file> | --replay <file>]
+                                        Start the interactive viewer
+  dotobjviewer --dataset <model> [options]
+                                        Render randomized views for ML datasets
+  dotobjviewer --replay <file> --out <dir>
+                                        Render every frame of a replay as PNGs
+  dotobjviewer --serve <address> --root <dir>
+                                        Serve PNG previews of the models in
+                                        <dir> over HTTP, e.g. on 127.0.0.1:8080,
+                                        at /render?model=<path>&view=<view>&size=<n>
+
+Viewer options:
+  --safe-mode               Start on the fallback adapter with post-processing
+                            off, ignoring and not saving the last session
+  --preset <name>           Presentation preset to start with, also for
+                            datasets: studio, dark, clay or blueprint
+  --record <file>           Record the camera and shortcut keys of every
+                            frame to a replay file, written on exit
+  --replay <file>           Play a recorded replay back, then continue
+                            interactively
+
+Dataset options:
+  --out <dir>               Output folder (default: dataset)
+  --count <n>               Number of frames (default: 100)
+  --size <width>x<height>   Image size (default: 512x512)
+  --seed <n>                Random seed (default: 0)
+  --random-lights           Randomize light position, color and intensity
+  --environment <file>      Light with and show an HDR environment (.hdr)
+  --passes <list>           Extra passes: normal,depth,id
+  --pass-format <png|exr>   Format of the extra passes (default: png)";
+
+pub enum Command {
+    Viewer {
+        safe_mode: bool,
+        preset: Option<PresentationPreset>,
+        record: Option<PathBuf>,
+        replay: Option<PathBuf>,
+    },
+    Dataset(DatasetOptions),
+    Replay { replay: PathBuf, output: PathBuf },
+    Serve { address: String, root: PathBuf },
+    Help,
+}
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command> {
+    let mut args = args.into_iter();
+    let mut dataset_model = None;
+    let mut safe_mode = false;
+    let mut serve_address = None;
+    let mut serve_root = None;
+    let mut record = None;
+    let mut replay = None;
+    let mut output = None;
+    let mut options = DatasetOptions {
+        model: PathBuf::new(),
+        output: PathBuf::new(),
+        count: 100,
+        width: 512,
+        height: 512,
+        seed: 0,
+        randomize_lights: false,
+        aov: AovSettings::default(),
+        preset: None,
+        environment: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| anyhow!("Missing value for {}", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--safe-mode" => safe_mode = true,
+            "--preset" => {
+                let name = value("--preset")?;
+                options.preset = Some(PresentationPreset::from_name(&name).ok_or_else(|| anyhow!("Unknown preset '{}'", name))?);
+            }
+            "--serve" => serve_address = Some(value("--serve")?),
+            "--root" => serve_root = Some(PathBuf::from(value("--root")?)),
+            "--dataset" => dataset_model = Some(PathBuf::from(value("--dataset")?)),
+            "--record" => record = Some(PathBuf::from(value("--record")?)),
+            "--replay" => replay = Some(PathBuf::from(value("--replay")?)),
+            "--out" => output = Some(PathBuf::from(value("--out")?)),
+            "--count" => options.count = value("--count")?.parse().context("Invalid --count")?,
+            "--seed" => options.seed = value("--seed")?.parse().context("Invalid --seed")?,
+            "--size" => (options.width, options.height) = parse_size(&value("--size")?)?,
+            "--random-lights" => options.randomize_lights = true,
+            "--environment" => options.environment = Some(PathBuf::from(value("--environment")?)),
+            "--passes" => {
+                for pass in value("--passes")?.split(',') {
+                    match pass.trim() {
+                        "normal" => options.aov.normal = true,
+                        "depth" => options.aov.depth = true,
+                        "id" => options.aov.object_id = true,
+                        other => bail!("Unknown pass '{}'", other),
+                    }
+                }
+            }
+            "--pass-format" => {
+                options.aov.format = match value("--pass-format")?.as_str() {
+                    "png" => AovFormat::Png,
+                    "exr" => AovFormat::Exr,
+                    other => bail!("Unknown pass format '{}'", other),
+                }
+            }
+            other => bail!("Unknown argument '{}'", other),
+        }
+    }
+
+    if record.is_some() && replay.is_some() {
+        bail!("--record and --replay can't be combined");
+    }
+    if serve_root.is_some() && serve_address.is_none() {
+        bail!("--root only applies to --serve");
+    }
+    match (dataset_model, serve_address) {
+        (Some(_), Some(_)) => bail!("--dataset and --serve can't be combined"),
+        (Some(_), None) | (None, Some(_)) if record.is_some() || replay.is_some() => {
+            bail!("--record and --replay only apply to the viewer")
+        }
+        (Some(model), None) => {
+            options.model = model;
+            options.output = output.unwrap_or_else(|| PathBuf::from("dataset"));
+            Ok(Command::Dataset(options))
+        }
+        (None, Some(address)) => Ok(Command::Serve {
+            address,
+            root: serve_root.ok_or_else(|| anyhow!("--serve needs --root <dir>, the folder models are served from"))?,
+        }),
+        (None, None) => match (replay, output) {
+            (Some(replay), Some(output)) => Ok(Command::Replay { replay, output }),
+            (replay, _) => Ok(Command::Viewer {
+                safe_mode,
+                preset: options.preset,
+                record,
+                replay,
+            }),
+        },
+    }
+}
+
+pub fn parse_size(text: &str) -> Result<(u32, u32)> {
+    let (width, height) = text
+        .split_once('x')
+        .ok_or_else(|| anyhow!("Invalid size '{}', expected <width>x<height>", text))?;
+    let width: u32 = width.parse().with_context(|| format!("Invalid width in '{}'", text))?;
+    let height: u32 = height.parse().with_context(|| format!("Invalid height in '{}'", text))?;
+    if width == 0 || height == 0 {
+        bail!("Size must be non-zero, got '{}'", text);
+    }
+    Ok((width, height))
+}
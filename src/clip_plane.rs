@@ -0,0 +1,209 @@
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Camera;
+use crate::handles::{self, Handle, HandleShape, Manipulator, Motion};
+use crate::overlay::Overlay;
+
+const PLANE_COLOR: [f32; 4] = [1.0, 0.45, 0.75, 1.0];
+const HANDLE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 115, 190);
+const CAP_COLOR: [f32; 3] = [0.8, 0.25, 0.2];
+
+/// A section plane saved under a name, to come back to or sweep between.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    pub name: String,
+    pub point: [f32; 3],
+    pub normal: [f32; 3],
+}
+
+/// Section plane cutting away the half of the model its normal points into,
+/// for looking inside. While edited, an arrow moves it along its normal and
+/// two rings tilt it. The cut can be capped, filling solid parts in with a
+/// flat color, and outlined where it meets the surface.
+pub struct ClipPlane {
+    pub enabled: bool,
+    pub editing: bool,
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// Draws the inside faces seen through the cut in `cap_color`, which
+    /// closes the cut of watertight parts.
+    pub cap: bool,
+    pub cap_color: [f32; 3],
+    /// Outlines where the plane cuts the surface.
+    pub contour: bool,
+    pub sections: Vec<Section>,
+    /// Saved sections, by index, that the plane moves between over the
+    /// timeline.
+    pub sweep: Option<(usize, usize)>,
+    manipulator: Manipulator,
+    // Point and normal when a handle was grabbed
+    start: Option<(Vec3, Vec3)>,
+}
+
+impl ClipPlane {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            editing: false,
+            point: Vec3::ZERO,
+            normal: Vec3::X,
+            cap: false,
+            cap_color: CAP_COLOR,
+            contour: false,
+            sections: Vec::new(),
+            sweep: None,
+            manipulator: Manipulator::new(),
+            start: None,
+        }
+    }
+
+    /// Plane coefficients for the shaders, which drop fragments where
+    /// `dot(xyz, position) + w` is positive. A disabled plane keeps everything.
+    pub fn equation(&self) -> [f32; 4] {
+        if !self.enabled {
+            return [0.0, 0.0, 0.0, -1.0];
+        }
+        let normal = self.normal.normalize_or_zero();
+        [normal.x, normal.y, normal.z, -normal.dot(self.point)]
+    }
+
+    /// Cap color for the shaders, with w 1 while the plane is capped.
+    pub fn cap_uniform(&self) -> [f32; 4] {
+        let [r, g, b] = self.cap_color;
+        [r, g, b, (self.enabled && self.cap) as u32 as f32]
+    }
+
+    /// Contour color for the shaders, with w 1 while the cut is outlined.
+    pub fn contour_uniform(&self) -> [f32; 4] {
+        let [r, g, b, _] = PLANE_COLOR;
+        [r, g, b, (self.enabled && self.contour) as u32 as f32]
+    }
+
+    /// Whether the back faces behind the cut have to be drawn.
+    pub fn needs_back_faces(&self) -> bool {
+        self.enabled && self.cap
+    }
+
+    /// Signed distance of the plane from the origin along its normal.
+    pub fn offset(&self) -> f32 {
+        self.normal.normalize_or_zero().dot(self.point)
+    }
+
+    /// Moves the plane along its normal to `offset` from the origin.
+    pub fn set_offset(&mut self, offset: f32) {
+        let normal = self.normal.normalize_or_zero();
+        self.point += normal * (offset - normal.dot(self.point));
+    }
+
+    /// Turns the plane to face along `axis`, keeping the point it goes through.
+    pub fn align(&mut self, axis: Vec3) {
+        self.normal = axis;
+    }
+
+    /// Saves the plane as it is under `name`, replacing a section of that name.
+    pub fn save_section(&mut self, name: &str) {
+        let section = Section {
+            name: name.to_string(),
+            point: self.point.to_array(),
+            normal: self.normal.normalize_or_zero().to_array(),
+        };
+        match self.sections.iter_mut().find(|existing| existing.name == name) {
+            Some(existing) => *existing = section,
+            None => self.sections.push(section),
+        }
+    }
+
+    /// Turns the plane on where section `index` was saved.
+    pub fn apply_section(&mut self, index: usize) {
+        if let Some(section) = self.sections.get(index) {
+            self.enabled = true;
+            self.point = Vec3::from(section.point);
+            self.normal = Vec3::from(section.normal);
+        }
+    }
+
+    /// Deletes section `index`, and the sweep, whose indices it would shift.
+    pub fn remove_section(&mut self, index: usize) {
+        if index < self.sections.len() {
+            self.sections.remove(index);
+            self.sweep = None;
+        }
+    }
+
+    /// Moves the plane `t` of the way along the sweep, 0 at its first section
+    /// and 1 at its second, turning it the short way between their normals.
+    pub fn sample_sweep(&mut self, t: f32) {
+        let Some((Some(from), Some(to))) = self.sweep.map(|(from, to)| (self.sections.get(from), self.sections.get(to))) else {
+            return;
+        };
+        let (from_normal, to_normal) = (Vec3::from(from.normal).normalize_or_zero(), Vec3::from(to.normal).normalize_or_zero());
+        let turn = Quat::IDENTITY.slerp(Quat::from_rotation_arc(from_normal, to_normal), t);
+        self.enabled = true;
+        self.point = Vec3::from(from.point).lerp(Vec3::from(to.point), t);
+        self.normal = turn * from_normal;
+    }
+
+    /// Puts the plane through the middle of `bounds`, facing along X.
+    pub fn reset(&mut self, bounds: Option<(Vec3, Vec3)>) {
+        self.point = bounds.map_or(Vec3::ZERO, |(min, max)| (min + max) * 0.5);
+        self.normal = Vec3::X;
+    }
+
+    /// Keeps the other half instead.
+    pub fn flip(&mut self) {
+        self.normal = -self.normal;
+    }
+
+    /// Whether a left press goes to the plane's handles rather than the camera.
+    pub fn is_active(&self) -> bool {
+        self.manipulator.is_active()
+    }
+
+    /// Drops the handles until the next `show`.
+    pub fn release(&mut self) {
+        self.manipulator.release();
+        self.start = None;
+    }
+
+    /// Outline of the plane, `size` across, for the overlay.
+    pub fn add_outline(&self, overlay: &mut Overlay, size: f32) {
+        let (u, v) = handles::plane_axes(self.normal.normalize_or_zero());
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(a, b)| self.point + (u * a + v * b) * size * 0.5);
+        for (i, corner) in corners.iter().enumerate() {
+            overlay.line(*corner, corners[(i + 1) % 4], PLANE_COLOR);
+        }
+        overlay.line(self.point, self.point + self.normal.normalize_or_zero() * size * 0.1, PLANE_COLOR);
+    }
+
+    /// Shows the handles while editing and applies drags to the plane.
+    pub fn show(&mut self, ctx: &egui::Context, camera: &Camera) {
+        if !(self.enabled && self.editing) {
+            self.release();
+            return;
+        }
+        // Handles stay put while one is dragged, so the rings turn about fixed axes
+        let normal = self.start.map_or(self.normal, |(_, normal)| normal).normalize_or_zero();
+        let (u, v) = handles::plane_axes(normal);
+        let handles = [
+            Handle::new(HandleShape::Arrow, normal, HANDLE_COLOR),
+            Handle::new(HandleShape::Ring, u, handles::AXIS_COLORS[0]),
+            Handle::new(HandleShape::Ring, v, handles::AXIS_COLORS[1]),
+        ];
+        let Some(drag) = self.manipulator.show(ctx, camera, self.point, &handles) else {
+            self.start = None;
+            return;
+        };
+        if drag.started || self.start.is_none() {
+            self.start = Some((self.point, self.normal));
+        }
+        let Some((point, normal)) = self.start else {
+            return;
+        };
+        match drag.motion {
+            Motion::Along(distance) => self.point = point + handles[drag.handle].axis * distance,
+            Motion::Rotate(angle) => self.normal = Quat::from_axis_angle(handles[drag.handle].axis, angle) * normal,
+            Motion::Scale(_) | Motion::Plane(_) => {}
+        }
+    }
+}
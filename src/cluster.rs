@@ -0,0 +1,124 @@
+use glam::Vec3;
+
+use crate::mesh::Mesh;
+
+/// Triangles per cluster; the last cluster of each object may be smaller.
+pub const CLUSTER_TRIANGLES: usize = 4096;
+/// Meshes with at least this many triangles are split into clusters on load.
+pub const MIN_CLUSTERED_TRIANGLES: usize = 1 << 20;
+
+/// A run of spatially close triangles within one object's draw range.
+#[derive(Debug, Clone, Copy)]
+pub struct Cluster {
+    pub object: usize,
+    pub start: u32,
+    pub count: u32,
+    pub bounds: (Vec3, Vec3),
+}
+
+/// Fixed-size clusters covering a mesh, so culling can skip parts of an
+/// object rather than only whole objects.
+pub struct Clusters {
+    // Sorted by object, then by index start
+    clusters: Vec<Cluster>,
+    // Original triangle for each triangle of the reordered index buffer
+    triangle_order: Vec<u32>,
+}
+
+impl Clusters {
+    /// Sorts the triangles of each draw range of `mesh` along a Z-order
+    /// curve through their centroids and cuts them into clusters of
+    /// `CLUSTER_TRIANGLES`. Rewrites `mesh.indices` in the new order.
+    pub fn build(mesh: &mut Mesh) -> Self {
+        let mut clusters = Vec::new();
+        let mut triangle_order: Vec<u32> = (0..mesh.triangle_count() as u32).collect();
+        let position = |index: u32| Vec3::from(mesh.vertices[index as usize].position);
+        for (object, range) in mesh.draw_ranges.iter().enumerate() {
+            let first = range.start as usize / 3;
+            let triangles = &mut triangle_order[first..first + range.count as usize / 3];
+            let centroid = |triangle: u32| {
+                let corners = &mesh.indices[triangle as usize * 3..][..3];
+                (position(corners[0]) + position(corners[1]) + position(corners[2])) / 3.0
+            };
+            let (min, max) = triangles.iter().fold((Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)), |(min, max), &triangle| {
+                let centroid = centroid(triangle);
+                (min.min(centroid), max.max(centroid))
+            });
+            let extent = (max - min).max(Vec3::splat(f32::EPSILON));
+            triangles.sort_by_cached_key(|&triangle| morton_code((centroid(triangle) - min) / extent));
+
+            for (chunk, triangles) in triangles.chunks(CLUSTER_TRIANGLES).enumerate() {
+                let bounds = triangles
+                    .iter()
+                    .flat_map(|&triangle| &mesh.indices[triangle as usize * 3..][..3])
+                    .fold((Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)), |(min, max), &index| {
+                        (min.min(position(index)), max.max(position(index)))
+                    });
+                clusters.push(Cluster {
+                    object,
+                    start: range.start + (chunk * CLUSTER_TRIANGLES * 3) as u32,
+                    count: triangles.len() as u32 * 3,
+                    bounds,
+                });
+            }
+        }
+        let clusters = Self { clusters, triangle_order };
+        clusters.reorder(&mut mesh.indices);
+        clusters
+    }
+
+    /// Puts the triangles of `indices`, in the mesh's original order, into
+    /// the clustered order; used for morph targets of a clustered mesh.
+    pub fn reorder(&self, indices: &mut Vec<u32>) {
+        if indices.len() != self.triangle_order.len() * 3 {
+            return;
+        }
+        *indices = self
+            .triangle_order
+            .iter()
+            .flat_map(|&triangle| indices[triangle as usize * 3..][..3].iter().copied())
+            .collect();
+    }
+
+    /// Grows each cluster's bounds to cover the same triangles in `mesh`, a
+    /// morph target with the clustered index order.
+    pub fn include_bounds(&mut self, mesh: &Mesh) {
+        for cluster in &mut self.clusters {
+            for &index in &mesh.indices[cluster.start as usize..(cluster.start + cluster.count) as usize] {
+                let position = Vec3::from(mesh.vertices[index as usize].position);
+                cluster.bounds = (cluster.bounds.0.min(position), cluster.bounds.1.max(position));
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.clusters.len()
+    }
+
+    pub fn get(&self, index: usize) -> &Cluster {
+        &self.clusters[index]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cluster> {
+        self.clusters.iter()
+    }
+
+    /// The clusters of `object` and the index of the first one.
+    pub fn of_object(&self, object: usize) -> (usize, &[Cluster]) {
+        let start = self.clusters.partition_point(|cluster| cluster.object < object);
+        let end = self.clusters.partition_point(|cluster| cluster.object <= object);
+        (start, &self.clusters[start..end])
+    }
+}
+
+// Interleaves 10 bits of each coordinate in 0..1
+fn morton_code(position: Vec3) -> u32 {
+    let spread = |value: f32| {
+        let mut bits = (value.clamp(0.0, 1.0) * 1023.0) as u32;
+        bits = (bits | (bits << 16)) & 0x0300_00ff;
+        bits = (bits | (bits << 8)) & 0x0300_f00f;
+        bits = (bits | (bits << 4)) & 0x030c_30c3;
+        (bits | (bits << 2)) & 0x0924_9249
+    };
+    spread(position.x) << 2 | spread(position.y) << 1 | spread(position.z)
+}
@@ -0,0 +1,156 @@
+use glam::{Mat3, Vec3};
+
+/// Color space the viewer's output is encoded in, on screen and in exported
+/// images. Both use the sRGB transfer curve; they differ in their primaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    #[default]
+    Srgb,
+    DisplayP3,
+}
+
+impl ColorSpace {
+    pub const ALL: [ColorSpace; 2] = [ColorSpace::Srgb, ColorSpace::DisplayP3];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "sRGB",
+            ColorSpace::DisplayP3 => "Display P3",
+        }
+    }
+
+    // Red, green and blue chromaticities; both have a D65 white point
+    fn primaries(self) -> [[f32; 2]; 3] {
+        match self {
+            ColorSpace::Srgb => [[0.64, 0.33], [0.30, 0.60], [0.15, 0.06]],
+            ColorSpace::DisplayP3 => [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060]],
+        }
+    }
+
+    /// ICC profile describing images in this color space, `None` for sRGB,
+    /// which PNG tags with its own chunk.
+    pub fn icc_profile(self) -> Option<Vec<u8>> {
+        match self {
+            ColorSpace::Srgb => None,
+            ColorSpace::DisplayP3 => Some(icc_profile("Display P3", self.primaries())),
+        }
+    }
+}
+
+const D65: [f32; 2] = [0.3127, 0.3290];
+// Illuminant of the ICC profile connection space
+const D50: Vec3 = Vec3::new(0.9642, 1.0, 0.8249);
+
+/// Whether the surface offers formats with more than 8 bits per channel,
+/// which platforms only expose for wide-gamut or HDR displays. winit can't
+/// tell the display's gamut itself, so this is the closest portable hint.
+pub fn is_wide_gamut_surface(formats: &[wgpu::TextureFormat]) -> bool {
+    formats
+        .iter()
+        .any(|format| matches!(format, wgpu::TextureFormat::Rgba16Float | wgpu::TextureFormat::Rgb10a2Unorm))
+}
+
+/// Converts linear colors with sRGB primaries, which scenes are lit in, to
+/// linear colors in `output`.
+pub fn gamut_conversion(output: ColorSpace) -> Mat3 {
+    rgb_to_xyz(output.primaries()).inverse() * rgb_to_xyz(ColorSpace::Srgb.primaries())
+}
+
+// Linear RGB with the given primaries and a D65 white to CIE XYZ
+fn rgb_to_xyz(primaries: [[f32; 2]; 3]) -> Mat3 {
+    let xyz = |[x, y]: [f32; 2]| Vec3::new(x / y, 1.0, (1.0 - x - y) / y);
+    let columns = Mat3::from_cols(xyz(primaries[0]), xyz(primaries[1]), xyz(primaries[2]));
+    // Scale the primaries so that RGB white lands on the white point
+    let scale = columns.inverse() * xyz(D65);
+    columns * Mat3::from_diagonal(scale)
+}
+
+// Bradford chromatic adaptation from D65 to the D50 connection space
+fn d65_to_d50() -> Mat3 {
+    let bradford = Mat3::from_cols_array(&[0.8951, -0.7502, 0.0389, 0.2664, 1.7135, -0.0685, -0.1614, 0.0367, 1.0296]);
+    let source = bradford * Vec3::new(D65[0] / D65[1], 1.0, (1.0 - D65[0] - D65[1]) / D65[1]);
+    let destination = bradford * D50;
+    bradford.inverse() * Mat3::from_diagonal(destination / source) * bradford
+}
+
+// ICC v4 display profile for an RGB space with a D65 white and the sRGB curve
+fn icc_profile(description: &str, primaries: [[f32; 2]; 3]) -> Vec<u8> {
+    let fixed = |value: f32| ((value * 65536.0).round() as i32).to_be_bytes();
+    let xyz_tag = |xyz: Vec3| {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        for value in xyz.to_array() {
+            tag.extend_from_slice(&fixed(value));
+        }
+        tag
+    };
+    let text_tag = |text: &str| {
+        let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        let mut tag = b"mluc\0\0\0\0".to_vec();
+        tag.extend_from_slice(&1u32.to_be_bytes());
+        tag.extend_from_slice(&12u32.to_be_bytes());
+        tag.extend_from_slice(b"enUS");
+        tag.extend_from_slice(&(utf16.len() as u32).to_be_bytes());
+        tag.extend_from_slice(&28u32.to_be_bytes());
+        tag.extend_from_slice(&utf16);
+        tag
+    };
+    // The sRGB curve as parametric function type 3
+    let mut curve = b"para\0\0\0\0\0\x03\0\0".to_vec();
+    for value in [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045] {
+        curve.extend_from_slice(&fixed(value));
+    }
+    let adaptation = d65_to_d50();
+    let mut chad = b"sf32\0\0\0\0".to_vec();
+    for row in 0..3 {
+        for value in adaptation.row(row).to_array() {
+            chad.extend_from_slice(&fixed(value));
+        }
+    }
+    let colorants = adaptation * rgb_to_xyz(primaries);
+
+    let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", text_tag(description)),
+        (b"cprt", text_tag("No copyright, use freely")),
+        (b"wtpt", xyz_tag(D50)),
+        (b"chad", chad),
+        (b"rXYZ", xyz_tag(colorants.x_axis)),
+        (b"gXYZ", xyz_tag(colorants.y_axis)),
+        (b"bXYZ", xyz_tag(colorants.z_axis)),
+        (b"rTRC", curve.clone()),
+        (b"gTRC", curve.clone()),
+        (b"bTRC", curve),
+    ];
+
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data = Vec::new();
+    let data_start = 128 + 4 + tags.len() * 12;
+    for (signature, tag) in &tags {
+        table.extend_from_slice(*signature);
+        table.extend_from_slice(&((data_start + data.len()) as u32).to_be_bytes());
+        table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        data.extend_from_slice(tag);
+        // Tags start on 4-byte boundaries
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+
+    let size = data_start + data.len();
+    let mut header = Vec::with_capacity(128);
+    header.extend_from_slice(&(size as u32).to_be_bytes());
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&0x0430_0000u32.to_be_bytes());
+    header.extend_from_slice(b"mntrRGB XYZ ");
+    // Creation date: 2024-01-01 00:00:00
+    for value in [2024u16, 1, 1, 0, 0, 0] {
+        header.extend_from_slice(&value.to_be_bytes());
+    }
+    header.extend_from_slice(b"acsp");
+    header.extend_from_slice(&[0; 24]);
+    // Perceptual rendering intent
+    header.extend_from_slice(&0u32.to_be_bytes());
+    for value in D50.to_array() {
+        header.extend_from_slice(&fixed(value));
+    }
+    header.resize(128, 0);
+
+    [header, table, data].concat()
+}
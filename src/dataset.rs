@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::aov::AovSettings;
+use crate::camera::CameraState;
+use crate::capture::save_png;
+use crate::light::{Light, LightKind};
+use crate::presentation::PresentationPreset;
+use crate::renderer::Renderer;
+
+#[derive(Debug, Clone)]
+pub struct DatasetOptions {
+    pub model: PathBuf,
+    pub output: PathBuf,
+    pub count: usize,
+    pub width: u32,
+    pub height: u32,
+    pub seed: u64,
+    pub randomize_lights: bool,
+    pub aov: AovSettings,
+    pub preset: Option<PresentationPreset>,
+    /// HDR environment lighting the model and shown behind it.
+    pub environment: Option<PathBuf>,
+}
+
+/// Per-frame sidecar written next to each image.
+#[derive(Debug, Serialize)]
+struct FrameMetadata {
+    image: String,
+    width: u32,
+    height: u32,
+    camera: CameraState,
+    view_matrix: [[f32; 4]; 4],
+    projection_matrix: [[f32; 4]; 4],
+    lights: Vec<Light>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment: Option<PathBuf>,
+}
+
+/// SplitMix64; keeps runs reproducible for a given seed without an extra dependency.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        let unit = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * unit
+    }
+}
+
+/// Renders `count` random viewpoints of a model headlessly for synthetic training data.
+pub fn run(options: &DatasetOptions) -> Result<()> {
+    let mut renderer = pollster::block_on(Renderer::new_headless(options.width, options.height))?;
+    renderer.load_mesh(&options.model)?;
+    renderer.flush_uploads();
+    renderer.set_aov_settings(options.aov);
+    if let Some(preset) = options.preset {
+        renderer.apply_presentation(preset);
+    }
+    if let Some(environment) = &options.environment {
+        renderer.load_environment(environment)?;
+    }
+
+    std::fs::create_dir_all(&options.output)
+        .with_context(|| format!("Failed to create output folder {:?}", options.output))?;
+
+    let (min, max) = renderer
+        .model_bounds()
+        .ok_or_else(|| anyhow::anyhow!("Model {:?} has no vertices", options.model))?;
+    let center = (min + max) * 0.5;
+    let radius = (max - min).length() * 0.5;
+
+    let mut camera = renderer.camera().clone();
+    camera.aspect_ratio = options.width as f32 / options.height as f32;
+    camera.auto_fit_to_model((min, max));
+    let base_distance = camera.distance;
+    let base_lights = renderer.lights().to_vec();
+
+    let mut rng = Rng(options.seed);
+    for index in 0..options.count {
+        camera.yaw = rng.range(-std::f32::consts::PI, std::f32::consts::PI);
+        camera.pitch = rng.range(-1.2, 1.2);
+        camera.distance = base_distance * rng.range(0.7, 1.4);
+        camera.update_position();
+
+        if options.randomize_lights {
+            let yaw = rng.range(-std::f32::consts::PI, std::f32::consts::PI);
+            let pitch = rng.range(-0.3, 1.4);
+            let offset = glam::Vec3::new(pitch.cos() * yaw.sin(), pitch.sin(), pitch.cos() * yaw.cos()) * radius * 4.0;
+            // Only the key light moves; any others stay as they were set up
+            let mut lights = base_lights.clone();
+            let key = lights.first().copied().unwrap_or_default();
+            lights.splice(
+                ..1.min(lights.len()),
+                [Light {
+                    kind: LightKind::Point,
+                    position: (center + offset).to_array(),
+                    color: [rng.range(0.8, 1.0), rng.range(0.8, 1.0), rng.range(0.8, 1.0)],
+                    intensity: rng.range(0.6, 1.4),
+                    ambient_strength: rng.range(0.1, 0.3),
+                    ..key
+                }],
+            );
+            renderer.set_lights(&lights);
+        }
+
+        let stem = format!("frame_{:05}", index);
+        let image = format!("{}.png", stem);
+        let pixels = renderer.render_to_image(&camera, options.width, options.height)?;
+        save_png(&options.output.join(&image), options.width, options.height, &pixels, renderer.color_space())?;
+
+        if options.aov.any() {
+            let aovs = renderer.render_aovs(&camera, options.width, options.height)?;
+            aovs.save(&options.output.join(&stem), &options.aov)?;
+        }
+
+        let metadata = FrameMetadata {
+            image,
+            width: options.width,
+            height: options.height,
+            camera: camera.state(),
+            view_matrix: camera.view_matrix().to_cols_array_2d(),
+            projection_matrix: camera.projection_matrix().to_cols_array_2d(),
+            lights: renderer.lights().to_vec(),
+            environment: renderer.environment_path().map(Path::to_path_buf),
+        };
+        let json_path = options.output.join(format!("{}.json", stem));
+        std::fs::write(&json_path, serde_json::to_string_pretty(&metadata)?)
+            .with_context(|| format!("Failed to write {:?}", json_path))?;
+
+        info!("Rendered {}/{}", index + 1, options.count);
+    }
+
+    info!("Dataset written to {:?}", options.output);
+    Ok(())
+}
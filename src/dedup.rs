@@ -0,0 +1,161 @@
+use glam::Vec3;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::mesh::{DrawRange, Mesh, Vertex};
+
+// Positions closer than this fraction of the model's diagonal count as equal
+const POSITION_TOLERANCE: f32 = 1e-6;
+// Same for normals, colors and texture coordinates, in their own units
+const ATTRIBUTE_TOLERANCE: f32 = 1e-4;
+
+/// A draw range whose triangles repeat an earlier range's, moved by `offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Duplicate {
+    pub source: usize,
+    pub offset: Vec3,
+}
+
+/// Finds the draw ranges that are moved copies of earlier ones: the same
+/// triangles in the same order, with the same normals, colors and texture
+/// coordinates, as kit-bashed scenes and instanced glTF nodes have. Rotated
+/// or scaled copies aren't recognized. Ranges are first grouped by a hash of
+/// their geometry, then compared vertex by vertex.
+pub fn find_duplicates(mesh: &Mesh) -> Vec<Option<Duplicate>> {
+    let tolerance = mesh.bounds().map_or(0.0, |(min, max)| (max - min).length() * POSITION_TOLERANCE).max(f32::MIN_POSITIVE);
+    let mut sources: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut duplicates = vec![None; mesh.draw_ranges.len()];
+    for (index, range) in mesh.draw_ranges.iter().enumerate() {
+        if range.count == 0 {
+            continue;
+        }
+        let candidates = sources.entry(geometry_hash(mesh, range, tolerance)).or_default();
+        let source = candidates
+            .iter()
+            .copied()
+            .find(|&candidate| same_geometry(mesh, &mesh.draw_ranges[candidate], range, tolerance));
+        match source {
+            Some(source) => {
+                let offset = anchor(mesh, range) - anchor(mesh, &mesh.draw_ranges[source]);
+                duplicates[index] = Some(Duplicate { source, offset });
+            }
+            None => candidates.push(index),
+        }
+    }
+    duplicates
+}
+
+fn range_indices<'a>(mesh: &'a Mesh, range: &DrawRange) -> &'a [u32] {
+    &mesh.indices[range.start as usize..(range.start + range.count) as usize]
+}
+
+// Position of the range's first vertex, which the others are measured from
+fn anchor(mesh: &Mesh, range: &DrawRange) -> Vec3 {
+    Vec3::from(mesh.vertices[mesh.indices[range.start as usize] as usize].position)
+}
+
+// Hashes the triangles as local vertex numbers in order of first use, and
+// each vertex once, relative to the anchor and snapped to the tolerances
+fn geometry_hash(mesh: &Mesh, range: &DrawRange, tolerance: f32) -> u64 {
+    let anchor = anchor(mesh, range);
+    let snap = |value: f32, step: f32| (value / step).round() as i64;
+    let mut hasher = DefaultHasher::new();
+    let mut local: HashMap<u32, u32> = HashMap::new();
+    for &index in range_indices(mesh, range) {
+        let next = local.len() as u32;
+        let number = *local.entry(index).or_insert(next);
+        number.hash(&mut hasher);
+        if number == next {
+            let vertex = &mesh.vertices[index as usize];
+            for value in (Vec3::from(vertex.position) - anchor).to_array() {
+                snap(value, tolerance).hash(&mut hasher);
+            }
+            for value in vertex.normal.iter().chain(&vertex.color).chain(&vertex.tex_coords) {
+                snap(*value, ATTRIBUTE_TOLERANCE).hash(&mut hasher);
+            }
+        }
+    }
+    hasher.finish()
+}
+
+fn same_geometry(mesh: &Mesh, a: &DrawRange, b: &DrawRange, tolerance: f32) -> bool {
+    if a.count != b.count {
+        return false;
+    }
+    let (anchor_a, anchor_b) = (anchor(mesh, a), anchor(mesh, b));
+    let close = |x: &[f32], y: &[f32], tolerance: f32| x.iter().zip(y).all(|(x, y)| (x - y).abs() <= tolerance);
+    let same_vertex = |x: &Vertex, y: &Vertex| {
+        (Vec3::from(x.position) - anchor_a).abs_diff_eq(Vec3::from(y.position) - anchor_b, tolerance)
+            && close(&x.normal, &y.normal, ATTRIBUTE_TOLERANCE)
+            && close(&x.color, &y.color, ATTRIBUTE_TOLERANCE)
+            && close(&x.tex_coords, &y.tex_coords, ATTRIBUTE_TOLERANCE)
+    };
+    // Vertices have to correspond one to one, not just look alike
+    let mut pairs: HashMap<u32, u32> = HashMap::new();
+    let mut used: HashMap<u32, u32> = HashMap::new();
+    range_indices(mesh, a).iter().zip(range_indices(mesh, b)).all(|(&x, &y)| {
+        if *pairs.entry(x).or_insert(y) != y || *used.entry(y).or_insert(x) != x {
+            return false;
+        }
+        same_vertex(&mesh.vertices[x as usize], &mesh.vertices[y as usize])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::MeshData;
+
+    const QUAD: [[f32; 3]; 4] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+
+    fn part(offset: [f32; 3], colors: [f32; 3]) -> MeshData {
+        MeshData {
+            positions: QUAD.iter().map(|position| [position[0] + offset[0], position[1] + offset[1], position[2] + offset[2]]).collect(),
+            triangles: vec![[0, 1, 2], [0, 2, 3]],
+            vertex_colors: Some(vec![colors; 4]),
+            ..Default::default()
+        }
+    }
+
+    fn duplicates(parts: Vec<MeshData>) -> Vec<Option<Duplicate>> {
+        let mut mesh = Mesh::new();
+        for part in parts {
+            mesh.append(part);
+        }
+        find_duplicates(&mesh)
+    }
+
+    #[test]
+    fn finds_translated_copies() {
+        let found = duplicates(vec![part([0.0; 3], [1.0; 3]), part([5.0, 0.0, 2.0], [1.0; 3]), part([0.0, -3.0, 0.0], [1.0; 3])]);
+        assert_eq!(found[0], None);
+        assert_eq!(found[1], Some(Duplicate { source: 0, offset: Vec3::new(5.0, 0.0, 2.0) }));
+        assert_eq!(found[2], Some(Duplicate { source: 0, offset: Vec3::new(0.0, -3.0, 0.0) }));
+    }
+
+    #[test]
+    fn keeps_near_misses_apart() {
+        let mut bent = part([5.0, 0.0, 0.0], [1.0; 3]);
+        bent.positions[2][2] += 0.01;
+        let recolored = part([10.0, 0.0, 0.0], [1.0, 0.99, 1.0]);
+        let found = duplicates(vec![part([0.0; 3], [1.0; 3]), bent, recolored, part([15.0, 0.0, 0.0], [1.0, 1.0 - 1e-6, 1.0])]);
+        assert_eq!(found[1], None);
+        assert_eq!(found[2], None);
+        // Within the attribute tolerance
+        assert_eq!(found[3].map(|duplicate| duplicate.source), Some(0));
+    }
+
+    #[test]
+    fn matches_renumbered_vertices_but_not_reordered_triangles() {
+        // The same triangles with the vertices stored in reverse
+        let mut renumbered = part([5.0, 0.0, 0.0], [1.0; 3]);
+        renumbered.positions.reverse();
+        renumbered.triangles = vec![[3, 2, 1], [3, 1, 0]];
+        let mut reordered = part([10.0, 0.0, 0.0], [1.0; 3]);
+        reordered.triangles.reverse();
+        let found = duplicates(vec![part([0.0; 3], [1.0; 3]), renumbered, reordered]);
+        assert_eq!(found[1].map(|duplicate| duplicate.source), Some(0));
+        assert_eq!(found[2], None);
+    }
+}
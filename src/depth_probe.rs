@@ -0,0 +1,223 @@
+use glam::Vec2;
+use std::sync::mpsc::{self, Receiver};
+
+use crate::shaders::create_shader_module;
+
+struct ProbeBuffers {
+    capacity: usize,
+    pixels: wgpu::Buffer,
+    depths: wgpu::Buffer,
+    readback: wgpu::Buffer,
+}
+
+impl ProbeBuffers {
+    fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        let buffer = |label, size: usize, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: size as u64,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        Self {
+            capacity,
+            pixels: buffer("Depth Probe Pixel Buffer", capacity * 8, wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST),
+            depths: buffer("Depth Probe Buffer", capacity * 4, wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC),
+            readback: buffer("Depth Probe Readback Buffer", capacity * 4, wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ),
+        }
+    }
+}
+
+struct Readback {
+    receiver: Receiver<Result<(), wgpu::BufferAsyncError>>,
+    count: usize,
+    near: f32,
+    far: f32,
+}
+
+/// Reads the scene depth at a few screen positions back to the CPU without
+/// stalling: a request is encoded with the frame, mapped after submission and
+/// picked up by `poll` a frame or two later. One request is in flight at a
+/// time.
+pub struct DepthProbes {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+    buffers: Option<ProbeBuffers>,
+    // Probe count and camera near/far of the request encoded this frame
+    encoded: Option<(usize, f32, f32)>,
+    readback: Option<Readback>,
+}
+
+impl DepthProbes {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Depth Probe Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let shader = create_shader_module(device, "Depth Probe Shader", include_str!("shaders/depth_probe.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Depth Probe Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Depth Probe Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            bind_group_layout,
+            pipeline,
+            buffers: None,
+            encoded: None,
+            readback: None,
+        }
+    }
+
+    /// Gathers the depth at `points` (normalized device coordinates) from
+    /// `depth_texture`, rendered with a camera spanning `near..far`. Returns
+    /// false, encoding nothing, while the previous request is still in flight.
+    pub fn encode(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        depth_texture: &wgpu::Texture,
+        points: &[Vec2],
+        (near, far): (f32, f32),
+    ) -> bool {
+        if self.readback.is_some() || self.encoded.is_some() || points.is_empty() {
+            return false;
+        }
+        if self.buffers.as_ref().is_none_or(|buffers| buffers.capacity < points.len()) {
+            self.buffers = Some(ProbeBuffers::new(device, points.len().next_power_of_two().max(16)));
+        }
+        let buffers = self.buffers.as_ref().unwrap();
+
+        let (width, height) = (depth_texture.width(), depth_texture.height());
+        let pixels: Vec<[u32; 2]> = points
+            .iter()
+            .map(|ndc| {
+                let x = ((ndc.x + 1.0) * 0.5 * width as f32) as u32;
+                let y = ((1.0 - ndc.y) * 0.5 * height as f32) as u32;
+                [x.min(width - 1), y.min(height - 1)]
+            })
+            .collect();
+        let pixel_bytes = (pixels.len() * 8) as u64;
+        let depth_bytes = (pixels.len() * 4) as u64;
+        queue.write_buffer(&buffers.pixels, 0, bytemuck::cast_slice(&pixels));
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Depth Probe Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth_texture.create_view(&wgpu::TextureViewDescriptor::default())),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &buffers.pixels,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(pixel_bytes),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &buffers.depths,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(depth_bytes),
+                    }),
+                },
+            ],
+        });
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Depth Probe Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups((pixels.len() as u32).div_ceil(64), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&buffers.depths, 0, &buffers.readback, 0, depth_bytes);
+        self.encoded = Some((points.len(), near, far));
+        true
+    }
+
+    /// Starts reading back this frame's request. Call after the encoder has
+    /// been submitted.
+    pub fn map(&mut self) {
+        let (Some((count, near, far)), Some(buffers)) = (self.encoded.take(), &self.buffers) else {
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        buffers.readback.slice(..(count * 4) as u64).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.readback = Some(Readback { receiver, count, near, far });
+    }
+
+    /// View-space distances at the probed points once the readback has
+    /// finished, infinite where nothing was drawn. Doesn't wait for the GPU.
+    pub fn poll(&mut self, device: &wgpu::Device) -> Option<Vec<f32>> {
+        self.readback.as_ref()?;
+        device.poll(wgpu::Maintain::Poll);
+        let result = self.readback.as_ref()?.receiver.try_recv().ok()?;
+        let readback = self.readback.take()?;
+        let buffers = self.buffers.as_ref()?;
+        result.ok()?;
+
+        let distances = {
+            let data = buffers.readback.slice(..(readback.count * 4) as u64).get_mapped_range();
+            bytemuck::cast_slice::<u8, f32>(&data)
+                .iter()
+                .map(|&depth| {
+                    if depth >= 1.0 {
+                        return f32::INFINITY;
+                    }
+                    // Back from the perspective depth buffer to view distance
+                    readback.near * readback.far / (readback.far - depth * (readback.far - readback.near))
+                })
+                .collect()
+        };
+        buffers.readback.unmap();
+        Some(distances)
+    }
+}
@@ -0,0 +1,100 @@
+use glam::Vec2;
+
+use crate::capture::CAPTURE_FORMAT;
+use crate::color::ColorSpace;
+use crate::postprocess::{ExposureSettings, PostProcess};
+
+/// Side of the square detail image, in pixels.
+pub const DETAIL_SIZE: u32 = 256;
+
+// Color and depth attachments of the detail view, with the egui texture showing them
+struct DetailTarget {
+    color_view: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+    post_process: PostProcess,
+    texture_id: egui::TextureId,
+}
+
+/// Picture-in-picture view magnifying the region around the cursor, seen
+/// from the same camera through a narrower field of view.
+pub struct DetailView {
+    pub enabled: bool,
+    pub magnification: f32,
+    // Cursor position in normalized device coordinates of the viewport it's over
+    pub cursor_ndc: Option<Vec2>,
+    // Viewport the cursor is over, 0 for the main one
+    pub viewport: usize,
+    target: Option<DetailTarget>,
+}
+
+impl DetailView {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            magnification: 4.0,
+            cursor_ndc: None,
+            viewport: 0,
+            target: None,
+        }
+    }
+
+    /// Creates the render targets and registers them with egui the first
+    /// time the view is shown.
+    pub fn ensure_target(&mut self, device: &wgpu::Device, egui_renderer: &mut egui_wgpu::Renderer, compute_shaders: bool) {
+        if self.target.is_some() {
+            return;
+        }
+        let size = wgpu::Extent3d {
+            width: DETAIL_SIZE,
+            height: DETAIL_SIZE,
+            depth_or_array_layers: 1,
+        };
+        let create_texture = |label, format, usage| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let color_texture = create_texture(
+            "Detail Color Texture",
+            CAPTURE_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let depth_texture = create_texture("Detail Depth Texture", wgpu::TextureFormat::Depth32Float, wgpu::TextureUsages::RENDER_ATTACHMENT);
+        let color_view = color_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let texture_id = egui_renderer.register_native_texture(device, &color_view, wgpu::FilterMode::Linear);
+        self.target = Some(DetailTarget {
+            depth_view: depth_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            color_view,
+            post_process: PostProcess::new(device, CAPTURE_FORMAT, DETAIL_SIZE, DETAIL_SIZE, compute_shaders),
+            texture_id,
+        });
+    }
+
+    /// Applies the main view's exposure settings and output color space to
+    /// the detail image.
+    pub fn set_output(&mut self, exposure: ExposureSettings, color_space: ColorSpace) {
+        if let Some(target) = &mut self.target {
+            target.post_process.exposure = exposure;
+            target.post_process.color_space = color_space;
+        }
+    }
+
+    /// The post process whose HDR target the scene is drawn into, the depth
+    /// attachment, and the color view it resolves into for egui.
+    pub fn attachments(&self) -> Option<(&PostProcess, &wgpu::TextureView, &wgpu::TextureView)> {
+        self.target
+            .as_ref()
+            .map(|target| (&target.post_process, &target.depth_view, &target.color_view))
+    }
+
+    pub fn texture_id(&self) -> Option<egui::TextureId> {
+        self.target.as_ref().map(|target| target.texture_id)
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{bail, ensure, Context, Result};
+use draco_oxide_core::attribute::{Attribute, AttributeType, ComponentDataType};
+use draco_oxide_core::mesh::Mesh;
+use std::path::Path;
+
+use crate::mesh::MeshData;
+
+/// Loads a Draco-compressed `.drc` mesh with its normals and vertex colors.
+pub fn load(path: &Path) -> Result<MeshData> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    Ok(MeshData {
+        name: path.file_stem().map_or_else(|| "Draco mesh".to_string(), |stem| stem.to_string_lossy().into_owned()),
+        ..parse(&bytes)?
+    })
+}
+
+fn parse(bytes: &[u8]) -> Result<MeshData> {
+    let mesh = decode(bytes)?;
+    let attribute = |kind| mesh.get_attributes().iter().find(|attribute| attribute.get_attribute_type() == kind);
+
+    let positions = vectors(attribute(AttributeType::Position).context("Draco mesh has no positions")?)?
+        .context("Draco positions have fewer than three components")?;
+    let triangles: Vec<[u32; 3]> = triangles(&mesh).chunks_exact(3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect();
+    ensure!(
+        triangles.iter().flatten().all(|&index| (index as usize) < positions.len()),
+        "Draco face references a point past the {} decoded",
+        positions.len()
+    );
+    let normals = attribute(AttributeType::Normal).map(vectors).transpose()?.flatten();
+    let vertex_colors = attribute(AttributeType::Color).map(vectors).transpose()?.flatten();
+
+    Ok(MeshData {
+        positions,
+        triangles,
+        normals,
+        vertex_colors,
+        ..Default::default()
+    })
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Mesh> {
+    draco_oxide_decoder::decode_mesh(bytes).map_err(|e| anyhow::anyhow!("Invalid Draco mesh: {:?}", e))
+}
+
+/// Point indices of the mesh's triangles, three per face.
+pub fn triangles(mesh: &Mesh) -> Vec<u32> {
+    mesh.get_faces().iter().flatten().map(|&point| usize::from(point) as u32).collect()
+}
+
+// The first three components of every point, or `None` for an attribute
+// with fewer; integers are read as normalized
+fn vectors(attribute: &Attribute) -> Result<Option<Vec<[f32; 3]>>> {
+    let components = attribute.get_num_components();
+    if components < 3 {
+        return Ok(None);
+    }
+    let values = values(attribute, true)?;
+    Ok(Some(values.chunks_exact(components).map(|value| [value[0] as f32, value[1] as f32, value[2] as f32]).collect()))
+}
+
+/// Components of a decoded attribute for every point, normalized integers
+/// mapped to 0..1 / -1..1.
+pub fn values(attribute: &Attribute, normalized: bool) -> Result<Vec<f64>> {
+    let components = attribute.get_num_components();
+    let kind = attribute.get_component_type();
+    let size = kind.size();
+    let max = match kind {
+        ComponentDataType::I8 => i8::MAX as f64,
+        ComponentDataType::U8 => u8::MAX as f64,
+        ComponentDataType::I16 => i16::MAX as f64,
+        ComponentDataType::U16 => u16::MAX as f64,
+        ComponentDataType::I32 => i32::MAX as f64,
+        ComponentDataType::U32 => u32::MAX as f64,
+        ComponentDataType::F32 | ComponentDataType::F64 => 1.0,
+        other => bail!("Unsupported Draco component type {:?}", other),
+    };
+    let data = attribute.get_data_as_bytes();
+    let point_map = attribute.point_map_as_slice();
+
+    let mut values = Vec::with_capacity(attribute.len() * components);
+    for point in 0..attribute.len() {
+        let value = point_map.map_or(point, |map| usize::from(map[point]));
+        for component in 0..components {
+            let offset = (value * components + component) * size;
+            let bytes = data.get(offset..offset + size).context("Draco attribute value is out of range")?;
+            let value = match kind {
+                ComponentDataType::I8 => bytes[0] as i8 as f64,
+                ComponentDataType::U8 => bytes[0] as f64,
+                ComponentDataType::I16 => i16::from_ne_bytes([bytes[0], bytes[1]]) as f64,
+                ComponentDataType::U16 => u16::from_ne_bytes([bytes[0], bytes[1]]) as f64,
+                ComponentDataType::I32 => i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                ComponentDataType::U32 => u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                ComponentDataType::F32 => f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                _ => f64::from_ne_bytes(bytes.try_into()?),
+            };
+            values.push(if normalized && !kind.is_float() { (value / max).max(-1.0) } else { value });
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tetrahedron with corners at the origin and on each axis, encoded with
+    // default Draco settings (11-bit positions)
+    const TETRAHEDRON: [u8; 96] = [
+        0x44, 0x52, 0x41, 0x43, 0x4f, 0x02, 0x02, 0x01, 0x01, 0x00, 0x00, 0x02, 0x04, 0x04, 0x00, 0x03,
+        0x00, 0x00, 0x01, 0x01, 0x10, 0x01, 0x01, 0x01, 0x04, 0x0b, 0x01, 0x40, 0x01, 0x00, 0x01, 0x01,
+        0x01, 0x01, 0x01, 0x40, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xff, 0x00, 0x00, 0x01, 0x00,
+        0x09, 0x03, 0x00, 0x00, 0x02, 0x01, 0x01, 0x01, 0x01, 0x02, 0x03, 0x01, 0x20, 0x01, 0x10, 0x01,
+        0x10, 0x05, 0x00, 0x70, 0xf0, 0xe2, 0x80, 0x00, 0x00, 0x00, 0x00, 0xff, 0x07, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x3f, 0x0b,
+    ];
+
+    #[test]
+    fn decodes_tetrahedron() {
+        let data = parse(&TETRAHEDRON).unwrap();
+        assert_eq!(data.triangles.len(), 4);
+        let mut corners = data.positions.iter().map(|position| position.map(|value| (value * 1000.0).round() / 1000.0)).collect::<Vec<_>>();
+        corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(corners, [[0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]]);
+        assert!(data.normals.is_none() && data.vertex_colors.is_none());
+    }
+
+    #[test]
+    fn rejects_other_data() {
+        assert!(parse(b"DRACO but truncated").is_err());
+    }
+}
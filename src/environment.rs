@@ -0,0 +1,611 @@
+use anyhow::{bail, ensure, Context, Result};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::camera::Camera;
+use crate::postprocess::HDR_FORMAT;
+use crate::shaders::create_shader_module;
+
+const CUBE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const IRRADIANCE_SIZE: u32 = 32;
+const SPECULAR_SIZE: u32 = 128;
+/// Roughness 0 to 1 in even steps, one per mip level of the specular cube.
+pub const SPECULAR_LEVELS: u32 = 6;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct EnvironmentUniforms {
+    inverse_view_projection: [[f32; 4]; 4],
+    rotation: f32,
+    intensity: f32,
+    background_lod: f32,
+    roughness: f32,
+    face: u32,
+    source_levels: f32,
+    source_width: f32,
+    _pad: f32,
+}
+
+/// How a loaded environment lights and backs the scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvironmentSettings {
+    pub intensity: f32,
+    /// Degrees about the world Y axis.
+    pub rotation: f32,
+    /// Draws the environment behind the model instead of the background color.
+    pub background: bool,
+    /// 0 shows the background sharp, 1 as blurred as its smallest mip level.
+    pub background_blur: f32,
+}
+
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            rotation: 0.0,
+            background: true,
+            background_blur: 0.0,
+        }
+    }
+}
+
+/// A Radiance HDR image as linear RGB floats, top row first.
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 3]>,
+}
+
+/// Reads a Radiance `.hdr` (RGBE) image, with or without run-length encoding.
+pub fn load_hdr(path: &Path) -> Result<HdrImage> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    decode_hdr(&bytes).with_context(|| format!("Failed to decode {:?}", path))
+}
+
+pub fn decode_hdr(bytes: &[u8]) -> Result<HdrImage> {
+    let mut lines = bytes.split(|&byte| byte == b'\n');
+    let mut offset = 0;
+    let mut next_line = || {
+        let line = lines.next()?;
+        offset += line.len() + 1;
+        Some(String::from_utf8_lossy(line).trim().to_string())
+    };
+    let magic = next_line().context("Empty file")?;
+    if !magic.starts_with("#?") {
+        bail!("Not a Radiance HDR file");
+    }
+    loop {
+        let line = next_line().context("Missing resolution")?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                bail!("Unsupported pixel format {}", format);
+            }
+        }
+    }
+    let resolution = next_line().context("Missing resolution")?;
+    let (flip, height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        [y @ ("-Y" | "+Y"), height, "+X", width] => (y == "+Y", height.parse::<u32>()?, width.parse::<u32>()?),
+        _ => bail!("Unsupported orientation {:?}", resolution),
+    };
+    if width == 0 || height == 0 {
+        bail!("Image is empty");
+    }
+
+    let mut data = bytes.get(offset..).context("Missing pixel data")?;
+    let mut rgbe = vec![[0u8; 4]; width as usize * height as usize];
+    for row in rgbe.chunks_exact_mut(width as usize) {
+        read_scanline(&mut data, row)?;
+    }
+    if flip {
+        rgbe.reverse();
+        for row in rgbe.chunks_exact_mut(width as usize) {
+            row.reverse();
+        }
+    }
+    let pixels = rgbe
+        .iter()
+        .map(|&[r, g, b, e]| match e {
+            0 => [0.0; 3],
+            _ => {
+                let scale = 2f32.powi(e as i32 - (128 + 8));
+                [r as f32 * scale, g as f32 * scale, b as f32 * scale]
+            }
+        })
+        .collect();
+    Ok(HdrImage { width, height, pixels })
+}
+
+fn take<'a>(data: &mut &'a [u8], count: usize) -> Result<&'a [u8]> {
+    let (head, tail) = data.split_at_checked(count).context("Pixel data ends early")?;
+    *data = tail;
+    Ok(head)
+}
+
+fn read_scanline(data: &mut &[u8], row: &mut [[u8; 4]]) -> Result<()> {
+    let width = row.len();
+    let peek = data.get(..4).context("Pixel data ends early")?;
+    let rle = (8..0x8000).contains(&width) && peek[0] == 2 && peek[1] == 2 && peek[2] & 0x80 == 0;
+    if !rle {
+        // Flat pixels; the old run-length scheme repeats the previous pixel
+        // on 1, 1, 1 markers
+        let mut x = 0;
+        let mut shift = 0;
+        while x < width {
+            let pixel: [u8; 4] = take(data, 4)?.try_into()?;
+            if pixel[..3] == [1, 1, 1] && x > 0 {
+                // Consecutive markers build up ever larger counts
+                let count = (pixel[3] as usize).checked_shl(shift).context("Repeat count overflows")?;
+                ensure!(count <= width - x, "Run past the end of a scanline");
+                let previous = row[x - 1];
+                for slot in row.iter_mut().skip(x).take(count) {
+                    *slot = previous;
+                }
+                x += count;
+                shift += 8;
+            } else {
+                row[x] = pixel;
+                x += 1;
+                shift = 0;
+            }
+        }
+        return Ok(());
+    }
+    let header = take(data, 4)?;
+    if ((header[2] as usize) << 8 | header[3] as usize) != width {
+        bail!("Scanline width mismatch");
+    }
+    // Each channel run-length encoded on its own
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = take(data, 1)?[0] as usize;
+            if count > 128 {
+                let count = count - 128;
+                let value = take(data, 1)?[0];
+                for pixel in row.get_mut(x..x + count).context("Run past the end of a scanline")? {
+                    pixel[channel] = value;
+                }
+                x += count;
+            } else {
+                let values = take(data, count)?;
+                for (pixel, &value) in row.get_mut(x..x + count).context("Run past the end of a scanline")?.iter_mut().zip(values) {
+                    pixel[channel] = value;
+                }
+                x += count.max(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Box-filtered mip levels of an RGB float image, largest first
+fn mip_chain(image: HdrImage) -> Vec<(u32, u32, Vec<[f32; 3]>)> {
+    let mut levels = vec![(image.width, image.height, image.pixels)];
+    while let Some(&(width, height, ref pixels)) = levels.last().filter(|(width, height, _)| *width > 1 || *height > 1) {
+        let (half_width, half_height) = ((width / 2).max(1), (height / 2).max(1));
+        let mut half = Vec::with_capacity((half_width * half_height) as usize);
+        for y in 0..half_height {
+            for x in 0..half_width {
+                let mut sum = [0.0; 3];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let texel = pixels[((y * 2 + dy).min(height - 1) * width + (x * 2 + dx).min(width - 1)) as usize];
+                    for channel in 0..3 {
+                        sum[channel] += texel[channel] * 0.25;
+                    }
+                }
+                half.push(sum);
+            }
+        }
+        levels.push((half_width, half_height, half));
+    }
+    levels
+}
+
+/// An HDR environment around the scene: drawn as the background and
+/// prefiltered into an irradiance cube for diffuse lighting and a specular
+/// cube, one roughness per mip level, for reflections. Without a loaded
+/// image both cubes are black and nothing is drawn.
+pub struct Environment {
+    path: Option<PathBuf>,
+    pub settings: EnvironmentSettings,
+    source: wgpu::Texture,
+    irradiance: wgpu::Texture,
+    specular: wgpu::Texture,
+    irradiance_view: wgpu::TextureView,
+    specular_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    source_sampler: wgpu::Sampler,
+    uniform_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    skybox_pipeline: wgpu::RenderPipeline,
+    irradiance_pipeline: wgpu::RenderPipeline,
+    specular_pipeline: wgpu::RenderPipeline,
+}
+
+impl Environment {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Environment Uniform Buffer"),
+            size: std::mem::size_of::<EnvironmentUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Environment Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        // Longitude wraps around, latitude stops at the poles
+        let source_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Environment Source Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Environment Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = create_shader_module(device, "Environment Shader", include_str!("shaders/environment.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Environment Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = |label, entry_point, format, depth_stencil| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        // Drawn first, behind everything, without touching the depth buffer
+        let skybox_depth = wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        };
+        let skybox_pipeline = pipeline("Skybox Pipeline", "fs_skybox", HDR_FORMAT, Some(skybox_depth));
+        let irradiance_pipeline = pipeline("Irradiance Pipeline", "fs_irradiance", CUBE_FORMAT, None);
+        let specular_pipeline = pipeline("Specular Pipeline", "fs_specular", CUBE_FORMAT, None);
+
+        // Black stand-ins until an image is loaded
+        let source = create_texture(device, "Environment Source", 1, 1, 1, 1, wgpu::TextureFormat::Rgba16Float);
+        let irradiance = create_texture(device, "Irradiance Cube", 1, 1, 6, 1, CUBE_FORMAT);
+        let specular = create_texture(device, "Specular Cube", 1, 1, 6, SPECULAR_LEVELS, CUBE_FORMAT);
+        let bind_group = create_bind_group(device, &bind_group_layout, &source, &source_sampler, &uniform_buffer);
+        Self {
+            path: None,
+            settings: EnvironmentSettings::default(),
+            irradiance_view: cube_view(&irradiance),
+            specular_view: cube_view(&specular),
+            source,
+            irradiance,
+            specular,
+            sampler,
+            source_sampler,
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+            skybox_pipeline,
+            irradiance_pipeline,
+            specular_pipeline,
+        }
+    }
+
+    /// The loaded image, `None` without an environment.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Loads the equirectangular image at `path` and prefilters it. The cube
+    /// views change, so bind groups sampling them have to be recreated.
+    pub fn load(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, path: &Path) -> Result<()> {
+        let image = load_hdr(path)?;
+        info!("Loaded environment {:?} ({}x{})", path, image.width, image.height);
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        let mut levels = mip_chain(image);
+        // Levels past the GPU's limit are dropped, as textures are downscaled
+        while levels.len() > 1 && levels[0].0.max(levels[0].1) > max_dimension {
+            levels.remove(0);
+        }
+        let (width, height, _) = levels[0];
+        let source = create_texture(
+            device,
+            "Environment Source",
+            width,
+            height,
+            1,
+            levels.len() as u32,
+            wgpu::TextureFormat::Rgba16Float,
+        );
+        for (level, (level_width, level_height, pixels)) in levels.iter().enumerate() {
+            let texels: Vec<u16> = pixels
+                .iter()
+                .flat_map(|&[r, g, b]| [r, g, b, 1.0])
+                .map(|value| half::f16::from_f32(value).to_bits())
+                .collect();
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &source,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytemuck::cast_slice(&texels),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(level_width * 8),
+                    rows_per_image: Some(*level_height),
+                },
+                wgpu::Extent3d {
+                    width: *level_width,
+                    height: *level_height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        self.source = source;
+        self.bind_group = create_bind_group(device, &self.bind_group_layout, &self.source, &self.source_sampler, &self.uniform_buffer);
+        self.irradiance = create_texture(device, "Irradiance Cube", IRRADIANCE_SIZE, IRRADIANCE_SIZE, 6, 1, CUBE_FORMAT);
+        self.specular = create_texture(device, "Specular Cube", SPECULAR_SIZE, SPECULAR_SIZE, 6, SPECULAR_LEVELS, CUBE_FORMAT);
+        self.prefilter(device, queue, width, levels.len() as u32);
+        self.irradiance_view = cube_view(&self.irradiance);
+        self.specular_view = cube_view(&self.specular);
+        self.path = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Drops the loaded image, leaving the scene to its lights.
+    pub fn clear(&mut self, device: &wgpu::Device) {
+        *self = Self {
+            settings: self.settings,
+            ..Self::new(device)
+        };
+    }
+
+    // Renders every face of the irradiance cube and every face and level of
+    // the specular cube; one submit each, as they differ only in uniforms
+    fn prefilter(&self, device: &wgpu::Device, queue: &wgpu::Queue, source_width: u32, source_levels: u32) {
+        let passes = (0..6)
+            .map(|face| (&self.irradiance_pipeline, &self.irradiance, face, 0, 0.0))
+            .chain((0..SPECULAR_LEVELS).flat_map(|level| {
+                let roughness = level as f32 / (SPECULAR_LEVELS - 1) as f32;
+                (0..6).map(move |face| (&self.specular_pipeline, &self.specular, face, level, roughness))
+            }));
+        for (pipeline, texture, face, level, roughness) in passes {
+            let uniforms = EnvironmentUniforms {
+                roughness,
+                face,
+                source_levels: source_levels as f32,
+                source_width: source_width as f32,
+                ..bytemuck::Zeroable::zeroed()
+            };
+            queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+            let view = texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_mip_level: level,
+                mip_level_count: Some(1),
+                base_array_layer: face,
+                array_layer_count: Some(1),
+                ..Default::default()
+            });
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Environment Prefilter Encoder"),
+            });
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Environment Prefilter Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(0, &self.bind_group, &[]);
+                render_pass.draw(0..3, 0..1);
+            }
+            queue.submit(std::iter::once(encoder.finish()));
+        }
+    }
+
+    pub fn irradiance_view(&self) -> &wgpu::TextureView {
+        &self.irradiance_view
+    }
+
+    pub fn specular_view(&self) -> &wgpu::TextureView {
+        &self.specular_view
+    }
+
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+
+    /// Intensity the scene is lit with, 0 without an environment.
+    pub fn lighting_intensity(&self) -> f32 {
+        if self.is_loaded() {
+            self.settings.intensity
+        } else {
+            0.0
+        }
+    }
+
+    pub fn shows_background(&self) -> bool {
+        self.is_loaded() && self.settings.background
+    }
+
+    /// Points the background at `camera`'s view directions.
+    pub fn write_uniforms(&self, queue: &wgpu::Queue, camera: &Camera) {
+        let mut view = camera.view_matrix();
+        view.w_axis = glam::Vec4::W;
+        let levels = self.source.mip_level_count() as f32;
+        let uniforms = EnvironmentUniforms {
+            inverse_view_projection: (camera.projection_matrix() * view).inverse().to_cols_array_2d(),
+            rotation: self.settings.rotation.to_radians(),
+            intensity: self.settings.intensity,
+            background_lod: self.settings.background_blur * (levels - 1.0),
+            ..bytemuck::Zeroable::zeroed()
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    pub fn draw_background<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.skybox_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    label: &str,
+    width: u32,
+    height: u32,
+    layers: u32,
+    mip_level_count: u32,
+    format: wgpu::TextureFormat,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        },
+        mip_level_count: mip_level_count.min(width.max(height).ilog2() + 1),
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    })
+}
+
+fn cube_view(texture: &wgpu::Texture) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    source: &wgpu::Texture,
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    let view = source.create_view(&wgpu::TextureViewDescriptor::default());
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Environment Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_pixels_with_old_run_length_markers() {
+        // A pixel, then repeated 2 times and 1 << 8 more times
+        let mut data: &[u8] = &[9, 8, 7, 130, 1, 1, 1, 2, 1, 1, 1, 1];
+        let mut row = [[0; 4]; 259];
+        read_scanline(&mut data, &mut row).unwrap();
+        assert!(row.iter().all(|&pixel| pixel == [9, 8, 7, 130]));
+    }
+
+    #[test]
+    fn rejects_runs_past_the_scanline() {
+        let mut data: &[u8] = &[9, 8, 7, 130, 1, 1, 1, 4];
+        assert!(read_scanline(&mut data, &mut [[0; 4]; 4]).is_err());
+
+        // Enough consecutive markers to shift the count out of range
+        let mut data = [9, 8, 7, 130].to_vec();
+        for _ in 0..10 {
+            data.extend([1, 1, 1, 0]);
+        }
+        assert!(read_scanline(&mut data.as_slice(), &mut [[0; 4]; 4]).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use glam::{EulerRot, Mat4, Quat, Vec3};
+
+use crate::camera::Camera;
+use crate::handles::{Handle, HandleShape, Manipulator, Motion};
+use crate::scene::SceneNode;
+
+/// What dragging the gizmo's handles does to the selected object, cycled
+/// with G.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoMode {
+    Off,
+    Move,
+    Rotate,
+    Scale,
+}
+
+impl GizmoMode {
+    pub const ALL: [GizmoMode; 4] = [GizmoMode::Off, GizmoMode::Move, GizmoMode::Rotate, GizmoMode::Scale];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GizmoMode::Off => "Off",
+            GizmoMode::Move => "Move",
+            GizmoMode::Rotate => "Rotate",
+            GizmoMode::Scale => "Scale",
+        }
+    }
+
+    pub fn next(self) -> GizmoMode {
+        match self {
+            GizmoMode::Off => GizmoMode::Move,
+            GizmoMode::Move => GizmoMode::Rotate,
+            GizmoMode::Rotate => GizmoMode::Scale,
+            GizmoMode::Scale => GizmoMode::Off,
+        }
+    }
+}
+
+// The object's transform when a handle was grabbed
+struct Start {
+    translation: Vec3,
+    rotation: Quat,
+    scale: f32,
+}
+
+/// Handles for the selected object along the world axes: arrows and plane
+/// squares to move it, rings to rotate it about its center and boxes to
+/// scale it. Drags are written to the object's transform, relative to its
+/// parent.
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    manipulator: Manipulator,
+    start: Option<Start>,
+}
+
+impl Gizmo {
+    pub fn new() -> Self {
+        Self {
+            mode: GizmoMode::Off,
+            manipulator: Manipulator::new(),
+            start: None,
+        }
+    }
+
+    /// Whether a left press goes to the gizmo rather than the camera.
+    pub fn is_active(&self) -> bool {
+        self.manipulator.is_active()
+    }
+
+    /// Hides the handles until the next `show`.
+    pub fn release(&mut self) {
+        self.manipulator.release();
+        self.start = None;
+    }
+
+    /// Paints the handles around `node`, whose world transform is `world` and
+    /// its parent's `parent`, and applies any drag to its transform.
+    pub fn show(&mut self, ctx: &egui::Context, camera: &Camera, node: &mut SceneNode, world: Mat4, parent: Mat4) {
+        let handles: Vec<Handle> = match self.mode {
+            GizmoMode::Off => {
+                self.release();
+                return;
+            }
+            GizmoMode::Move => Handle::world_axes(HandleShape::Arrow).into_iter().chain(Handle::world_axes(HandleShape::Plane)).collect(),
+            GizmoMode::Rotate => Handle::world_axes(HandleShape::Ring).to_vec(),
+            GizmoMode::Scale => Handle::world_axes(HandleShape::Scale).to_vec(),
+        };
+        let center = world.transform_point3(node.pivot);
+        let Some(drag) = self.manipulator.show(ctx, camera, center, &handles) else {
+            self.start = None;
+            return;
+        };
+        if drag.started || self.start.is_none() {
+            let [x, y, z] = node.rotation.to_array().map(f32::to_radians);
+            self.start = Some(Start {
+                translation: node.translation,
+                rotation: Quat::from_euler(EulerRot::XYZ, x, y, z),
+                scale: node.scale,
+            });
+        }
+        let Some(start) = &self.start else {
+            return;
+        };
+        let to_parent = parent.inverse();
+        let axis = handles[drag.handle].axis;
+        match drag.motion {
+            Motion::Along(distance) => node.translation = start.translation + to_parent.transform_vector3(axis * distance),
+            Motion::Plane(offset) => node.translation = start.translation + to_parent.transform_vector3(offset),
+            Motion::Scale(factor) => node.scale = (start.scale * factor).clamp(0.001, 1000.0),
+            Motion::Rotate(angle) => {
+                let local_axis = to_parent.transform_vector3(axis).normalize_or_zero();
+                let rotation = Quat::from_axis_angle(local_axis, angle) * start.rotation;
+                let (x, y, z) = rotation.to_euler(EulerRot::XYZ);
+                node.rotation = Vec3::from_array([x, y, z].map(f32::to_degrees));
+            }
+        }
+    }
+}
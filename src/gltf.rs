@@ -0,0 +1,749 @@
+use anyhow::{bail, ensure, Context, Result};
+use glam::{Mat4, Quat, Vec3};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::mesh::MeshData;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+// Compression extensions that need a decoder this viewer doesn't have. Files
+// that only list them as used carry uncompressed fallback data.
+const UNSUPPORTED_EXTENSIONS: &[(&str, &str)] = &[
+    ("EXT_meshopt_compression", "meshopt compression"),
+    #[cfg(not(feature = "draco"))]
+    ("KHR_draco_mesh_compression", "Draco compression without the `draco` feature"),
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Document {
+    extensions_required: Vec<String>,
+    scene: Option<usize>,
+    scenes: Vec<SceneDef>,
+    nodes: Vec<Node>,
+    meshes: Vec<MeshDef>,
+    accessors: Vec<Accessor>,
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+    materials: Vec<MaterialDef>,
+    textures: Vec<TextureDef>,
+    images: Vec<Image>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SceneDef {
+    nodes: Vec<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Node {
+    name: Option<String>,
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MeshDef {
+    name: Option<String>,
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    mode: Option<u32>,
+    extensions: PrimitiveExtensions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PrimitiveExtensions {
+    #[serde(rename = "KHR_draco_mesh_compression")]
+    draco: Option<DracoExtension>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+#[cfg_attr(not(feature = "draco"), allow(dead_code))]
+struct DracoExtension {
+    buffer_view: usize,
+    // Draco attribute ids by glTF semantic
+    attributes: HashMap<String, usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Accessor {
+    buffer_view: Option<usize>,
+    byte_offset: usize,
+    component_type: u32,
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct BufferView {
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Buffer {
+    uri: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct MaterialDef {
+    name: Option<String>,
+    pbr_metallic_roughness: Pbr,
+    normal_texture: Option<TextureRef>,
+    occlusion_texture: Option<TextureRef>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Pbr {
+    base_color_factor: Option<[f32; 4]>,
+    base_color_texture: Option<TextureRef>,
+    metallic_factor: Option<f32>,
+    roughness_factor: Option<f32>,
+    metallic_roughness_texture: Option<TextureRef>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TextureRef {
+    index: usize,
+    // Strength of normal textures
+    scale: Option<f32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TextureDef {
+    source: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Image {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+/// A glTF scene as parts for `Mesh`: one part per node, nested like the
+/// nodes and placed by their transforms, plus materials and the images
+/// stored in the file.
+pub struct GltfModel {
+    pub parts: Vec<MeshData>,
+    pub materials: Vec<tobj::Material>,
+    pub textures: HashMap<String, Vec<u8>>,
+}
+
+/// Loads a `.gltf` (with external or data URI buffers) or binary `.glb` file.
+pub fn load(path: &Path) -> Result<GltfModel> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    parse(&bytes, path.parent().unwrap_or(Path::new(".")))
+}
+
+// `base_dir` resolves the relative URIs of external buffers and images
+fn parse(bytes: &[u8], base_dir: &Path) -> Result<GltfModel> {
+    let (json, bin) = if bytes.starts_with(GLB_MAGIC) { split_glb(bytes)? } else { (bytes, None) };
+    let document: Document = serde_json::from_slice(json).context("Invalid glTF JSON")?;
+
+    for (extension, description) in UNSUPPORTED_EXTENSIONS {
+        if document.extensions_required.iter().any(|name| name == extension) {
+            bail!("The model uses {} ({}), which isn't supported; re-export it without compression", description, extension);
+        }
+    }
+
+    let buffers = document
+        .buffers
+        .iter()
+        .enumerate()
+        .map(|(i, buffer)| match &buffer.uri {
+            Some(uri) => read_uri(base_dir, uri),
+            None if i == 0 => bin.map(<[u8]>::to_vec).context("GLB has no binary chunk"),
+            None => bail!("Buffer {} has no data", i),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut textures = HashMap::new();
+    let materials = document
+        .materials
+        .iter()
+        .enumerate()
+        .map(|(i, material)| {
+            let mut texture = |texture: &Option<TextureRef>| match texture.as_ref().and_then(|texture| document.textures.get(texture.index)?.source) {
+                Some(image) => image_texture(&document, &buffers, base_dir, image, &mut textures).map(Some),
+                None => Ok(None),
+            };
+            let pbr = &material.pbr_metallic_roughness;
+            let maps = MaterialMaps {
+                base_color: texture(&pbr.base_color_texture)?,
+                metallic_roughness: texture(&pbr.metallic_roughness_texture)?,
+                normal: texture(&material.normal_texture)?,
+                occlusion: texture(&material.occlusion_texture)?,
+            };
+            Ok(convert_material(material, i, maps))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let roots = match document.scenes.get(document.scene.unwrap_or(0)) {
+        Some(scene) => scene.nodes.clone(),
+        // Without scenes every node that isn't a child is a root
+        None => (0..document.nodes.len()).filter(|&node| !document.nodes.iter().any(|parent| parent.children.contains(&node))).collect(),
+    };
+    let mut parts: Vec<MeshData> = Vec::new();
+    let mut stack: Vec<NodeVisit> = roots
+        .into_iter()
+        .rev()
+        .map(|node| NodeVisit { node, parent: None, world: Mat4::IDENTITY, mirrored: false, depth: 0 })
+        .collect();
+    while let Some(visit) = stack.pop() {
+        ensure!(visit.depth <= document.nodes.len(), "glTF node hierarchy has a cycle");
+        let node = document.nodes.get(visit.node).with_context(|| format!("Missing node {}", visit.node))?;
+        let local = local_transform(node);
+        let world = visit.world * local;
+        // A mirroring transform would turn the winding inside out on the GPU,
+        // so the mirror is moved into the geometry instead: every placement
+        // keeps a positive determinant, and children undo their parent's
+        // mirror before their own transform
+        let mirrored = world.determinant() < 0.0;
+        let mirror = |mirrored: bool| if mirrored { Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0)) } else { Mat4::IDENTITY };
+        let transform = mirror(visit.mirrored) * local * mirror(mirrored);
+
+        let mut primitives = Vec::new();
+        let mut name = node.name.clone();
+        if let Some(mesh_index) = node.mesh {
+            let mesh = document.meshes.get(mesh_index).with_context(|| format!("Missing mesh {}", mesh_index))?;
+            name = name.or_else(|| mesh.name.clone()).or_else(|| Some(format!("mesh {}", mesh_index)));
+            for primitive in &mesh.primitives {
+                primitives.extend(load_primitive(&document, &buffers, primitive, mirrored, name.clone().unwrap_or_default())?);
+            }
+        }
+        if primitives.is_empty() && node.children.is_empty() {
+            continue;
+        }
+        let index = parts.len();
+        let name = name.unwrap_or_else(|| format!("node {}", visit.node));
+        match primitives.len() {
+            1 => parts.extend(primitives.into_iter().map(|part| MeshData { parent: visit.parent, transform, ..part })),
+            // Several primitives become parts of an empty node, like the
+            // materials of an OBJ group
+            _ => {
+                parts.push(MeshData { name, parent: visit.parent, transform, ..Default::default() });
+                parts.extend(primitives.into_iter().map(|part| MeshData { parent: Some(index), ..part }));
+            }
+        }
+        stack.extend(node.children.iter().rev().map(|&child| NodeVisit {
+            node: child,
+            parent: Some(index),
+            world,
+            mirrored,
+            depth: visit.depth + 1,
+        }));
+    }
+
+    Ok(GltfModel { parts, materials, textures })
+}
+
+// A node waiting to be turned into a part, with what its parent left for it
+struct NodeVisit {
+    node: usize,
+    // Part of the parent node
+    parent: Option<usize>,
+    world: Mat4,
+    // Whether the parent's geometry was mirrored
+    mirrored: bool,
+    depth: usize,
+}
+
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>)> {
+    let word = |offset: usize| -> Result<u32> {
+        let word = bytes.get(offset..offset + 4).context("Truncated GLB")?;
+        Ok(u32::from_le_bytes(word.try_into().unwrap()))
+    };
+    ensure!(word(4)? == 2, "Unsupported GLB version {}", word(4)?);
+
+    let (mut json, mut bin) = (None, None);
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let (length, kind) = (word(offset)? as usize, word(offset + 4)?);
+        let chunk = bytes.get(offset + 8..offset + 8 + length).context("Truncated GLB chunk")?;
+        match kind {
+            CHUNK_JSON => json = Some(chunk),
+            CHUNK_BIN => bin = Some(chunk),
+            _ => {}
+        }
+        offset += 8 + length;
+    }
+    Ok((json.context("GLB has no JSON chunk")?, bin))
+}
+
+fn read_uri(base_dir: &Path, uri: &str) -> Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data.split_once(";base64,").context("Only base64 data URIs are supported")?;
+        return decode_base64(encoded);
+    }
+    let path = base_dir.join(uri.replace("%20", " "));
+    std::fs::read(&path).with_context(|| format!("Failed to read {:?}", path))
+}
+
+fn decode_base64(encoded: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for byte in encoded.bytes().filter(|&byte| byte != b'=' && !byte.is_ascii_whitespace()) {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => bail!("Invalid base64 data"),
+        };
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+fn local_transform(node: &Node) -> Mat4 {
+    if let Some(matrix) = node.matrix {
+        return Mat4::from_cols_array(&matrix);
+    }
+    Mat4::from_scale_rotation_translation(
+        node.scale.map_or(Vec3::ONE, Vec3::from),
+        node.rotation.map_or(Quat::IDENTITY, Quat::from_array),
+        node.translation.map_or(Vec3::ZERO, Vec3::from),
+    )
+}
+
+/// Registers image `index` as a texture and returns the name the material
+/// refers to it by: the file name for external images, a placeholder key for
+/// images embedded in the file.
+fn image_texture(document: &Document, buffers: &[Vec<u8>], base_dir: &Path, index: usize, textures: &mut HashMap<String, Vec<u8>>) -> Result<String> {
+    let image = document.images.get(index).with_context(|| format!("Missing image {}", index))?;
+    match (&image.uri, image.buffer_view) {
+        (Some(uri), _) if !uri.starts_with("data:") => Ok(uri.replace("%20", " ")),
+        (Some(uri), _) => {
+            let key = format!("<embedded image {}>", index);
+            textures.insert(key.clone(), read_uri(base_dir, uri)?);
+            Ok(key)
+        }
+        (None, Some(view)) => {
+            let key = format!("<embedded image {}>", index);
+            textures.insert(key.clone(), buffer_view(document, buffers, view)?.to_vec());
+            Ok(key)
+        }
+        (None, None) => bail!("Image {} has no data", index),
+    }
+}
+
+fn buffer_view<'a>(document: &Document, buffers: &'a [Vec<u8>], index: usize) -> Result<&'a [u8]> {
+    let view = document.buffer_views.get(index).with_context(|| format!("Missing buffer view {}", index))?;
+    let buffer = buffers.get(view.buffer).with_context(|| format!("Missing buffer {}", view.buffer))?;
+    buffer
+        .get(view.byte_offset..view.byte_offset + view.byte_length)
+        .with_context(|| format!("Buffer view {} is out of range", index))
+}
+
+// Names of the textures a material uses, as returned by `image_texture`
+struct MaterialMaps {
+    base_color: Option<String>,
+    metallic_roughness: Option<String>,
+    normal: Option<String>,
+    occlusion: Option<String>,
+}
+
+/// Base color as the diffuse color and texture, and the metallic-roughness
+/// parameters and maps as the MTL PBR extension has them. Phong colors are
+/// approximated from the factors too, for when PBR shading is turned off.
+fn convert_material(material: &MaterialDef, index: usize, maps: MaterialMaps) -> tobj::Material {
+    let pbr = &material.pbr_metallic_roughness;
+    let [r, g, b, _] = pbr.base_color_factor.unwrap_or([1.0; 4]);
+    let metallic = pbr.metallic_factor.unwrap_or(1.0).clamp(0.0, 1.0);
+    let roughness = pbr.roughness_factor.unwrap_or(1.0).clamp(0.0, 1.0);
+    let specular = Vec3::splat(0.04).lerp(Vec3::new(r, g, b), metallic) * (1.0 - roughness);
+    let mut mtl = tobj::Material {
+        name: material.name.clone().unwrap_or_else(|| format!("material {}", index)),
+        ambient: Some([r, g, b]),
+        diffuse: Some([r, g, b]),
+        specular: Some(specular.to_array()),
+        shininess: Some(2.0 + (1.0 - roughness).powi(2) * 254.0),
+        diffuse_texture: maps.base_color,
+        ..Default::default()
+    };
+    let mut set = |key: &str, value: String| mtl.unknown_param.insert(key.to_string(), value);
+    set("Pm", metallic.to_string());
+    set("Pr", roughness.to_string());
+    // Metallic is read from the blue channel and roughness from the green one
+    if let Some(texture) = maps.metallic_roughness {
+        set("map_Pm", texture.clone());
+        set("map_Pr", texture);
+    }
+    if let Some(texture) = maps.normal {
+        let scale = material.normal_texture.as_ref().and_then(|normal| normal.scale).unwrap_or(1.0);
+        set("norm", format!("-bm {} {}", scale, texture));
+    }
+    if let Some(texture) = maps.occlusion {
+        set("map_ao", texture);
+    }
+    mtl
+}
+
+/// Triangles of `primitive` in the space of its node, mirrored along X when
+/// `mirrored` is set.
+fn load_primitive(document: &Document, buffers: &[Vec<u8>], primitive: &Primitive, mirrored: bool, name: String) -> Result<Option<MeshData>> {
+    // Only triangles, strips and fans; points and lines aren't drawn
+    let mode = primitive.mode.unwrap_or(4);
+    if !(4..=6).contains(&mode) {
+        return Ok(None);
+    }
+    let Some(&position_accessor) = primitive.attributes.get("POSITION") else {
+        return Ok(None);
+    };
+    // Without the `draco` feature, primitives that only optionally use Draco
+    // are read from their uncompressed fallback accessors
+    let (draco_indices, mut draco_attributes) = match &primitive.extensions.draco {
+        #[cfg(feature = "draco")]
+        Some(extension) => {
+            ensure!(mode == 4, "Draco-compressed primitive of {:?} isn't a triangle list", name);
+            let draco = decode_draco(document, buffers, primitive, extension).with_context(|| format!("Failed to decode the Draco mesh of {:?}", name))?;
+            (Some(draco.indices), draco.attributes)
+        }
+        _ => (None, HashMap::<String, _>::new()),
+    };
+    // Attributes the Draco mesh has come from it, the rest from their accessors
+    let mut read_attribute = |semantic: &str, accessor: usize| match draco_attributes.remove(semantic) {
+        Some(values) => Ok(values),
+        None => read_accessor(document, buffers, accessor),
+    };
+
+    let sign = if mirrored { -1.0 } else { 1.0 };
+    let positions: Vec<[f32; 3]> = read_attribute("POSITION", position_accessor)?
+        .chunks_exact(3)
+        .map(|p| [sign * p[0] as f32, p[1] as f32, p[2] as f32])
+        .collect();
+
+    let indices: Vec<u32> = match (draco_indices, primitive.indices) {
+        (Some(indices), _) => indices,
+        (None, Some(accessor)) => read_accessor(document, buffers, accessor)?.into_iter().map(|index| index as u32).collect(),
+        (None, None) => (0..positions.len() as u32).collect(),
+    };
+    let mut triangles: Vec<[u32; 3]> = match mode {
+        4 => indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+        5 => (2..indices.len())
+            .map(|i| if i % 2 == 0 { [indices[i - 2], indices[i - 1], indices[i]] } else { [indices[i - 1], indices[i - 2], indices[i]] })
+            .collect(),
+        _ => (2..indices.len()).map(|i| [indices[0], indices[i - 1], indices[i]]).collect(),
+    };
+    if let Some(index) = triangles.iter().flatten().find(|&&index| index as usize >= positions.len()) {
+        bail!("Primitive of {:?} references vertex {} but has only {}", name, index, positions.len());
+    }
+    // Mirroring turns the winding inside out
+    if mirrored {
+        for triangle in &mut triangles {
+            triangle.swap(1, 2);
+        }
+    }
+
+    let normals = match primitive.attributes.get("NORMAL") {
+        Some(&accessor) => Some(
+            read_attribute("NORMAL", accessor)?
+                .chunks_exact(3)
+                .map(|n| Vec3::new(sign * n[0] as f32, n[1] as f32, n[2] as f32).normalize_or_zero().to_array())
+                .collect(),
+        ),
+        None => None,
+    };
+    let tex_coords = match primitive.attributes.get("TEXCOORD_0") {
+        // glTF puts the UV origin at the top left, OBJ at the bottom left
+        Some(&accessor) => Some(read_attribute("TEXCOORD_0", accessor)?.chunks_exact(2).map(|uv| [uv[0] as f32, 1.0 - uv[1] as f32]).collect()),
+        None => None,
+    };
+    let vertex_colors = match primitive.attributes.get("COLOR_0") {
+        Some(&accessor) => {
+            let kind = &document.accessors.get(accessor).with_context(|| format!("Missing accessor {}", accessor))?.kind;
+            ensure!(kind == "VEC3" || kind == "VEC4", "COLOR_0 of {:?} is {}, not VEC3 or VEC4", name, kind);
+            let components = component_count(kind)?;
+            Some(read_attribute("COLOR_0", accessor)?.chunks_exact(components).map(|c| [c[0] as f32, c[1] as f32, c[2] as f32]).collect())
+        }
+        None => None,
+    };
+
+    Ok(Some(MeshData {
+        name,
+        positions,
+        triangles,
+        normals,
+        vertex_colors,
+        tex_coords,
+        material: primitive.material.filter(|&material| material < document.materials.len()),
+        ..Default::default()
+    }))
+}
+
+// A `KHR_draco_mesh_compression` primitive decoded from its buffer view
+#[cfg(feature = "draco")]
+struct DracoPrimitive {
+    indices: Vec<u32>,
+    // Values per point, like `read_accessor`'s, by glTF semantic
+    attributes: HashMap<String, Vec<f64>>,
+}
+
+#[cfg(feature = "draco")]
+fn decode_draco(document: &Document, buffers: &[Vec<u8>], primitive: &Primitive, extension: &DracoExtension) -> Result<DracoPrimitive> {
+    let data = buffer_view(document, buffers, extension.buffer_view)?;
+    let mesh = crate::draco::decode(data)?;
+
+    let mut attributes = HashMap::new();
+    for (semantic, &id) in &extension.attributes {
+        // The accessor still gives the type, and whether integers are normalized
+        let Some(accessor) = primitive.attributes.get(semantic).and_then(|&accessor| document.accessors.get(accessor)) else {
+            continue;
+        };
+        let attribute = mesh
+            .get_attributes()
+            .iter()
+            .find(|attribute| attribute.get_id().as_usize() == id)
+            .with_context(|| format!("Draco mesh has no attribute {} for {}", id, semantic))?;
+        let components = attribute.get_num_components();
+        ensure!(
+            components == component_count(&accessor.kind)?,
+            "Draco attribute {} has {} components but {} is {}",
+            id,
+            components,
+            semantic,
+            accessor.kind
+        );
+        attributes.insert(semantic.clone(), crate::draco::values(attribute, accessor.normalized)?);
+    }
+    Ok(DracoPrimitive {
+        indices: crate::draco::triangles(&mesh),
+        attributes,
+    })
+}
+
+fn component_count(kind: &str) -> Result<usize> {
+    Ok(match kind {
+        "SCALAR" => 1,
+        "VEC2" => 2,
+        "VEC3" => 3,
+        "VEC4" | "MAT2" => 4,
+        "MAT3" => 9,
+        "MAT4" => 16,
+        other => bail!("Unknown accessor type {:?}", other),
+    })
+}
+
+/// All components of an accessor, normalized integers mapped to 0..1 / -1..1.
+fn read_accessor(document: &Document, buffers: &[Vec<u8>], index: usize) -> Result<Vec<f64>> {
+    let accessor = document.accessors.get(index).with_context(|| format!("Missing accessor {}", index))?;
+    ensure!(accessor.sparse.is_none(), "Sparse accessors aren't supported");
+    let components = component_count(&accessor.kind)?;
+    let Some(view_index) = accessor.buffer_view else {
+        return Ok(vec![0.0; accessor.count * components]);
+    };
+
+    let (size, max): (usize, f64) = match accessor.component_type {
+        5120 => (1, i8::MAX as f64),
+        5121 => (1, u8::MAX as f64),
+        5122 => (2, i16::MAX as f64),
+        5123 => (2, u16::MAX as f64),
+        5125 => (4, u32::MAX as f64),
+        5126 => (4, 1.0),
+        other => bail!("Unknown accessor component type {}", other),
+    };
+    let data = buffer_view(document, buffers, view_index)?;
+    let stride = document.buffer_views[view_index].byte_stride.unwrap_or(size * components);
+
+    let mut values = Vec::with_capacity(accessor.count * components);
+    for element in 0..accessor.count {
+        for component in 0..components {
+            let offset = accessor.byte_offset + element * stride + component * size;
+            let bytes = data.get(offset..offset + size).with_context(|| format!("Accessor {} is out of range", index))?;
+            let value = match accessor.component_type {
+                5120 => bytes[0] as i8 as f64,
+                5121 => bytes[0] as f64,
+                5122 => i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                5123 => u16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+                5125 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+            };
+            values.push(if accessor.normalized { (value / max).max(-1.0) } else { value });
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tetrahedron with corners at the origin and on each axis, encoded with
+    // default Draco settings (11-bit positions)
+    #[cfg(feature = "draco")]
+    const DRACO_TETRAHEDRON: &str = "RFJBQ08CAgEBAAACBAQAAwAAAQEQAQEBBAsBQAEAAQEBAQFAAQAAAAAAAf8AAAEACQMAAAIBAQEBAgMBIAEQARAFAHDw4oAAAAAA/wcAAAAAAAAAAAAAAAAAAAAAgD8L";
+
+    // One triangle: three float positions, then three u16 indices padded to 4 bytes
+    fn triangle_buffer() -> Vec<u8> {
+        let mut bytes: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter().flat_map(|value| value.to_le_bytes()).collect();
+        bytes.extend([0u16, 1, 2, 0].iter().flat_map(|index| index.to_le_bytes()));
+        bytes
+    }
+
+    // Document around `triangle_buffer` with the given scene, nodes and
+    // primitive. Accessor 2 reads position bytes as indices, one of them 16256.
+    fn triangle_json(nodes: &str, primitive: &str) -> String {
+        format!(
+            r#"{{
+                "scenes": [{{"nodes": [0]}}],
+                "nodes": {},
+                "meshes": [{{"primitives": [{}]}}],
+                "accessors": [
+                    {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}},
+                    {{"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}},
+                    {{"bufferView": 0, "byteOffset": 12, "componentType": 5123, "count": 3, "type": "SCALAR"}}
+                ],
+                "bufferViews": [
+                    {{"buffer": 0, "byteLength": 36}},
+                    {{"buffer": 0, "byteOffset": 36, "byteLength": 6}}
+                ],
+                "buffers": [{{"byteLength": 44}}]
+            }}"#,
+            nodes, primitive
+        )
+    }
+
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let chunk = |kind: u32, data: &[u8], pad: u8| {
+            let mut chunk = Vec::new();
+            let length = data.len().next_multiple_of(4);
+            chunk.extend((length as u32).to_le_bytes());
+            chunk.extend(kind.to_le_bytes());
+            chunk.extend(data);
+            chunk.resize(8 + length, pad);
+            chunk
+        };
+        let chunks = [chunk(CHUNK_JSON, json.as_bytes(), b' '), chunk(CHUNK_BIN, bin, 0)].concat();
+        let mut bytes = GLB_MAGIC.to_vec();
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend((12 + chunks.len() as u32).to_le_bytes());
+        bytes.extend(chunks);
+        bytes
+    }
+
+    fn load_triangle(nodes: &str, primitive: &str) -> Result<GltfModel> {
+        parse(&glb(&triangle_json(nodes, primitive), &triangle_buffer()), Path::new("."))
+    }
+
+    #[test]
+    fn loads_glb_with_embedded_buffer() {
+        let model = load_triangle(r#"[{"name": "triangle", "mesh": 0}]"#, r#"{"attributes": {"POSITION": 0}, "indices": 1}"#).unwrap();
+        let [part] = &model.parts[..] else { panic!("expected one part, got {}", model.parts.len()) };
+        assert_eq!(part.name, "triangle");
+        assert_eq!(part.positions, [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+        assert_eq!(part.triangles, [[0, 1, 2]]);
+        assert_eq!(part.parent, None);
+        assert_eq!(part.transform, Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn keeps_node_hierarchy() {
+        let nodes = r#"[
+            {"name": "root", "translation": [1, 0, 0], "children": [1, 2]},
+            {"name": "mirrored", "mesh": 0, "scale": [-1, 1, 1]},
+            {"name": "empty"}
+        ]"#;
+        let model = load_triangle(nodes, r#"{"attributes": {"POSITION": 0}, "indices": 1}"#).unwrap();
+        let [root, mirrored] = &model.parts[..] else { panic!("expected two parts, got {}", model.parts.len()) };
+        assert_eq!((root.name.as_str(), root.parent, root.triangles.len()), ("root", None, 0));
+        assert_eq!(root.transform, Mat4::from_translation(Vec3::X));
+        assert_eq!((mirrored.name.as_str(), mirrored.parent), ("mirrored", Some(0)));
+
+        // The mirror moves into the geometry, turning the winding around,
+        // and the node ends up where the file put it
+        assert!(mirrored.transform.determinant() > 0.0);
+        assert_eq!(mirrored.triangles, [[0, 2, 1]]);
+        let world = root.transform * mirrored.transform;
+        let placed: Vec<Vec3> = mirrored.positions.iter().map(|&position| world.transform_point3(Vec3::from(position))).collect();
+        assert_eq!(placed, [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0)]);
+    }
+
+    #[test]
+    fn rejects_required_meshopt() {
+        let json = triangle_json(r#"[{"mesh": 0}]"#, r#"{"attributes": {"POSITION": 0}}"#)
+            .replacen('{', r#"{"extensionsRequired": ["EXT_meshopt_compression"],"#, 1);
+        let error = parse(&glb(&json, &triangle_buffer()), Path::new(".")).err().expect("meshopt should be rejected");
+        assert!(error.to_string().contains("EXT_meshopt_compression"), "{}", error);
+    }
+
+    #[test]
+    fn reports_malformed_accessors() {
+        let error = |primitive: &str| load_triangle(r#"[{"mesh": 0}]"#, primitive).err().expect("should fail").to_string();
+        assert!(error(r#"{"attributes": {"POSITION": 7}}"#).contains("Missing accessor 7"));
+        assert!(error(r#"{"attributes": {"POSITION": 0}, "indices": 2}"#).contains("references vertex 16256"));
+        assert!(error(r#"{"attributes": {"POSITION": 0, "COLOR_0": 1}}"#).contains("not VEC3 or VEC4"));
+        assert!(error(r#"{"attributes": {"POSITION": 0, "COLOR_0": 9}}"#).contains("Missing accessor 9"));
+    }
+
+    #[test]
+    #[cfg(feature = "draco")]
+    fn decodes_draco_primitives() {
+        let json = format!(
+            r#"{{
+                "extensionsUsed": ["KHR_draco_mesh_compression"],
+                "extensionsRequired": ["KHR_draco_mesh_compression"],
+                "scenes": [{{"nodes": [0]}}],
+                "nodes": [{{"mesh": 0}}],
+                "meshes": [{{"primitives": [{{
+                    "attributes": {{"POSITION": 0}},
+                    "indices": 1,
+                    "extensions": {{"KHR_draco_mesh_compression": {{"bufferView": 0, "attributes": {{"POSITION": 0}}}}}}
+                }}]}}],
+                "accessors": [
+                    {{"componentType": 5126, "count": 4, "type": "VEC3"}},
+                    {{"componentType": 5125, "count": 12, "type": "SCALAR"}}
+                ],
+                "bufferViews": [{{"buffer": 0, "byteLength": 96}}],
+                "buffers": [{{"byteLength": 96, "uri": "data:application/octet-stream;base64,{}"}}]
+            }}"#,
+            DRACO_TETRAHEDRON
+        );
+        let model = parse(json.as_bytes(), Path::new(".")).unwrap();
+        let [part] = &model.parts[..] else { panic!("expected one part, got {}", model.parts.len()) };
+        assert_eq!(part.triangles.len(), 4);
+        let corners = [Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::Z];
+        assert_eq!(part.positions.len(), corners.len());
+        for corner in corners {
+            assert!(part.positions.iter().any(|&position| Vec3::from(position).distance(corner) < 1e-3), "missing corner {}", corner);
+        }
+    }
+}
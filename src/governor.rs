@@ -0,0 +1,62 @@
+use std::time::{Duration, Instant};
+
+const ADJUST_INTERVAL: Duration = Duration::from_millis(250);
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+const SCALE_STEP: f32 = 0.1;
+const MIN_SCALE_FACTOR: f32 = 0.25;
+
+/// Lowers the render scale while the camera is moving and the frame rate is
+/// below target, then restores full quality once the camera settles.
+pub struct QualityGovernor {
+    pub enabled: bool,
+    pub target_fps: f32,
+    scale_factor: f32,
+    last_adjustment: Instant,
+    last_motion: Instant,
+}
+
+impl QualityGovernor {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            target_fps: 30.0,
+            scale_factor: 1.0,
+            last_adjustment: Instant::now(),
+            last_motion: Instant::now(),
+        }
+    }
+
+    /// Returns the factor to apply to the user-selected render scale this frame.
+    pub fn update(&mut self, fps: f32, camera_moving: bool) -> f32 {
+        let now = Instant::now();
+
+        if !self.enabled {
+            self.scale_factor = 1.0;
+            return self.scale_factor;
+        }
+
+        if camera_moving {
+            self.last_motion = now;
+        }
+
+        // Full quality as soon as the view is still
+        if now.duration_since(self.last_motion) >= SETTLE_TIME {
+            self.scale_factor = 1.0;
+            return self.scale_factor;
+        }
+
+        if now.duration_since(self.last_adjustment) < ADJUST_INTERVAL {
+            return self.scale_factor;
+        }
+
+        if fps < self.target_fps * 0.9 && self.scale_factor > MIN_SCALE_FACTOR {
+            self.scale_factor = (self.scale_factor - SCALE_STEP).max(MIN_SCALE_FACTOR);
+            self.last_adjustment = now;
+        } else if fps > self.target_fps * 1.25 && self.scale_factor < 1.0 {
+            self.scale_factor = (self.scale_factor + SCALE_STEP).min(1.0);
+            self.last_adjustment = now;
+        }
+
+        self.scale_factor
+    }
+}
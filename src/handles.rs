@@ -0,0 +1,302 @@
+use glam::{Vec2, Vec3};
+
+use crate::camera::Camera;
+use crate::picking::Ray;
+
+/// Colors of handles for the world X, Y and Z axes.
+pub const AXIS_COLORS: [egui::Color32; 3] = [
+    egui::Color32::from_rgb(230, 70, 70),
+    egui::Color32::from_rgb(90, 200, 90),
+    egui::Color32::from_rgb(80, 130, 240),
+];
+const ACTIVE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 220, 60);
+// Length of the handles, and radius of the rings, on screen
+const HANDLE_POINTS: f32 = 90.0;
+// How close the pointer has to be to a handle to grab it
+const GRAB_DISTANCE: f32 = 7.0;
+const RING_SEGMENTS: usize = 48;
+// Extent of the plane squares along their two axes, as fractions of the handle length
+const PLANE_SQUARE: (f32, f32) = (0.25, 0.45);
+
+/// What a handle looks like and what dragging it does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleShape {
+    /// Arrow along the axis, moving along it.
+    Arrow,
+    /// Line along the axis ending in a box, scaling.
+    Scale,
+    /// Ring around the axis, turning about it.
+    Ring,
+    /// Square across the axis, moving within the plane it's normal to.
+    Plane,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Handle {
+    pub shape: HandleShape,
+    pub axis: Vec3,
+    pub color: egui::Color32,
+}
+
+impl Handle {
+    pub fn new(shape: HandleShape, axis: Vec3, color: egui::Color32) -> Self {
+        Self { shape, axis: axis.normalize_or_zero(), color }
+    }
+
+    /// One handle of `shape` per world axis, in the axis colors.
+    pub fn world_axes(shape: HandleShape) -> [Handle; 3] {
+        [0, 1, 2].map(|axis| Handle::new(shape, Vec3::AXES[axis], AXIS_COLORS[axis]))
+    }
+}
+
+/// How far a handle has been dragged since it was grabbed.
+#[derive(Debug, Clone, Copy)]
+pub enum Motion {
+    /// World distance along the axis.
+    Along(f32),
+    /// Scale factor.
+    Scale(f32),
+    /// Radians about the axis, counterclockwise looking down at it.
+    Rotate(f32),
+    /// World offset within the plane.
+    Plane(Vec3),
+}
+
+/// The handle being dragged, and how far.
+#[derive(Debug, Clone, Copy)]
+pub struct HandleDrag {
+    pub handle: usize,
+    /// Set on the frame the handle is grabbed, for the owner to keep what it
+    /// started from.
+    pub started: bool,
+    pub motion: Motion,
+}
+
+// A handle being dragged, with where it started on screen and in the world
+struct Drag {
+    handle: usize,
+    start: egui::Pos2,
+    center: egui::Pos2,
+    // Screen extent of the handle along its axis, and its world length
+    axis_screen: egui::Vec2,
+    length: f32,
+    // Where the pointer hit the plane of a plane handle
+    plane_start: Option<Vec3>,
+}
+
+/// Screen-space handles around a point in the scene, shared by everything
+/// dragged in the viewport: arrows, scale boxes, rotation rings and plane
+/// squares. Hit testing and the drag math live here; owners decide what a
+/// drag changes.
+pub struct Manipulator {
+    hovered: Option<usize>,
+    drag: Option<Drag>,
+}
+
+impl Manipulator {
+    pub fn new() -> Self {
+        Self { hovered: None, drag: None }
+    }
+
+    /// Whether a left press goes to the handles rather than the camera.
+    pub fn is_active(&self) -> bool {
+        self.hovered.is_some() || self.drag.is_some()
+    }
+
+    /// Forgets the hovered handle and any drag until the next `show`.
+    pub fn release(&mut self) {
+        self.hovered = None;
+        self.drag = None;
+    }
+
+    /// Paints `handles` around `center`, seen through `camera` over the whole
+    /// window, and tracks hovering and dragging them. Returns the drag in
+    /// progress.
+    pub fn show(&mut self, ctx: &egui::Context, camera: &Camera, center: Vec3, handles: &[Handle]) -> Option<HandleDrag> {
+        let screen = ctx.screen_rect();
+        let view_projection = camera.projection_matrix() * camera.view_matrix();
+        let to_screen = |point: Vec3| {
+            let clip = view_projection * point.extend(1.0);
+            (clip.w > 0.0).then(|| {
+                let ndc = clip.truncate() / clip.w;
+                egui::pos2(screen.min.x + (ndc.x + 1.0) * 0.5 * screen.width(), screen.min.y + (1.0 - ndc.y) * 0.5 * screen.height())
+            })
+        };
+        let Some(center_screen) = to_screen(center) else {
+            self.release();
+            return None;
+        };
+        // World size of a point at the center's depth
+        let forward = (camera.target - camera.position).normalize_or_zero();
+        let depth = if camera.orthographic { camera.distance } else { (center - camera.position).dot(forward).max(camera.near) };
+        let length = HANDLE_POINTS * 2.0 * depth * (camera.fov * 0.5).tan() / screen.height().max(1.0);
+
+        let outlines: Vec<Vec<egui::Pos2>> = handles
+            .iter()
+            .map(|handle| {
+                let (u, v) = plane_axes(handle.axis);
+                match handle.shape {
+                    HandleShape::Ring => (0..=RING_SEGMENTS)
+                        .filter_map(|i| {
+                            let angle = i as f32 / RING_SEGMENTS as f32 * std::f32::consts::TAU;
+                            to_screen(center + (u * angle.cos() + v * angle.sin()) * length)
+                        })
+                        .collect(),
+                    HandleShape::Plane => {
+                        let (near, far) = PLANE_SQUARE;
+                        [(near, near), (far, near), (far, far), (near, far)]
+                            .into_iter()
+                            .filter_map(|(a, b)| to_screen(center + (u * a + v * b) * length))
+                            .collect()
+                    }
+                    HandleShape::Arrow | HandleShape::Scale => [center, center + handle.axis * length].into_iter().filter_map(to_screen).collect(),
+                }
+            })
+            .collect();
+
+        let (pointer, pressed, down) = ctx.input(|input| (input.pointer.hover_pos(), input.pointer.primary_pressed(), input.pointer.primary_down()));
+        if self.drag.is_none() {
+            self.hovered = pointer.filter(|_| !ctx.is_pointer_over_area()).and_then(|pointer| {
+                outlines
+                    .iter()
+                    .enumerate()
+                    .map(|(i, outline)| {
+                        let inside = handles[i].shape == HandleShape::Plane && outline.len() == 4 && contains(outline, pointer);
+                        (i, if inside { 0.0 } else { polyline_distance(outline, pointer) })
+                    })
+                    .filter(|&(_, distance)| distance < GRAB_DISTANCE)
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(i, _)| i)
+            });
+        }
+        let mut started = false;
+        if let (true, Some(handle), Some(pointer)) = (pressed, self.hovered.filter(|&handle| handle < handles.len()), pointer) {
+            let plane_start = (handles[handle].shape == HandleShape::Plane)
+                .then(|| intersect_plane(camera, screen, pointer, center, handles[handle].axis))
+                .flatten();
+            self.drag = Some(Drag {
+                handle,
+                start: pointer,
+                center: center_screen,
+                axis_screen: outlines[handle].last().map_or(egui::Vec2::ZERO, |end| *end - center_screen),
+                length,
+                plane_start,
+            });
+            started = true;
+        }
+        let motion = match (&self.drag, pointer) {
+            (Some(drag), Some(pointer)) if down && drag.handle < handles.len() => Some(motion(drag, &handles[drag.handle], pointer, camera, screen, center)),
+            _ => {
+                self.drag = None;
+                None
+            }
+        };
+
+        let active = self.drag.as_ref().map(|drag| drag.handle).or(self.hovered);
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        for (i, (handle, outline)) in handles.iter().zip(&outlines).enumerate() {
+            let color = if active == Some(i) { ACTIVE_COLOR } else { handle.color };
+            match handle.shape {
+                HandleShape::Plane => {
+                    painter.add(egui::Shape::convex_polygon(outline.clone(), color.gamma_multiply(0.35), egui::Stroke::new(1.5, color)));
+                }
+                HandleShape::Ring => {
+                    painter.add(egui::Shape::line(outline.clone(), egui::Stroke::new(2.5, color)));
+                }
+                HandleShape::Arrow | HandleShape::Scale => {
+                    painter.add(egui::Shape::line(outline.clone(), egui::Stroke::new(2.5, color)));
+                    let Some(&end) = outline.last() else {
+                        continue;
+                    };
+                    if handle.shape == HandleShape::Scale {
+                        painter.rect_filled(egui::Rect::from_center_size(end, egui::vec2(8.0, 8.0)), 0.0, color);
+                    } else {
+                        painter.circle_filled(end, 5.0, color);
+                    }
+                }
+            }
+        }
+
+        Some(HandleDrag {
+            handle: self.drag.as_ref()?.handle,
+            started,
+            motion: motion?,
+        })
+    }
+}
+
+fn motion(drag: &Drag, handle: &Handle, pointer: egui::Pos2, camera: &Camera, screen: egui::Rect, center: Vec3) -> Motion {
+    // Fraction of the handle's length dragged along it on screen
+    let along = (pointer - drag.start).dot(drag.axis_screen) / drag.axis_screen.length_sq().max(1.0);
+    match handle.shape {
+        HandleShape::Arrow => Motion::Along(along * drag.length),
+        HandleShape::Scale => Motion::Scale(1.0 + along),
+        HandleShape::Ring => {
+            // Counterclockwise on screen turns about an axis pointing at the camera
+            let screen_angle = |point: egui::Pos2| (drag.center.y - point.y).atan2(point.x - drag.center.x);
+            let facing = if handle.axis.dot(camera.position - center) >= 0.0 { 1.0 } else { -1.0 };
+            Motion::Rotate((screen_angle(pointer) - screen_angle(drag.start)) * facing)
+        }
+        HandleShape::Plane => {
+            let start = drag.plane_start;
+            let offset = start
+                .and_then(|start| Some(intersect_plane(camera, screen, pointer, start, handle.axis)? - start))
+                .unwrap_or(Vec3::ZERO);
+            Motion::Plane(offset)
+        }
+    }
+}
+
+// Where the ray under `pointer` meets the plane through `point` normal to `normal`
+fn intersect_plane(camera: &Camera, screen: egui::Rect, pointer: egui::Pos2, point: Vec3, normal: Vec3) -> Option<Vec3> {
+    let ndc = Vec2::new(
+        (pointer.x - screen.min.x) / screen.width().max(1.0) * 2.0 - 1.0,
+        1.0 - (pointer.y - screen.min.y) / screen.height().max(1.0) * 2.0,
+    );
+    let ray = Ray::from_camera(camera, ndc);
+    let denominator = ray.direction.dot(normal);
+    // A plane seen edge-on would send the point off to infinity
+    if denominator.abs() < 1e-4 * ray.direction.length() {
+        return None;
+    }
+    Some(ray.at((point - ray.origin).dot(normal) / denominator))
+}
+
+/// Two unit vectors spanning the plane normal to `axis`, following the world
+/// axes for axis-aligned ones: Y and Z for X, Z and X for Y, X and Y for Z.
+pub fn plane_axes(axis: Vec3) -> (Vec3, Vec3) {
+    let magnitudes = axis.abs();
+    let major = if magnitudes.x >= magnitudes.y.max(magnitudes.z) {
+        0
+    } else if magnitudes.y >= magnitudes.z {
+        1
+    } else {
+        2
+    };
+    let candidate = Vec3::AXES[(major + 1) % 3];
+    let u = (candidate - axis * candidate.dot(axis)).normalize_or_zero();
+    (u, axis.cross(u))
+}
+
+fn polyline_distance(points: &[egui::Pos2], point: egui::Pos2) -> f32 {
+    points
+        .windows(2)
+        .map(|segment| {
+            let (a, b) = (segment[0], segment[1]);
+            let t = ((point - a).dot(b - a) / (b - a).length_sq().max(f32::EPSILON)).clamp(0.0, 1.0);
+            (a + (b - a) * t).distance(point)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+// Whether `point` lies inside the convex polygon `corners`, wound either way
+fn contains(corners: &[egui::Pos2], point: egui::Pos2) -> bool {
+    let (mut positive, mut negative) = (false, false);
+    for (i, &a) in corners.iter().enumerate() {
+        let b = corners[(i + 1) % corners.len()];
+        let side = (b - a).x * (point - a).y - (b - a).y * (point - a).x;
+        positive |= side > 0.0;
+        negative |= side < 0.0;
+    }
+    !(positive && negative)
+}
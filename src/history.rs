@@ -0,0 +1,129 @@
+use glam::Vec3;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::loader::{LoadedModel, ModelSummary};
+use crate::mesh;
+
+// Cells along the longest side of the model when decimating a snapshot
+const GRID_CELLS: f32 = 64.0;
+const MAX_SNAPSHOTS: usize = 32;
+
+/// One version of a model, decimated by merging the vertices in each cell
+/// of a coarse grid and kept as the edges of what's left.
+pub struct Snapshot {
+    pub summary: ModelSummary,
+    pub taken: Instant,
+    positions: Vec<Vec3>,
+    // Pairs of indices into `positions`
+    edges: Vec<u32>,
+}
+
+impl Snapshot {
+    /// Snapshot of a mesh; point clouds and empty meshes have none.
+    pub fn new(model: &LoadedModel, summary: ModelSummary) -> Option<Self> {
+        let LoadedModel::Mesh(mesh) = model else {
+            return None;
+        };
+        if mesh.vertices.is_empty() {
+            return None;
+        }
+        let (min, max) = summary.bounds;
+        let cell_size = ((max - min).max_element() / GRID_CELLS).max(f32::EPSILON);
+        let mut cells: HashMap<[i32; 3], u32> = HashMap::new();
+        // Sum and count of the positions merged into each cell
+        let mut merged: Vec<(Vec3, u32)> = Vec::new();
+        let remap: Vec<u32> = mesh
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let position = Vec3::from(vertex.position);
+                let key = ((position - min) / cell_size).floor().as_ivec3().to_array();
+                let cell = *cells.entry(key).or_insert_with(|| {
+                    merged.push((Vec3::ZERO, 0));
+                    merged.len() as u32 - 1
+                });
+                let (sum, count) = &mut merged[cell as usize];
+                *sum += position;
+                *count += 1;
+                cell
+            })
+            .collect();
+        let triangles: Vec<u32> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|corner| remap[triangle[corner] as usize]))
+            .filter(|[a, b, c]| a != b && b != c && c != a)
+            .flatten()
+            .collect();
+        Some(Self {
+            summary,
+            taken: Instant::now(),
+            positions: merged.into_iter().map(|(sum, count)| sum / count as f32).collect(),
+            edges: mesh::unique_edges(&triangles),
+        })
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len() / 2
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+        self.edges
+            .chunks_exact(2)
+            .map(|edge| (self.positions[edge[0] as usize], self.positions[edge[1] as usize]))
+    }
+
+    /// How long ago the snapshot was taken, e.g. "12 s ago".
+    pub fn age(&self) -> String {
+        let seconds = self.taken.elapsed().as_secs();
+        match seconds {
+            0..=59 => format!("{} s ago", seconds),
+            60..=3599 => format!("{} min ago", seconds / 60),
+            _ => format!("{} h ago", seconds / 3600),
+        }
+    }
+}
+
+/// Snapshots of every version of the loaded model seen during the session,
+/// oldest first; the last one is the model showing.
+#[derive(Default)]
+pub struct ModelHistory {
+    path: Option<PathBuf>,
+    pub snapshots: Vec<Snapshot>,
+    /// Earlier version drawn over the current model.
+    pub compare: Option<usize>,
+}
+
+impl ModelHistory {
+    /// Adds the newest version of `path`, starting over for another file.
+    pub fn record(&mut self, path: &Path, snapshot: Snapshot) {
+        if self.path.as_deref() != Some(path) {
+            self.clear();
+            self.path = Some(path.to_path_buf());
+        }
+        self.snapshots.push(snapshot);
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.remove(0);
+            self.compare = self.compare.and_then(|compare| compare.checked_sub(1));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.path = None;
+        self.snapshots.clear();
+        self.compare = None;
+    }
+
+    /// Drops every version but the one showing.
+    pub fn keep_latest(&mut self) {
+        let start = self.snapshots.len().saturating_sub(1);
+        self.snapshots.drain(..start);
+        self.compare = None;
+    }
+
+    pub fn compared(&self) -> Option<&Snapshot> {
+        self.compare.and_then(|compare| self.snapshots.get(compare))
+    }
+}
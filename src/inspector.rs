@@ -0,0 +1,87 @@
+use glam::Vec3;
+
+use crate::mesh::{Mesh, Vertex};
+use crate::picking::Pick;
+
+/// Everything about a picked face worth checking when an exporter's output
+/// looks wrong. Indices are into the viewer's buffers, which match the file
+/// as long as the normals weren't recomputed on load.
+#[derive(Debug, Clone)]
+pub struct FaceDetails {
+    object: String,
+    material: Option<String>,
+    /// Index among all triangles, and among those of its object.
+    face: usize,
+    face_in_object: usize,
+    point: Vec3,
+    face_normal: Vec3,
+    corners: [(u32, Vertex); 3],
+}
+
+impl FaceDetails {
+    pub fn new(mesh: &Mesh, pick: &Pick) -> Option<Self> {
+        let range = mesh.draw_ranges.get(pick.object)?;
+        let indices = mesh.indices.get(pick.face * 3..pick.face * 3 + 3)?;
+        let corners = [0, 1, 2].map(|corner| (indices[corner], mesh.vertices[indices[corner] as usize]));
+        let [a, b, c] = corners.map(|(_, vertex)| Vec3::from(vertex.position));
+        Some(Self {
+            object: range.name.clone(),
+            material: range.material.and_then(|material| mesh.materials.get(material)).map(|material| material.name.clone()),
+            face: pick.face,
+            face_in_object: pick.face - range.start as usize / 3,
+            point: pick.point,
+            face_normal: (b - a).cross(c - a).normalize_or_zero(),
+            corners,
+        })
+    }
+
+    pub fn face(&self) -> usize {
+        self.face
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        let vector = |values: &[f32]| values.iter().map(|value| format!("{:.4}", value)).collect::<Vec<_>>().join(", ");
+        egui::Grid::new("face_details").num_columns(2).show(ui, |ui| {
+            ui.label("Object");
+            ui.label(&self.object);
+            ui.end_row();
+            ui.label("Face");
+            ui.label(format!("{} ({} in object)", self.face, self.face_in_object));
+            ui.end_row();
+            ui.label("Material");
+            ui.label(self.material.as_deref().unwrap_or("(none)"));
+            ui.end_row();
+            ui.label("Hit point");
+            ui.label(vector(&self.point.to_array()));
+            ui.end_row();
+            ui.label("Face normal");
+            ui.label(vector(&self.face_normal.to_array()));
+            ui.end_row();
+        });
+        for (index, vertex) in &self.corners {
+            ui.separator();
+            ui.strong(format!("Vertex {}", index));
+            egui::Grid::new(("vertex_details", *index)).num_columns(2).show(ui, |ui| {
+                ui.label("Position");
+                ui.label(vector(&vertex.position));
+                ui.end_row();
+                ui.label("Normal");
+                // Inverted against the winding, or missing
+                let normal = Vec3::from(vertex.normal);
+                let text = vector(&vertex.normal);
+                if normal.dot(self.face_normal) < 0.0 || normal.length_squared() < 1e-12 {
+                    ui.colored_label(egui::Color32::from_rgb(230, 90, 90), text);
+                } else {
+                    ui.label(text);
+                }
+                ui.end_row();
+                ui.label("UV");
+                ui.label(vector(&vertex.tex_coords));
+                ui.end_row();
+                ui.label("Color");
+                ui.label(vector(&vertex.color));
+                ui.end_row();
+            });
+        }
+    }
+}
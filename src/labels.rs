@@ -0,0 +1,139 @@
+use glam::{Vec2, Vec3};
+
+use crate::camera::Camera;
+use crate::depth_probe::DepthProbes;
+
+// Opacity of a label hidden behind geometry
+const OCCLUDED_OPACITY: f32 = 0.25;
+// Labels count as occluded when the surface is this much closer than the anchor
+const OCCLUSION_TOLERANCE: f32 = 0.01;
+const FADE_SPEED: f32 = 10.0;
+
+/// A text label pinned to a point in the scene.
+#[derive(Debug, Clone)]
+pub struct ScreenLabel {
+    pub anchor: Vec3,
+    pub text: String,
+    pub color: egui::Color32,
+}
+
+// Where a label was drawn, to compare with the depth buffer underneath it
+#[derive(Debug, Clone, Copy)]
+struct Probe {
+    label: usize,
+    ndc: Vec2,
+    // View-space distance of the anchor
+    distance: f32,
+}
+
+/// Labels anchored in 3D and painted with egui over the scene. Each frame the
+/// depth buffer under every label is read back and compared with the label's
+/// own depth; labels behind geometry fade out rather than vanish. The
+/// readback lands a frame or two late, which the fade hides.
+pub struct ScreenLabels {
+    labels: Vec<ScreenLabel>,
+    opacity: Vec<f32>,
+    occluded: Vec<bool>,
+    probes: Vec<Probe>,
+    camera_range: (f32, f32),
+    depth: DepthProbes,
+    // Probes of the depth request in flight
+    in_flight: Vec<Probe>,
+}
+
+impl ScreenLabels {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            labels: Vec::new(),
+            opacity: Vec::new(),
+            occluded: Vec::new(),
+            probes: Vec::new(),
+            camera_range: (0.1, 1000.0),
+            depth: DepthProbes::new(device),
+            in_flight: Vec::new(),
+        }
+    }
+
+    /// Removes all labels; call before adding this frame's labels. Labels
+    /// keep their fade state as long as they're added in the same order.
+    pub fn clear(&mut self) {
+        self.labels.clear();
+    }
+
+    pub fn add(&mut self, anchor: Vec3, text: impl Into<String>, color: egui::Color32) {
+        self.labels.push(ScreenLabel {
+            anchor,
+            text: text.into(),
+            color,
+        });
+    }
+
+    /// Projects the labels through `camera` and paints them behind the egui
+    /// windows.
+    pub fn paint(&mut self, ctx: &egui::Context, camera: &Camera) {
+        if self.opacity.len() != self.labels.len() {
+            self.opacity = vec![0.0; self.labels.len()];
+            self.occluded = vec![false; self.labels.len()];
+        }
+        self.probes.clear();
+        self.camera_range = (camera.near, camera.far);
+
+        let view_projection = camera.projection_matrix() * camera.view_matrix();
+        let screen = ctx.screen_rect();
+        let fade = 1.0 - (-FADE_SPEED * ctx.input(|input| input.stable_dt)).exp();
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        for (i, label) in self.labels.iter().enumerate() {
+            let clip = view_projection * label.anchor.extend(1.0);
+            let ndc = clip.truncate() / clip.w;
+            if clip.w <= camera.near || ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 {
+                self.opacity[i] = 0.0;
+                continue;
+            }
+            self.probes.push(Probe {
+                label: i,
+                ndc: ndc.truncate(),
+                distance: clip.w,
+            });
+
+            let target = if self.occluded[i] { OCCLUDED_OPACITY } else { 1.0 };
+            self.opacity[i] += (target - self.opacity[i]) * fade;
+            let position = egui::pos2(
+                screen.min.x + (ndc.x + 1.0) * 0.5 * screen.width(),
+                screen.min.y + (1.0 - ndc.y) * 0.5 * screen.height(),
+            );
+            let alpha = self.opacity[i];
+            let font = egui::FontId::proportional(14.0);
+            let text_position = position - egui::vec2(0.0, 5.0);
+            painter.circle_filled(position, 3.0, label.color.gamma_multiply(alpha));
+            painter.text(text_position + egui::vec2(1.0, 1.0), egui::Align2::CENTER_BOTTOM, &label.text, font.clone(), egui::Color32::BLACK.gamma_multiply(alpha * 0.8));
+            painter.text(text_position, egui::Align2::CENTER_BOTTOM, &label.text, font, label.color.gamma_multiply(alpha));
+        }
+    }
+
+    /// Requests the depth under each painted label from `depth_texture`,
+    /// unless the previous request is still in flight.
+    pub fn encode_probes(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, depth_texture: &wgpu::Texture) {
+        let points: Vec<Vec2> = self.probes.iter().map(|probe| probe.ndc).collect();
+        if self.depth.encode(device, queue, encoder, depth_texture, &points, self.camera_range) {
+            self.in_flight = self.probes.clone();
+        }
+    }
+
+    /// Starts reading back the probes encoded this frame. Call after the
+    /// encoder has been submitted.
+    pub fn map_probes(&mut self) {
+        self.depth.map();
+    }
+
+    /// Picks up a finished readback, without waiting for the GPU.
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        let Some(distances) = self.depth.poll(device) else {
+            return;
+        };
+        for (probe, surface) in self.in_flight.iter().zip(distances) {
+            if let Some(occluded) = self.occluded.get_mut(probe.label) {
+                *occluded = surface < probe.distance * (1.0 - OCCLUSION_TOLERANCE);
+            }
+        }
+    }
+}
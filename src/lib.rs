@@ -0,0 +1,76 @@
+//! The viewer as a library, so integration tests can drive the headless
+//! renderer; `main.rs` is the command line around it.
+
+// The modules are public for the tests' sake rather than as an API
+#![allow(clippy::new_without_default, clippy::len_without_is_empty)]
+
+pub mod analysis;
+pub mod amf;
+pub mod bom;
+pub mod blueprint;
+pub mod aov;
+pub mod app;
+pub mod camera;
+pub mod camera_controller;
+pub mod capture;
+pub mod clip_plane;
+pub mod cli;
+pub mod cluster;
+pub mod color;
+pub mod dataset;
+pub mod dedup;
+pub mod depth_probe;
+pub mod detail_view;
+#[cfg(feature = "draco")]
+pub mod draco;
+pub mod environment;
+pub mod gizmo;
+pub mod governor;
+pub mod grid;
+pub mod gltf;
+pub mod handles;
+pub mod history;
+pub mod inspector;
+pub mod labels;
+pub mod light;
+pub mod loader;
+pub mod material;
+pub mod measure;
+pub mod menu;
+pub mod morph;
+pub mod mesh;
+pub mod normal_lines;
+pub mod obj_export;
+pub mod obj_groups;
+pub mod renderer;
+pub mod replay;
+pub mod ruler;
+pub mod samples;
+pub mod scene;
+pub mod server;
+pub mod shading;
+pub mod session;
+pub mod settings;
+pub mod shaders;
+pub mod still;
+pub mod streaming;
+pub mod terrain;
+pub mod texture_cache;
+pub mod texture_decoder;
+pub mod timeline;
+pub mod performance;
+pub mod picking;
+pub mod ply;
+pub mod point_cloud;
+pub mod postprocess;
+pub mod presentation;
+pub mod quantize;
+pub mod upload;
+pub mod uv_layout;
+pub mod view_cube;
+pub mod view_state;
+pub mod viewports;
+pub mod watcher;
+pub mod x3d;
+pub mod xml;
+pub mod overlay;
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+/// Most lights the shaders take; further ones are left out.
+pub const MAX_LIGHTS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LightKind {
+    /// Shines from `position` in every direction.
+    #[default]
+    Point,
+    /// Shines from infinitely far away, from the direction of `position`
+    /// as seen from the origin.
+    Directional,
+}
+
+impl LightKind {
+    pub const ALL: [LightKind; 2] = [LightKind::Point, LightKind::Directional];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LightKind::Point => "Point",
+            LightKind::Directional => "Directional",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Light {
+    // Views saved before there were directional lights only had point lights
+    #[serde(default)]
+    pub kind: LightKind,
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub ambient_strength: f32,
+    pub diffuse_strength: f32,
+    pub specular_strength: f32,
+    pub shininess: f32,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point,
+            position: [5.0, 5.0, 5.0],
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+            ambient_strength: 0.2,
+            diffuse_strength: 0.7,
+            specular_strength: 0.5,
+            shininess: 32.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightUniforms {
+    // w is 1 for point lights and 0 for directional ones
+    position: [f32; 4],
+    color: [f32; 4],
+    intensity: f32,
+    ambient_strength: f32,
+    diffuse_strength: f32,
+    specular_strength: f32,
+    shininess: f32,
+    _pad: [f32; 3], // Pad to 16-byte alignment
+}
+
+impl From<&Light> for LightUniforms {
+    fn from(light: &Light) -> Self {
+        let [x, y, z] = light.position;
+        let [r, g, b] = light.color;
+        let w = match light.kind {
+            LightKind::Point => 1.0,
+            LightKind::Directional => 0.0,
+        };
+        Self {
+            position: [x, y, z, w],
+            color: [r, g, b, 0.0],
+            intensity: light.intensity,
+            ambient_strength: light.ambient_strength,
+            diffuse_strength: light.diffuse_strength,
+            specular_strength: light.specular_strength,
+            shininess: light.shininess,
+            _pad: [0.0; 3],
+        }
+    }
+}
+
+/// Every light of the scene, as one uniform block.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightsUniform {
+    lights: [LightUniforms; MAX_LIGHTS],
+    count: u32,
+    environment_intensity: f32,
+    environment_rotation: f32,
+    specular_levels: f32,
+}
+
+impl LightsUniform {
+    pub fn new(lights: &[Light]) -> Self {
+        let mut uniform: Self = bytemuck::Zeroable::zeroed();
+        for (slot, light) in uniform.lights.iter_mut().zip(lights) {
+            *slot = LightUniforms::from(light);
+        }
+        uniform.count = lights.len().min(MAX_LIGHTS) as u32;
+        uniform
+    }
+
+    /// Lights the scene with an environment map as well, `intensity` 0 for
+    /// none, turned by `rotation` degrees about Y.
+    pub fn with_environment(mut self, intensity: f32, rotation: f32, specular_levels: u32) -> Self {
+        self.environment_intensity = intensity;
+        self.environment_rotation = rotation.to_radians();
+        self.specular_levels = specular_levels as f32;
+        self
+    }
+}
@@ -0,0 +1,266 @@
+use anyhow::{Context, Result};
+use glam::Vec3;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+use crate::history::Snapshot;
+use crate::mesh::Mesh;
+use crate::point_cloud::{self, PointCloud};
+use crate::shading::{self, ShadingOptions};
+use crate::terrain::{self, TerrainOptions};
+
+/// Counters a model load updates while parsing, read by the UI for its
+/// progress bar.
+pub struct LoadProgress {
+    pub path: PathBuf,
+    total_bytes: u64,
+    bytes_read: AtomicU64,
+    vertices: AtomicUsize,
+}
+
+impl LoadProgress {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            total_bytes: std::fs::metadata(path).map_or(0, |metadata| metadata.len()),
+            bytes_read: AtomicU64::new(0),
+            vertices: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn add_bytes(&self, bytes: usize) {
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_vertices(&self, vertices: usize) {
+        self.vertices.fetch_add(vertices, Ordering::Relaxed);
+    }
+
+    /// Marks the whole file as read, for formats parsed in one go.
+    pub fn finish_reading(&self) {
+        self.bytes_read.store(self.total_bytes, Ordering::Relaxed);
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed).min(self.total_bytes)
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    pub fn vertices(&self) -> usize {
+        self.vertices.load(Ordering::Relaxed)
+    }
+
+    /// Fraction of the file read so far.
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        self.bytes_read() as f32 / self.total_bytes as f32
+    }
+}
+
+/// Passes reads through to `inner`, counting the bytes consumed into a
+/// `LoadProgress`.
+pub struct ProgressReader<'a, R> {
+    inner: R,
+    progress: &'a LoadProgress,
+}
+
+impl<'a, R> ProgressReader<'a, R> {
+    pub fn new(inner: R, progress: &'a LoadProgress) -> Self {
+        Self { inner, progress }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.add_bytes(read);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for ProgressReader<'_, R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.progress.add_bytes(amount);
+        self.inner.consume(amount);
+    }
+}
+
+/// A model parsed into memory, ready to be uploaded by the renderer.
+pub enum LoadedModel {
+    Mesh(Box<Mesh>),
+    PointCloud(PointCloud),
+}
+
+/// Counts, bounds and file checksum of a loaded model, compared on reload.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelSummary {
+    pub vertices: usize,
+    pub faces: usize,
+    pub bounds: (Vec3, Vec3),
+    pub checksum: u64,
+}
+
+impl ModelSummary {
+    pub fn new(model: &LoadedModel, path: &Path) -> Result<Self> {
+        let (vertices, faces, bounds) = match model {
+            LoadedModel::Mesh(mesh) => {
+                let bounds = mesh.vertices.iter().fold(
+                    (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+                    |(min, max), vertex| (min.min(Vec3::from(vertex.position)), max.max(Vec3::from(vertex.position))),
+                );
+                (mesh.vertices.len(), mesh.triangle_count(), bounds)
+            }
+            LoadedModel::PointCloud(cloud) => (cloud.points.len(), 0, cloud.bounds),
+        };
+        Ok(Self {
+            vertices,
+            faces,
+            bounds,
+            checksum: checksum(path).with_context(|| format!("Failed to read {:?}", path))?,
+        })
+    }
+
+    /// One line per property, showing how it changed from `previous` when
+    /// there's one to compare with.
+    pub fn diff(&self, previous: Option<&ModelSummary>) -> Vec<String> {
+        let count = |name: &str, new: usize, old: Option<usize>| match old {
+            Some(old) if old != new => format!("{}: {} -> {} ({:+})", name, old, new, new as i64 - old as i64),
+            Some(_) => format!("{}: {} (unchanged)", name, new),
+            None => format!("{}: {}", name, new),
+        };
+        let size = self.bounds.1 - self.bounds.0;
+        let size_text = format!("Size: {:.4} x {:.4} x {:.4}", size.x, size.y, size.z);
+        let bounds = match previous {
+            Some(previous) if previous.bounds != self.bounds => {
+                let delta = size - (previous.bounds.1 - previous.bounds.0);
+                let shift = (self.bounds.0 + self.bounds.1 - previous.bounds.0 - previous.bounds.1) * 0.5;
+                format!(
+                    "{} ({:+.4}, {:+.4}, {:+.4}), center moved {:.4}",
+                    size_text,
+                    delta.x,
+                    delta.y,
+                    delta.z,
+                    shift.length()
+                )
+            }
+            Some(_) => format!("{} (unchanged)", size_text),
+            None => size_text,
+        };
+        let checksum = match previous {
+            Some(previous) if previous.checksum != self.checksum => {
+                format!("Checksum: {:016x} (was {:016x})", self.checksum, previous.checksum)
+            }
+            Some(_) => format!("Checksum: {:016x} (file unchanged)", self.checksum),
+            None => format!("Checksum: {:016x}", self.checksum),
+        };
+        vec![
+            count("Vertices", self.vertices, previous.map(|previous| previous.vertices)),
+            count("Faces", self.faces, previous.map(|previous| previous.faces)),
+            bounds,
+            checksum,
+        ]
+    }
+}
+
+// 64-bit FNV-1a of the file; enough to tell files apart without an extra
+// dependency. Read in chunks so large models aren't held in memory twice
+fn checksum(path: &Path) -> std::io::Result<u64> {
+    let mut reader = BufReader::with_capacity(64 * 1024, File::open(path)?);
+    let mut hash = 0xcbf2_9ce4_8422_2325;
+    loop {
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            return Ok(hash);
+        }
+        hash = chunk.iter().fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3));
+        let length = chunk.len();
+        reader.consume(length);
+    }
+}
+
+/// Parses the mesh, height map or point cloud at `path`, with its normals
+/// made as `shading` asks.
+pub fn parse(path: &Path, terrain_options: TerrainOptions, shading: ShadingOptions, progress: &LoadProgress) -> Result<LoadedModel> {
+    if point_cloud::is_point_cloud(path) {
+        let cloud = point_cloud::load(path)?;
+        progress.finish_reading();
+        progress.add_vertices(cloud.points.len());
+        return Ok(LoadedModel::PointCloud(cloud));
+    }
+    let mut mesh = Mesh::new();
+    if terrain::is_heightmap(path) {
+        mesh.load_heightmap(path, terrain_options)?;
+        progress.finish_reading();
+        progress.add_vertices(mesh.vertices.len());
+    } else {
+        mesh.load_with_progress(path, progress)?;
+    }
+    shading::apply(&mut mesh, shading);
+    Ok(LoadedModel::Mesh(Box::new(mesh)))
+}
+
+/// What a background load hands back: the model with what the thread
+/// worked out about it.
+pub struct ParsedModel {
+    pub model: LoadedModel,
+    pub summary: ModelSummary,
+    pub snapshot: Option<Snapshot>,
+}
+
+/// A model being parsed on a background thread, so the window keeps
+/// drawing the previous one meanwhile.
+pub struct BackgroundLoad {
+    pub progress: Arc<LoadProgress>,
+    // Set when the file changed on disk and replaces the model showing
+    pub reload: bool,
+    // Set when the same file is parsed again with other normal or terrain
+    // options; the view and hierarchy are kept but no history version added
+    pub reparse: bool,
+    receiver: Receiver<Result<ParsedModel>>,
+}
+
+impl BackgroundLoad {
+    pub fn spawn(path: &Path, terrain_options: TerrainOptions, shading: ShadingOptions) -> Self {
+        let progress = Arc::new(LoadProgress::new(path));
+        let (sender, receiver) = mpsc::channel();
+        let thread_progress = progress.clone();
+        std::thread::spawn(move || {
+            // The receiver is gone when another load replaced this one
+            let path = &thread_progress.path;
+            let result = parse(path, terrain_options, shading, &thread_progress).and_then(|model| {
+                let summary = ModelSummary::new(&model, path)?;
+                let snapshot = Snapshot::new(&model, summary);
+                Ok(ParsedModel { model, summary, snapshot })
+            });
+            let _ = sender.send(result);
+        });
+        Self {
+            progress,
+            reload: false,
+            reparse: false,
+            receiver,
+        }
+    }
+
+    /// The parsed model, once the thread is done.
+    pub fn try_finish(&self) -> Option<Result<ParsedModel>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(anyhow::anyhow!("The loader thread stopped without a result"))),
+        }
+    }
+}
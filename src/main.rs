@@ -1,23 +1,38 @@
 use anyhow::Result;
 use tracing::info;
 
-use crate::app::App;
-
-mod app;
-mod camera;
-mod menu;
-mod mesh;
-mod renderer;
-mod shaders;
-mod performance;
-// mod overlay;
+use dotobjviewer::app::App;
+use dotobjviewer::cli::{self, Command};
+use dotobjviewer::{dataset, replay, server};
 
 fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
-    info!("Starting DotObjViewer...");
-    
-    let app = App::new()?;
-    app.run()?;
-    
+
+    match cli::parse(std::env::args().skip(1))? {
+        Command::Help => {
+            println!("{}", cli::USAGE);
+        }
+        Command::Dataset(options) => {
+            info!("Rendering dataset from {:?}...", options.model);
+            dataset::run(&options)?;
+        }
+        Command::Serve { address, root } => {
+            server::run(&address, &root)?;
+        }
+        Command::Replay { replay, output } => {
+            info!("Replaying {:?}...", replay);
+            replay::render(&replay, &output)?;
+        }
+        Command::Viewer { safe_mode, preset, record, replay } => {
+            info!("Starting DotObjViewer...");
+            if safe_mode {
+                info!("Safe mode: fallback adapter, no post-processing, session left untouched");
+            }
+
+            let app = App::new(safe_mode, preset, record, replay)?;
+            app.run()?;
+        }
+    }
+
     Ok(())
 }
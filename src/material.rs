@@ -0,0 +1,631 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use wgpu::util::DeviceExt;
+
+use crate::upload;
+
+const PLACEHOLDER_SIZE: u32 = 64;
+const PLACEHOLDER_CHECK: u32 = 8;
+
+/// A texture referenced by a material that could not be used.
+#[derive(Debug, Clone)]
+pub struct TextureWarning {
+    pub material: String,
+    pub path: String,
+    pub reason: String,
+}
+
+/// Texture maps of a material, in the order they're bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureSlot {
+    BaseColor,
+    // Metallic in the blue channel, roughness in the green one, as glTF
+    // packs them; grayscale maps work for either
+    Metallic,
+    Roughness,
+    Normal,
+    // Ambient occlusion in the red channel
+    Occlusion,
+}
+
+impl TextureSlot {
+    pub const ALL: [TextureSlot; 5] = [
+        TextureSlot::BaseColor,
+        TextureSlot::Metallic,
+        TextureSlot::Roughness,
+        TextureSlot::Normal,
+        TextureSlot::Occlusion,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TextureSlot::BaseColor => "Base color",
+            TextureSlot::Metallic => "Metallic",
+            TextureSlot::Roughness => "Roughness",
+            TextureSlot::Normal => "Normal",
+            TextureSlot::Occlusion => "Occlusion",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    // Colors are stored in sRGB; the other maps hold linear values
+    pub fn srgb(self) -> bool {
+        self == TextureSlot::BaseColor
+    }
+
+    // Texel of the stand-in for a map the material doesn't have
+    fn default_texel(self) -> [u8; 4] {
+        match self {
+            TextureSlot::Normal => [128, 128, 255, 255],
+            _ => [255; 4],
+        }
+    }
+}
+
+/// Metallic-roughness parameters of a material. OBJ files carry them in the
+/// MTL PBR extension (`Pm`, `Pr`, `map_Pm`, `map_Pr` and `norm`), and glTF
+/// materials are converted to the same statements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PbrParams {
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+impl Default for PbrParams {
+    fn default() -> Self {
+        Self {
+            metallic: 0.0,
+            roughness: 0.5,
+        }
+    }
+}
+
+impl PbrParams {
+    /// The material's parameters, or `None` for materials without any, which
+    /// keep Phong shading.
+    pub fn from_mtl(mtl: &tobj::Material) -> Option<Self> {
+        let param = |key: &str| mtl.unknown_param.get(key);
+        if !["Pm", "Pr", "map_Pm", "map_Pr"].into_iter().any(|key| param(key).is_some()) {
+            return None;
+        }
+        let value = |key: &str, default: f32| param(key).and_then(|value| value.trim().parse().ok()).unwrap_or(default);
+        let default = Self::default();
+        // A map alone scales a factor of 1, as in glTF
+        let factor_default = |map: &str, default: f32| if param(map).is_some() { 1.0 } else { default };
+        Some(Self {
+            metallic: value("Pm", factor_default("map_Pm", default.metallic)).clamp(0.0, 1.0),
+            roughness: value("Pr", factor_default("map_Pr", default.roughness)).clamp(0.0, 1.0),
+        })
+    }
+
+    /// Writes the parameters into `mtl`, or with `None` removes them so it's
+    /// shaded with Phong again. Its maps are kept either way.
+    pub fn store(params: Option<Self>, mtl: &mut tobj::Material) {
+        match params {
+            Some(params) => {
+                mtl.unknown_param.insert("Pm".to_string(), params.metallic.to_string());
+                mtl.unknown_param.insert("Pr".to_string(), params.roughness.to_string());
+            }
+            None => {
+                mtl.unknown_param.remove("Pm");
+                mtl.unknown_param.remove("Pr");
+                // Maps alone would turn it back into a PBR material
+                for key in ["map_Pm", "map_Pr"] {
+                    if let Some(map) = mtl.unknown_param.remove(key) {
+                        mtl.unknown_param.insert(format!("#{}", key), map);
+                    }
+                }
+            }
+        }
+        if params.is_some() {
+            for key in ["map_Pm", "map_Pr"] {
+                if let Some(map) = mtl.unknown_param.remove(&format!("#{}", key)) {
+                    mtl.unknown_param.insert(key.to_string(), map);
+                }
+            }
+        }
+    }
+}
+
+/// Splits the options off an MTL texture statement such as
+/// `-bm 0.5 textures/normal.png`, returning the file and the bump
+/// multiplier (1 without `-bm`).
+pub fn parse_texture_statement(statement: &str) -> (String, f32) {
+    let mut tokens = statement.split_whitespace().peekable();
+    let mut bump_multiplier = 1.0;
+    while let Some(option) = tokens.peek().copied().filter(|token| token.starts_with('-') && token.parse::<f32>().is_err()) {
+        tokens.next();
+        match option {
+            // Options taking a word
+            "-blendu" | "-blendv" | "-cc" | "-clamp" | "-imfchan" | "-type" => {
+                tokens.next();
+            }
+            _ => {
+                let mut values = Vec::new();
+                while let Some(value) = tokens.peek().and_then(|token| token.parse::<f32>().ok()) {
+                    values.push(value);
+                    tokens.next();
+                }
+                if option == "-bm" {
+                    bump_multiplier = values.first().copied().unwrap_or(1.0);
+                }
+            }
+        }
+    }
+    (tokens.collect::<Vec<_>>().join(" "), bump_multiplier)
+}
+
+/// The texture file `mtl` uses in `slot`, with its bump multiplier. Normal
+/// and occlusion maps only apply to PBR materials: for the others `map_Bump`
+/// is usually a height map.
+pub fn texture_statement(mtl: &tobj::Material, slot: TextureSlot) -> Option<(String, f32)> {
+    let param = |key: &str| mtl.unknown_param.get(key).cloned();
+    let pbr = PbrParams::from_mtl(mtl).is_some();
+    let statement = match slot {
+        TextureSlot::BaseColor => mtl.diffuse_texture.clone(),
+        TextureSlot::Metallic => param("map_Pm"),
+        TextureSlot::Roughness => param("map_Pr"),
+        TextureSlot::Normal => param("norm").or_else(|| mtl.normal_texture.clone()).filter(|_| pbr),
+        TextureSlot::Occlusion => param("map_ao").or_else(|| param("map_AO")).filter(|_| pbr),
+    }?;
+    let (file, bump_multiplier) = parse_texture_statement(&statement);
+    (!file.is_empty()).then_some((file, bump_multiplier))
+}
+
+/// Sets the strength of `mtl`'s normal map, if it has one.
+pub fn set_normal_scale(mtl: &mut tobj::Material, scale: f32) {
+    let Some((texture, _)) = texture_statement(mtl, TextureSlot::Normal) else {
+        return;
+    };
+    let statement = format!("-bm {} {}", scale, texture);
+    match mtl.unknown_param.get_mut("norm") {
+        Some(norm) => *norm = statement,
+        None => mtl.normal_texture = Some(statement),
+    }
+}
+
+/// MTL colors and PBR parameters of a material, bound next to its textures.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialUniforms {
+    // `w` is 1 when the material sets its own colors, 0 to keep vertex colors
+    diffuse: [f32; 4],
+    // `w` is 1 for clay, blending from this color below to the diffuse one above
+    ambient: [f32; 4],
+    // `w` is the shininess (Ns), 0 to use the light's. Clay's rim light.
+    specular: [f32; 4],
+    // Metallic, roughness and normal map strength; `w` is 1 for physically
+    // based shading
+    pbr: [f32; 4],
+}
+
+impl MaterialUniforms {
+    /// Keeps vertex colors and the light's default shading.
+    pub const DEFAULT: Self = Self {
+        diffuse: [1.0, 1.0, 1.0, 0.0],
+        ambient: [1.0, 1.0, 1.0, 0.0],
+        specular: [1.0, 1.0, 1.0, 0.0],
+        pbr: [0.0, 0.5, 1.0, 0.0],
+    };
+
+    /// Two-tone clay, `top` on surfaces facing up and `bottom` on those
+    /// facing down, lit by the lights and a `rim` light around the
+    /// silhouette.
+    pub fn clay(top: [f32; 3], bottom: [f32; 3], rim: [f32; 3]) -> Self {
+        Self {
+            diffuse: [top[0], top[1], top[2], 1.0],
+            ambient: [bottom[0], bottom[1], bottom[2], 1.0],
+            specular: [rim[0], rim[1], rim[2], 0.0],
+            pbr: [0.0, 0.5, 1.0, 0.0],
+        }
+    }
+
+    /// `Kd`, `Ka`, `Ks` and `Ns` of an MTL material. Missing ambient falls
+    /// back to the diffuse color and missing specular to none.
+    pub fn from_mtl(mtl: &tobj::Material) -> Self {
+        let diffuse = mtl.diffuse.unwrap_or([0.8; 3]);
+        let ambient = mtl.ambient.unwrap_or(diffuse);
+        let specular = mtl.specular.unwrap_or([0.0; 3]);
+        let pbr = PbrParams::from_mtl(mtl);
+        let normal_scale = texture_statement(mtl, TextureSlot::Normal).map_or(1.0, |(_, scale)| scale);
+        let params = pbr.unwrap_or_default();
+        Self {
+            diffuse: [diffuse[0], diffuse[1], diffuse[2], 1.0],
+            ambient: [ambient[0], ambient[1], ambient[2], 0.0],
+            specular: [specular[0], specular[1], specular[2], mtl.shininess.unwrap_or(0.0).max(0.0)],
+            pbr: [params.metallic, params.roughness, normal_scale, if pbr.is_some() { 1.0 } else { 0.0 }],
+        }
+    }
+}
+
+/// Bind group layout, sampler and stand-in textures all materials share.
+pub struct MaterialResources {
+    pub layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    // 1x1 textures for the maps a material doesn't have, by slot
+    defaults: [Rc<wgpu::Texture>; 5],
+    // Magenta/black checker shown in place of color maps that failed to load
+    missing: Rc<wgpu::Texture>,
+}
+
+impl MaterialResources {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let upload = |label, width, height, rgba: &[u8], srgb| {
+            let texture = create_texture(device, label, width, height, 1, srgb);
+            queue.write_texture(
+                texture.as_image_copy(),
+                rgba,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width * 4),
+                    rows_per_image: Some(height),
+                },
+                texture.size(),
+            );
+            Rc::new(texture)
+        };
+        let mut checker = Vec::with_capacity((PLACEHOLDER_SIZE * PLACEHOLDER_SIZE * 4) as usize);
+        for y in 0..PLACEHOLDER_SIZE {
+            for x in 0..PLACEHOLDER_SIZE {
+                let magenta = (x / PLACEHOLDER_CHECK + y / PLACEHOLDER_CHECK).is_multiple_of(2);
+                checker.extend_from_slice(if magenta { &[255, 0, 255, 255] } else { &[0, 0, 0, 255] });
+            }
+        }
+        Self {
+            layout: create_bind_group_layout(device),
+            sampler: create_sampler(device),
+            defaults: TextureSlot::ALL.map(|slot| upload("Default Material Texture", 1, 1, &slot.default_texel(), slot.srgb())),
+            missing: upload("Missing Texture", PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, &checker, true),
+        }
+    }
+}
+
+// Mip levels of a map still being written, the level being written and its rows done
+struct PendingUpload {
+    texture: Rc<wgpu::Texture>,
+    levels: Vec<Vec<u8>>,
+    level: u32,
+    rows_written: u32,
+}
+
+/// GPU side of a material: its texture maps and colors bound at group 2.
+pub struct GpuMaterial {
+    // Shared with the texture cache and other materials using the same images
+    textures: [Rc<wgpu::Texture>; 5],
+    uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pending: Vec<PendingUpload>,
+}
+
+fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    };
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Material Bind Group Layout"),
+        entries: &[
+            texture_entry(0),
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            texture_entry(3),
+            texture_entry(4),
+            texture_entry(5),
+            texture_entry(6),
+        ],
+    })
+}
+
+fn create_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Material Sampler"),
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        address_mode_w: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+fn create_texture(device: &wgpu::Device, label: &str, width: u32, height: u32, mip_level_count: u32, srgb: bool) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: if srgb { wgpu::TextureFormat::Rgba8UnormSrgb } else { wgpu::TextureFormat::Rgba8Unorm },
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+impl GpuMaterial {
+    /// Material with the given colors and no maps; `set_texture` and
+    /// `set_deferred` add them.
+    pub fn new(device: &wgpu::Device, resources: &MaterialResources, label: &str, uniforms: MaterialUniforms) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let textures = resources.defaults.clone();
+        let bind_group = Self::create_bind_group(device, resources, label, &textures, &uniform_buffer);
+        Self {
+            textures,
+            uniform_buffer,
+            bind_group,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Draws with `texture`, e.g. from the texture cache, in `slot`.
+    pub fn set_texture(&mut self, device: &wgpu::Device, resources: &MaterialResources, slot: TextureSlot, texture: Rc<wgpu::Texture>) {
+        self.textures[slot.index()] = texture;
+        self.bind_group = Self::create_bind_group(device, resources, "Material", &self.textures, &self.uniform_buffer);
+    }
+
+    /// Like `set_texture` for the mip levels of `mip_chain`, but the pixels
+    /// are written by `upload_pending` over the next frames instead of right
+    /// away. Returns the texture, for the cache.
+    pub fn set_deferred(
+        &mut self,
+        device: &wgpu::Device,
+        resources: &MaterialResources,
+        slot: TextureSlot,
+        label: &str,
+        levels: Vec<(u32, u32, Vec<u8>)>,
+    ) -> Rc<wgpu::Texture> {
+        let (width, height) = levels.first().map_or((1, 1), |&(width, height, _)| (width, height));
+        let texture = Rc::new(create_texture(device, label, width, height, levels.len().max(1) as u32, slot.srgb()));
+        self.pending.push(PendingUpload {
+            texture: texture.clone(),
+            levels: levels.into_iter().map(|(_, _, rgba)| rgba).collect(),
+            level: 0,
+            rows_written: 0,
+        });
+        self.set_texture(device, resources, slot, texture.clone());
+        texture
+    }
+
+    /// Shows the checker in place of a color map that couldn't be loaded;
+    /// other maps fall back to their stand-ins.
+    pub fn set_missing(&mut self, device: &wgpu::Device, resources: &MaterialResources, slot: TextureSlot) {
+        let texture = if slot == TextureSlot::BaseColor { resources.missing.clone() } else { resources.defaults[slot.index()].clone() };
+        self.set_texture(device, resources, slot, texture);
+    }
+
+    pub fn set_uniforms(&self, queue: &wgpu::Queue, uniforms: MaterialUniforms) {
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    /// Textures still being uploaded, which aren't complete yet.
+    pub fn pending_textures(&self) -> impl Iterator<Item = &Rc<wgpu::Texture>> {
+        self.pending.iter().map(|upload| &upload.texture)
+    }
+
+    /// Writes the next rows of deferred uploads within `budget` bytes, which
+    /// are taken off the budget. Returns true while rows are still waiting.
+    pub fn upload_pending(&mut self, queue: &wgpu::Queue, budget: &mut usize) -> bool {
+        for upload in &mut self.pending {
+            while *budget > 0 && (upload.level as usize) < upload.levels.len() {
+                if upload::write_texture_rows(queue, &upload.texture, upload.level, &upload.levels[upload.level as usize], &mut upload.rows_written, budget) {
+                    upload.level += 1;
+                    upload.rows_written = 0;
+                }
+            }
+        }
+        self.pending.retain(|upload| (upload.level as usize) < upload.levels.len());
+        !self.pending.is_empty()
+    }
+
+    /// False while a texture is still being uploaded.
+    pub fn is_uploaded(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Bytes of deferred uploads that haven't been written yet.
+    pub fn pending_upload_bytes(&self) -> usize {
+        self.pending
+            .iter()
+            .map(|upload| {
+                let level_width = (upload.texture.width() >> upload.level).max(1) as usize;
+                upload.levels[upload.level as usize..].iter().map(Vec::len).sum::<usize>() - upload.rows_written as usize * level_width * 4
+            })
+            .sum()
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        resources: &MaterialResources,
+        label: &str,
+        textures: &[Rc<wgpu::Texture>; 5],
+        uniform_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        let views = textures.each_ref().map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        let [base_color, metallic, roughness, normal, occlusion] = &views;
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout: &resources.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(base_color),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&resources.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(metallic),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(roughness),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(normal),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(occlusion),
+                },
+            ],
+        })
+    }
+}
+
+/// Finds a texture referenced from an MTL file. Besides the path as written
+/// (relative to the model), the bare file name is looked up next to the model
+/// and in a `textures` folder, which covers most exports from other machines.
+pub fn resolve_texture_path(model_dir: &Path, texture: &str) -> Option<PathBuf> {
+    let texture = texture.replace('\\', "/");
+    let written = model_dir.join(&texture);
+    let file_name = Path::new(&texture).file_name()?;
+    [
+        written,
+        model_dir.join(file_name),
+        model_dir.join("textures").join(file_name),
+    ]
+    .into_iter()
+    .find(|candidate| candidate.is_file())
+}
+
+/// Decodes an image file to tightly packed RGBA8.
+pub fn load_rgba(path: &Path) -> Result<(u32, u32, Vec<u8>)> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    let open = || std::fs::File::open(path).map(std::io::BufReader::new).with_context(|| format!("Failed to open {:?}", path));
+    match extension.as_deref() {
+        Some("png") => decode_png(open()?),
+        Some("jpg" | "jpeg") => decode_jpeg(open()?),
+        Some(other) => bail!("Unsupported texture format .{}", other),
+        None => bail!("Unknown texture format"),
+    }
+}
+
+/// Decodes a PNG or JPEG image held in memory, e.g. embedded in a GLB.
+pub fn decode_rgba(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>)> {
+    if bytes.starts_with(b"\x89PNG") {
+        decode_png(bytes)
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        decode_jpeg(bytes)
+    } else {
+        bail!("Unsupported embedded image format")
+    }
+}
+
+fn decode_png(reader: impl std::io::Read) -> Result<(u32, u32, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => bail!("Indexed PNG was not expanded"),
+    };
+    Ok((info.width, info.height, rgba))
+}
+
+fn decode_jpeg(reader: impl std::io::Read) -> Result<(u32, u32, Vec<u8>)> {
+    let mut decoder = jpeg_decoder::Decoder::new(reader);
+    let pixels = decoder.decode()?;
+    let info = decoder.info().context("JPEG has no image header")?;
+
+    let rgba = match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => pixels.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        jpeg_decoder::PixelFormat::L8 => pixels.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        // Big-endian 16-bit grey, keep the high byte
+        jpeg_decoder::PixelFormat::L16 => pixels.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], 255]).collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => pixels
+            .chunks_exact(4)
+            .flat_map(|p| {
+                let k = 255 - p[3] as u32;
+                let channel = |c: u8| ((255 - c as u32) * k / 255) as u8;
+                [channel(p[0]), channel(p[1]), channel(p[2]), 255]
+            })
+            .collect(),
+    };
+    Ok((info.width as u32, info.height as u32, rgba))
+}
+
+/// Halves an RGBA8 image with a 2x2 box filter until both sides fit within
+/// `max_dimension`, so oversized textures still load on smaller GPUs.
+pub fn fit_to_dimension(mut image: (u32, u32, Vec<u8>), max_dimension: u32) -> (u32, u32, Vec<u8>) {
+    while image.0 > max_dimension || image.1 > max_dimension {
+        image = downsample(&image, image.0.div_ceil(2), image.1.div_ceil(2));
+    }
+    image
+}
+
+/// The image followed by its mip levels, each half the size of the one
+/// before (rounded down, as the GPU sizes them) down to 1x1.
+pub fn mip_chain(image: (u32, u32, Vec<u8>)) -> Vec<(u32, u32, Vec<u8>)> {
+    let mut levels = vec![image];
+    while let Some(last) = levels.last().filter(|(width, height, _)| *width > 1 || *height > 1) {
+        let half = downsample(last, (last.0 / 2).max(1), (last.1 / 2).max(1));
+        levels.push(half);
+    }
+    levels
+}
+
+// 2x2 box filter of an RGBA8 image down to `half_width` by `half_height`
+fn downsample((width, height, rgba): &(u32, u32, Vec<u8>), half_width: u32, half_height: u32) -> (u32, u32, Vec<u8>) {
+    let (width, height) = (*width, *height);
+    let mut half = Vec::with_capacity((half_width * half_height * 4) as usize);
+    for y in 0..half_height {
+        for x in 0..half_width {
+            let texels = [(0, 0), (1, 0), (0, 1), (1, 1)]
+                .map(|(dx, dy)| (((y * 2 + dy).min(height - 1) * width + (x * 2 + dx).min(width - 1)) * 4) as usize);
+            for channel in 0..4 {
+                let sum: u32 = texels.iter().map(|&texel| rgba[texel + channel] as u32).sum();
+                half.push(((sum + 2) / 4) as u8);
+            }
+        }
+    }
+    (half_width, half_height, half)
+}
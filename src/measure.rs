@@ -0,0 +1,95 @@
+use glam::Vec3;
+
+use crate::camera::Camera;
+
+const MEASURE_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 230, 255);
+
+/// Vertex to vertex measurement: each click on the model adds the vertex
+/// nearest to it, and the points are joined into a polyline labeled with
+/// each segment's length and, past one segment, the total. Without
+/// `polyline` a third click starts over, measuring between two points.
+pub struct Measure {
+    pub enabled: bool,
+    pub polyline: bool,
+    // World space, so they stay put when objects are moved afterwards
+    points: Vec<Vec3>,
+}
+
+impl Measure {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            polyline: false,
+            points: Vec::new(),
+        }
+    }
+
+    pub fn points(&self) -> &[Vec3] {
+        &self.points
+    }
+
+    pub fn add(&mut self, point: Vec3) {
+        if !self.polyline && self.points.len() >= 2 {
+            self.points.clear();
+        }
+        self.points.push(point);
+    }
+
+    /// Drops the last point.
+    pub fn undo(&mut self) {
+        self.points.pop();
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Length of the whole polyline.
+    pub fn total(&self) -> f32 {
+        self.points.windows(2).map(|pair| pair[0].distance(pair[1])).sum()
+    }
+
+    /// Paints the points and the lines between them as `camera` sees them.
+    /// The measurement is dropped when the tool is turned off.
+    pub fn paint(&mut self, ctx: &egui::Context, camera: &Camera) {
+        if !self.enabled {
+            self.points.clear();
+            return;
+        }
+        let view_projection = camera.projection_matrix() * camera.view_matrix();
+        let screen = ctx.screen_rect();
+        // Points behind the camera aren't drawn, nor the lines to them
+        let project = |point: Vec3| {
+            let clip = view_projection * point.extend(1.0);
+            (clip.w > 0.0).then(|| {
+                let ndc = clip.truncate() / clip.w;
+                egui::pos2(
+                    screen.min.x + (ndc.x * 0.5 + 0.5) * screen.width(),
+                    screen.min.y + (0.5 - ndc.y * 0.5) * screen.height(),
+                )
+            })
+        };
+        let screen_points: Vec<Option<egui::Pos2>> = self.points.iter().map(|&point| project(point)).collect();
+
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let stroke = egui::Stroke::new(2.0, MEASURE_COLOR);
+        let font = egui::FontId::proportional(14.0);
+        let label = |anchor: egui::Pos2, text: String| {
+            painter.text(anchor + egui::vec2(1.0, 1.0), egui::Align2::CENTER_BOTTOM, &text, font.clone(), egui::Color32::BLACK);
+            painter.text(anchor, egui::Align2::CENTER_BOTTOM, text, font.clone(), MEASURE_COLOR);
+        };
+        for (pair, screen_pair) in self.points.windows(2).zip(screen_points.windows(2)) {
+            let (Some(start), Some(end)) = (screen_pair[0], screen_pair[1]) else {
+                continue;
+            };
+            painter.line_segment([start, end], stroke);
+            label(start + (end - start) * 0.5 + egui::vec2(0.0, -8.0), format!("{:.4}", pair[0].distance(pair[1])));
+        }
+        for point in screen_points.iter().flatten() {
+            painter.circle_filled(*point, 4.0, MEASURE_COLOR);
+        }
+        if let Some(Some(last)) = screen_points.last().filter(|_| self.points.len() > 2) {
+            label(*last + egui::vec2(0.0, -10.0), format!("Total {:.4}", self.total()));
+        }
+    }
+}
@@ -13,8 +13,16 @@ impl Menu {
         info!("Opening file dialog...");
         
         let path = FileDialog::new()
-            .set_title("Open OBJ File")
+            .set_title("Open Model")
+            .add_filter("All Models", &["obj", "drc", "amf", "x3d", "wrl", "vrml", "ply", "gltf", "glb", "pcd", "e57", "png"])
             .add_filter("OBJ Files", &["obj"])
+            .add_filter("Draco Meshes", &["drc"])
+            .add_filter("AMF Files", &["amf"])
+            .add_filter("X3D / VRML Files", &["x3d", "wrl", "vrml"])
+            .add_filter("PLY Files", &["ply"])
+            .add_filter("glTF Files", &["gltf", "glb"])
+            .add_filter("Point Clouds", &["pcd", "e57"])
+            .add_filter("Height Maps", &["png"])
             .add_filter("All Files", &["*"])
             .show_open_single_file()?;
 
@@ -30,6 +38,75 @@ impl Menu {
         }
     }
 
+    pub fn open_view_file(&self) -> Result<Option<std::path::PathBuf>> {
+        let path = FileDialog::new()
+            .set_title("Import Camera")
+            .add_filter("Camera JSON", &["json"])
+            .show_open_single_file()?;
+        Ok(path)
+    }
+
+    pub fn open_samples_file(&self) -> Result<Option<std::path::PathBuf>> {
+        let path = FileDialog::new()
+            .set_title("Import Samples")
+            .add_filter("Sample Data", &["csv", "txt", "npy"])
+            .add_filter("All Files", &["*"])
+            .show_open_single_file()?;
+        Ok(path)
+    }
+
+    pub fn open_environment_file(&self) -> Result<Option<std::path::PathBuf>> {
+        let path = FileDialog::new()
+            .set_title("Load Environment")
+            .add_filter("Radiance HDR", &["hdr"])
+            .show_open_single_file()?;
+        Ok(path)
+    }
+
+    pub fn save_view_file(&self) -> Result<Option<std::path::PathBuf>> {
+        let path = FileDialog::new()
+            .set_title("Export Camera")
+            .set_filename("camera.json")
+            .add_filter("Camera JSON", &["json"])
+            .show_save_single_file()?;
+        Ok(path)
+    }
+
+    pub fn save_obj_file(&self, file_name: &str) -> Result<Option<std::path::PathBuf>> {
+        let path = FileDialog::new()
+            .set_title("Export OBJ")
+            .set_filename(file_name)
+            .add_filter("OBJ Files", &["obj"])
+            .show_save_single_file()?;
+        Ok(path)
+    }
+
+    pub fn save_png_file(&self, file_name: &str) -> Result<Option<std::path::PathBuf>> {
+        let path = FileDialog::new()
+            .set_title("Save Screenshot")
+            .set_filename(file_name)
+            .add_filter("PNG Images", &["png"])
+            .show_save_single_file()?;
+        Ok(path)
+    }
+
+    pub fn save_csv_file(&self, file_name: &str) -> Result<Option<std::path::PathBuf>> {
+        let path = FileDialog::new()
+            .set_title("Export CSV")
+            .set_filename(file_name)
+            .add_filter("CSV Files", &["csv"])
+            .show_save_single_file()?;
+        Ok(path)
+    }
+
+    pub fn pick_folder(&self, title: &str) -> Result<Option<std::path::PathBuf>> {
+        let path = FileDialog::new()
+            .set_title(title)
+            .show_open_single_dir()?;
+        Ok(path)
+    }
+
+    #[allow(dead_code)]
     pub fn save_file(&self) -> Result<()> {
         info!("Opening save file dialog...");
         
@@ -52,6 +129,7 @@ impl Menu {
         Ok(())
     }
 
+    #[allow(dead_code)]
     pub fn show_about(&self) -> Result<()> {
         MessageDialog::new()
             .set_type(MessageType::Info)
@@ -1,9 +1,17 @@
-use anyhow::Result;
-use tobj::{load_obj, LoadOptions};
+use anyhow::{Context, Result};
+use tobj::{load_mtl, load_obj_buf, LoadOptions};
+use std::collections::HashMap;
 use std::path::Path;
-use tracing::info;
+use tracing::{info, warn};
 use wgpu::util::DeviceExt;
-use glam::Vec3;
+use glam::{Mat4, Vec3};
+
+use crate::dedup::{self, Duplicate};
+use crate::loader::{LoadProgress, ProgressReader};
+use crate::obj_groups::StatementScanner;
+use crate::quantize::QuantizedVertices;
+use crate::shading::NormalMode;
+use crate::upload;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -11,6 +19,7 @@ pub struct Vertex {
     pub position: [f32; 3],
     pub normal: [f32; 3],
     pub color: [f32; 3],
+    pub tex_coords: [f32; 2],
 }
 
 impl Vertex {
@@ -34,17 +43,143 @@ impl Vertex {
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x3,
                 },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 3]>() * 3) as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
             ],
         }
     }
+
+    /// Position and normal of the morph target, read from a second buffer of
+    /// `Vertex` at locations 4 and 5.
+    pub fn morph_desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
+
+/// A run of indices drawn with a single material.
+#[derive(Debug, Clone)]
+pub struct DrawRange {
+    pub name: String,
+    pub start: u32,
+    pub count: u32,
+    pub material: Option<usize>,
+    /// Range of the enclosing object or group, e.g. an OBJ `o` around its
+    /// `g` groups. Ranges that only group others are empty; the material
+    /// parts of a group have its name.
+    pub parent: Option<usize>,
+    /// Placement relative to the parent given by the file, e.g. a glTF
+    /// node's transform; the identity for formats without one.
+    pub transform: Mat4,
+}
+
+/// Indexed triangles produced by the non-OBJ importers, appended to a `Mesh`
+/// as one draw range each.
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+    pub triangles: Vec<[u32; 3]>,
+    // Per position
+    pub normals: Option<Vec<[f32; 3]>>,
+    pub vertex_colors: Option<Vec<[f32; 3]>>,
+    pub tex_coords: Option<Vec<[f32; 2]>>,
+    // Per triangle, takes precedence over vertex colors
+    pub face_colors: Option<Vec<[f32; 3]>>,
+    /// Index into the mesh's materials.
+    pub material: Option<usize>,
+    /// Draw range this one is nested under. Parts are appended in order
+    /// after the mesh is cleared, so it's the index among the parts.
+    pub parent: Option<usize>,
+    /// Placement relative to the parent, applied on the GPU.
+    pub transform: Mat4,
 }
 
+const DEFAULT_COLOR: [f32; 3] = [0.8, 0.8, 0.8];
+
 pub struct Mesh {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
+    pub draw_ranges: Vec<DrawRange>,
+    pub materials: Vec<tobj::Material>,
+    // Set when the OBJ references a material library that couldn't be loaded
+    pub material_error: Option<String>,
     pub vertex_buffer: Option<wgpu::Buffer>,
     pub index_buffer: Option<wgpu::Buffer>,
     pub num_indices: u32,
+    // Unique edges of each draw range as index pairs, for the wireframe
+    edges: Vec<u32>,
+    pub edge_ranges: Vec<std::ops::Range<u32>>,
+    edge_buffer: Option<wgpu::Buffer>,
+    // Bytes of vertex, index and edge data written so far while the buffers
+    // are filled over several frames
+    upload: Option<[usize; 3]>,
+    // Compressed copy of `vertices` that fills the vertex buffer instead
+    quantized: Option<QuantizedVertices>,
+    pub has_uvs: bool,
+    /// Image bytes of textures stored inside the model file (GLB), keyed by
+    /// the name used as the material's diffuse texture.
+    pub embedded_textures: HashMap<String, Vec<u8>>,
+    // Axis-aligned box around the vertices, kept up to date by the loaders
+    bounds: Option<(Vec3, Vec3)>,
+    // Compacted buffer contents without the draw ranges that repeat others
+    shared: Option<SharedGeometry>,
+    /// How the vertex normals were made, see `shading::apply`.
+    pub normals: NormalMode,
+}
+
+// Vertices and indices of the draw ranges that aren't copies of earlier ones;
+// the copies are drawn from their source's indices, moved into place by their
+// object transform
+struct SharedGeometry {
+    duplicates: Vec<Option<Duplicate>>,
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    // First index of each draw range in `indices`
+    starts: Vec<u32>,
+}
+
+impl SharedGeometry {
+    // Copies the vertices and indices of the ranges that aren't duplicates,
+    // renumbering the vertices in order of first use
+    fn new(mesh: &Mesh, duplicates: Vec<Option<Duplicate>>) -> Self {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut starts = Vec::with_capacity(mesh.draw_ranges.len());
+        let mut renumbered = vec![u32::MAX; mesh.vertices.len()];
+        for (range, duplicate) in mesh.draw_ranges.iter().zip(&duplicates) {
+            if let Some(duplicate) = duplicate {
+                starts.push(starts[duplicate.source]);
+                continue;
+            }
+            starts.push(indices.len() as u32);
+            for &index in &mesh.indices[range.start as usize..(range.start + range.count) as usize] {
+                if renumbered[index as usize] == u32::MAX {
+                    renumbered[index as usize] = vertices.len() as u32;
+                    vertices.push(mesh.vertices[index as usize]);
+                }
+                indices.push(renumbered[index as usize]);
+            }
+        }
+        Self { duplicates, vertices, indices, starts }
+    }
 }
 
 impl Mesh {
@@ -52,22 +187,182 @@ impl Mesh {
         Self {
             vertices: Vec::new(),
             indices: Vec::new(),
+            draw_ranges: Vec::new(),
+            materials: Vec::new(),
+            material_error: None,
             vertex_buffer: None,
             index_buffer: None,
             num_indices: 0,
+            edges: Vec::new(),
+            edge_ranges: Vec::new(),
+            edge_buffer: None,
+            upload: None,
+            quantized: None,
+            has_uvs: false,
+            embedded_textures: HashMap::new(),
+            bounds: None,
+            shared: None,
+            normals: NormalMode::FromFile,
         }
     }
 
-    pub fn load_from_obj<P: AsRef<Path> + std::fmt::Debug>(&mut self, path: P) -> Result<()> {
-        info!("Loading OBJ file: {:?}", path.as_ref());
+    /// Loads any supported model format, picked by file extension.
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        self.load_with_progress(path, &LoadProgress::new(path))
+    }
+
+    /// Like `load`, counting the bytes and vertices parsed into `progress`;
+    /// only OBJ files report them as they go.
+    pub fn load_with_progress(&mut self, path: &Path, progress: &LoadProgress) -> Result<()> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+            .unwrap_or_default();
+        let parts: Vec<MeshData> = match extension.as_str() {
+            #[cfg(feature = "draco")]
+            "drc" => vec![crate::draco::load(path)?],
+            #[cfg(not(feature = "draco"))]
+            "drc" => anyhow::bail!("{:?} is a Draco mesh, which needs a build with the `draco` feature", path),
+            "amf" => crate::amf::load(path)?,
+            "wrl" | "vrml" => crate::x3d::load_vrml(path)?,
+            "x3d" => crate::x3d::load_x3d(path)?,
+            "ply" => crate::ply::load(path)?,
+            "gltf" | "glb" => {
+                self.load_gltf(path)?;
+                progress.finish_reading();
+                progress.add_vertices(self.vertices.len());
+                return Ok(());
+            }
+            _ => return self.load_from_obj(path, progress),
+        };
+
+        self.load_parts(path, parts)?;
+        progress.finish_reading();
+        progress.add_vertices(self.vertices.len());
+        Ok(())
+    }
+
+    /// Replaces the mesh with a terrain generated from a height map image.
+    pub fn load_heightmap(&mut self, path: &Path, options: crate::terrain::TerrainOptions) -> Result<()> {
+        let terrain = crate::terrain::load(path, options)?;
+        self.load_parts(path, vec![terrain])
+    }
+
+    fn load_parts(&mut self, path: &Path, parts: Vec<MeshData>) -> Result<()> {
+        info!("Loading {} parts from {:?}", parts.len(), path);
+        self.clear();
+        for part in parts {
+            self.append(part);
+        }
+        if self.indices.is_empty() {
+            anyhow::bail!("No triangles found in {:?}", path);
+        }
+        info!("Loaded mesh with {} vertices and {} indices", self.vertices.len(), self.indices.len());
+        Ok(())
+    }
+
+    /// Loads a glTF 2.0 scene with node transforms applied and base colors as
+    /// materials.
+    fn load_gltf(&mut self, path: &Path) -> Result<()> {
+        let model = crate::gltf::load(path)?;
+        self.load_parts(path, model.parts)?;
+        self.materials = model.materials;
+        self.embedded_textures = model.textures;
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.draw_ranges.clear();
+        self.materials.clear();
+        self.material_error = None;
+        self.has_uvs = false;
+        self.embedded_textures.clear();
+        self.quantized = None;
+        self.bounds = None;
+        self.shared = None;
+        self.normals = NormalMode::FromFile;
+    }
+
+    /// Adds `data` as a new draw range. Faces with their own color get
+    /// unshared, flat-shaded vertices so the color doesn't bleed.
+    pub fn append(&mut self, data: MeshData) {
+        self.shared = None;
+        self.bounds = data.positions.iter().map(|&position| Vec3::from(position)).fold(self.bounds, extend_bounds);
+        let start = self.indices.len() as u32;
+        let base_vertex = self.vertices.len() as u32;
+        let tex_coords = |index: usize| data.tex_coords.as_ref().and_then(|tex_coords| tex_coords.get(index).copied()).unwrap_or([0.0, 0.0]);
+        self.has_uvs |= data.tex_coords.is_some();
+
+        if let Some(face_colors) = &data.face_colors {
+            for (triangle, color) in data.triangles.iter().zip(face_colors) {
+                let [a, b, c] = triangle.map(|index| Vec3::from(data.positions[index as usize]));
+                let normal = (b - a).cross(c - a).normalize_or_zero();
+                for index in triangle {
+                    self.indices.push(self.vertices.len() as u32);
+                    self.vertices.push(Vertex {
+                        position: data.positions[*index as usize],
+                        normal: normal.into(),
+                        color: *color,
+                        tex_coords: tex_coords(*index as usize),
+                    });
+                }
+            }
+        } else {
+            let normals = data.normals.clone().unwrap_or_else(|| smooth_normals(&data.positions, &data.triangles));
+            for (i, position) in data.positions.iter().enumerate() {
+                self.vertices.push(Vertex {
+                    position: *position,
+                    normal: normals.get(i).copied().unwrap_or([0.0, 1.0, 0.0]),
+                    color: data.vertex_colors.as_ref().and_then(|colors| colors.get(i).copied()).unwrap_or(DEFAULT_COLOR),
+                    tex_coords: tex_coords(i),
+                });
+            }
+            self.indices.extend(data.triangles.iter().flatten().map(|index| index + base_vertex));
+        }
+
+        self.draw_ranges.push(DrawRange {
+            name: data.name,
+            start,
+            count: self.indices.len() as u32 - start,
+            material: data.material,
+            parent: data.parent,
+            transform: data.transform,
+        });
+    }
+
+    fn load_from_obj(&mut self, path: &Path, progress: &LoadProgress) -> Result<()> {
+        info!("Loading OBJ file: {:?}", path);
         
-        let (models, _materials) = load_obj(
-            path,
-            &LoadOptions::default(),
+        let file = std::fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+        let mut reader = StatementScanner::new(ProgressReader::new(std::io::BufReader::new(file), progress));
+        // Single index so positions, normals and texture coordinates line up per vertex
+        let (models, materials) = load_obj_buf(
+            &mut reader,
+            &LoadOptions {
+                single_index: true,
+                triangulate: true,
+                ..Default::default()
+            },
+            // Material libraries are relative to the OBJ file
+            |mtl_path| load_mtl(path.parent().map_or_else(|| mtl_path.to_path_buf(), |parent| parent.join(mtl_path))),
         )?;
 
         self.vertices.clear();
         self.indices.clear();
+        self.draw_ranges.clear();
+        self.embedded_textures.clear();
+        self.shared = None;
+        (self.materials, self.material_error) = match materials {
+            Ok(materials) => (materials, None),
+            Err(e) => {
+                warn!("Failed to load materials: {}", e);
+                (Vec::new(), Some(e.to_string()))
+            }
+        };
+        self.has_uvs = models.iter().any(|model| !model.mesh.texcoords.is_empty());
 
         for model in &models {
             let mesh = &model.mesh;
@@ -98,38 +393,62 @@ impl Mesh {
             }
 
             // Load indices
+            let mut indices = Vec::new();
             if !mesh.indices.is_empty() {
-                self.indices.extend(mesh.indices.iter().map(|&i| i as u32));
+                indices.extend(mesh.indices.iter().copied());
             } else {
                 // Generate indices for triangle list
                 for i in (0..positions.len()).step_by(3) {
                     if i + 2 < positions.len() {
-                        self.indices.push(i as u32);
-                        self.indices.push((i + 1) as u32);
-                        self.indices.push((i + 2) as u32);
+                        indices.push(i as u32);
+                        indices.push((i + 1) as u32);
+                        indices.push((i + 2) as u32);
                     }
                 }
             }
 
+            // Indices are local to each model, offset them past the vertices already loaded
+            let base_vertex = self.vertices.len() as u32;
+            self.draw_ranges.push(DrawRange {
+                name: model.name.clone(),
+                start: self.indices.len() as u32,
+                count: indices.len() as u32,
+                material: mesh.material_id.filter(|&id| id < self.materials.len()),
+                parent: None,
+                transform: Mat4::IDENTITY,
+            });
+            self.indices.extend(indices.iter().map(|index| index + base_vertex));
+
             // Create vertices with calculated normals if needed
             for i in 0..positions.len() {
                 let mut normal = normals[i];
                 
                 // If no normals provided, calculate from geometry
                 if mesh.normals.is_empty() {
-                    normal = self.calculate_normal_for_vertex(i, &positions, &self.indices);
+                    normal = self.calculate_normal_for_vertex(i, &positions, &indices);
                 }
                 
                 let color = [0.8, 0.8, 0.8]; // Default gray color
+
+                let tex_coords = if i < mesh.texcoords.len() / 2 {
+                    [mesh.texcoords[i * 2], mesh.texcoords[i * 2 + 1]]
+                } else {
+                    [0.0, 0.0]
+                };
                 
                 self.vertices.push(Vertex {
                     position: positions[i],
                     normal,
                     color,
+                    tex_coords,
                 });
+                progress.add_vertices(1);
             }
         }
 
+        self.draw_ranges = reader.nest(std::mem::take(&mut self.draw_ranges));
+        self.bounds = self.vertices.iter().map(|vertex| Vec3::from(vertex.position)).fold(None, extend_bounds);
+
         info!("Loaded mesh with {} vertices and {} indices", self.vertices.len(), self.indices.len());
         Ok(())
     }
@@ -169,30 +488,276 @@ impl Mesh {
         [normal.x, normal.y, normal.z]
     }
 
+    /// Switches the vertex buffer contents between `Vertex` and the
+    /// compressed `QuantizedVertex` layout; takes effect when the buffers are
+    /// next created. Meshes without indices always stay uncompressed.
+    pub fn set_quantized(&mut self, quantized: bool) {
+        self.quantized = (quantized && !self.indices.is_empty()).then(|| QuantizedVertices::new(self.gpu_vertices()));
+    }
+
+    /// Leaves draw ranges that are moved copies of earlier ones out of the
+    /// buffers, drawing them from their source's indices instead; takes
+    /// effect when the buffers are next created. Returns the number of
+    /// copies found.
+    pub fn set_shared(&mut self, shared: bool) -> usize {
+        let duplicates = if shared { dedup::find_duplicates(self) } else { Vec::new() };
+        let copies = duplicates.iter().flatten().count();
+        self.shared = (copies > 0).then(|| SharedGeometry::new(self, duplicates));
+        if self.quantized.is_some() {
+            self.set_quantized(true);
+        }
+        copies
+    }
+
+    /// The earlier draw range that `range` is drawn from, when the buffers
+    /// are shared.
+    pub fn duplicate_of(&self, range: usize) -> Option<Duplicate> {
+        self.shared.as_ref()?.duplicates.get(range).copied().flatten()
+    }
+
+    /// Placement of draw range `range` from the file: its transform with its
+    /// parents' applied.
+    pub fn file_transform(&self, range: usize) -> Mat4 {
+        let mut transform = self.draw_ranges[range].transform;
+        let mut parent = self.draw_ranges[range].parent;
+        while let Some(index) = parent {
+            transform = self.draw_ranges[index].transform * transform;
+            parent = self.draw_ranges[index].parent;
+        }
+        transform
+    }
+
+    /// Indices of draw range `range` within the index buffer.
+    pub fn gpu_range(&self, range: usize) -> std::ops::Range<u32> {
+        let DrawRange { start, count, .. } = self.draw_ranges[range];
+        let start = self.shared.as_ref().map_or(start, |shared| shared.starts[range]);
+        start..start + count
+    }
+
+    /// Bytes the vertex and index buffers need in the full vertex layout,
+    /// less what shared draw ranges save.
+    pub fn geometry_bytes(&self) -> usize {
+        std::mem::size_of_val(self.gpu_vertices()) + std::mem::size_of_val(self.gpu_indices())
+    }
+
+    /// Bytes shared draw ranges keep out of the vertex and index buffers.
+    pub fn shared_bytes(&self) -> usize {
+        std::mem::size_of_val(self.vertices.as_slice()) + std::mem::size_of_val(self.indices.as_slice()) - self.geometry_bytes()
+    }
+
+    // Vertices and indices the buffers are filled from
+    fn gpu_vertices(&self) -> &[Vertex] {
+        self.shared.as_ref().map_or(&self.vertices, |shared| &shared.vertices)
+    }
+
+    fn gpu_indices(&self) -> &[u32] {
+        self.shared.as_ref().map_or(&self.indices, |shared| &shared.indices)
+    }
+
+    pub fn quantized(&self) -> Option<&QuantizedVertices> {
+        self.quantized.as_ref()
+    }
+
+    // Contents of the vertex buffer
+    fn vertex_bytes(&self) -> &[u8] {
+        match &self.quantized {
+            Some(quantized) => bytemuck::cast_slice(&quantized.vertices),
+            None => bytemuck::cast_slice(self.gpu_vertices()),
+        }
+    }
+
+    // Collects the edges of each draw range, or of all triangles without
+    // ranges. Shared draw ranges reuse their source's.
+    fn build_edges(&mut self) {
+        let mut edges = Vec::new();
+        let mut edge_ranges: Vec<std::ops::Range<u32>> = Vec::new();
+        let indices = self.gpu_indices();
+        if self.draw_ranges.is_empty() {
+            edges = unique_edges(indices);
+            edge_ranges.push(0..edges.len() as u32);
+        }
+        for range in 0..self.draw_ranges.len() {
+            if let Some(duplicate) = self.duplicate_of(range) {
+                edge_ranges.push(edge_ranges[duplicate.source].clone());
+                continue;
+            }
+            let start = edges.len() as u32;
+            let range = self.gpu_range(range);
+            edges.extend(unique_edges(&indices[range.start as usize..range.end as usize]));
+            edge_ranges.push(start..edges.len() as u32);
+        }
+        self.edges = edges;
+        self.edge_ranges = edge_ranges;
+    }
+
     pub fn create_buffers(&mut self, device: &wgpu::Device) {
-        if !self.vertices.is_empty() {
+        self.build_edges();
+        if !self.gpu_vertices().is_empty() {
             self.vertex_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Mesh Vertex Buffer"),
-                contents: bytemuck::cast_slice(&self.vertices),
+                contents: self.vertex_bytes(),
                 usage: wgpu::BufferUsages::VERTEX,
             }));
         }
 
-        if !self.indices.is_empty() {
+        if !self.gpu_indices().is_empty() {
             self.index_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Mesh Index Buffer"),
-                contents: bytemuck::cast_slice(&self.indices),
+                contents: bytemuck::cast_slice(self.gpu_indices()),
                 usage: wgpu::BufferUsages::INDEX,
             }));
-            self.num_indices = self.indices.len() as u32;
+            self.num_indices = self.gpu_indices().len() as u32;
+        }
+        self.edge_buffer = (!self.edges.is_empty()).then(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Mesh Edge Buffer"),
+                contents: bytemuck::cast_slice(&self.edges),
+                usage: wgpu::BufferUsages::INDEX,
+            })
+        });
+        self.upload = None;
+    }
+
+    /// Frees the GPU buffers, for meshes drawn from other buffers.
+    pub fn release_buffers(&mut self) {
+        self.vertex_buffer = None;
+        self.index_buffer = None;
+        self.num_indices = 0;
+        self.edge_buffer = None;
+        self.upload = None;
+    }
+
+    /// Allocates the buffers without filling them; `upload_pending` copies the
+    /// data over the next frames. The mesh isn't drawn until it's complete.
+    pub fn create_buffers_deferred(&mut self, device: &wgpu::Device) {
+        self.build_edges();
+        let buffer = |label, size: usize, usage| {
+            (size > 0).then(|| {
+                device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(label),
+                    size: size as u64,
+                    usage: usage | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+        };
+        self.vertex_buffer = buffer("Mesh Vertex Buffer", self.vertex_bytes().len(), wgpu::BufferUsages::VERTEX);
+        self.index_buffer = buffer("Mesh Index Buffer", std::mem::size_of_val(self.gpu_indices()), wgpu::BufferUsages::INDEX);
+        self.edge_buffer = buffer("Mesh Edge Buffer", std::mem::size_of_val(self.edges.as_slice()), wgpu::BufferUsages::INDEX);
+        self.num_indices = self.gpu_indices().len() as u32;
+        self.upload = Some([0; 3]);
+    }
+
+    /// Copies the next part of a deferred upload within `budget` bytes, which
+    /// are taken off the budget. Returns true while data is still waiting.
+    pub fn upload_pending(&mut self, queue: &wgpu::Queue, budget: &mut usize) -> bool {
+        let Some([mut vertices_written, mut indices_written, mut edges_written]) = self.upload else {
+            return false;
+        };
+        let mut done = true;
+        if let Some(buffer) = &self.vertex_buffer {
+            done &= upload::write_buffer_chunk(queue, buffer, self.vertex_bytes(), &mut vertices_written, budget);
+        }
+        if let Some(buffer) = &self.index_buffer {
+            done &= upload::write_buffer_chunk(queue, buffer, bytemuck::cast_slice(self.gpu_indices()), &mut indices_written, budget);
+        }
+        if let Some(buffer) = &self.edge_buffer {
+            done &= upload::write_buffer_chunk(queue, buffer, bytemuck::cast_slice(&self.edges), &mut edges_written, budget);
+        }
+        self.upload = (!done).then_some([vertices_written, indices_written, edges_written]);
+        !done
+    }
+
+    /// Bytes of a deferred upload that haven't been written yet.
+    pub fn pending_upload_bytes(&self) -> usize {
+        self.upload.map_or(0, |written| {
+            self.vertex_bytes().len() + std::mem::size_of_val(self.gpu_indices()) + std::mem::size_of_val(self.edges.as_slice())
+                - written.iter().sum::<usize>()
+        })
+    }
+
+    /// Builds an unshared copy of the mesh with one solid color per triangle
+    /// (or the original vertex colors for `None`), used to visualize per-face
+    /// data without touching the original.
+    pub fn with_face_colors(&self, colors: Option<&[[f32; 3]]>) -> Mesh {
+        let mut mesh = Mesh::new();
+        mesh.has_uvs = self.has_uvs;
+        mesh.bounds = self.bounds;
+        mesh.draw_ranges = self.draw_ranges.clone();
+        for (face, triangle) in self.indices.chunks_exact(3).enumerate() {
+            for &index in triangle {
+                let mut vertex = self.vertices[index as usize];
+                if let Some(colors) = colors {
+                    vertex.color = colors[face];
+                }
+                mesh.indices.push(mesh.vertices.len() as u32);
+                mesh.vertices.push(vertex);
+            }
+        }
+        mesh
+    }
+
+    /// Recolors one triangle of a mesh built by `with_face_colors`.
+    pub fn paint_face(&mut self, face: usize, color: [f32; 3]) {
+        for vertex in self.vertices.iter_mut().skip(face * 3).take(3) {
+            vertex.color = color;
         }
     }
 
+    /// Smallest and largest corner of the box around the vertices, `None`
+    /// while the mesh is empty.
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.bounds
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// The vertex buffer, once its data is complete.
     pub fn get_vertex_buffer(&self) -> Option<&wgpu::Buffer> {
-        self.vertex_buffer.as_ref()
+        self.vertex_buffer.as_ref().filter(|_| self.upload.is_none())
     }
 
     pub fn get_index_buffer(&self) -> Option<&wgpu::Buffer> {
-        self.index_buffer.as_ref()
+        self.index_buffer.as_ref().filter(|_| self.upload.is_none())
+    }
+
+    /// Index buffer of the edges in `edge_ranges`, drawn as a line list.
+    pub fn get_edge_buffer(&self) -> Option<&wgpu::Buffer> {
+        self.edge_buffer.as_ref().filter(|_| self.upload.is_none())
     }
-} 
\ No newline at end of file
+}
+
+/// Each edge of `triangles` once, as pairs of vertex indices in ascending
+/// order.
+fn extend_bounds(bounds: Option<(Vec3, Vec3)>, position: Vec3) -> Option<(Vec3, Vec3)> {
+    Some(bounds.map_or((position, position), |(min, max)| (min.min(position), max.max(position))))
+}
+
+pub fn unique_edges(triangles: &[u32]) -> Vec<u32> {
+    let mut edges: Vec<(u32, u32)> = triangles
+        .chunks_exact(3)
+        .flat_map(|triangle| [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])])
+        .map(|(a, b)| (a.min(b), a.max(b)))
+        .collect();
+    edges.sort_unstable();
+    edges.dedup();
+    edges.into_iter().flat_map(|(a, b)| [a, b]).collect()
+}
+
+/// Area-weighted vertex normals for indexed triangles.
+fn smooth_normals(positions: &[[f32; 3]], triangles: &[[u32; 3]]) -> Vec<[f32; 3]> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in triangles {
+        let [a, b, c] = triangle.map(|index| Vec3::from(positions[index as usize]));
+        let face_normal = (b - a).cross(c - a);
+        for index in triangle {
+            normals[*index as usize] += face_normal;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|normal| normal.try_normalize().unwrap_or(Vec3::Y).into())
+        .collect()
+}
@@ -0,0 +1,65 @@
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+use crate::mesh::Mesh;
+
+/// A second version of the loaded model with identical topology. The
+/// shaders blend each vertex towards it by the morph weight.
+pub struct MorphTarget {
+    pub path: PathBuf,
+    mesh: Mesh,
+    // Same vertices unshared per triangle, matching the base mesh's color overlay
+    overlay: Mesh,
+    pub changed_vertices: usize,
+    pub max_displacement: f32,
+}
+
+impl MorphTarget {
+    pub fn new(device: &wgpu::Device, path: &Path, mut mesh: Mesh, base: &Mesh) -> Result<Self> {
+        if mesh.vertices.len() != base.vertices.len() {
+            bail!(
+                "Morph target has {} vertices but the model has {}",
+                mesh.vertices.len(),
+                base.vertices.len()
+            );
+        }
+        if mesh.indices != base.indices {
+            bail!("Morph target has the same vertex count but different faces");
+        }
+
+        let mut changed_vertices = 0;
+        let mut max_displacement: f32 = 0.0;
+        for (target, vertex) in mesh.vertices.iter().zip(&base.vertices) {
+            let displacement = glam::Vec3::from(target.position).distance(glam::Vec3::from(vertex.position));
+            if displacement > 0.0 {
+                changed_vertices += 1;
+                max_displacement = max_displacement.max(displacement);
+            }
+        }
+
+        mesh.create_buffers(device);
+        let mut overlay = mesh.with_face_colors(None);
+        overlay.create_buffers(device);
+        Ok(Self {
+            path: path.to_path_buf(),
+            mesh,
+            overlay,
+            changed_vertices,
+            max_displacement,
+        })
+    }
+
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    /// Vertex buffer to bind next to the base mesh; `overlay` when the base
+    /// is drawn through its color overlay copy.
+    pub fn vertex_buffer(&self, overlay: bool) -> Option<&wgpu::Buffer> {
+        if overlay {
+            self.overlay.get_vertex_buffer()
+        } else {
+            self.mesh.get_vertex_buffer()
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use glam::{Mat4, Vec3};
+use std::ops::Range;
+use wgpu::util::DeviceExt;
+
+use crate::mesh::Mesh;
+use crate::postprocess::HDR_FORMAT;
+use crate::scene::ObjectBindings;
+use crate::shaders::create_shader_module;
+
+const VERTEX_NORMAL_COLOR: [f32; 3] = [0.3, 0.6, 1.0];
+const FACE_NORMAL_COLOR: [f32; 3] = [1.0, 0.85, 0.2];
+// Vertex normals pointing away from the side their faces are wound towards
+const INVERTED_COLOR: [f32; 3] = [1.0, 0.2, 0.2];
+// Vertex normals of zero length, drawn along their faces' normal instead
+const MISSING_COLOR: [f32; 3] = [1.0, 0.2, 1.0];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct NormalLineVertex {
+    position: [f32; 3],
+    // Zero at the base of a line, the unit normal at its tip
+    direction: [f32; 3],
+    color: [f32; 3],
+}
+
+impl NormalLineVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<NormalLineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct NormalLineUniforms {
+    // Maps model-space positions to the vertex positions the object
+    // transforms expect, which differ for quantized meshes
+    encode: [[f32; 4]; 4],
+    length: f32,
+    _pad: [f32; 3],
+}
+
+/// Which normals are drawn and how long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalLineSettings {
+    pub vertex: bool,
+    pub face: bool,
+    /// Percent of the model's bounding box diagonal.
+    pub length: f32,
+}
+
+impl NormalLineSettings {
+    pub fn enabled(&self) -> bool {
+        self.vertex || self.face
+    }
+}
+
+impl Default for NormalLineSettings {
+    fn default() -> Self {
+        Self {
+            vertex: false,
+            face: false,
+            length: 2.0,
+        }
+    }
+}
+
+/// Debug overlay drawing vertex and face normals as short line segments,
+/// built once per model into a vertex buffer and scaled to the chosen
+/// length in the shader. Vertex normals that point against their faces'
+/// winding are drawn in red, missing ones in magenta.
+pub struct NormalLines {
+    pub settings: NormalLineSettings,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: Option<wgpu::Buffer>,
+    // Per object, the vertices of its vertex normal lines and of its face
+    // normal lines
+    objects: Vec<(Range<u32>, Range<u32>)>,
+}
+
+impl NormalLines {
+    pub fn new(device: &wgpu::Device, camera_bind_group_layout: &wgpu::BindGroupLayout, object_bindings: &ObjectBindings) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Normal Lines Uniform Buffer"),
+            size: std::mem::size_of::<NormalLineUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Normal Lines Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Normal Lines Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = create_shader_module(device, "Normal Lines Shader", include_str!("shaders/normal_lines.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Normal Lines Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout, object_bindings.layout()],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Normal Lines Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[NormalLineVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            settings: NormalLineSettings::default(),
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            vertex_buffer: None,
+            objects: Vec::new(),
+        }
+    }
+
+    pub fn is_built(&self) -> bool {
+        self.vertex_buffer.is_some()
+    }
+
+    /// Drops the lines of the previous model.
+    pub fn clear(&mut self) {
+        self.vertex_buffer = None;
+        self.objects.clear();
+    }
+
+    /// Builds the lines for every object of `mesh`: one from each vertex its
+    /// triangles use and one from the center of each triangle.
+    pub fn build(&mut self, device: &wgpu::Device, mesh: &Mesh) {
+        self.clear();
+        let position = |index: u32| Vec3::from(mesh.vertices[index as usize].position);
+        let mut vertices = Vec::new();
+        let line = |vertices: &mut Vec<NormalLineVertex>, start: Vec3, direction: Vec3, color: [f32; 3]| {
+            vertices.push(NormalLineVertex { position: start.into(), direction: [0.0; 3], color });
+            vertices.push(NormalLineVertex { position: start.into(), direction: direction.into(), color });
+        };
+        // Sum of the area-weighted normals of the faces around each vertex
+        let mut face_sums = vec![Vec3::ZERO; mesh.vertices.len()];
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|corner| position(triangle[corner]));
+            let normal = (b - a).cross(c - a);
+            for &index in triangle {
+                face_sums[index as usize] += normal;
+            }
+        }
+
+        // Last object each vertex got a line for, plus one
+        let mut drawn = vec![0; mesh.vertices.len()];
+        for (object, range) in mesh.draw_ranges.iter().enumerate() {
+            let corners = &mesh.indices[range.start as usize..(range.start + range.count) as usize];
+            let vertex_start = vertices.len() as u32;
+            for &index in corners {
+                // Objects sharing another's geometry get lines of their own
+                if std::mem::replace(&mut drawn[index as usize], object + 1) == object + 1 {
+                    continue;
+                }
+                let faces = face_sums[index as usize].normalize_or_zero();
+                let normal = Vec3::from(mesh.vertices[index as usize].normal);
+                let (direction, color) = match normal.try_normalize() {
+                    Some(normal) if normal.dot(faces) < 0.0 => (normal, INVERTED_COLOR),
+                    Some(normal) => (normal, VERTEX_NORMAL_COLOR),
+                    None => (faces, MISSING_COLOR),
+                };
+                line(&mut vertices, position(index), direction, color);
+            }
+            let face_start = vertices.len() as u32;
+            for triangle in corners.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|corner| position(triangle[corner]));
+                if let Some(normal) = (b - a).cross(c - a).try_normalize() {
+                    line(&mut vertices, (a + b + c) / 3.0, normal, FACE_NORMAL_COLOR);
+                }
+            }
+            self.objects.push((vertex_start..face_start, face_start..vertices.len() as u32));
+        }
+        if !vertices.is_empty() {
+            self.vertex_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Normal Lines Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }));
+        }
+    }
+
+    /// Sets the line length for a model with bounding box diagonal
+    /// `model_size`; `position_decode` is the quantized mesh's, if any.
+    pub fn write_uniforms(&self, queue: &wgpu::Queue, model_size: f32, position_decode: Option<Mat4>) {
+        let uniforms = NormalLineUniforms {
+            encode: position_decode.map_or(Mat4::IDENTITY, |decode| decode.inverse()).to_cols_array_2d(),
+            length: model_size * self.settings.length * 0.01,
+            _pad: [0.0; 3],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    /// Draws the lines of the objects `is_visible` accepts, with their
+    /// transforms from `object_bindings`.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+        object_bindings: &'a ObjectBindings,
+        is_visible: impl Fn(usize) -> bool,
+    ) {
+        let Some(vertex_buffer) = self.vertex_buffer.as_ref().filter(|_| self.settings.enabled()) else {
+            return;
+        };
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        for (object, (vertex_lines, face_lines)) in self.objects.iter().enumerate() {
+            if !is_visible(object) {
+                continue;
+            }
+            object_bindings.bind(render_pass, 2, Some(object));
+            if self.settings.vertex {
+                render_pass.draw(vertex_lines.clone(), 0..1);
+            }
+            if self.settings.face {
+                render_pass.draw(face_lines.clone(), 0..1);
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use glam::{Mat3, Vec3};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::material;
+use crate::mesh::{DrawRange, Mesh};
+
+// Stands in for groups without a material
+const DEFAULT_MATERIAL: &str = "dotobjviewer_default";
+
+#[derive(Debug, Clone, Copy)]
+pub struct ObjExportOptions {
+    /// Merge vertices, texture coordinates and normals with identical values
+    /// and drop faces that collapse as a result.
+    pub weld: bool,
+}
+
+impl Default for ObjExportOptions {
+    fn default() -> Self {
+        Self { weld: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ObjExportStats {
+    pub vertices: usize,
+    pub faces: usize,
+    pub dropped_faces: usize,
+}
+
+// Assigns OBJ indices (1-based) to attribute values, sharing equal values when welding
+struct AttributeTable<const N: usize> {
+    weld: bool,
+    lookup: HashMap<[u32; N], usize>,
+    values: Vec<[f32; N]>,
+}
+
+impl<const N: usize> AttributeTable<N> {
+    fn new(weld: bool) -> Self {
+        Self {
+            weld,
+            lookup: HashMap::new(),
+            values: Vec::new(),
+        }
+    }
+
+    fn index(&mut self, value: [f32; N]) -> usize {
+        if self.weld {
+            if let Some(&index) = self.lookup.get(&value.map(f32::to_bits)) {
+                return index;
+            }
+        }
+        self.values.push(value);
+        let index = self.values.len();
+        if self.weld {
+            self.lookup.insert(value.map(f32::to_bits), index);
+        }
+        index
+    }
+}
+
+/// Writes the mesh as it is currently displayed (material reassignments
+/// included) to `path`, plus a companion MTL file when it has materials.
+/// Texture paths in the MTL point at the files resolved next to the original
+/// model, so the export can be saved anywhere.
+pub fn write_obj(path: &Path, mesh: &Mesh, model_dir: Option<&Path>, options: ObjExportOptions) -> Result<ObjExportStats> {
+    let mut positions = AttributeTable::<3>::new(options.weld);
+    let mut tex_coords = AttributeTable::<2>::new(options.weld);
+    let mut normals = AttributeTable::<3>::new(options.weld);
+    let mut body = String::new();
+    let mut faces = 0;
+    let mut dropped_faces = 0;
+
+    // Object and group of the last range written, so the material parts of a
+    // group share one `g`
+    let mut current: Option<(&str, Option<&str>)> = None;
+    for (index, range) in mesh.draw_ranges.iter().enumerate().filter(|(_, range)| range.count > 0) {
+        let mut root = range;
+        while let Some(parent) = root.parent {
+            root = &mesh.draw_ranges[parent];
+        }
+        let object = export_name(root);
+        let group = Some(export_name(range)).filter(|&group| group != object);
+        if current.map(|(current, _)| current) != Some(object) {
+            writeln!(body, "o {}", object)?;
+        }
+        if let Some(group) = group.filter(|_| current != Some((object, group))) {
+            writeln!(body, "g {}", group)?;
+        }
+        current = Some((object, group));
+        if !mesh.materials.is_empty() {
+            match range.material.and_then(|id| mesh.materials.get(id)) {
+                Some(material) => writeln!(body, "usemtl {}", material.name)?,
+                None => writeln!(body, "usemtl {}", DEFAULT_MATERIAL)?,
+            }
+        }
+
+        // OBJ has no transforms, so placements from the file are applied to
+        // the vertices
+        let transform = mesh.file_transform(index);
+        let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+        let indices = &mesh.indices[range.start as usize..(range.start + range.count) as usize];
+        for triangle in indices.chunks_exact(3) {
+            let corners = [0, 1, 2].map(|i| {
+                let vertex = &mesh.vertices[triangle[i] as usize];
+                let position = transform.transform_point3(Vec3::from(vertex.position));
+                let normal = (normal_matrix * Vec3::from(vertex.normal)).normalize_or_zero();
+                (
+                    positions.index(position.to_array()),
+                    tex_coords.index(vertex.tex_coords),
+                    normals.index(normal.to_array()),
+                )
+            });
+            if corners[0].0 == corners[1].0 || corners[1].0 == corners[2].0 || corners[2].0 == corners[0].0 {
+                dropped_faces += 1;
+                continue;
+            }
+
+            body.push('f');
+            for (v, vt, vn) in corners {
+                if mesh.has_uvs {
+                    write!(body, " {}/{}/{}", v, vt, vn)?;
+                } else {
+                    write!(body, " {}//{}", v, vn)?;
+                }
+            }
+            body.push('\n');
+            faces += 1;
+        }
+    }
+
+    let mut obj = String::from("# Exported by DotObjViewer\n");
+    if !mesh.materials.is_empty() {
+        let mtl_path = path.with_extension("mtl");
+        write_mtl(&mtl_path, mesh, model_dir)?;
+        let mtl_name = mtl_path.file_name().context("Invalid export path")?.to_string_lossy();
+        writeln!(obj, "mtllib {}", mtl_name)?;
+    }
+    for [x, y, z] in &positions.values {
+        writeln!(obj, "v {} {} {}", x, y, z)?;
+    }
+    if mesh.has_uvs {
+        for [u, v] in &tex_coords.values {
+            writeln!(obj, "vt {} {}", u, v)?;
+        }
+    }
+    for [x, y, z] in &normals.values {
+        writeln!(obj, "vn {} {} {}", x, y, z)?;
+    }
+    obj.push_str(&body);
+
+    std::fs::write(path, obj).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(ObjExportStats {
+        vertices: positions.values.len(),
+        faces,
+        dropped_faces,
+    })
+}
+
+fn write_mtl(path: &Path, mesh: &Mesh, model_dir: Option<&Path>) -> Result<()> {
+    let texture_path = |texture: &String| {
+        model_dir
+            .and_then(|dir| material::resolve_texture_path(dir, texture))
+            .and_then(|resolved| resolved.canonicalize().ok())
+            .map(|resolved| resolved.display().to_string())
+            .unwrap_or_else(|| texture.clone())
+    };
+
+    let mut mtl = String::from("# Exported by DotObjViewer\n");
+    if mesh.draw_ranges.iter().any(|range| range.material.is_none()) {
+        writeln!(mtl, "\nnewmtl {}\nKd 0.8 0.8 0.8", DEFAULT_MATERIAL)?;
+    }
+    for material in &mesh.materials {
+        writeln!(mtl, "\nnewmtl {}", material.name)?;
+        let colors = [("Ka", material.ambient), ("Kd", material.diffuse), ("Ks", material.specular)];
+        for (key, color) in colors {
+            if let Some([r, g, b]) = color {
+                writeln!(mtl, "{} {} {} {}", key, r, g, b)?;
+            }
+        }
+        let scalars = [("Ns", material.shininess), ("d", material.dissolve), ("Ni", material.optical_density)];
+        for (key, value) in scalars {
+            if let Some(value) = value {
+                writeln!(mtl, "{} {}", key, value)?;
+            }
+        }
+        if let Some(illum) = material.illumination_model {
+            writeln!(mtl, "illum {}", illum)?;
+        }
+        let textures = [
+            ("map_Ka", &material.ambient_texture),
+            ("map_Kd", &material.diffuse_texture),
+            ("map_Ks", &material.specular_texture),
+            ("map_Bump", &material.normal_texture),
+            ("map_Ns", &material.shininess_texture),
+            ("map_d", &material.dissolve_texture),
+        ];
+        for (key, texture) in textures {
+            if let Some(texture) = texture {
+                writeln!(mtl, "{} {}", key, texture_path(texture))?;
+            }
+        }
+        // PBR extension
+        for key in ["Pm", "Pr"] {
+            if let Some(value) = material.unknown_param.get(key) {
+                writeln!(mtl, "{} {}", key, value)?;
+            }
+        }
+        for key in ["map_Pm", "map_Pr", "norm", "map_ao"] {
+            if let Some(statement) = material.unknown_param.get(key) {
+                let (texture, bump_multiplier) = material::parse_texture_statement(statement);
+                if bump_multiplier == 1.0 {
+                    writeln!(mtl, "{} {}", key, texture_path(&texture))?;
+                } else {
+                    writeln!(mtl, "{} -bm {} {}", key, bump_multiplier, texture_path(&texture))?;
+                }
+            }
+        }
+    }
+
+    std::fs::write(path, mtl).with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(())
+}
+
+fn export_name(range: &DrawRange) -> &str {
+    if range.name.is_empty() {
+        "default"
+    } else {
+        &range.name
+    }
+}
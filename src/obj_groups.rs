@@ -0,0 +1,235 @@
+use std::io::{BufRead, Read};
+
+use crate::mesh::DrawRange;
+
+// tobj's name for objects and groups without one
+const UNNAMED: &str = "unnamed_object";
+
+/// An `o` or `g` statement of an OBJ file.
+#[derive(Debug)]
+struct Statement {
+    name: String,
+    is_object: bool,
+}
+
+/// Passes an OBJ file through to the parser, noting its `o` and `g`
+/// statements in order; tobj reports both as plain models, and splits a
+/// group into one model per material.
+pub struct StatementScanner<R> {
+    inner: R,
+    line: Vec<u8>,
+    // The current line can't be a statement
+    skip_line: bool,
+    statements: Vec<Statement>,
+}
+
+impl<R> StatementScanner<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            line: Vec::new(),
+            skip_line: false,
+            statements: Vec::new(),
+        }
+    }
+
+    /// Nests `ranges`, one per model parsed by tobj in file order, the way
+    /// the file grouped them: groups under the object they follow, and the
+    /// material parts of a group under an empty range of the same name.
+    pub fn nest(&self, ranges: Vec<DrawRange>) -> Vec<DrawRange> {
+        // Statement each model came from; models named differently from the
+        // statements (e.g. faces before the first one) stand alone
+        let mut cursor = 0;
+        let owners: Vec<Option<usize>> = ranges
+            .iter()
+            .map(|range| {
+                let found = (cursor..self.statements.len()).find(|&statement| self.statements[statement].name == range.name);
+                cursor = found.unwrap_or(cursor);
+                found
+            })
+            .collect();
+
+        let mut nested: Vec<DrawRange> = Vec::with_capacity(ranges.len());
+        let empty = |name: &str, parent: Option<usize>, start: u32| DrawRange {
+            name: name.to_string(),
+            start,
+            count: 0,
+            material: None,
+            parent,
+            transform: glam::Mat4::IDENTITY,
+        };
+        // Statement and node of the current `o`, added when its first part shows up
+        let mut object: Option<(usize, usize)> = None;
+        let mut ranges = ranges.into_iter().zip(owners).peekable();
+        while let Some((range, owner)) = ranges.next() {
+            let mut run = vec![range];
+            while let Some((next, _)) = ranges.next_if(|(_, next_owner)| owner.is_some() && *next_owner == owner) {
+                run.push(next);
+            }
+
+            let is_object = owner.is_some_and(|owner| self.statements[owner].is_object);
+            let enclosing = owner
+                .filter(|_| !is_object)
+                .and_then(|owner| self.statements[..owner].iter().rposition(|statement| statement.is_object));
+            let parent = enclosing.map(|enclosing| match object {
+                Some((statement, node)) if statement == enclosing => node,
+                _ => {
+                    nested.push(empty(&self.statements[enclosing].name, None, run[0].start));
+                    object = Some((enclosing, nested.len() - 1));
+                    nested.len() - 1
+                }
+            });
+
+            let node = if run.len() == 1 {
+                let mut range = run.pop().unwrap();
+                range.parent = parent;
+                nested.push(range);
+                nested.len() - 1
+            } else {
+                nested.push(empty(&run[0].name, parent, run[0].start));
+                let group = nested.len() - 1;
+                for mut part in run {
+                    part.parent = Some(group);
+                    nested.push(part);
+                }
+                group
+            };
+            if let Some(owner) = owner.filter(|_| is_object) {
+                object = Some((owner, node));
+            }
+        }
+        nested
+    }
+
+    fn scan(line: &mut Vec<u8>, skip_line: &mut bool, statements: &mut Vec<Statement>, bytes: &[u8]) {
+        for &byte in bytes {
+            if byte == b'\n' {
+                Self::end_line(line, skip_line, statements);
+            } else if !*skip_line {
+                // Only `o` and `g` lines are kept, the rest is skipped from the first character
+                if line.iter().all(u8::is_ascii_whitespace) && !byte.is_ascii_whitespace() && byte != b'o' && byte != b'g' {
+                    *skip_line = true;
+                    line.clear();
+                } else {
+                    line.push(byte);
+                }
+            }
+        }
+    }
+
+    // Records the pending line if it's a statement; called on every newline
+    // and at the end of the file, whose last line may not have one
+    fn end_line(line: &mut Vec<u8>, skip_line: &mut bool, statements: &mut Vec<Statement>) {
+        let text = String::from_utf8_lossy(line);
+        let text = text.trim();
+        if let Some(keyword @ ("o" | "g")) = text.split_whitespace().next() {
+            let name = text[1..].trim();
+            statements.push(Statement {
+                name: if name.is_empty() { UNNAMED.to_string() } else { name.to_string() },
+                is_object: keyword == "o",
+            });
+        }
+        line.clear();
+        *skip_line = false;
+    }
+}
+
+impl<R: Read> Read for StatementScanner<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            Self::end_line(&mut self.line, &mut self.skip_line, &mut self.statements);
+        }
+        Self::scan(&mut self.line, &mut self.skip_line, &mut self.statements, &buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for StatementScanner<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        let buffer = self.inner.fill_buf()?;
+        if buffer.is_empty() {
+            Self::end_line(&mut self.line, &mut self.skip_line, &mut self.statements);
+        }
+        Ok(buffer)
+    }
+
+    fn consume(&mut self, amount: usize) {
+        // The consumed bytes are still at the start of the buffer
+        if let Ok(buffer) = self.inner.fill_buf() {
+            Self::scan(&mut self.line, &mut self.skip_line, &mut self.statements, &buffer[..amount.min(buffer.len())]);
+        }
+        self.inner.consume(amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads `obj` through the scanner, then nests one range per model name
+    // the way tobj would report them; returns each node's name and parent
+    fn nest(obj: &str, models: &[&str]) -> Vec<(String, Option<usize>)> {
+        let mut scanner = StatementScanner::new(obj.as_bytes());
+        std::io::copy(&mut scanner, &mut std::io::sink()).unwrap();
+        let ranges = models
+            .iter()
+            .enumerate()
+            .map(|(i, name)| DrawRange {
+                name: name.to_string(),
+                start: i as u32 * 3,
+                count: 3,
+                material: None,
+                parent: None,
+                transform: glam::Mat4::IDENTITY,
+            })
+            .collect();
+        scanner.nest(ranges).into_iter().map(|range| (range.name, range.parent)).collect()
+    }
+
+    fn node(name: &str, parent: Option<usize>) -> (String, Option<usize>) {
+        (name.to_string(), parent)
+    }
+
+    #[test]
+    fn faces_before_the_first_statement_stand_alone() {
+        let obj = "f 1 2 3\no car\nf 1 2 3\ng wheel\nf 1 2 3\n";
+        assert_eq!(
+            nest(obj, &[UNNAMED, "car", "wheel"]),
+            [node(UNNAMED, None), node("car", None), node("wheel", Some(1))]
+        );
+    }
+
+    #[test]
+    fn nests_material_parts_under_their_group() {
+        let obj = "o car\ng body\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 2 3\n";
+        assert_eq!(
+            nest(obj, &["body", "body"]),
+            [node("car", None), node("body", Some(0)), node("body", Some(1)), node("body", Some(1))]
+        );
+    }
+
+    #[test]
+    fn nests_groups_under_the_object_before_them() {
+        let obj = "o car\nf 1 2 3\ng wheel\nf 1 2 3\no house\nf 1 2 3\ng door\nf 1 2 3";
+        assert_eq!(
+            nest(obj, &["car", "wheel", "house", "door"]),
+            [node("car", None), node("wheel", Some(0)), node("house", None), node("door", Some(2))]
+        );
+    }
+
+    #[test]
+    fn records_a_statement_on_the_last_line() {
+        let obj = b"o car\nf 1 2 3\ng wheel";
+        let mut read = StatementScanner::new(&obj[..]);
+        std::io::copy(&mut read, &mut std::io::sink()).unwrap();
+        let mut buffered = StatementScanner::new(&obj[..]);
+        while !buffered.fill_buf().unwrap().is_empty() {
+            buffered.consume(1);
+        }
+        for scanner in [read, buffered] {
+            let names: Vec<_> = scanner.statements.iter().map(|statement| statement.name.as_str()).collect();
+            assert_eq!(names, ["car", "wheel"]);
+        }
+    }
+}
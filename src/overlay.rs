@@ -0,0 +1,456 @@
+use glam::Vec3;
+use wgpu::util::DeviceExt;
+
+use crate::postprocess::HDR_FORMAT;
+use crate::shaders::create_shader_module;
+
+// Glyph cells in the font atlas, one pixel of spacing right of and below each glyph
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+const ATLAS_COLUMNS: u32 = 16;
+const FIRST_CHAR: u8 = b' ';
+/// Screen pixels per font pixel.
+const TEXT_SCALE: f32 = 2.0;
+/// Distance of a label from its anchor point, in screen pixels.
+const LABEL_OFFSET: f32 = 6.0;
+
+/// Classic 5x7 font for printable ASCII, one byte per column with the top
+/// row in the lowest bit.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // f
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // j
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // l
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // t
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct OverlayVertex {
+    position: [f32; 3],
+    // Screen-space offset from `position` in pixels, +Y up
+    offset: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+impl OverlayVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x2, 3 => Float32x4];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<OverlayVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct OverlayUniforms {
+    viewport_size: [f32; 2],
+    _padding: [f32; 2],
+}
+
+/// Which built-in annotations are drawn into the scene.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OverlaySettings {
+    /// Reference grid on the XZ plane.
+    pub grid: bool,
+    /// World axes at the origin with X/Y/Z labels.
+    pub axes: bool,
+    /// Object names at the objects' centers.
+    pub object_labels: bool,
+    /// Marker at the point light's position.
+    pub light: bool,
+    /// Frustums of the camera path keys and the path between them.
+    pub camera_frustums: bool,
+    /// Box around the whole model.
+    pub bounding_box: bool,
+}
+
+/// Lines and text labels drawn in the 3D pass, depth tested against the
+/// scene. Content is rebuilt every frame with `clear`, `line` and `label`.
+pub struct Overlay {
+    line_pipeline: wgpu::RenderPipeline,
+    text_pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    _font_texture: wgpu::Texture,
+    lines: Vec<OverlayVertex>,
+    glyphs: Vec<OverlayVertex>,
+    vertex_buffer: Option<wgpu::Buffer>,
+    uploaded: (u32, u32),
+}
+
+impl Overlay {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, camera_bind_group_layout: &wgpu::BindGroupLayout) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Overlay Uniform Buffer"),
+            size: std::mem::size_of::<OverlayUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let (atlas_width, atlas_height, atlas) = font_atlas();
+        let font_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Overlay Font"),
+                size: wgpu::Extent3d {
+                    width: atlas_width,
+                    height: atlas_height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            &atlas,
+        );
+        let font_view = font_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let font_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Overlay Font Sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Overlay Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Overlay Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&font_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&font_sampler),
+                },
+            ],
+        });
+
+        let shader = create_shader_module(device, "Overlay Shader", include_str!("shaders/overlay.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label: &str, fragment_entry: &str, topology: wgpu::PrimitiveTopology| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[OverlayVertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fragment_entry,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: HDR_FORMAT,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                // Tested against the scene but not written, so overlays never hide each other
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        Self {
+            line_pipeline: create_pipeline("Overlay Line Pipeline", "fs_line", wgpu::PrimitiveTopology::LineList),
+            text_pipeline: create_pipeline("Overlay Text Pipeline", "fs_text", wgpu::PrimitiveTopology::TriangleList),
+            uniform_buffer,
+            bind_group,
+            _font_texture: font_texture,
+            lines: Vec::new(),
+            glyphs: Vec::new(),
+            vertex_buffer: None,
+            uploaded: (0, 0),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.glyphs.clear();
+    }
+
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: [f32; 4]) {
+        for position in [start, end] {
+            self.lines.push(OverlayVertex {
+                position: position.to_array(),
+                offset: [0.0; 2],
+                tex_coords: [0.0; 2],
+                color,
+            });
+        }
+    }
+
+    /// The twelve edges of the axis-aligned box from `min` to `max`.
+    pub fn box_edges(&mut self, (min, max): (Vec3, Vec3), color: [f32; 4]) {
+        let corner = |index: usize| Vec3::select(glam::BVec3::new(index & 1 != 0, index & 2 != 0, index & 4 != 0), max, min);
+        for index in 0..8 {
+            for bit in [1, 2, 4] {
+                if index & bit == 0 {
+                    self.line(corner(index), corner(index | bit), color);
+                }
+            }
+        }
+    }
+
+    /// Text placed just up and right of `anchor`, at a fixed size on screen.
+    /// Characters outside printable ASCII are drawn as `?`.
+    pub fn label(&mut self, anchor: Vec3, text: &str, color: [f32; 4]) {
+        let (atlas_width, atlas_height) = atlas_size();
+        let (width, height) = (GLYPH_WIDTH as f32 * TEXT_SCALE, GLYPH_HEIGHT as f32 * TEXT_SCALE);
+        for (i, character) in text.chars().enumerate() {
+            let glyph = glyph_index(character);
+            let left = LABEL_OFFSET + i as f32 * CELL_WIDTH as f32 * TEXT_SCALE;
+            let bottom = LABEL_OFFSET;
+            let u = (glyph % ATLAS_COLUMNS * CELL_WIDTH) as f32 / atlas_width as f32;
+            let v = (glyph / ATLAS_COLUMNS * CELL_HEIGHT) as f32 / atlas_height as f32;
+            let (du, dv) = (GLYPH_WIDTH as f32 / atlas_width as f32, GLYPH_HEIGHT as f32 / atlas_height as f32);
+
+            let corner = |x: f32, y: f32, s: f32, t: f32| OverlayVertex {
+                position: anchor.to_array(),
+                offset: [x, y],
+                tex_coords: [s, t],
+                color,
+            };
+            let top_left = corner(left, bottom + height, u, v);
+            let top_right = corner(left + width, bottom + height, u + du, v);
+            let bottom_left = corner(left, bottom, u, v + dv);
+            let bottom_right = corner(left + width, bottom, u + du, v + dv);
+            self.glyphs.extend([bottom_left, bottom_right, top_right, bottom_left, top_right, top_left]);
+        }
+    }
+
+    /// Copies this frame's lines and labels to the GPU.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let vertices: Vec<OverlayVertex> = self.lines.iter().chain(&self.glyphs).copied().collect();
+        self.uploaded = (self.lines.len() as u32, self.glyphs.len() as u32);
+        if vertices.is_empty() {
+            return;
+        }
+        let size = std::mem::size_of_val(vertices.as_slice()) as wgpu::BufferAddress;
+        if self.vertex_buffer.as_ref().is_none_or(|buffer| buffer.size() < size) {
+            self.vertex_buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Overlay Vertex Buffer"),
+                size: size.next_power_of_two(),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        if let Some(buffer) = &self.vertex_buffer {
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&vertices));
+        }
+    }
+
+    pub fn write_uniforms(&self, queue: &wgpu::Queue, aspect_ratio: f32, viewport_height: u32) {
+        let uniforms = OverlayUniforms {
+            viewport_size: [viewport_height as f32 * aspect_ratio, viewport_height as f32],
+            _padding: [0.0; 2],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera_bind_group: &'a wgpu::BindGroup) {
+        let (lines, glyphs) = self.uploaded;
+        let Some(buffer) = self.vertex_buffer.as_ref().filter(|_| lines + glyphs > 0) else {
+            return;
+        };
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, buffer.slice(..));
+        if lines > 0 {
+            render_pass.set_pipeline(&self.line_pipeline);
+            render_pass.draw(0..lines, 0..1);
+        }
+        if glyphs > 0 {
+            render_pass.set_pipeline(&self.text_pipeline);
+            render_pass.draw(lines..lines + glyphs, 0..1);
+        }
+    }
+}
+
+fn glyph_index(character: char) -> u32 {
+    let code = character as u32;
+    if (FIRST_CHAR as u32..FIRST_CHAR as u32 + FONT.len() as u32).contains(&code) {
+        code - FIRST_CHAR as u32
+    } else {
+        (b'?' - FIRST_CHAR) as u32
+    }
+}
+
+fn atlas_size() -> (u32, u32) {
+    let rows = (FONT.len() as u32).div_ceil(ATLAS_COLUMNS);
+    (ATLAS_COLUMNS * CELL_WIDTH, rows * CELL_HEIGHT)
+}
+
+// Single-channel coverage atlas of `FONT`, glyphs in rows of `ATLAS_COLUMNS`
+fn font_atlas() -> (u32, u32, Vec<u8>) {
+    let (width, height) = atlas_size();
+    let mut pixels = vec![0; (width * height) as usize];
+    for (glyph, columns) in FONT.iter().enumerate() {
+        let (cell_x, cell_y) = (glyph as u32 % ATLAS_COLUMNS * CELL_WIDTH, glyph as u32 / ATLAS_COLUMNS * CELL_HEIGHT);
+        for (x, column) in columns.iter().enumerate() {
+            for y in 0..GLYPH_HEIGHT {
+                if column & (1 << y) != 0 {
+                    pixels[((cell_y + y) * width + cell_x + x as u32) as usize] = 255;
+                }
+            }
+        }
+    }
+    (width, height, pixels)
+}
@@ -19,7 +19,9 @@ pub struct PerformanceMonitor {
     last_frame_time: Instant,
     
     // GPU 
+    #[allow(dead_code)]
     gpu_memory_used: Option<u64>,
+    #[allow(dead_code)]
     gpu_memory_total: Option<u64>,
 }
 
@@ -88,6 +90,7 @@ impl PerformanceMonitor {
         }
     }
     
+    #[allow(dead_code)]
     pub fn set_gpu_memory(&mut self, used: u64, total: u64) {
         self.gpu_memory_used = Some(used);
         self.gpu_memory_total = Some(total);
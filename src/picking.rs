@@ -0,0 +1,184 @@
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::camera::Camera;
+use crate::mesh::{DrawRange, Mesh};
+
+// Triangles per leaf; splitting further costs more in box tests than it saves
+const LEAF_TRIANGLES: usize = 4;
+
+/// A ray through the scene, `origin + t * direction`.
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// Ray from the near plane of `camera` through the point `ndc` of its
+    /// image, for perspective and orthographic cameras alike.
+    pub fn from_camera(camera: &Camera, ndc: Vec2) -> Self {
+        let inverse = (camera.projection_matrix() * camera.view_matrix()).inverse();
+        let near = inverse.project_point3(ndc.extend(0.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+        Self {
+            origin: near,
+            direction: far - near,
+        }
+    }
+
+    /// The same ray in the space `transform` maps out of. Distances along it
+    /// keep their meaning, as the direction isn't renormalized.
+    pub fn transformed(&self, transform: Mat4) -> Self {
+        Self {
+            origin: transform.transform_point3(self.origin),
+            direction: transform.transform_vector3(self.direction),
+        }
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    // Distance to where the ray enters the box, if it hits it before `max_t`
+    fn hits_box(&self, inverse_direction: Vec3, (min, max): (Vec3, Vec3), max_t: f32) -> Option<f32> {
+        let t0 = (min - self.origin) * inverse_direction;
+        let t1 = (max - self.origin) * inverse_direction;
+        let enter = t0.min(t1).max_element().max(0.0);
+        let exit = t0.max(t1).min_element().min(max_t);
+        (enter <= exit).then_some(enter)
+    }
+
+    // Möller–Trumbore, hitting both sides of the triangle
+    fn hits_triangle(&self, [a, b, c]: [Vec3; 3]) -> Option<f32> {
+        let (edge1, edge2) = (b - a, c - a);
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+        if determinant.abs() < f32::EPSILON * edge1.length_squared().max(edge2.length_squared()) {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let s = self.origin - a;
+        let u = s.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge1);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = edge2.dot(q) * inverse;
+        (t >= 0.0).then_some(t)
+    }
+}
+
+struct BvhNode {
+    bounds: (Vec3, Vec3),
+    // Leaves: their triangles in `Bvh::triangles`; inner nodes: the second
+    // child, the first one follows right after the node
+    first: u32,
+    count: u32,
+}
+
+/// Bounding volume hierarchy over the triangles of one draw range, in model
+/// space, built by splitting at the median along the longest axis.
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    // Index of each triangle's first corner in `Mesh::indices`, in leaf order
+    triangles: Vec<u32>,
+}
+
+impl Bvh {
+    pub fn build(mesh: &Mesh, range: &DrawRange) -> Self {
+        let mut triangles: Vec<u32> = (range.start..range.start + range.count).step_by(3).collect();
+        let mut nodes = Vec::new();
+        if !triangles.is_empty() {
+            let centroids: Vec<Vec3> = triangles.iter().map(|&first| corners(mesh, first).into_iter().sum::<Vec3>() / 3.0).collect();
+            // Centroids are looked up by position in the range, which the sort below shuffles
+            let mut order: Vec<u32> = (0..triangles.len() as u32).collect();
+            split(mesh, &triangles, &centroids, &mut order, 0, &mut nodes);
+            triangles = order.iter().map(|&i| triangles[i as usize]).collect();
+        }
+        Self { nodes, triangles }
+    }
+
+    /// Closest triangle hit by `ray`, given in model space, as the distance
+    /// along the ray and the face index in the mesh.
+    pub fn intersect(&self, mesh: &Mesh, ray: &Ray) -> Option<(f32, usize)> {
+        let inverse_direction = ray.direction.recip();
+        let mut closest: Option<(f32, usize)> = None;
+        let mut stack = vec![0usize];
+        while let Some(index) = stack.pop() {
+            let Some(node) = self.nodes.get(index) else {
+                continue;
+            };
+            let max_t = closest.map_or(f32::INFINITY, |(t, _)| t);
+            if ray.hits_box(inverse_direction, node.bounds, max_t).is_none() {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first as usize);
+                stack.push(index + 1);
+                continue;
+            }
+            for &first in &self.triangles[node.first as usize..(node.first + node.count) as usize] {
+                if let Some(t) = ray.hits_triangle(corners(mesh, first)) {
+                    if t < closest.map_or(f32::INFINITY, |(closest, _)| closest) {
+                        closest = Some((t, first as usize / 3));
+                    }
+                }
+            }
+        }
+        closest
+    }
+}
+
+fn corners(mesh: &Mesh, first: u32) -> [Vec3; 3] {
+    [0, 1, 2].map(|corner| Vec3::from(mesh.vertices[mesh.indices[(first + corner) as usize] as usize].position))
+}
+
+// Adds the node over `order` (positions into `triangles`), starting at
+// `offset` of the final leaf order, and its subtree
+fn split(mesh: &Mesh, triangles: &[u32], centroids: &[Vec3], order: &mut [u32], offset: usize, nodes: &mut Vec<BvhNode>) {
+    let bounds = order
+        .iter()
+        .flat_map(|&i| corners(mesh, triangles[i as usize]))
+        .fold((Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)), |(min, max), point| (min.min(point), max.max(point)));
+    let node = nodes.len();
+    nodes.push(BvhNode {
+        bounds,
+        first: offset as u32,
+        count: order.len() as u32,
+    });
+    if order.len() <= LEAF_TRIANGLES {
+        return;
+    }
+
+    let (centroid_min, centroid_max) = order
+        .iter()
+        .map(|&i| centroids[i as usize])
+        .fold((Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)), |(min, max), point| (min.min(point), max.max(point)));
+    let extent = centroid_max - centroid_min;
+    let axis = if extent.x >= extent.y && extent.x >= extent.z {
+        0
+    } else if extent.y >= extent.z {
+        1
+    } else {
+        2
+    };
+    let middle = order.len() / 2;
+    order.select_nth_unstable_by(middle, |&a, &b| centroids[a as usize][axis].total_cmp(&centroids[b as usize][axis]));
+    let (left, right) = order.split_at_mut(middle);
+    split(mesh, triangles, centroids, left, offset, nodes);
+    nodes[node].first = nodes.len() as u32;
+    nodes[node].count = 0;
+    split(mesh, triangles, centroids, right, offset + middle, nodes);
+}
+
+/// The object and face a ray hit first, and where.
+#[derive(Debug, Clone, Copy)]
+pub struct Pick {
+    pub object: usize,
+    pub face: usize,
+    pub point: Vec3,
+}
@@ -0,0 +1,311 @@
+use anyhow::{bail, ensure, Context, Result};
+use std::path::Path;
+
+use crate::mesh::MeshData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            other => bail!("Unknown PLY property type {:?}", other),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    ty: Scalar,
+    // Count type of list properties
+    list: Option<Scalar>,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+/// Reads values from the body in the file's format.
+struct Body<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Body<'_> {
+    fn read(&mut self, ty: Scalar) -> Result<f64> {
+        if self.format == Format::Ascii {
+            let rest = &self.bytes[self.position..];
+            let start = rest.iter().position(|byte| !byte.is_ascii_whitespace()).context("Unexpected end of PLY data")?;
+            let length = rest[start..].iter().position(|byte| byte.is_ascii_whitespace()).unwrap_or(rest.len() - start);
+            self.position += start + length;
+            let token = std::str::from_utf8(&rest[start..start + length])?;
+            return token.parse().with_context(|| format!("Invalid PLY value {:?}", token));
+        }
+
+        let size = ty.size();
+        let mut raw = [0; 8];
+        raw[..size].copy_from_slice(self.bytes.get(self.position..self.position + size).context("Truncated PLY data")?);
+        self.position += size;
+        if self.format == Format::BinaryBigEndian {
+            raw[..size].reverse();
+        }
+        Ok(match ty {
+            Scalar::I8 => raw[0] as i8 as f64,
+            Scalar::U8 => raw[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([raw[0], raw[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([raw[0], raw[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(raw),
+        })
+    }
+}
+
+/// Loads an ASCII or binary PLY mesh with optional per-vertex normals and
+/// colors. Polygons are triangulated as fans.
+pub fn load(path: &Path) -> Result<Vec<MeshData>> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    parse(&bytes, name)
+}
+
+fn parse(bytes: &[u8], name: String) -> Result<Vec<MeshData>> {
+    ensure!(bytes.starts_with(b"ply"), "Not a PLY file");
+    let header_end = bytes
+        .windows(10)
+        .position(|window| window == b"end_header")
+        .context("PLY header has no end_header")?;
+    let body_start = header_end + bytes[header_end..].iter().position(|&byte| byte == b'\n').context("Truncated PLY header")? + 1;
+    let header = std::str::from_utf8(&bytes[..header_end])?;
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in header.lines().skip(1) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    other => bail!("Unknown PLY format {:?}", other),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().with_context(|| format!("Invalid PLY element count {:?}", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_type, item_type, name] => {
+                let element = elements.last_mut().context("PLY property outside an element")?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    ty: Scalar::parse(item_type)?,
+                    list: Some(Scalar::parse(count_type)?),
+                });
+            }
+            ["property", ty, name] => {
+                let element = elements.last_mut().context("PLY property outside an element")?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    ty: Scalar::parse(ty)?,
+                    list: None,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let mut body = Body {
+        format: format.context("PLY header has no format")?,
+        bytes: &bytes[body_start..],
+        position: 0,
+    };
+    let mut data = MeshData {
+        name,
+        ..Default::default()
+    };
+    let mut normals = Vec::new();
+    let mut colors = Vec::new();
+
+    for element in &elements {
+        let column = |name: &str| element.properties.iter().position(|property| property.name == name);
+        let position_columns = [column("x"), column("y"), column("z")];
+        let normal_columns = [column("nx"), column("ny"), column("nz")];
+        let color_columns = [
+            column("red").or(column("r")),
+            column("green").or(column("g")),
+            column("blue").or(column("b")),
+        ];
+        let index_column = column("vertex_indices").or(column("vertex_index"));
+
+        let mut values = vec![0.0; element.properties.len()];
+        let mut list = Vec::new();
+        for _ in 0..element.count {
+            for (i, property) in element.properties.iter().enumerate() {
+                match property.list {
+                    Some(count_type) => {
+                        let count = body.read(count_type)? as usize;
+                        let keep = Some(i) == index_column;
+                        if keep {
+                            list.clear();
+                        }
+                        for _ in 0..count {
+                            let value = body.read(property.ty)?;
+                            if keep {
+                                list.push(value as u32);
+                            }
+                        }
+                    }
+                    None => values[i] = body.read(property.ty)?,
+                }
+            }
+
+            match element.name.as_str() {
+                "vertex" => {
+                    let [Some(x), Some(y), Some(z)] = position_columns else {
+                        bail!("PLY vertices have no x, y, z properties");
+                    };
+                    data.positions.push([values[x] as f32, values[y] as f32, values[z] as f32]);
+                    if let [Some(x), Some(y), Some(z)] = normal_columns {
+                        normals.push([values[x] as f32, values[y] as f32, values[z] as f32]);
+                    }
+                    if let [Some(r), Some(g), Some(b)] = color_columns {
+                        colors.push([r, g, b].map(|channel| color_channel(values[channel], element.properties[channel].ty)));
+                    }
+                }
+                "face" if index_column.is_some() => {
+                    for k in 1..list.len().saturating_sub(1) {
+                        data.triangles.push([list[0], list[k], list[k + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let vertex_count = data.positions.len() as u32;
+    if let Some(index) = data.triangles.iter().flatten().find(|&&index| index >= vertex_count) {
+        bail!("PLY face references vertex {} but there are only {}", index, vertex_count);
+    }
+    data.normals = (!normals.is_empty()).then_some(normals);
+    data.vertex_colors = (!colors.is_empty()).then_some(colors);
+    Ok(vec![data])
+}
+
+// Float channels are already 0..1, integer channels span their type's range
+fn color_channel(value: f64, ty: Scalar) -> f32 {
+    match ty {
+        Scalar::F32 | Scalar::F64 => value as f32,
+        Scalar::U16 => value as f32 / 65535.0,
+        _ => value as f32 / 255.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    const POSITIONS: [[f32; 3]; 4] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+
+    // A red, green, blue and white quad with `face` as its one face list
+    fn binary(format: &str, face: &[i32], to_bytes: fn(f32) -> [u8; 4], int_bytes: fn(i32) -> [u8; 4]) -> Vec<u8> {
+        let mut bytes = format!("ply\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+        for (position, color) in POSITIONS.iter().zip([[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 255]]) {
+            bytes.extend(position.iter().flat_map(|&value| to_bytes(value)));
+            bytes.extend(color);
+        }
+        bytes.push(face.len() as u8);
+        bytes.extend(face.iter().flat_map(|&index| int_bytes(index)));
+        bytes
+    }
+
+    fn check_quad(parts: &[MeshData]) {
+        let [part] = parts else { panic!("expected one part, got {}", parts.len()) };
+        assert_eq!(part.positions, POSITIONS);
+        assert_eq!(part.triangles, [[0, 1, 2], [0, 2, 3]]);
+        let colors = part.vertex_colors.as_ref().expect("vertex colors");
+        assert_eq!(colors[..2], [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+    }
+
+    #[test]
+    fn loads_ascii() {
+        let ply = format!("ply\nformat ascii 1.0\n{}0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n4 0 1 2 3\n", HEADER);
+        check_quad(&parse(ply.as_bytes(), "quad".to_string()).unwrap());
+    }
+
+    #[test]
+    fn loads_binary_little_endian() {
+        let ply = binary("binary_little_endian", &[0, 1, 2, 3], f32::to_le_bytes, i32::to_le_bytes);
+        check_quad(&parse(&ply, "quad".to_string()).unwrap());
+    }
+
+    #[test]
+    fn loads_binary_big_endian() {
+        let ply = binary("binary_big_endian", &[0, 1, 2, 3], f32::to_be_bytes, i32::to_be_bytes);
+        check_quad(&parse(&ply, "quad".to_string()).unwrap());
+    }
+
+    #[test]
+    fn rejects_bad_faces() {
+        let out_of_range = binary("binary_little_endian", &[0, 1, 4], f32::to_le_bytes, i32::to_le_bytes);
+        let error = parse(&out_of_range, "quad".to_string()).unwrap_err();
+        assert!(error.to_string().contains("references vertex 4"), "{}", error);
+
+        let mut truncated = binary("binary_little_endian", &[0, 1, 2, 3], f32::to_le_bytes, i32::to_le_bytes);
+        truncated.truncate(truncated.len() - 2);
+        let error = parse(&truncated, "quad".to_string()).unwrap_err();
+        assert!(error.to_string().contains("Truncated PLY data"), "{}", error);
+    }
+}
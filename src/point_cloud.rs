@@ -0,0 +1,715 @@
+use anyhow::{bail, ensure, Context, Result};
+use std::collections::VecDeque;
+use std::path::Path;
+use tracing::info;
+use wgpu::util::DeviceExt;
+
+use crate::postprocess::HDR_FORMAT;
+use crate::shaders::create_shader_module;
+
+/// Points per GPU buffer. Clouds are uploaded one chunk at a time so huge
+/// scans don't need a single giant allocation or stall a frame.
+pub const CHUNK_POINTS: usize = 1 << 20;
+
+const DEFAULT_COLOR: [u8; 4] = [200, 200, 200, 255];
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointVertex {
+    pub position: [f32; 3],
+    pub color: [u8; 4],
+}
+
+impl PointVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<PointVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Unorm8x4,
+                },
+            ],
+        }
+    }
+}
+
+/// A loaded scan. Positions are stored relative to `origin` (the first
+/// point) so survey coordinates keep their precision as `f32`.
+pub struct PointCloud {
+    pub points: Vec<PointVertex>,
+    pub origin: [f64; 3],
+    pub bounds: (glam::Vec3, glam::Vec3),
+}
+
+pub fn is_point_cloud(path: &Path) -> bool {
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    matches!(extension.as_deref(), Some("pcd" | "e57"))
+}
+
+/// Loads a PCD or E57 file. The points are shuffled so that any prefix of
+/// the cloud (and of every chunk) is a uniform subsample, which is what the
+/// level-of-detail budget relies on.
+pub fn load(path: &Path) -> Result<PointCloud> {
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    let mut collector = Collector::default();
+    match extension.as_deref() {
+        Some("pcd") => read_pcd(&std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?, &mut collector)?,
+        Some("e57") => load_e57(path, &mut collector)?,
+        _ => bail!("Unsupported point cloud format: {:?}", path),
+    }
+    if collector.points.is_empty() {
+        bail!("No valid points found in {:?}", path);
+    }
+    Ok(collector.finish())
+}
+
+struct Collector {
+    points: Vec<PointVertex>,
+    origin: Option<[f64; 3]>,
+    min: glam::Vec3,
+    max: glam::Vec3,
+}
+
+impl Default for Collector {
+    fn default() -> Self {
+        Self {
+            points: Vec::new(),
+            origin: None,
+            min: glam::Vec3::splat(f32::INFINITY),
+            max: glam::Vec3::splat(f32::NEG_INFINITY),
+        }
+    }
+}
+
+impl Collector {
+    fn push(&mut self, position: [f64; 3], color: [u8; 4]) {
+        if !position.iter().all(|value| value.is_finite()) {
+            return;
+        }
+        let origin = *self.origin.get_or_insert(position);
+        let position = [0, 1, 2].map(|i| (position[i] - origin[i]) as f32);
+        let vector = glam::Vec3::from(position);
+        self.min = self.min.min(vector);
+        self.max = self.max.max(vector);
+        self.points.push(PointVertex { position, color });
+    }
+
+    fn finish(mut self) -> PointCloud {
+        shuffle(&mut self.points);
+        info!("Loaded point cloud with {} points", self.points.len());
+        PointCloud {
+            points: self.points,
+            origin: self.origin.unwrap_or_default(),
+            bounds: (self.min, self.max),
+        }
+    }
+}
+
+// SplitMix64-driven Fisher-Yates; deterministic so reloading gives the same LOD
+fn shuffle(points: &mut [PointVertex]) {
+    let mut state = 0x5EED_u64;
+    for i in (1..points.len()).rev() {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        points.swap(i, (z % (i as u64 + 1)) as usize);
+    }
+}
+
+fn load_e57(path: &Path, collector: &mut Collector) -> Result<()> {
+    let mut reader = e57::E57Reader::from_file(path).with_context(|| format!("Failed to open {:?}", path))?;
+    for cloud in reader.pointclouds() {
+        collector.points.reserve(cloud.records as usize);
+        // Spherical coordinates are converted, scan poses applied and intensity
+        // turned into grey by the reader's defaults
+        for point in reader.pointcloud_simple(&cloud)? {
+            let point = point?;
+            let e57::CartesianCoordinate::Valid { x, y, z } = point.cartesian else {
+                continue;
+            };
+            let color = match point.color {
+                Some(color) => [color.red, color.green, color.blue].map(unit_to_byte),
+                None => [DEFAULT_COLOR[0], DEFAULT_COLOR[1], DEFAULT_COLOR[2]],
+            };
+            collector.push([x, y, z], [color[0], color[1], color[2], 255]);
+        }
+    }
+    Ok(())
+}
+
+fn unit_to_byte(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+struct PcdField {
+    name: String,
+    size: usize,
+    kind: u8,
+    count: usize,
+    offset: usize,
+}
+
+impl PcdField {
+    fn read(&self, bytes: &[u8]) -> f64 {
+        match (self.kind, self.size) {
+            (b'F', 4) => f32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            (b'F', 8) => f64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            (b'U', 1) => bytes[0] as f64,
+            (b'U', 2) => u16::from_le_bytes(bytes[..2].try_into().unwrap()) as f64,
+            (b'U', 4) => u32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            (b'I', 1) => bytes[0] as i8 as f64,
+            (b'I', 2) => i16::from_le_bytes(bytes[..2].try_into().unwrap()) as f64,
+            (b'I', 4) => i32::from_le_bytes(bytes[..4].try_into().unwrap()) as f64,
+            (b'U', 8) => u64::from_le_bytes(bytes[..8].try_into().unwrap()) as f64,
+            (b'I', 8) => i64::from_le_bytes(bytes[..8].try_into().unwrap()) as f64,
+            _ => f64::NAN,
+        }
+    }
+
+    // Writes an ASCII value with the field's binary encoding
+    fn encode(&self, token: &str, out: &mut Vec<u8>) -> Result<()> {
+        let invalid = || format!("Invalid PCD value {:?} for field {}", token, self.name);
+        match (self.kind, self.size) {
+            (b'F', 4) => out.extend(token.parse::<f32>().with_context(invalid)?.to_le_bytes()),
+            (b'F', 8) => out.extend(token.parse::<f64>().with_context(invalid)?.to_le_bytes()),
+            (b'U' | b'I', size) => {
+                let value: i128 = token.parse().with_context(invalid)?;
+                out.extend_from_slice(&value.to_le_bytes()[..size]);
+            }
+            _ => bail!("Unsupported PCD field type {}{}", self.kind as char, self.size),
+        }
+        Ok(())
+    }
+}
+
+/// Reads ASCII, binary and binary_compressed PCD files. Colors come from a
+/// packed `rgb`/`rgba` field, or from `intensity` as grey.
+fn read_pcd(bytes: &[u8], collector: &mut Collector) -> Result<()> {
+    let mut fields: Vec<PcdField> = Vec::new();
+    let mut sizes = Vec::new();
+    let mut kinds = Vec::new();
+    let mut counts = Vec::new();
+    let mut points = None;
+    let mut cursor = 0;
+    let data = loop {
+        let end = bytes[cursor..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map(|offset| cursor + offset + 1)
+            .context("PCD header ended without a DATA line")?;
+        let line = String::from_utf8_lossy(&bytes[cursor..end]);
+        cursor = end;
+
+        let mut tokens = line.split_whitespace();
+        let values: Vec<&str> = tokens.clone().skip(1).collect();
+        match tokens.next() {
+            Some("FIELDS") => {
+                fields = values
+                    .iter()
+                    .map(|name| PcdField { name: name.to_string(), size: 4, kind: b'F', count: 1, offset: 0 })
+                    .collect()
+            }
+            Some("SIZE") => sizes = values.iter().map(|v| v.parse::<usize>()).collect::<Result<_, _>>()?,
+            Some("TYPE") => kinds = values.iter().map(|v| v.as_bytes()[0]).collect(),
+            Some("COUNT") => counts = values.iter().map(|v| v.parse::<usize>()).collect::<Result<_, _>>()?,
+            Some("POINTS") => points = Some(values.first().context("Empty POINTS line")?.parse::<usize>()?),
+            Some("DATA") => break values.first().context("Empty DATA line")?.to_string(),
+            _ => {}
+        }
+    };
+
+    let mut offset = 0;
+    for (i, field) in fields.iter_mut().enumerate() {
+        field.size = sizes.get(i).copied().unwrap_or(4);
+        field.kind = kinds.get(i).copied().unwrap_or(b'F');
+        field.count = counts.get(i).copied().unwrap_or(1);
+        field.offset = offset;
+        offset += field.size * field.count;
+        ensure!(
+            field.count > 0 && matches!((field.kind, field.size), (b'F', 4 | 8) | (b'U' | b'I', 1 | 2 | 4 | 8)),
+            "Unsupported PCD field type {}{} (count {}) for {}",
+            field.kind as char,
+            field.size,
+            field.count,
+            field.name
+        );
+    }
+    let stride = offset;
+    let points = points.context("PCD header without POINTS")?;
+    let field = |name: &str| fields.iter().find(|field| field.name == name);
+    let (Some(x), Some(y), Some(z)) = (field("x"), field("y"), field("z")) else {
+        bail!("PCD file has no x/y/z fields");
+    };
+    let color = field("rgb").or_else(|| field("rgba"));
+    if let Some(color) = color {
+        ensure!(
+            color.size == 4 && matches!(color.kind, b'U' | b'F'),
+            "PCD {} field is {}{}, but packed colors are 4-byte U or F values",
+            color.name,
+            color.kind as char,
+            color.size
+        );
+    }
+    let intensity = field("intensity");
+
+    // Everything is normalized to little-endian records; compressed files
+    // store each field as a contiguous column instead
+    let body = &bytes[cursor..];
+    let (records, columnar) = match data.as_str() {
+        "ascii" => {
+            let mut records = Vec::with_capacity(points * stride);
+            for line in String::from_utf8_lossy(body).lines().filter(|line| !line.trim().is_empty()).take(points) {
+                let mut tokens = line.split_whitespace();
+                for field in &fields {
+                    for _ in 0..field.count {
+                        field.encode(tokens.next().context("Truncated PCD point")?, &mut records)?;
+                    }
+                }
+            }
+            (records, false)
+        }
+        "binary" => (body.to_vec(), false),
+        "binary_compressed" => {
+            ensure!(body.len() >= 8, "Truncated compressed PCD data");
+            let compressed = u32::from_le_bytes(body[..4].try_into().unwrap()) as usize;
+            let uncompressed = u32::from_le_bytes(body[4..8].try_into().unwrap()) as usize;
+            let input = body.get(8..8 + compressed).context("Truncated compressed PCD data")?;
+            (lzf_decompress(input, uncompressed)?, true)
+        }
+        other => bail!("Unsupported PCD DATA encoding {:?}", other),
+    };
+    // Columns are located from the point count, so a compressed body has to
+    // hold exactly that many; records are simply cut off at the last full one
+    let points = if columnar {
+        ensure!(
+            Some(records.len()) == points.checked_mul(stride),
+            "Compressed PCD data holds {} bytes, expected {} points of {} bytes",
+            records.len(),
+            points,
+            stride
+        );
+        points
+    } else {
+        points.min(records.len() / stride)
+    };
+    let locate = |point: usize, field: &PcdField| {
+        if columnar {
+            field.offset * points + point * field.size * field.count
+        } else {
+            point * stride + field.offset
+        }
+    };
+
+    let max_intensity = match intensity {
+        Some(intensity) if color.is_none() => (0..points)
+            .map(|point| intensity.read(&records[locate(point, intensity)..]))
+            .filter(|value| value.is_finite())
+            .fold(0.0_f64, f64::max),
+        _ => 0.0,
+    };
+
+    collector.points.reserve(points);
+    for point in 0..points {
+        let position = [x, y, z].map(|field| field.read(&records[locate(point, field)..]));
+        let rgba = if let Some(color) = color {
+            // Packed 0x00RRGGBB, stored either as a float's bits or as an integer
+            let packed = u32::from_le_bytes(records[locate(point, color)..][..4].try_into().unwrap());
+            [(packed >> 16) as u8, (packed >> 8) as u8, packed as u8, 255]
+        } else if let Some(intensity) = intensity.filter(|_| max_intensity > 0.0) {
+            let grey = unit_to_byte((intensity.read(&records[locate(point, intensity)..]) / max_intensity) as f32);
+            [grey, grey, grey, 255]
+        } else {
+            DEFAULT_COLOR
+        };
+        collector.push(position, rgba);
+    }
+    Ok(())
+}
+
+// LZF as used by PCL's binary_compressed encoding
+fn lzf_decompress(input: &[u8], output_len: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(output_len);
+    let mut i = 0;
+    while i < input.len() {
+        let control = input[i] as usize;
+        i += 1;
+        if control < 32 {
+            let literal = input.get(i..i + control + 1).context("Corrupt LZF literal run")?;
+            output.extend_from_slice(literal);
+            i += control + 1;
+        } else {
+            let mut length = control >> 5;
+            if length == 7 {
+                length += *input.get(i).context("Corrupt LZF back reference")? as usize;
+                i += 1;
+            }
+            let distance = ((control & 0x1f) << 8) + *input.get(i).context("Corrupt LZF back reference")? as usize + 1;
+            i += 1;
+            ensure!(distance <= output.len(), "Corrupt LZF back reference");
+            let start = output.len() - distance;
+            for k in 0..length + 2 {
+                output.push(output[start + k]);
+            }
+        }
+    }
+    ensure!(output.len() == output_len, "Compressed PCD data has the wrong size");
+    Ok(output)
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointUniforms {
+    point_size: f32,
+    viewport_height: f32,
+    aspect_ratio: f32,
+    _padding: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PointSettings {
+    /// Point diameter in pixels.
+    pub point_size: f32,
+    /// Maximum number of points drawn per frame; larger clouds are drawn
+    /// as an evenly thinned subset.
+    pub budget: usize,
+    /// Chunks uploaded to the GPU per frame while a cloud is streaming in.
+    pub uploads_per_frame: usize,
+}
+
+impl Default for PointSettings {
+    fn default() -> Self {
+        Self {
+            point_size: 2.0,
+            budget: 5_000_000,
+            uploads_per_frame: 4,
+        }
+    }
+}
+
+struct PointChunk {
+    buffer: wgpu::Buffer,
+    count: u32,
+}
+
+/// Draws point clouds as screen-space discs, streaming chunks to the GPU
+/// over several frames.
+pub struct PointRenderer {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    chunks: Vec<PointChunk>,
+    pending: VecDeque<Vec<PointVertex>>,
+    total_points: usize,
+    origin: [f64; 3],
+    pub settings: PointSettings,
+}
+
+impl PointRenderer {
+    pub fn new(device: &wgpu::Device, camera_bind_group_layout: &wgpu::BindGroupLayout) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Point Uniform Buffer"),
+            size: std::mem::size_of::<PointUniforms>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Point Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Point Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = create_shader_module(device, "Point Shader", include_str!("shaders/points.wgsl"));
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Point Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Point Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[PointVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: HDR_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            chunks: Vec::new(),
+            pending: VecDeque::new(),
+            total_points: 0,
+            origin: [0.0; 3],
+            settings: PointSettings::default(),
+        }
+    }
+
+    /// Replaces the current cloud. Nothing is uploaded until `upload_pending`.
+    pub fn set_cloud(&mut self, mut cloud: PointCloud) {
+        self.clear();
+        self.total_points = cloud.points.len();
+        self.origin = cloud.origin;
+        while !cloud.points.is_empty() {
+            let split = cloud.points.len().saturating_sub(CHUNK_POINTS);
+            self.pending.push_back(cloud.points.split_off(split));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.pending.clear();
+        self.total_points = 0;
+        self.origin = [0.0; 3];
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total_points == 0
+    }
+
+    pub fn total_points(&self) -> usize {
+        self.total_points
+    }
+
+    pub fn uploaded_points(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.count as usize).sum()
+    }
+
+    pub fn origin(&self) -> [f64; 3] {
+        self.origin
+    }
+
+    /// Uploads up to `max_chunks` waiting chunks. Returns whether any are left.
+    pub fn upload_pending(&mut self, device: &wgpu::Device, max_chunks: usize) -> bool {
+        for _ in 0..max_chunks {
+            let Some(points) = self.pending.pop_front() else {
+                break;
+            };
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Point Chunk Buffer"),
+                contents: bytemuck::cast_slice(&points),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            self.chunks.push(PointChunk { buffer, count: points.len() as u32 });
+        }
+        !self.pending.is_empty()
+    }
+
+    pub fn write_uniforms(&self, queue: &wgpu::Queue, aspect_ratio: f32, viewport_height: u32) {
+        let uniforms = PointUniforms {
+            point_size: self.settings.point_size,
+            viewport_height: viewport_height as f32,
+            aspect_ratio,
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    // Every chunk is a random sample of the cloud, so drawing the same
+    // leading fraction of each one thins the cloud evenly
+    fn drawn_fraction(&self) -> f64 {
+        let uploaded = self.uploaded_points();
+        if uploaded == 0 {
+            return 0.0;
+        }
+        (self.settings.budget as f64 / uploaded as f64).min(1.0)
+    }
+
+    pub fn drawn_points(&self) -> usize {
+        let fraction = self.drawn_fraction();
+        self.chunks
+            .iter()
+            .map(|chunk| (chunk.count as f64 * fraction).ceil() as usize)
+            .sum()
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera_bind_group: &'a wgpu::BindGroup) {
+        if self.chunks.is_empty() {
+            return;
+        }
+        let fraction = self.drawn_fraction();
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        for chunk in &self.chunks {
+            let count = (chunk.count as f64 * fraction).ceil() as u32;
+            render_pass.set_vertex_buffer(0, chunk.buffer.slice(..));
+            render_pass.draw(0..6, 0..count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "VERSION .7
+FIELDS x y z rgb
+SIZE 4 4 4 4
+TYPE F F F U
+COUNT 1 1 1 1
+WIDTH 3
+HEIGHT 1
+POINTS 3
+";
+
+    // Positions and packed colors of the three points in every encoding
+    const POINTS: [([f32; 3], u32); 3] = [([10.0, 20.0, 30.0], 0xFF0000), ([11.0, 20.0, 30.0], 0x00FF00), ([10.0, 22.0, 33.0], 0x0000FF)];
+
+    fn parse(bytes: &[u8]) -> Result<PointCloud> {
+        let mut collector = Collector::default();
+        read_pcd(bytes, &mut collector)?;
+        Ok(collector.finish())
+    }
+
+    fn check_points(cloud: &PointCloud) {
+        assert_eq!(cloud.origin, [10.0, 20.0, 30.0]);
+        assert_eq!(cloud.bounds, (glam::Vec3::ZERO, glam::Vec3::new(1.0, 2.0, 3.0)));
+        let mut points: Vec<([f32; 3], [u8; 4])> = cloud.points.iter().map(|point| (point.position, point.color)).collect();
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        assert_eq!(
+            points,
+            [([0.0, 0.0, 0.0], [255, 0, 0, 255]), ([0.0, 2.0, 3.0], [0, 0, 255, 255]), ([1.0, 0.0, 0.0], [0, 255, 0, 255])]
+        );
+    }
+
+    #[test]
+    fn loads_ascii_pcd() {
+        let mut pcd = format!("{}DATA ascii\n", HEADER);
+        for ([x, y, z], rgb) in POINTS {
+            pcd += &format!("{} {} {} {}\n", x, y, z, rgb);
+        }
+        check_points(&parse(pcd.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn loads_binary_pcd() {
+        let mut pcd = format!("{}DATA binary\n", HEADER).into_bytes();
+        for (position, rgb) in POINTS {
+            pcd.extend(position.iter().flat_map(|value| value.to_le_bytes()));
+            pcd.extend(rgb.to_le_bytes());
+        }
+        check_points(&parse(&pcd).unwrap());
+    }
+
+    // The three points as binary_compressed data, one column per field
+    // stored as LZF literal runs of up to 32 bytes, less `missing` bytes
+    fn compressed_pcd(missing: usize) -> Vec<u8> {
+        let mut columns = Vec::new();
+        for field in 0..4 {
+            for (position, rgb) in POINTS {
+                columns.extend(if field < 3 { position[field].to_le_bytes() } else { rgb.to_le_bytes() });
+            }
+        }
+        columns.truncate(columns.len() - missing);
+        let mut compressed = Vec::new();
+        for run in columns.chunks(32) {
+            compressed.push(run.len() as u8 - 1);
+            compressed.extend(run);
+        }
+        let mut pcd = format!("{}DATA binary_compressed\n", HEADER).into_bytes();
+        pcd.extend((compressed.len() as u32).to_le_bytes());
+        pcd.extend((columns.len() as u32).to_le_bytes());
+        pcd.extend(compressed);
+        pcd
+    }
+
+    #[test]
+    fn loads_compressed_pcd() {
+        check_points(&parse(&compressed_pcd(0)).unwrap());
+    }
+
+    #[test]
+    fn rejects_truncated_compressed_pcd() {
+        let error = parse(&compressed_pcd(4)).err().expect("should fail");
+        assert!(error.to_string().contains("expected 3 points of 16 bytes"), "{}", error);
+    }
+
+    #[test]
+    fn decompresses_lzf_back_references() {
+        // "ab" as a literal, then a 4-byte copy starting 2 back
+        assert_eq!(lzf_decompress(&[1, b'a', b'b', 0x40, 1], 6).unwrap(), b"ababab");
+        assert!(lzf_decompress(&[0x40, 5], 3).is_err());
+    }
+
+    #[test]
+    fn shades_intensity_as_grey() {
+        let pcd = "FIELDS x y z intensity\nPOINTS 2\nDATA ascii\n0 0 0 50\n1 0 0 100\n";
+        let cloud = parse(pcd.as_bytes()).unwrap();
+        let mut colors: Vec<[u8; 4]> = cloud.points.iter().map(|point| point.color).collect();
+        colors.sort();
+        assert_eq!(colors, [[128, 128, 128, 255], [255, 255, 255, 255]]);
+    }
+
+    #[test]
+    fn rejects_pcd_without_positions() {
+        let error = parse(b"FIELDS a b c\nPOINTS 1\nDATA ascii\n1 2 3\n").err().expect("should fail");
+        assert!(error.to_string().contains("no x/y/z fields"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_unsupported_field_types() {
+        let error = parse(b"FIELDS x y z\nSIZE 4 4 32\nTYPE F F U\nPOINTS 1\nDATA ascii\n1 2 3\n").err().expect("should fail");
+        assert!(error.to_string().contains("Unsupported PCD field type U32"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_narrow_packed_colors() {
+        let pcd = b"FIELDS x y z rgb\nSIZE 4 4 4 2\nTYPE F F F U\nPOINTS 1\nDATA binary\n0123456789ab01";
+        let error = parse(pcd).err().expect("should fail");
+        assert!(error.to_string().contains("packed colors are 4-byte"), "{}", error);
+    }
+}
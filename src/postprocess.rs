@@ -0,0 +1,529 @@
+use wgpu::util::DeviceExt;
+
+use crate::color::{self, ColorSpace};
+use crate::shaders::create_shader_module;
+
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ExposureParams {
+    delta_time: f32,
+    adaptation_speed: f32,
+    min_luminance: f32,
+    max_luminance: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ExposureState {
+    average_luminance: f32,
+    adapted_luminance: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompositeParams {
+    exposure_compensation: f32,
+    auto_exposure: u32,
+    key_value: f32,
+    stereo_mode: u32,
+    // Columns of the matrix taking linear sRGB to the output color space
+    output_gamut: [[f32; 4]; 3],
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExposureSettings {
+    pub auto_exposure: bool,
+    pub exposure_compensation: f32,
+    pub adaptation_speed: f32,
+    pub key_value: f32,
+}
+
+impl Default for ExposureSettings {
+    fn default() -> Self {
+        Self {
+            auto_exposure: false,
+            exposure_compensation: 0.0,
+            adaptation_speed: 1.5,
+            key_value: 0.18,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoMode {
+    Off,
+    Anaglyph,
+    SideBySide,
+}
+
+impl StereoMode {
+    pub const ALL: [StereoMode; 3] = [StereoMode::Off, StereoMode::Anaglyph, StereoMode::SideBySide];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StereoMode::Off => "Off",
+            StereoMode::Anaglyph => "Anaglyph (red/cyan)",
+            StereoMode::SideBySide => "Side by side",
+        }
+    }
+}
+
+/// Offscreen HDR scene target plus the passes that resolve it onto the surface.
+pub struct PostProcess {
+    hdr_texture: wgpu::Texture,
+    hdr_view: wgpu::TextureView,
+    // Second eye, only allocated while stereo is enabled
+    hdr_right: Option<(wgpu::Texture, wgpu::TextureView)>,
+    sampler: wgpu::Sampler,
+
+    exposure_params_buffer: wgpu::Buffer,
+    exposure_state_buffer: wgpu::Buffer,
+    composite_params_buffer: wgpu::Buffer,
+
+    // Auto exposure's reduction; `None` on adapters without compute shaders
+    luminance: Option<LuminancePass>,
+
+    composite_bind_group_layout: wgpu::BindGroupLayout,
+    composite_bind_group: wgpu::BindGroup,
+    composite_pipeline: wgpu::RenderPipeline,
+
+    pub exposure: ExposureSettings,
+    /// What the composite encodes for; the target format stays sRGB-encoded.
+    pub color_space: ColorSpace,
+    stereo_mode: StereoMode,
+}
+
+struct LuminancePass {
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl PostProcess {
+    /// `compute_shaders` tells whether the adapter runs compute shaders;
+    /// without them auto exposure stays off.
+    pub fn new(device: &wgpu::Device, surface_format: wgpu::TextureFormat, width: u32, height: u32, compute_shaders: bool) -> Self {
+        let hdr_texture = Self::create_hdr_texture(device, width, height);
+        let hdr_view = hdr_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("HDR Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let exposure = ExposureSettings::default();
+
+        let exposure_params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Exposure Params Buffer"),
+            contents: bytemuck::cast_slice(&[ExposureParams {
+                delta_time: 0.0,
+                adaptation_speed: exposure.adaptation_speed,
+                min_luminance: 0.001,
+                max_luminance: 1000.0,
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let exposure_state_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Exposure State Buffer"),
+            contents: bytemuck::cast_slice(&[ExposureState {
+                average_luminance: 0.0,
+                adapted_luminance: 0.0,
+            }]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let composite_params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Composite Params Buffer"),
+            contents: bytemuck::cast_slice(&[Self::composite_params(&exposure, StereoMode::Off, ColorSpace::Srgb)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let composite_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Composite Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let composite_shader = create_shader_module(device, "Composite Shader", include_str!("shaders/composite.wgsl"));
+
+        let composite_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Composite Pipeline Layout"),
+            bind_group_layouts: &[&composite_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let composite_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Composite Pipeline"),
+            layout: Some(&composite_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &composite_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &composite_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let luminance = compute_shaders.then(|| {
+            let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Luminance Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+            let shader = create_shader_module(device, "Luminance Shader", include_str!("shaders/luminance.wgsl"));
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Luminance Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+            let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Luminance Pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: "cs_main",
+            });
+            let bind_group = Self::create_luminance_bind_group(device, &bind_group_layout, &hdr_view, &exposure_params_buffer, &exposure_state_buffer);
+            LuminancePass {
+                bind_group_layout,
+                bind_group,
+                pipeline,
+            }
+        });
+        let composite_bind_group = Self::create_composite_bind_group(
+            device,
+            &composite_bind_group_layout,
+            &hdr_view,
+            &hdr_view,
+            &sampler,
+            &composite_params_buffer,
+            &exposure_state_buffer,
+        );
+
+        Self {
+            hdr_texture,
+            hdr_view,
+            hdr_right: None,
+            sampler,
+            exposure_params_buffer,
+            exposure_state_buffer,
+            composite_params_buffer,
+            luminance,
+            composite_bind_group_layout,
+            composite_bind_group,
+            composite_pipeline,
+            exposure,
+            color_space: ColorSpace::Srgb,
+            stereo_mode: StereoMode::Off,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.hdr_texture = Self::create_hdr_texture(device, width, height);
+        self.hdr_view = self.hdr_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        if let Some(luminance) = &mut self.luminance {
+            luminance.bind_group = Self::create_luminance_bind_group(
+                device,
+                &luminance.bind_group_layout,
+                &self.hdr_view,
+                &self.exposure_params_buffer,
+                &self.exposure_state_buffer,
+            );
+        }
+        self.create_stereo_target(device, width, height);
+    }
+
+    pub fn stereo_mode(&self) -> StereoMode {
+        self.stereo_mode
+    }
+
+    pub fn set_stereo_mode(&mut self, device: &wgpu::Device, mode: StereoMode) {
+        if mode != self.stereo_mode {
+            self.stereo_mode = mode;
+            let size = self.hdr_texture.size();
+            self.create_stereo_target(device, size.width, size.height);
+        }
+    }
+
+    // (Re)allocates the right-eye target to match the left one and rebinds both for compositing
+    fn create_stereo_target(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.hdr_right = (self.stereo_mode != StereoMode::Off).then(|| {
+            let texture = Self::create_hdr_texture(device, width, height);
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            (texture, view)
+        });
+
+        self.composite_bind_group = Self::create_composite_bind_group(
+            device,
+            &self.composite_bind_group_layout,
+            &self.hdr_view,
+            self.hdr_right_view(),
+            &self.sampler,
+            &self.composite_params_buffer,
+            &self.exposure_state_buffer,
+        );
+    }
+
+    pub fn hdr_view(&self) -> &wgpu::TextureView {
+        &self.hdr_view
+    }
+
+    /// Target for the right eye; falls back to the main target when stereo is off.
+    pub fn hdr_right_view(&self) -> &wgpu::TextureView {
+        self.hdr_right.as_ref().map_or(&self.hdr_view, |(_, view)| view)
+    }
+
+    pub fn supports_auto_exposure(&self) -> bool {
+        self.luminance.is_some()
+    }
+
+    // The settings as applied, with auto exposure off where it can't run
+    fn active_exposure(&self) -> ExposureSettings {
+        ExposureSettings {
+            auto_exposure: self.exposure.auto_exposure && self.supports_auto_exposure(),
+            ..self.exposure
+        }
+    }
+
+    pub fn toggle_auto_exposure(&mut self) {
+        self.exposure.auto_exposure = !self.exposure.auto_exposure;
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, delta_time: f32) {
+        let exposure_params = ExposureParams {
+            delta_time,
+            adaptation_speed: self.exposure.adaptation_speed,
+            min_luminance: 0.001,
+            max_luminance: 1000.0,
+        };
+        queue.write_buffer(&self.exposure_params_buffer, 0, bytemuck::cast_slice(&[exposure_params]));
+        queue.write_buffer(
+            &self.composite_params_buffer,
+            0,
+            bytemuck::cast_slice(&[Self::composite_params(&self.active_exposure(), self.stereo_mode, self.color_space)]),
+        );
+    }
+
+    /// Records the luminance reduction (when auto exposure is on) and the
+    /// composite of the HDR scene onto `target`.
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        if let Some(luminance) = self.luminance.as_ref().filter(|_| self.exposure.auto_exposure) {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Luminance Pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&luminance.pipeline);
+            compute_pass.set_bind_group(0, &luminance.bind_group, &[]);
+            compute_pass.dispatch_workgroups(1, 1, 1);
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Composite Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, &self.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn composite_params(exposure: &ExposureSettings, stereo_mode: StereoMode, color_space: ColorSpace) -> CompositeParams {
+        let gamut = color::gamut_conversion(color_space);
+        CompositeParams {
+            exposure_compensation: exposure.exposure_compensation,
+            auto_exposure: exposure.auto_exposure as u32,
+            key_value: exposure.key_value,
+            stereo_mode: match stereo_mode {
+                StereoMode::Off => 0,
+                StereoMode::Anaglyph => 1,
+                StereoMode::SideBySide => 2,
+            },
+            output_gamut: [gamut.x_axis, gamut.y_axis, gamut.z_axis].map(|column| column.extend(0.0).to_array()),
+        }
+    }
+
+    fn create_hdr_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("HDR Scene Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    fn create_luminance_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        hdr_view: &wgpu::TextureView,
+        params_buffer: &wgpu::Buffer,
+        state_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Luminance Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(hdr_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: state_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    fn create_composite_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        hdr_view: &wgpu::TextureView,
+        hdr_right_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+        params_buffer: &wgpu::Buffer,
+        state_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Composite Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(hdr_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: state_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(hdr_right_view),
+                },
+            ],
+        })
+    }
+}
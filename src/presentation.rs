@@ -0,0 +1,169 @@
+use crate::light::{Light, LightKind};
+use crate::postprocess::ExposureSettings;
+use crate::viewports::DisplayMode;
+
+/// Named looks for screenshots and captures, applied from the View menu or
+/// with `--preset <name>` on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentationPreset {
+    Studio,
+    Dark,
+    Clay,
+    Blueprint,
+}
+
+/// Everything a preset sets at once.
+#[derive(Debug, Clone)]
+pub struct Presentation {
+    /// Clear color, used for every display mode.
+    pub background: [f32; 3],
+    pub lights: Vec<Light>,
+    pub display_mode: DisplayMode,
+    pub exposure: ExposureSettings,
+    /// Whether the clay material replaces the model's own.
+    pub clay: bool,
+}
+
+/// Two-tone matte material that replaces every material, texture and vertex
+/// color, with a rim light tracing the silhouette: the neutral look of
+/// portfolio turntables.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClaySettings {
+    pub enabled: bool,
+    /// Color of surfaces facing up, blending into `bottom` as they turn down.
+    pub top: [f32; 3],
+    pub bottom: [f32; 3],
+    pub rim_color: [f32; 3],
+    pub rim_strength: f32,
+}
+
+impl ClaySettings {
+    /// Rim light color scaled by its strength.
+    pub fn rim(&self) -> [f32; 3] {
+        self.rim_color.map(|channel| channel * self.rim_strength)
+    }
+}
+
+impl Default for ClaySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            top: [0.72, 0.68, 0.62],
+            bottom: [0.34, 0.31, 0.28],
+            rim_color: [1.0, 0.96, 0.9],
+            rim_strength: 0.3,
+        }
+    }
+}
+
+impl PresentationPreset {
+    pub const ALL: [PresentationPreset; 4] = [PresentationPreset::Studio, PresentationPreset::Dark, PresentationPreset::Clay, PresentationPreset::Blueprint];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PresentationPreset::Studio => "Studio",
+            PresentationPreset::Dark => "Dark",
+            PresentationPreset::Clay => "Clay",
+            PresentationPreset::Blueprint => "Blueprint",
+        }
+    }
+
+    /// Name used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            PresentationPreset::Studio => "studio",
+            PresentationPreset::Dark => "dark",
+            PresentationPreset::Clay => "clay",
+            PresentationPreset::Blueprint => "blueprint",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PresentationPreset> {
+        PresentationPreset::ALL.into_iter().find(|preset| preset.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn presentation(self) -> Presentation {
+        let light = Light::default();
+        let exposure = ExposureSettings::default();
+        match self {
+            // Bright, even backdrop with a soft key light and a fill from
+            // the other side
+            PresentationPreset::Studio => Presentation {
+                background: [0.8, 0.8, 0.82],
+                lights: vec![
+                    Light {
+                        kind: LightKind::Directional,
+                        position: [4.0, 8.0, 6.0],
+                        ambient_strength: 0.3,
+                        diffuse_strength: 0.65,
+                        specular_strength: 0.3,
+                        ..light
+                    },
+                    Light {
+                        kind: LightKind::Directional,
+                        position: [-6.0, 2.0, -3.0],
+                        intensity: 0.4,
+                        ambient_strength: 0.0,
+                        specular_strength: 0.0,
+                        ..light
+                    },
+                ],
+                display_mode: DisplayMode::Shaded,
+                exposure,
+                clay: false,
+            },
+            // Near-black backdrop with a strong, glossy highlight and a cool
+            // rim light from behind
+            PresentationPreset::Dark => Presentation {
+                background: [0.02, 0.02, 0.025],
+                lights: vec![
+                    Light {
+                        position: [-5.0, 6.0, 4.0],
+                        intensity: 1.3,
+                        ambient_strength: 0.08,
+                        specular_strength: 0.8,
+                        shininess: 64.0,
+                        ..light
+                    },
+                    Light {
+                        kind: LightKind::Directional,
+                        position: [3.0, 2.0, -8.0],
+                        color: [0.6, 0.75, 1.0],
+                        intensity: 0.8,
+                        ambient_strength: 0.0,
+                        ..light
+                    },
+                ],
+                display_mode: DisplayMode::Shaded,
+                exposure: ExposureSettings {
+                    exposure_compensation: 0.5,
+                    ..exposure
+                },
+                clay: false,
+            },
+            // Matte two-tone clay, warm-lit, for judging form
+            PresentationPreset::Clay => Presentation {
+                background: [0.45, 0.43, 0.4],
+                lights: vec![Light {
+                    color: [1.0, 0.95, 0.88],
+                    ambient_strength: 0.3,
+                    diffuse_strength: 0.75,
+                    specular_strength: 0.05,
+                    shininess: 8.0,
+                    ..light
+                }],
+                display_mode: DisplayMode::Shaded,
+                exposure,
+                clay: true,
+            },
+            // Edges on blueprint blue
+            PresentationPreset::Blueprint => Presentation {
+                background: [0.05, 0.16, 0.35],
+                lights: vec![light],
+                display_mode: DisplayMode::HiddenLine,
+                exposure,
+                clay: false,
+            },
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::mesh::Vertex;
+
+/// Models with at least this many vertices are quantized when loaded.
+pub const AUTO_VERTEX_COUNT: usize = 1 << 20;
+
+/// Compressed vertex, 20 bytes instead of the 44 of `Vertex`: positions as
+/// 16-bit fractions of the model bounds, octahedral-encoded normals, 8-bit
+/// colors and half-float texture coordinates.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct QuantizedVertex {
+    pub position: [u16; 4],
+    pub normal: [i16; 2],
+    pub color: [u8; 4],
+    pub tex_coords: [u16; 2],
+}
+
+impl QuantizedVertex {
+    /// Same locations as `Vertex::desc`, read by the shaders' `vs_quantized`.
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<QuantizedVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Unorm16x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: 8,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Snorm16x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: 12,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Unorm8x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: 16,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float16x2,
+                },
+            ],
+        }
+    }
+}
+
+/// A model's vertices in the compressed layout, with the bounds their
+/// positions are relative to.
+pub struct QuantizedVertices {
+    pub vertices: Vec<QuantizedVertex>,
+    min: Vec3,
+    extent: Vec3,
+}
+
+impl QuantizedVertices {
+    pub fn new(vertices: &[Vertex]) -> Self {
+        let (min, max) = vertices.iter().fold((Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)), |(min, max), vertex| {
+            let position = Vec3::from(vertex.position);
+            (min.min(position), max.max(position))
+        });
+        // Flat models keep a unit extent on their flat axis
+        let extent = Vec3::select((max - min).cmpgt(Vec3::ZERO), max - min, Vec3::ONE);
+        let vertices = vertices
+            .iter()
+            .map(|vertex| {
+                let position = (Vec3::from(vertex.position) - min) / extent;
+                QuantizedVertex {
+                    position: [unorm16(position.x), unorm16(position.y), unorm16(position.z), 0],
+                    normal: oct_encode(Vec3::from(vertex.normal)),
+                    color: [unorm8(vertex.color[0]), unorm8(vertex.color[1]), unorm8(vertex.color[2]), 255],
+                    tex_coords: vertex.tex_coords.map(f16_bits),
+                }
+            })
+            .collect();
+        Self { vertices, min, extent }
+    }
+
+    /// Maps the quantized positions back to model space; applied in front
+    /// of the object transforms.
+    pub fn position_decode(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.extent, Quat::IDENTITY, self.min)
+    }
+
+    /// Largest rounding error of a position, in model units.
+    pub fn position_step(&self) -> f32 {
+        self.extent.max_element() / u16::MAX as f32 / 2.0
+    }
+}
+
+fn unorm16(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+}
+
+fn unorm8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8
+}
+
+fn snorm16(value: f32) -> i16 {
+    (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16
+}
+
+// Projects the unit normal onto an octahedron unfolded into the [-1, 1] square
+fn oct_encode(normal: Vec3) -> [i16; 2] {
+    let sum = normal.x.abs() + normal.y.abs() + normal.z.abs();
+    if sum == 0.0 {
+        return [0, 0];
+    }
+    let normal = normal / sum;
+    let (x, y) = if normal.z >= 0.0 {
+        (normal.x, normal.y)
+    } else {
+        ((1.0 - normal.y.abs()) * normal.x.signum(), (1.0 - normal.x.abs()) * normal.y.signum())
+    };
+    [snorm16(x), snorm16(y)]
+}
+
+// IEEE half-float bits of `value`, rounded to nearest
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // Subnormal, or too small for a half
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        return sign | ((mantissa >> shift) + ((mantissa >> (shift - 1)) & 1)) as u16;
+    }
+    // A rounding carry moves into the exponent, which is still correct
+    sign | ((((exponent as u32) << 10) | (mantissa >> 13)) + ((mantissa >> 12) & 1)) as u16
+}
@@ -1,140 +1,531 @@
 use anyhow::Result;
-use tracing::info;
+use tracing::{debug, error, info};
 use wgpu::{
-    Backends, Device, Instance, Queue, SurfaceConfiguration,
+    Backends, Device, Instance, Queue, Surface, SurfaceConfiguration,
 };
 use wgpu::util::DeviceExt;
 use winit::window::Window;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
+use crate::cluster::{self, Clusters};
 use crate::mesh::{Mesh, Vertex};
-use crate::camera::Camera;
+use crate::quantize::{self, QuantizedVertex};
+use crate::morph::MorphTarget;
+use crate::obj_export::{self, ObjExportOptions, ObjExportStats};
+use crate::color::{self, ColorSpace};
+use crate::material::{self, GpuMaterial, MaterialResources, MaterialUniforms, TextureSlot, TextureWarning};
+use crate::point_cloud::{PointCloud, PointRenderer};
+use crate::shading::{self, Colormap, CullMode, LineStyle, ShadingMode, ShadingOptions, VertexColorSpace};
+use crate::terrain::{self, TerrainOptions};
+use crate::texture_cache::{self, TextureCache, TextureKey};
+use crate::texture_decoder::{DecodedTexture, TextureDecoder, TextureRequest, TextureSource};
+use crate::samples::{self, SampleSet};
+use crate::timeline::{CameraPath, FrameSequence, Timeline};
+use crate::blueprint;
+use crate::bom::{self, Part};
+use crate::analysis::{self, ColorMode, QualitySettings, TopologyReport};
+use crate::aov::{AovImage, AovPipeline, AovSettings, AovTarget};
+use crate::camera::{Camera, CameraState, SensorFormat, ViewPreset};
+use crate::camera_controller::{CameraController, ControllerKind, FlyController, OrbitController, PathPlaybackController, TurntableController};
+use crate::capture::{save_png, OffscreenTarget, CAPTURE_FORMAT};
+use crate::clip_plane::{ClipPlane, Section};
+use crate::detail_view::{DetailView, DETAIL_SIZE};
+use crate::history::{ModelHistory, Snapshot};
+use crate::labels::ScreenLabels;
+use crate::environment::{Environment, SPECULAR_LEVELS};
+use crate::light::{Light, LightKind, LightsUniform, MAX_LIGHTS};
+use crate::loader::{self, LoadProgress, LoadedModel};
+use crate::inspector::FaceDetails;
+use crate::measure::Measure;
+use crate::ruler::Ruler;
+use crate::upload;
+use crate::view_state::ViewState;
+use crate::gizmo::Gizmo;
+use crate::handles::{self, Handle, HandleShape, Manipulator, Motion};
+use crate::governor::QualityGovernor;
+use crate::grid::Grid;
+use crate::normal_lines::NormalLines;
 use crate::performance::PerformanceMonitor;
+use crate::postprocess::{PostProcess, StereoMode, HDR_FORMAT};
+use crate::presentation::{ClaySettings, PresentationPreset};
+use crate::overlay::{Overlay, OverlaySettings};
+use crate::picking::{Bvh, Pick, Ray};
+use crate::scene::{self, ObjectBindings, Scene};
+use crate::settings::Settings;
+use crate::shaders::create_shader_module;
+use crate::still::StillAccumulator;
+use crate::streaming::{self, ClusterStreamer};
+use crate::view_cube::ViewCube;
+use crate::viewports::{DisplayMode, RenderMode, Viewport, ViewportLayout};
 use egui_winit::State as EguiWinitState;
 use egui_wgpu::Renderer as EguiRenderer;
 use egui::Context as EguiContext;
 
+mod ui;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct CameraUniforms {
     view_projection: [[f32; 4]; 4],
     view_matrix: [[f32; 4]; 4],
     camera_position: [f32; 3],
-    _padding: f32,
+    morph_weight: f32,
+    clip_plane: [f32; 4],
+    // Wireframe line color, with w 1 where it replaces the vertex colors
+    line_color: [f32; 4],
+    // Clip space units per pixel along x and y
+    pixel_size: [f32; 2],
+    line_width: f32,
+    // 1 when vertex colors are sRGB encoded
+    srgb_vertex_colors: f32,
+    // `ShadingMode` and `Colormap` as indices
+    shading_mode: u32,
+    colormap: u32,
+    // Values the colormap spans, world height or distance from `centroid`
+    value_range: [f32; 2],
+    centroid: [f32; 3],
+    _pad: f32,
+    // Color mixed into back faces, with w 1 when they're tinted
+    backface_color: [f32; 4],
+    // Section plane cap and contour colors, each with w 1 when shown
+    section_cap: [f32; 4],
+    section_contour: [f32; 4],
 }
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct LightUniforms {
-    position: [f32; 4],
-    color: [f32; 4],
-    intensity: f32,
-    ambient_strength: f32,
-    diffuse_strength: f32,
-    specular_strength: f32,
-    shininess: f32,
-    _pad: [f32; 3], // Pad to 16-byte alignment
+/// Requests raised from egui widgets that need to be handled by `App`
+/// (typically because they open native dialogs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiAction {
+    OpenModel,
+    CloseModel,
+    ImportView,
+    ExportView,
+    ExportStandardViews,
+    ExportBlueprint,
+    ExportObj,
+    ImportSamples,
+    LoadMorphTarget,
+    LoadEnvironment,
+    LoadSequence,
+    ExportParts,
+    CaptureTimeline,
+    SaveScreenshot,
+    SetViewPreset(ViewPreset),
+    // Compact always-on-top window on or off
+    SetCompactMode(bool),
+    // Borderless transparent window on or off
+    SetWindowTransparent(bool),
+    // Settings panel's "Reset to defaults"
+    ResetSettings,
+    // Parse the open model again with the current normal and terrain options
+    ReparseModel,
+    Quit,
+}
+
+/// Counts from wgpu's resource tracking, to spot leaks across model loads.
+#[derive(Debug, Clone, Copy)]
+pub struct GpuResourceCounts {
+    pub buffers: usize,
+    pub textures: usize,
+    pub bind_groups: usize,
+}
+
+impl std::fmt::Display for GpuResourceCounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "GPU: {} buffers, {} textures, {} bind groups", self.buffers, self.textures, self.bind_groups)
+    }
 }
 
+const MIN_RENDER_SCALE: f32 = 0.25;
+const SELECTION_COLOR: [f32; 3] = [1.0, 0.55, 0.0];
+const SEARCH_COLOR: [f32; 4] = [0.2, 0.85, 1.0, 1.0];
+// Outline of an earlier version of the model
+const HISTORY_COLOR: [f32; 4] = [0.3, 0.9, 1.0, 1.0];
+const BOUNDING_BOX_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+// Default tint of back faces when they're highlighted
+const BACKFACE_COLOR: [f32; 3] = [1.0, 0.1, 0.3];
+pub(crate) const DEFAULT_BACKGROUND: [f32; 3] = [0.1, 0.2, 0.3];
+const MAX_RENDER_SCALE: f32 = 2.0;
+const MIN_SENSITIVITY: f32 = 0.1;
+const MAX_SENSITIVITY: f32 = 5.0;
+
 pub struct Renderer {
+    // Kept for its GPU resource report and to recreate the surface on resume
     instance: Instance,
+    // None when headless
+    surface: Option<Surface<'static>>,
+    // Cleared when `config` changes; the surface is reconfigured before the next frame
+    surface_configured: bool,
+    // The window has no area, so nothing is drawn until it's resized
+    minimized: bool,
     device: Device,
     queue: Queue,
     config: SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
-    render_pipeline: wgpu::RenderPipeline,
+    render_scale: f32,
+    effective_render_scale: f32,
+    scene_size: winit::dpi::PhysicalSize<u32>,
+    quality_governor: QualityGovernor,
+    last_view_matrix: glam::Mat4,
+    // Scene redraws per second when limited; the UI still redraws on input
+    scene_fps_limit: Option<u32>,
+    // The HDR target no longer holds a valid scene and must be redrawn
+    scene_stale: bool,
+    // Shaded surfaces culled as `cull_mode` says, with the other modes'
+    // pipelines kept once built
+    surface_pipelines: SurfacePipelines,
+    cached_surface_pipelines: HashMap<CullMode, SurfacePipelines>,
+    surface_shader: wgpu::ShaderModule,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    cull_mode: CullMode,
+    backface_tint: bool,
+    backface_color: [f32; 3],
     wireframe_pipeline: wgpu::RenderPipeline,
+    // Dark edges drawn over the hidden-line fills
+    hidden_line_edge_pipeline: wgpu::RenderPipeline,
+    // Variants reading `QuantizedVertex` buffers
+    quantized_wireframe_pipeline: wgpu::RenderPipeline,
+    quantized_hidden_line_edge_pipeline: wgpu::RenderPipeline,
     mesh: Mesh,
     has_mesh: bool,
+    model_path: Option<PathBuf>,
+    model_bounds: Option<(glam::Vec3, glam::Vec3)>,
+    topology: Option<TopologyReport>,
+    parts: Vec<Part>,
+    scene: Scene,
+    selected_node: Option<usize>,
+    // One per scene node, for picking with the mouse
+    bvhs: Vec<Bvh>,
+    // Object and face last clicked in the viewport
+    pick: Option<Pick>,
+    gizmo: Gizmo,
+    view_cube: ViewCube,
+    clip_plane: ClipPlane,
+    // Handles for dragging the light around, and where it was when grabbed
+    light_handles: Manipulator,
+    light_drag_start: Option<glam::Vec3>,
+    move_light: bool,
+    // Side panel listing the scene objects, for models with more than one
+    show_outliner: bool,
+    // Bare FPS, triangle count and file name, for screen recordings
+    show_stats_hud: bool,
+    // Window with the options kept in the settings file
+    show_settings: bool,
+    // Outliner filter; objects whose names match are outlined in the viewport
+    search: String,
+    // Puts the keyboard focus into the search box next frame
+    focus_search: bool,
+    // Text box of the camera window for pasting a copied view, and why the
+    // last paste failed
+    view_text: String,
+    // Name typed for the next saved section
+    section_name: String,
+    view_text_error: Option<String>,
+    // Format the Camera window converts between field of view and focal length for
+    sensor: SensorFormat,
+    object_bindings: ObjectBindings,
+    // Frustum culling result per object for the last camera written
+    object_visibility: RefCell<Vec<bool>>,
+    // Set for meshes large enough to be culled per cluster
+    clusters: Option<Clusters>,
+    cluster_visibility: RefCell<Vec<bool>>,
+    // Set while the model exceeds the geometry budget and is drawn cluster by cluster
+    streamer: Option<ClusterStreamer>,
+    geometry_budget_mb: u32,
+    overlay: Overlay,
+    grid: Grid,
+    normal_lines: NormalLines,
+    overlay_settings: OverlaySettings,
+    screen_labels: ScreenLabels,
+    ruler: Ruler,
+    measure: Measure,
+    // Face inspection on hover, and the details of the face clicked last
+    inspect: bool,
+    inspected: Option<FaceDetails>,
+    color_mode: ColorMode,
+    quality_settings: QualitySettings,
+    // Triangles past the quality threshold, counted while the heatmap is shown
+    quality_failures: usize,
+    // Face-colored copy of `mesh` drawn instead of it while a color mode or selection is active
+    color_overlay: Option<Mesh>,
+    selected_face: Option<usize>,
     default_vertex_buffer: wgpu::Buffer,
+    material_resources: MaterialResources,
+    default_material: GpuMaterial,
+    clay: ClaySettings,
+    clay_material: GpuMaterial,
+    materials: Vec<GpuMaterial>,
+    texture_cache: TextureCache,
+    texture_decoder: TextureDecoder,
+    texture_warnings: Vec<TextureWarning>,
+    points: PointRenderer,
+    terrain_options: TerrainOptions,
+    // How normals are made for models loaded from now on
+    shading_options: ShadingOptions,
+    vertex_color_space: VertexColorSpace,
+    line_style: LineStyle,
+    shading_mode: ShadingMode,
+    colormap: Colormap,
+    // Vertex centroid of the model and the largest distance from it
+    centroid: (glam::Vec3, f32),
+    // Set while `App` has a model loading in the background
+    load_progress: Option<Arc<LoadProgress>>,
+    // Reload the model when its file changes on disk
+    reload_on_change: bool,
+    // Title, lines and time shown of the notification in the corner
+    toast: Option<(String, Vec<String>, Instant)>,
+    history: ModelHistory,
+    samples: Option<SampleSet>,
+    sample_points: PointRenderer,
+    sample_arrows: Option<Mesh>,
+    arrow_scale: f32,
+    morph_target: Option<MorphTarget>,
+    morph_weight: f32,
+    timeline: Timeline,
+    frame_sequence: Option<FrameSequence>,
+    camera_path: CameraPath,
+    turntable: bool,
+    // Camera yaw at frame 0 of the turntable
+    turntable_yaw: f32,
     camera: Camera,
+    camera_controller: Box<dyn CameraController>,
+    // Multiplier on mouse and wheel camera movement, for every viewport
+    camera_sensitivity: f32,
+    viewport_layout: ViewportLayout,
+    // Cameras of the split layout's other viewports
+    viewports: Vec<Viewport>,
+    // Viewport the mouse last pressed or scrolled in, which gets the camera input
+    active_viewport: usize,
+    cursor_position: winit::dpi::PhysicalPosition<f64>,
+    // Render mode of the secondary viewport being drawn; the main one follows the global settings
+    view_mode: Cell<Option<RenderMode>>,
+    detail_view: DetailView,
     camera_uniform_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
+    lights: Vec<Light>,
+    // Light edited in the Light window and dragged by the light handles
+    selected_light: usize,
+    // Puts the first light at the eye of whichever camera is drawing
+    headlight: bool,
     light_uniform_buffer: wgpu::Buffer,
+    light_bind_group_layout: wgpu::BindGroupLayout,
     light_bind_group: wgpu::BindGroup,
+    // HDR image lighting the scene and drawn behind it, when loaded
+    environment: Environment,
     depth_texture: wgpu::Texture,
     depth_texture_view: wgpu::TextureView,
-    wireframe_mode: bool,
+    // Compositing modes the surface supports, empty when headless
+    surface_alpha_modes: Vec<wgpu::CompositeAlphaMode>,
+    // The surface offers 10-bit or float formats, as it does on wide-gamut displays
+    wide_gamut_surface: bool,
+    // Clears the background to transparent, for a see-through window and exports
+    transparent_background: bool,
+    // Clear color for each display mode, in `DisplayMode` order
+    background_colors: [[f32; 3]; 3],
+    display_mode: DisplayMode,
+    post_process: PostProcess,
+    // Fraction of the orbit distance between the two stereo eyes
+    eye_separation: f32,
+    export_width: u32,
+    export_height: u32,
+    // Screenshot size as a multiple of the window size
+    screenshot_scale: u32,
+    // High quality still: jittered frames of the main view are averaged
+    // while it holds still, up to `still_samples`; screenshots take as many
+    still_mode: bool,
+    still_samples: u32,
+    still: Option<StillAccumulator>,
+    aov_pipeline: AovPipeline,
+    aov_settings: AovSettings,
+    ui_actions: Vec<UiAction>,
+    obj_export_options: ObjExportOptions,
+    pub reopen_last_session: bool,
+    // Started with --safe-mode: post-processing stays off
+    safe_mode: bool,
+    // Small always-on-top window showing just the model and the menu bar
+    compact_mode: bool,
+    // Presenting waits for the display's refresh
+    vsync: bool,
+    adapter_info: Option<wgpu::AdapterInfo>,
     
     // Performance monitoring
     performance_monitor: PerformanceMonitor,
     // egui integration
-    pub egui_winit_state: EguiWinitState,
+    pub egui_winit_state: Option<EguiWinitState>,
     pub egui_ctx: EguiContext,
     egui_renderer: EguiRenderer,
 }
 
 impl Renderer {
-    pub async fn new(window: &Window) -> Result<Self> {
+    /// Creates the renderer for `window`. In safe mode it prefers the fallback
+    /// (software) adapter and keeps post-processing off.
+    pub async fn new(window: Arc<Window>, safe_mode: bool) -> Result<Self> {
         let size = window.inner_size();
         let instance = Instance::new(wgpu::InstanceDescriptor {
             backends: Backends::all(),
             ..Default::default()
         });
 
-        let surface = instance.create_surface(window)?;
+        let surface = instance.create_surface(window.clone())?;
+        let mut adapter = None;
+        if safe_mode {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::LowPower,
+                    compatible_surface: Some(&surface),
+                    force_fallback_adapter: true,
+                })
+                .await;
+            if adapter.is_none() {
+                tracing::warn!("No fallback adapter available, using the default one");
+            }
+        }
+        let adapter = match adapter {
+            Some(adapter) => adapter,
+            None => instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: Some(&surface),
+                    force_fallback_adapter: false,
+                })
+                .await
+                .ok_or_else(|| anyhow::anyhow!("Failed to find an appropriate adapter"))?,
+        };
+        let adapter_info = adapter.get_info();
+        info!(
+            "Adapter: {} ({:?}, {:?}), driver: {} {}",
+            adapter_info.name, adapter_info.backend, adapter_info.device_type, adapter_info.driver, adapter_info.driver_info
+        );
+
+        let (device, queue) = Self::request_device(&adapter).await?;
+        let compute_shaders = Self::supports_compute_shaders(&adapter);
+
+        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_format = surface_caps
+            .formats
+            .iter()
+            .copied()
+            .find(|f| f.is_srgb())
+            .unwrap_or(surface_caps.formats[0]);
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            // A window created minimized has no area yet
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(&device, &config);
+
+        let egui_ctx = EguiContext::default();
+        let egui_winit_state = EguiWinitState::new(
+            egui_ctx.clone(),
+            egui::ViewportId::ROOT,
+            &window,
+            None,
+            None,
+        );
+
+        let mut renderer = Self::from_device(instance, device, queue, config, compute_shaders, egui_ctx, Some(egui_winit_state))?;
+        renderer.surface = Some(surface);
+        renderer.surface_configured = true;
+        renderer.minimized = size.width == 0 || size.height == 0;
+        renderer.safe_mode = safe_mode;
+        renderer.adapter_info = Some(adapter_info);
+        renderer.surface_alpha_modes = surface_caps.alpha_modes;
+        renderer.wide_gamut_surface = color::is_wide_gamut_surface(&surface_caps.formats);
+        info!("Surface formats: {:?} (wide gamut: {})", surface_caps.formats, renderer.wide_gamut_surface);
+        // Inspecting starts with surfaces facing the viewer lit; batch renders keep the scene lights
+        renderer.headlight = true;
+        Ok(renderer)
+    }
+
+    /// Creates a renderer without a window, for batch rendering through the offscreen path.
+    pub async fn new_headless(width: u32, height: u32) -> Result<Self> {
+        let instance = Instance::new(wgpu::InstanceDescriptor {
+            backends: Backends::all(),
+            ..Default::default()
+        });
+
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
+                compatible_surface: None,
                 force_fallback_adapter: false,
             })
             .await
             .ok_or_else(|| anyhow::anyhow!("Failed to find an appropriate adapter"))?;
+        let adapter_info = adapter.get_info();
+        info!("Headless adapter: {}", adapter_info.name);
+
+        let (device, queue) = Self::request_device(&adapter).await?;
+        let compute_shaders = Self::supports_compute_shaders(&adapter);
 
-        // Check for POLYGON_MODE_LINE support
-        let required_features = wgpu::Features::POLYGON_MODE_LINE;
-        let adapter_features = adapter.features();
-        let enable_wireframe = adapter_features.contains(required_features);
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: CAPTURE_FORMAT,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        let mut renderer = Self::from_device(instance, device, queue, config, compute_shaders, EguiContext::default(), None)?;
+        renderer.adapter_info = Some(adapter_info);
+        Ok(renderer)
+    }
 
+    async fn request_device(adapter: &wgpu::Adapter) -> Result<(Device, Queue)> {
+        // The wireframe draws edge lists as line primitives, which every
+        // backend supports, so no optional features are needed. Downlevel
+        // limits keep GL adapters usable, with textures as large as the
+        // adapter allows
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: if enable_wireframe { required_features } else { wgpu::Features::empty() },
-                    required_limits: wgpu::Limits::default(),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
                 },
                 None,
             )
             .await?;
 
-        if !enable_wireframe {
-            tracing::warn!("Wireframe mode not supported on this device. The W key will have no effect.");
-        }
+        Ok((device, queue))
+    }
 
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+    fn supports_compute_shaders(adapter: &wgpu::Adapter) -> bool {
+        let supported = adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+        if !supported {
+            tracing::warn!("The adapter has no compute shaders; auto exposure is unavailable");
+        }
+        supported
+    }
 
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
-        };
-        surface.configure(&device, &config);
+    fn from_device(
+        instance: Instance,
+        device: Device,
+        queue: Queue,
+        config: SurfaceConfiguration,
+        compute_shaders: bool,
+        egui_ctx: EguiContext,
+        egui_winit_state: Option<EguiWinitState>,
+    ) -> Result<Self> {
+        let size = winit::dpi::PhysicalSize::new(config.width, config.height);
 
-        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Depth Texture"),
-            size: wgpu::Extent3d {
-                width: size.width,
-                height: size.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
-        });
+        let render_scale = 1.0;
+        let scene_size = scaled_size(size, render_scale, device.limits().max_texture_dimension_2d);
+        let depth_texture = create_depth_texture(&device, scene_size);
         let depth_texture_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         let camera = Camera::new(size.width as f32 / size.height as f32);
@@ -143,7 +534,20 @@ impl Renderer {
             view_projection: (camera.projection_matrix() * camera.view_matrix()).to_cols_array_2d(),
             view_matrix: camera.view_matrix().to_cols_array_2d(),
             camera_position: [camera.position.x, camera.position.y, camera.position.z],
-            _padding: 0.0,
+            morph_weight: 0.0,
+            clip_plane: [0.0, 0.0, 0.0, -1.0],
+            line_color: [0.0; 4],
+            pixel_size: [2.0 / size.width.max(1) as f32, 2.0 / size.height.max(1) as f32],
+            line_width: 1.0,
+            srgb_vertex_colors: 0.0,
+            shading_mode: 0,
+            colormap: 0,
+            value_range: [0.0, 1.0],
+            centroid: [0.0; 3],
+            _pad: 0.0,
+            backface_color: [0.0; 4],
+            section_cap: [0.0; 4],
+            section_contour: [0.0; 4],
         };
 
         let camera_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -175,16 +579,8 @@ impl Renderer {
             }],
         });
 
-        let light_uniforms = LightUniforms {
-            position: [5.0, 5.0, 5.0, 0.0],
-            color: [1.0, 1.0, 1.0, 0.0],
-            intensity: 1.0,
-            ambient_strength: 0.2,
-            diffuse_strength: 0.7,
-            specular_strength: 0.5,
-            shininess: 32.0,
-            _pad: [0.0; 3],
-        };
+        let lights = vec![Light::default()];
+        let light_uniforms = LightsUniform::new(&lights);
 
         let light_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Light Uniform Buffer"),
@@ -194,141 +590,154 @@ impl Renderer {
 
         let light_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Light Bind Group Layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                // Irradiance and specular cubes of the environment map
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
         });
 
-        let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Light Bind Group"),
-            layout: &light_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: light_uniform_buffer.as_entire_binding(),
-            }],
-        });
+        let environment = Environment::new(&device);
+        let light_bind_group = create_light_bind_group(&device, &light_bind_group_layout, &light_uniform_buffer, &environment);
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/triangle.wgsl").into()),
-        });
+        let shader = create_shader_module(&device, "Shader", concat!(include_str!("shaders/triangle.wgsl"), include_str!("shaders/quantized.wgsl")));
+        let wireframe_shader = create_shader_module(
+            &device,
+            "Wireframe Shader",
+            concat!(include_str!("shaders/wireframe.wgsl"), include_str!("shaders/quantized.wgsl")),
+        );
 
-        let wireframe_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Wireframe Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/wireframe.wgsl").into()),
-        });
+        let material_resources = MaterialResources::new(&device, &queue);
+        let default_material = GpuMaterial::new(&device, &material_resources, "Default Material", MaterialUniforms::DEFAULT);
+        let clay = ClaySettings::default();
+        let clay_material = GpuMaterial::new(&device, &material_resources, "Clay Material", MaterialUniforms::clay(clay.top, clay.bottom, clay.rim()));
+
+        let object_bindings = ObjectBindings::new(&device);
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&camera_bind_group_layout, &light_bind_group_layout],
+                bind_group_layouts: &[
+                    &camera_bind_group_layout,
+                    &light_bind_group_layout,
+                    &material_resources.layout,
+                    object_bindings.layout(),
+                ],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+        let wireframe_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Wireframe Pipeline Layout"),
+                bind_group_layouts: &[&camera_bind_group_layout, &light_bind_group_layout, object_bindings.layout()],
+                push_constant_ranges: &[],
+            });
 
-        let wireframe_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Wireframe Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &wireframe_shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &wireframe_shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::LineList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Line,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+        let surface_pipelines = SurfacePipelines::new(&device, &render_pipeline_layout, &shader, CullMode::default());
+        let mesh_buffers = [Vertex::desc(), Vertex::morph_desc()];
+        let quantized_buffers = [QuantizedVertex::desc()];
+
+        let create_wireframe_pipeline = |label, entry_point, fragment_entry_point, buffers: &[wgpu::VertexBufferLayout]| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&wireframe_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &wireframe_shader,
+                    entry_point,
+                    buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &wireframe_shader,
+                    entry_point: fragment_entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: HDR_FORMAT,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    // Polygon mode only applies to triangles
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+        let wireframe_pipeline = create_wireframe_pipeline("Wireframe Pipeline", "vs_main", "fs_main", &mesh_buffers);
+        let quantized_wireframe_pipeline = create_wireframe_pipeline("Quantized Wireframe Pipeline", "vs_quantized", "fs_main", &quantized_buffers);
+        let hidden_line_edge_pipeline = create_wireframe_pipeline("Hidden Line Edge Pipeline", "vs_main", "fs_edges", &mesh_buffers);
+        let quantized_hidden_line_edge_pipeline =
+            create_wireframe_pipeline("Quantized Hidden Line Edge Pipeline", "vs_quantized", "fs_edges", &quantized_buffers);
 
         let vertices = &[
             Vertex {
                 position: [0.0, 0.5, 0.0],
                 normal: [0.0, 0.0, 1.0],
                 color: [1.0, 0.0, 0.0],
+                tex_coords: [0.0, 0.0],
             },
             Vertex {
                 position: [-0.5, -0.5, 0.0],
                 normal: [0.0, 0.0, 1.0],
                 color: [0.0, 1.0, 0.0],
+                tex_coords: [0.0, 0.0],
             },
             Vertex {
                 position: [0.5, -0.5, 0.0],
                 normal: [0.0, 0.0, 1.0],
                 color: [0.0, 0.0, 1.0],
+                tex_coords: [0.0, 0.0],
             },
         ];
 
@@ -340,36 +749,166 @@ impl Renderer {
 
         let mesh = Mesh::new();
 
-        let egui_ctx = EguiContext::default();
-        let egui_winit_state = EguiWinitState::new(
-            egui_ctx.clone(),
-            egui::ViewportId::ROOT,
-            window,
-            None,
-            None,
-        );
+        let aov_pipeline = AovPipeline::new(&device, &camera_bind_group_layout, object_bindings.layout());
+        let points = PointRenderer::new(&device, &camera_bind_group_layout);
+        let sample_points = PointRenderer::new(&device, &camera_bind_group_layout);
+        let overlay = Overlay::new(&device, &queue, &camera_bind_group_layout);
+        let grid = Grid::new(&device, &camera_bind_group_layout);
+        let normal_lines = NormalLines::new(&device, &camera_bind_group_layout, &object_bindings);
+        let screen_labels = ScreenLabels::new(&device);
+        let ruler = Ruler::new(&device);
+
+        let post_process = PostProcess::new(&device, config.format, scene_size.width, scene_size.height, compute_shaders);
+
         let egui_renderer = EguiRenderer::new(&device, config.format, None, 1);
 
         info!("Renderer initialized successfully");
         Ok(Self {
             instance,
+            surface: None,
+            surface_configured: false,
+            minimized: false,
             device,
             queue,
             config,
             size,
-            render_pipeline,
+            render_scale,
+            effective_render_scale: render_scale,
+            scene_size,
+            quality_governor: QualityGovernor::new(),
+            last_view_matrix: glam::Mat4::IDENTITY,
+            scene_fps_limit: None,
+            scene_stale: true,
+            surface_pipelines,
+            cached_surface_pipelines: HashMap::new(),
+            surface_shader: shader,
+            render_pipeline_layout,
+            cull_mode: CullMode::default(),
+            backface_tint: false,
+            backface_color: BACKFACE_COLOR,
             wireframe_pipeline,
+            hidden_line_edge_pipeline,
+            quantized_wireframe_pipeline,
+            quantized_hidden_line_edge_pipeline,
             mesh,
             has_mesh: false,
+            model_path: None,
+            model_bounds: None,
+            topology: None,
+            parts: Vec::new(),
+            scene: Scene::default(),
+            selected_node: None,
+            bvhs: Vec::new(),
+            pick: None,
+            gizmo: Gizmo::new(),
+            view_cube: ViewCube::new(),
+            clip_plane: ClipPlane::new(),
+            light_handles: Manipulator::new(),
+            light_drag_start: None,
+            move_light: false,
+            show_outliner: true,
+            show_stats_hud: false,
+            show_settings: false,
+            search: String::new(),
+            focus_search: false,
+            view_text: String::new(),
+            section_name: String::new(),
+            view_text_error: None,
+            sensor: SensorFormat::default(),
+            object_bindings,
+            object_visibility: RefCell::new(Vec::new()),
+            clusters: None,
+            cluster_visibility: RefCell::new(Vec::new()),
+            streamer: None,
+            geometry_budget_mb: streaming::DEFAULT_GEOMETRY_BUDGET_MB,
+            overlay,
+            grid,
+            normal_lines,
+            overlay_settings: OverlaySettings::default(),
+            screen_labels,
+            ruler,
+            measure: Measure::new(),
+            inspect: false,
+            inspected: None,
+            color_mode: ColorMode::Shaded,
+            quality_settings: QualitySettings::default(),
+            quality_failures: 0,
+            color_overlay: None,
+            selected_face: None,
             default_vertex_buffer,
+            material_resources,
+            default_material,
+            clay,
+            clay_material,
+            materials: Vec::new(),
+            texture_cache: TextureCache::new(texture_cache::DEFAULT_TEXTURE_BUDGET_MB as usize * (1 << 20)),
+            texture_decoder: TextureDecoder::new(),
+            texture_warnings: Vec::new(),
+            points,
+            terrain_options: TerrainOptions::default(),
+            shading_options: ShadingOptions::default(),
+            vertex_color_space: VertexColorSpace::default(),
+            line_style: LineStyle::default(),
+            shading_mode: ShadingMode::default(),
+            colormap: Colormap::default(),
+            centroid: (glam::Vec3::ZERO, 0.0),
+            load_progress: None,
+            reload_on_change: true,
+            toast: None,
+            history: ModelHistory::default(),
+            samples: None,
+            sample_points,
+            sample_arrows: None,
+            arrow_scale: 1.0,
+            morph_target: None,
+            morph_weight: 0.0,
+            timeline: Timeline::new(),
+            frame_sequence: None,
+            camera_path: CameraPath::default(),
+            turntable: false,
+            turntable_yaw: 0.0,
+            viewports: Viewport::secondary(camera.aspect_ratio),
             camera,
+            camera_controller: Box::new(OrbitController::default()),
+            camera_sensitivity: 1.0,
+            viewport_layout: ViewportLayout::Single,
+            active_viewport: 0,
+            cursor_position: winit::dpi::PhysicalPosition::new(0.0, 0.0),
+            view_mode: Cell::new(None),
+            detail_view: DetailView::new(),
             camera_uniform_buffer,
             camera_bind_group,
+            lights,
+            selected_light: 0,
+            headlight: false,
             light_uniform_buffer,
+            light_bind_group_layout,
             light_bind_group,
+            environment,
             depth_texture,
             depth_texture_view,
-            wireframe_mode: false,
+            surface_alpha_modes: Vec::new(),
+            wide_gamut_surface: false,
+            transparent_background: false,
+            background_colors: [DEFAULT_BACKGROUND; 3],
+            display_mode: DisplayMode::Shaded,
+            post_process,
+            eye_separation: 0.03,
+            export_width: 1920,
+            export_height: 1080,
+            screenshot_scale: 1,
+            still_mode: false,
+            still_samples: 64,
+            still: None,
+            aov_pipeline,
+            aov_settings: AovSettings::default(),
+            ui_actions: Vec::new(),
+            obj_export_options: ObjExportOptions::default(),
+            reopen_last_session: false,
+            safe_mode: false,
+            compact_mode: false,
+            vsync: true,
+            adapter_info: None,
             
             // Performance monitoring
             performance_monitor: PerformanceMonitor::new(),
@@ -380,214 +919,2397 @@ impl Renderer {
         })
     }
 
+    /// Loads the model at `path`, blocking until it's parsed and uploaded.
     pub fn load_mesh(&mut self, path: &std::path::Path) -> Result<()> {
         info!("Loading mesh from: {:?}", path);
-        self.mesh.load_from_obj(path)?;
-        self.mesh.create_buffers(&self.device);
-        self.has_mesh = true;
-        
-        if !self.mesh.vertices.is_empty() {
-            let mut min_pos = glam::Vec3::splat(f32::INFINITY);
-            let mut max_pos = glam::Vec3::splat(f32::NEG_INFINITY);
-            
-            for vertex in &self.mesh.vertices {
-                let pos = glam::Vec3::from_slice(&vertex.position);
-                min_pos = min_pos.min(pos);
-                max_pos = max_pos.max(pos);
-            }
-            
-            self.camera.auto_fit_to_model((min_pos, max_pos));
-        }
-        
-        info!("Mesh loaded successfully");
-        Ok(())
+        let model = loader::parse(path, self.terrain_options, self.shading_options, &LoadProgress::new(path))?;
+        self.install_model(path, model)
     }
 
-    pub fn handle_input(&mut self, event: &winit::event::WindowEvent) {
-        self.camera.handle_input(event);
+    pub fn terrain_options(&self) -> TerrainOptions {
+        self.terrain_options
     }
 
-    pub fn toggle_wireframe(&mut self) {
-        self.wireframe_mode = !self.wireframe_mode;
-        info!("Wireframe mode: {}", self.wireframe_mode);
+    pub fn shading_options(&self) -> ShadingOptions {
+        self.shading_options
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.camera.aspect_ratio = new_size.width as f32 / new_size.height as f32;
-
-            // Recreate depth texture
-            self.depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-                label: Some("Depth Texture"),
-                size: wgpu::Extent3d {
-                    width: new_size.width,
-                    height: new_size.height,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Depth32Float,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            });
-            self.depth_texture_view = self.depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        }
+    /// Shows a progress bar for a model loading in the background, or hides it.
+    pub fn set_load_progress(&mut self, progress: Option<Arc<LoadProgress>>) {
+        self.load_progress = progress;
     }
 
-    pub fn render(&mut self, window: &Window) -> Result<(), wgpu::SurfaceError> {
-        // Update performance monitor
-        self.performance_monitor.update();
+    /// Shows a notification in the corner of the window for a few seconds.
+    pub fn show_toast(&mut self, title: String, lines: Vec<String>) {
+        self.toast = Some((title, lines, Instant::now()));
+    }
 
-        // Begin egui frame
-        let raw_input = self.egui_winit_state.take_egui_input(window);
-        self.egui_ctx.begin_frame(raw_input);
+    /// Model file to reload when it changes: the loaded model unless
+    /// watching is off or it's a frame of a sequence.
+    pub fn watched_model_path(&self) -> Option<&Path> {
+        self.model_path.as_deref().filter(|_| self.reload_on_change && self.frame_sequence.is_none())
+    }
 
-        // Draw performance stats in egui
-        let stats = self.performance_monitor.get_stats();
-        egui::Window::new("Performance")
-            .anchor(egui::Align2::LEFT_TOP, [10.0, 10.0])
-            .resizable(false)
-            .collapsible(false)
-            .show(&self.egui_ctx, |ui| {
-                ui.label(format!("CPU: {:.1}%", stats.cpu_usage));
-                ui.label(format!("RAM: {:.1}% ({:.0}MB/{:.0}MB)", stats.memory_usage, stats.memory_used_mb, stats.memory_total_mb));
-                ui.label(format!("FPS: {:.1}", stats.fps));
-                ui.label(format!("Frame: {:.1}ms", stats.frame_time_ms));
-                ui.label(format!("Frames: {}", stats.frame_count));
-            });
-        let egui_output = self.egui_ctx.end_frame();
-        let pixels_per_point = window.scale_factor() as f32;
-        let paint_jobs = self.egui_ctx.tessellate(egui_output.shapes, pixels_per_point);
-        let screen_descriptor = egui_wgpu::ScreenDescriptor {
-            size_in_pixels: [self.size.width, self.size.height],
-            pixels_per_point,
-        };
+    /// Adds a version of the model at `path` to the history; models without
+    /// a snapshot end it.
+    pub fn record_snapshot(&mut self, path: &Path, snapshot: Option<Snapshot>) {
+        match snapshot {
+            Some(snapshot) => self.history.record(path, snapshot),
+            None => self.history.clear(),
+        }
+    }
+
+    /// Like `install_model`, but keeps the view and the object hierarchy,
+    /// for a model rewritten on disk.
+    pub fn reload_model(&mut self, path: &Path, model: LoadedModel) -> Result<()> {
+        let view = self.view_state();
+        let scene = self.scene.clone();
+        self.install_model(path, model)?;
+        self.apply_view_state(&view);
+        self.scene.keep_hierarchy(&scene);
+        Ok(())
+    }
 
-        let surface = self.instance.create_surface(window).map_err(|_| wgpu::SurfaceError::Lost)?;
-        surface.configure(&self.device, &self.config);
+    /// Replaces the current model with one parsed by `loader::parse`,
+    /// uploading it and refitting the cameras.
+    pub fn install_model(&mut self, path: &Path, model: LoadedModel) -> Result<()> {
+        if self.model_path.as_deref() != Some(path) {
+            self.history.clear();
+        }
+        self.frame_sequence = None;
+        self.mesh = match model {
+            LoadedModel::Mesh(mesh) => *mesh,
+            LoadedModel::PointCloud(cloud) => {
+                self.set_point_cloud(path, cloud);
+                return Ok(());
+            }
+        };
+        self.clusters = (self.mesh.triangle_count() >= cluster::MIN_CLUSTERED_TRIANGLES).then(|| Clusters::build(&mut self.mesh));
+        let copies = self.mesh.set_shared(true);
+        if copies > 0 {
+            info!("{} objects repeat others' geometry, sharing saves {} KB", copies, self.mesh.shared_bytes() >> 10);
+        }
+        // Geometry that fits neither the budget nor a single buffer is streamed
+        let geometry_bytes = self.mesh.geometry_bytes();
+        let budget = self.geometry_budget_mb as usize * (1 << 20);
+        let too_large = geometry_bytes > budget || geometry_bytes as u64 > self.device.limits().max_buffer_size;
+        self.streamer = self
+            .clusters
+            .as_ref()
+            .filter(|_| too_large)
+            .map(|clusters| ClusterStreamer::new(&self.device, &self.mesh, clusters, budget));
+        if let Some(streamer) = &self.streamer {
+            info!("Model needs {} MB of geometry, streaming {} clusters", geometry_bytes >> 20, streamer.len());
+            // Clusters are streamed from the full geometry
+            self.mesh.set_shared(false);
+            self.mesh.set_quantized(false);
+            self.mesh.release_buffers();
+        } else {
+            self.mesh.set_quantized(self.mesh.vertices.len() >= quantize::AUTO_VERTEX_COUNT);
+            self.mesh.create_buffers_deferred(&self.device);
+        }
+        self.points.clear();
+        self.morph_target = None;
+        self.has_mesh = true;
+        self.model_path = Some(path.to_path_buf());
+        self.reload_textures();
         
-        let output = surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        // Objects may be placed by the file, so the bounds come from the scene
+        self.scene = Scene::from_mesh(&self.mesh);
+        if let Some(bounds) = self.scene.bounds() {
+            self.model_bounds = Some(bounds);
+            self.fit_cameras(bounds);
+        }
+        self.topology = Some(analysis::topology_report(&self.mesh));
+        self.centroid = shading::centroid(&self.mesh);
+        self.parts = bom::parts(&self.mesh);
+        self.bvhs = self.mesh.draw_ranges.iter().map(|range| Bvh::build(&self.mesh, range)).collect();
+        self.selected_node = None;
+        self.pick = None;
+        self.measure.clear();
+        self.inspected = None;
+        self.object_bindings.resize(&self.device, self.scene.nodes.len());
+        if self.normal_lines.settings.enabled() {
+            self.normal_lines.build(&self.device, &self.mesh);
+        } else {
+            self.normal_lines.clear();
+        }
+        self.selected_face = None;
+        self.update_color_overlay();
+        
+        info!("Mesh loaded successfully");
+        Ok(())
+    }
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
+    /// Unloads the model and everything loaded for it (textures, morph
+    /// target, samples, sequence), freeing their GPU resources. The camera
+    /// and display settings stay.
+    pub fn close_model(&mut self) {
+        // A sequence set the timeline length; the default comes back
+        if self.frame_sequence.take().is_some() {
+            self.timeline.frame_count = Timeline::new().frame_count;
+            self.timeline.set_frame(0);
+        }
+        self.mesh = Mesh::new();
+        self.has_mesh = false;
+        self.history.clear();
+        self.morph_target = None;
+        self.morph_weight = 0.0;
+        self.clusters = None;
+        self.cluster_visibility.borrow_mut().clear();
+        self.streamer = None;
+        self.points.clear();
+        self.clear_samples();
+        self.model_path = None;
+        self.model_bounds = None;
+        self.reload_textures();
+        self.topology = None;
+        self.parts.clear();
+        self.scene = Scene::default();
+        self.object_visibility.borrow_mut().clear();
+        self.bvhs.clear();
+        self.normal_lines.clear();
+        self.selected_node = None;
+        self.pick = None;
+        self.measure.clear();
+        self.inspected = None;
+        self.selected_face = None;
+        self.update_color_overlay();
+        // Let wgpu destroy the dropped resources now rather than on a later submit
+        self.device.poll(wgpu::Maintain::Wait);
+        match self.gpu_resource_counts() {
+            Some(counts) => info!("Model closed, {}", counts),
+            None => info!("Model closed"),
+        }
+    }
 
-        // Update camera uniforms
-        let camera_uniforms = CameraUniforms {
-            view_projection: (self.camera.projection_matrix() * self.camera.view_matrix()).to_cols_array_2d(),
-            view_matrix: self.camera.view_matrix().to_cols_array_2d(),
-            camera_position: [self.camera.position.x, self.camera.position.y, self.camera.position.z],
-            _padding: 0.0,
-        };
-        self.queue.write_buffer(&self.camera_uniform_buffer, 0, bytemuck::cast_slice(&[camera_uniforms]));
+    /// Live GPU buffers, textures and bind groups according to wgpu's
+    /// resource tracking.
+    pub fn gpu_resource_counts(&self) -> Option<GpuResourceCounts> {
+        let report = self.instance.generate_report()?;
+        let hub = report.hub_report(self.adapter_info.as_ref()?.backend);
+        Some(GpuResourceCounts {
+            buffers: hub.buffers.num_allocated,
+            textures: hub.textures.num_allocated,
+            bind_groups: hub.bind_groups.num_allocated,
+        })
+    }
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
+    // Point clouds replace the mesh; their chunks are uploaded over the next frames
+    fn set_point_cloud(&mut self, path: &Path, cloud: PointCloud) {
+        info!("Loaded point cloud from: {:?}", path);
+        self.model_bounds = Some(cloud.bounds);
+        self.fit_cameras(cloud.bounds);
+        self.points.set_cloud(cloud);
+        self.points.upload_pending(&self.device, 1);
 
-            let pipeline = if self.wireframe_mode {
-                &self.wireframe_pipeline
-            } else {
-                &self.render_pipeline
-            };
+        self.mesh = Mesh::new();
+        self.morph_target = None;
+        self.has_mesh = false;
+        self.model_path = Some(path.to_path_buf());
+        self.reload_textures();
+        self.topology = None;
+        self.clusters = None;
+        self.streamer = None;
+        self.parts.clear();
+        self.scene = Scene::default();
+        self.bvhs.clear();
+        self.normal_lines.clear();
+        self.pick = None;
+        self.measure.clear();
+        self.inspected = None;
+        self.selected_face = None;
+        self.update_color_overlay();
+    }
 
-            render_pass.set_pipeline(pipeline);
-            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.light_bind_group, &[]);
+    /// Loads the numbered sequence `path` belongs to and plays it back one
+    /// file per timeline frame.
+    pub fn load_sequence(&mut self, path: &Path) -> Result<()> {
+        let sequence = FrameSequence::discover(path)?;
+        info!("Loaded sequence of {} frames", sequence.len());
+        self.load_mesh(sequence.path(0))?;
+        self.timeline.frame_count = sequence.len() as u32;
+        self.timeline.set_frame(0);
+        self.frame_sequence = Some(sequence);
+        Ok(())
+    }
 
-            if self.has_mesh {
-                if let Some(vertex_buffer) = self.mesh.get_vertex_buffer() {
-                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                    
-                    if let Some(index_buffer) = self.mesh.get_index_buffer() {
-                        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                        if self.wireframe_mode {
-                            // For wireframe, draw edges
-                            for i in (0..self.mesh.num_indices).step_by(3) {
-                                if i + 2 < self.mesh.num_indices {
-                                    render_pass.draw_indexed(i..i+3, 0, 0..1);
-                                }
-                            }
-                        } else {
-                            render_pass.draw_indexed(0..self.mesh.num_indices, 0, 0..1);
-                        }
-                    } else {
-                        render_pass.draw(0..self.mesh.vertices.len() as u32, 0..1);
-                    }
+    /// Shows `frame` of every timeline-driven element: the sequence file,
+    /// then the camera path, or the turntable when there are no camera keys,
+    /// and the section sweep.
+    fn apply_timeline_frame(&mut self, frame: u32) {
+        if let Some(sequence) = self.frame_sequence.take() {
+            let path = sequence.path(frame).to_path_buf();
+            if self.model_path.as_deref() != Some(path.as_path()) {
+                let view = self.view_state();
+                let scene = self.scene.clone();
+                if let Err(e) = self.load_mesh(&path) {
+                    error!("Failed to load sequence frame {:?}: {:#}", path, e);
                 }
-            } else {
-                render_pass.set_vertex_buffer(0, self.default_vertex_buffer.slice(..));
-                render_pass.draw(0..3, 0..1);
+                self.apply_view_state(&view);
+                self.scene.keep_hierarchy(&scene);
             }
+            self.frame_sequence = Some(sequence);
         }
 
-        for (id, image_delta) in &egui_output.textures_delta.set {
-            self.egui_renderer.update_texture(&self.device, &self.queue, *id, image_delta);
+        if let Some(state) = self.camera_path.sample(frame) {
+            self.camera.apply_state(&state);
+            self.camera.update_position();
+        } else if self.turntable {
+            self.camera.yaw = self.turntable_yaw + std::f32::consts::TAU * self.timeline.progress(frame);
+            self.camera.update_position();
         }
-        self.egui_renderer.update_buffers(&self.device, &self.queue, &mut encoder, &paint_jobs, &screen_descriptor);
-
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("egui Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-            self.egui_renderer.render(&mut rpass, &paint_jobs, &screen_descriptor);
+        if self.clip_plane.sweep.is_some() {
+            // Reaches the second section on the last frame rather than past it
+            let last = self.timeline.frame_count.saturating_sub(1).max(1);
+            self.clip_plane.sample_sweep(frame as f32 / last as f32);
+            self.update_surface_pipelines();
         }
+    }
 
-        for id in &egui_output.textures_delta.free {
-            self.egui_renderer.free_texture(id);
+    /// Renders every timeline frame at the export size into numbered PNGs.
+    pub fn capture_timeline(&mut self, folder: &Path) -> Result<Vec<PathBuf>> {
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let width = self.export_width.clamp(1, max_dimension);
+        let height = self.export_height.clamp(1, max_dimension);
+        let current = self.timeline.frame();
+
+        let mut written = Vec::new();
+        for frame in 0..self.timeline.frame_count {
+            self.apply_timeline_frame(frame);
+            self.flush_uploads();
+            let mut camera = self.camera.clone();
+            camera.aspect_ratio = width as f32 / height as f32;
+            let pixels = self.render_to_image(&camera, width, height)?;
+            let path = folder.join(format!("frame_{:05}.png", frame));
+            save_png(&path, width, height, &pixels, self.color_space())?;
+            written.push(path);
         }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        self.timeline.set_frame(current);
+        self.apply_timeline_frame(current);
+        Ok(written)
+    }
 
+    /// Loads another version of the current model to blend towards. It
+    /// must have the same vertices and faces, only positions may differ.
+    pub fn load_morph_target(&mut self, path: &Path) -> Result<()> {
+        if !self.has_mesh {
+            anyhow::bail!("Load a model before its morph target");
+        }
+        if self.streamer.is_some() {
+            anyhow::bail!("Morph targets aren't supported for models streamed in clusters");
+        }
+        let mut mesh = Mesh::new();
+        if terrain::is_heightmap(path) {
+            mesh.load_heightmap(path, self.terrain_options)?;
+        } else {
+            mesh.load(path)?;
+        }
+        if let Some(clusters) = &self.clusters {
+            clusters.reorder(&mut mesh.indices);
+        }
+        shading::follow(&mut mesh, &self.mesh);
+        let target = MorphTarget::new(&self.device, path, mesh, &self.mesh)?;
+        // The quantized shader paths don't blend towards a morph target, and
+        // its vertex buffer lines up with the base mesh's unshared one
+        self.mesh.set_quantized(false);
+        self.mesh.set_shared(false);
+        self.mesh.create_buffers_deferred(&self.device);
+        info!(
+            "Loaded morph target {:?}: {} vertices moved, up to {}",
+            path, target.changed_vertices, target.max_displacement
+        );
+        self.scene.include_bounds(target.mesh());
+        if let Some(clusters) = &mut self.clusters {
+            clusters.include_bounds(target.mesh());
+        }
+        self.morph_target = Some(target);
+        self.morph_weight = 1.0;
         Ok(())
     }
-    
+
+    pub fn vertex_quantization(&self) -> bool {
+        self.mesh.quantized().is_some()
+    }
+
+    /// Re-uploads the model's vertices in the compressed or the full layout.
+    pub fn set_vertex_quantization(&mut self, enabled: bool) {
+        let enabled = enabled && self.morph_target.is_none() && self.streamer.is_none();
+        if self.has_mesh && enabled != self.vertex_quantization() {
+            self.mesh.set_quantized(enabled);
+            self.mesh.create_buffers_deferred(&self.device);
+        }
+    }
+
+    // Whether the mesh drawn this frame has a `QuantizedVertex` buffer
+    fn display_quantized(&self) -> bool {
+        self.has_mesh && self.display_mesh().quantized().is_some()
+    }
+
+    // Bound at vertex slot 1; without a morph target the mesh is blended with itself
+    fn morph_vertex_buffer<'a>(&'a self, vertex_buffer: &'a wgpu::Buffer) -> wgpu::BufferSlice<'a> {
+        self.morph_target
+            .as_ref()
+            .and_then(|target| target.vertex_buffer(!std::ptr::eq(self.display_mesh(), &self.mesh)))
+            .unwrap_or(vertex_buffer)
+            .slice(..)
+    }
+
+    /// Overlays sample points (and vector arrows) from a CSV or NPY file on
+    /// the current model, replacing earlier samples.
+    pub fn load_samples(&mut self, path: &Path) -> Result<()> {
+        let samples = samples::load(path)?;
+        info!("Loaded {} samples from {:?}", samples.positions.len(), path);
+        self.sample_points.set_cloud(samples.point_cloud());
+        self.sample_points.upload_pending(&self.device, usize::MAX);
+        if self.model_bounds.is_none() {
+            self.fit_cameras(samples.bounds());
+        }
+        self.samples = Some(samples);
+        self.update_sample_arrows();
+        Ok(())
+    }
+
+    pub fn clear_samples(&mut self) {
+        self.samples = None;
+        self.sample_points.clear();
+        self.sample_arrows = None;
+    }
+
+    fn update_sample_arrows(&mut self) {
+        self.sample_arrows = self
+            .samples
+            .as_ref()
+            .and_then(|samples| samples.arrow_mesh(self.arrow_scale))
+            .map(|arrows| {
+                let mut mesh = Mesh::new();
+                mesh.append(arrows);
+                mesh.create_buffers(&self.device);
+                mesh
+            });
+    }
+
+    /// Spends this frame's upload budget on the mesh and texture data still
+    /// waiting to be copied to the GPU.
+    fn upload_pending(&mut self, mut budget: usize) {
+        let decoded = self.texture_decoder.poll();
+        if !decoded.is_empty() {
+            self.install_decoded_textures(decoded);
+        }
+        self.update_streaming(budget);
+        self.mesh.upload_pending(&self.queue, &mut budget);
+        for material in &mut self.materials {
+            if budget == 0 {
+                break;
+            }
+            material.upload_pending(&self.queue, &mut budget);
+        }
+    }
+
+    // Brings the clusters nearest to the camera in and drops distant ones
+    fn update_streaming(&mut self, upload_budget: usize) {
+        if let (Some(streamer), Some(clusters)) = (&mut self.streamer, &self.clusters) {
+            let transforms = self.scene.world_transforms();
+            let order = streaming::priority_order(clusters, &transforms, &self.cluster_visibility.borrow(), self.camera.position);
+            streamer.update(&self.device, &self.mesh, clusters, &order, upload_budget);
+        }
+    }
+
+    /// Bytes of mesh and texture data not yet copied to the GPU.
+    pub fn pending_upload_bytes(&self) -> usize {
+        self.mesh.pending_upload_bytes() + self.materials.iter().map(GpuMaterial::pending_upload_bytes).sum::<usize>()
+    }
+
+    /// Uploads everything still waiting, including point chunks, for renders
+    /// that can't wait for frames.
+    pub fn flush_uploads(&mut self) {
+        let decoded = self.texture_decoder.finish();
+        self.install_decoded_textures(decoded);
+        while self.points.upload_pending(&self.device, usize::MAX) {}
+        self.upload_pending(usize::MAX);
+    }
+
+    /// Color space of the displayed and exported images.
+    pub fn color_space(&self) -> ColorSpace {
+        self.post_process.color_space
+    }
+
+    pub fn view_state(&self) -> ViewState {
+        ViewState::new(self.camera.state(), self.lights.clone())
+    }
+
+    pub fn apply_view_state(&mut self, state: &ViewState) {
+        self.camera.apply_state(&state.camera);
+        if !state.lights.is_empty() {
+            self.set_lights(&state.lights);
+        }
+    }
+
+    pub fn model_path(&self) -> Option<&Path> {
+        self.model_path.as_deref()
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn set_camera_state(&mut self, state: &CameraState) {
+        self.camera.apply_state(state);
+    }
+
+    /// Largest width or height of an offscreen image.
+    pub fn max_image_dimension(&self) -> u32 {
+        self.device.limits().max_texture_dimension_2d
+    }
+
+    pub fn model_bounds(&self) -> Option<(glam::Vec3, glam::Vec3)> {
+        self.model_bounds
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    /// Replaces the scene's lights, keeping the first `MAX_LIGHTS`.
+    pub fn set_lights(&mut self, lights: &[Light]) {
+        self.lights = lights.iter().copied().take(MAX_LIGHTS).collect();
+        self.selected_light = self.selected_light.min(self.lights.len().saturating_sub(1));
+        self.write_light_uniforms(&self.camera);
+    }
+
+    // Uploads the lights as `camera` sees them, which only matters for the
+    // headlight
+    fn write_light_uniforms(&self, camera: &Camera) {
+        let mut lights = self.lights.clone();
+        if let Some(light) = lights.first_mut().filter(|_| self.headlight) {
+            light.kind = LightKind::Point;
+            light.position = camera.position.to_array();
+        }
+        let uniform = LightsUniform::new(&lights).with_environment(
+            self.environment.lighting_intensity(),
+            self.environment.settings.rotation,
+            SPECULAR_LEVELS,
+        );
+        self.queue.write_buffer(&self.light_uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Loads the `.hdr` environment at `path`, which then lights the scene
+    /// and shows behind it.
+    pub fn load_environment(&mut self, path: &Path) -> Result<()> {
+        self.environment.load(&self.device, &self.queue, path)?;
+        self.environment_changed();
+        Ok(())
+    }
+
+    pub fn clear_environment(&mut self) {
+        self.environment.clear(&self.device);
+        self.environment_changed();
+    }
+
+    pub fn environment_path(&self) -> Option<&Path> {
+        self.environment.path()
+    }
+
+    // The cubes are new textures, so the light bind group is rebuilt
+    fn environment_changed(&mut self) {
+        self.light_bind_group =
+            create_light_bind_group(&self.device, &self.light_bind_group_layout, &self.light_uniform_buffer, &self.environment);
+        self.write_light_uniforms(&self.camera);
+    }
+
+    pub fn headlight(&self) -> bool {
+        self.headlight
+    }
+
+    /// Locks the first light to the camera, or lets it go back to its own
+    /// position.
+    pub fn toggle_headlight(&mut self) {
+        self.headlight = !self.headlight;
+        self.write_light_uniforms(&self.camera);
+        info!("Headlight: {}", self.headlight);
+    }
+
+    pub fn set_aov_settings(&mut self, settings: AovSettings) {
+        self.aov_settings = settings;
+    }
+
+    pub fn set_color_mode(&mut self, mode: ColorMode, quality: QualitySettings) {
+        let mode_changed = mode != self.color_mode;
+        let quality_changed = quality != self.quality_settings;
+        self.color_mode = mode;
+        self.quality_settings = quality;
+        if mode_changed {
+            info!("Color mode: {}", mode.label());
+        }
+        if mode_changed || (mode == ColorMode::Quality && quality_changed) {
+            self.update_color_overlay();
+        }
+    }
+
+    fn update_color_overlay(&mut self) {
+        self.quality_failures = if self.has_mesh && self.color_mode == ColorMode::Quality {
+            analysis::triangle_quality(&self.mesh, self.quality_settings.metric)
+                .into_iter()
+                .filter(|&value| self.quality_settings.fails(value))
+                .count()
+        } else {
+            0
+        };
+        // A per-triangle copy of a streamed model wouldn't fit either
+        if !self.has_mesh || self.streamer.is_some() {
+            self.color_overlay = None;
+            return;
+        }
+
+        let colors = self.color_mode.face_colors(&self.mesh, &self.quality_settings);
+        if colors.is_none() && self.selected_face.is_none() {
+            self.color_overlay = None;
+            return;
+        }
+
+        let mut overlay = self.mesh.with_face_colors(colors.as_deref());
+        if let Some(face) = self.selected_face {
+            overlay.paint_face(face, SELECTION_COLOR);
+        }
+        overlay.create_buffers(&self.device);
+        self.color_overlay = Some(overlay);
+    }
+
+    /// (Re)creates material textures from the loaded model's MTL, looking the
+    /// files up again so textures fixed on disk are picked up. Images already
+    /// in the texture cache aren't decoded again; the others are decoded on
+    /// worker threads, with the material's plain colors until they're done.
+    pub fn reload_textures(&mut self) {
+        self.texture_decoder.cancel();
+        // A texture dropped mid-upload would come back from the cache incomplete
+        for material in self.materials.drain(..) {
+            for texture in material.pending_textures() {
+                self.texture_cache.remove(texture);
+            }
+        }
+        self.texture_cache.trim();
+        self.texture_warnings.clear();
+        let Some(model_path) = &self.model_path else {
+            return;
+        };
+        let model_dir = model_path.parent().unwrap_or(Path::new("."));
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+
+        if let Some(error) = &self.mesh.material_error {
+            self.texture_warnings.push(TextureWarning {
+                material: String::new(),
+                path: model_path.display().to_string(),
+                reason: format!("Material library: {}", error),
+            });
+        }
+
+        for (index, mtl) in self.mesh.materials.iter().enumerate() {
+            let mut gpu_material = GpuMaterial::new(&self.device, &self.material_resources, &mtl.name, MaterialUniforms::from_mtl(mtl));
+            for slot in TextureSlot::ALL {
+                let Some((texture, _)) = material::texture_statement(mtl, slot) else {
+                    continue;
+                };
+                let embedded = self.mesh.embedded_textures.get(&texture);
+                let key = match embedded {
+                    Some(_) => Some(TextureKey::embedded(model_path, &texture)),
+                    None => material::resolve_texture_path(model_dir, &texture).map(|path| TextureKey::file(&path)),
+                }
+                .map(|key| if slot.srgb() { key } else { key.linear() });
+                if let Some(cached) = key.as_ref().and_then(|key| self.texture_cache.get(key)) {
+                    gpu_material.set_texture(&self.device, &self.material_resources, slot, cached);
+                    continue;
+                }
+
+                let source = match (embedded, &key) {
+                    (Some(bytes), _) => TextureSource::Embedded(bytes.clone()),
+                    (None, Some(key)) => TextureSource::File(key.path().to_path_buf()),
+                    (None, None) => {
+                        tracing::warn!("Texture {:?} for material {:?}: File not found", texture, mtl.name);
+                        self.texture_warnings.push(TextureWarning {
+                            material: mtl.name.clone(),
+                            path: texture.clone(),
+                            reason: "File not found".to_string(),
+                        });
+                        gpu_material.set_missing(&self.device, &self.material_resources, slot);
+                        continue;
+                    }
+                };
+                self.texture_decoder.decode(TextureRequest {
+                    material: index,
+                    name: mtl.name.clone(),
+                    texture,
+                    key,
+                    slot,
+                    source,
+                    max_dimension,
+                });
+            }
+            self.materials.push(gpu_material);
+        }
+        self.texture_cache.trim();
+        info!(
+            "Loaded {} materials, decoding {} textures, {} texture warnings",
+            self.materials.len(),
+            self.texture_decoder.in_flight(),
+            self.texture_warnings.len()
+        );
+    }
+
+    // Swaps decoded textures in for the stand-ins of their materials
+    fn install_decoded_textures(&mut self, decoded: Vec<DecodedTexture>) {
+        for DecodedTexture { request, levels } in decoded {
+            let Some(material) = self.materials.get_mut(request.material) else {
+                continue;
+            };
+            match levels {
+                Ok(levels) => {
+                    let texture = material.set_deferred(&self.device, &self.material_resources, request.slot, &request.name, levels);
+                    if let Some(key) = request.key {
+                        self.texture_cache.insert(key, texture);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Texture {:?} for material {:?}: {:#}", request.texture, request.name, e);
+                    self.texture_warnings.push(TextureWarning {
+                        material: request.name,
+                        path: request.texture,
+                        reason: format!("{:#}", e),
+                    });
+                    material.set_missing(&self.device, &self.material_resources, request.slot);
+                }
+            }
+        }
+        self.texture_cache.trim();
+    }
+
+    /// Draws the mesh's group `range` with material `material` (`None` for the
+    /// default material) instead of the one assigned in the OBJ.
+    pub fn assign_material(&mut self, range: usize, material: Option<usize>) {
+        let material = material.filter(|&id| id < self.mesh.materials.len());
+        let Some(draw_range) = self.mesh.draw_ranges.get_mut(range) else {
+            return;
+        };
+        draw_range.material = material;
+        info!("Assigned material {:?} to {:?}", material.map(|id| &self.mesh.materials[id].name), draw_range.name);
+        if let Some(overlay) = &mut self.color_overlay {
+            overlay.draw_ranges[range].material = material;
+        }
+    }
+
+    /// Replaces material `index` of the loaded model with `mtl`, e.g. after
+    /// editing its parameters. Textures are only reloaded when it uses
+    /// different maps, e.g. after switching to PBR shading.
+    pub fn update_material(&mut self, index: usize, mtl: tobj::Material) {
+        let Some(material) = self.mesh.materials.get_mut(index) else {
+            return;
+        };
+        let maps = |mtl: &tobj::Material| TextureSlot::ALL.map(|slot| material::texture_statement(mtl, slot).map(|(texture, _)| texture));
+        let same_maps = maps(material) == maps(&mtl);
+        if let Some(gpu_material) = self.materials.get(index).filter(|_| same_maps) {
+            gpu_material.set_uniforms(&self.queue, MaterialUniforms::from_mtl(&mtl));
+        }
+        *material = mtl;
+        if !same_maps {
+            self.reload_textures();
+        }
+    }
+
+    /// Gives draw range `range` its own copy of its material, so editing it
+    /// leaves the other objects using it alone. Ranges without a material get
+    /// a new one.
+    pub fn make_material_unique(&mut self, range: usize) {
+        let Some(draw_range) = self.mesh.draw_ranges.get(range) else {
+            return;
+        };
+        let mut mtl = match draw_range.material.and_then(|material| self.mesh.materials.get(material)) {
+            Some(material) => material.clone(),
+            None => tobj::Material {
+                diffuse: Some([0.8; 3]),
+                ..Default::default()
+            },
+        };
+        let base = if draw_range.name.is_empty() { "material" } else { draw_range.name.as_str() };
+        mtl.name = (1..)
+            .map(|n| if n == 1 { base.to_string() } else { format!("{} {}", base, n) })
+            .find(|name| !self.mesh.materials.iter().any(|material| &material.name == name))
+            .unwrap_or_default();
+        self.mesh.materials.push(mtl);
+        self.assign_material(range, Some(self.mesh.materials.len() - 1));
+        self.reload_textures();
+    }
+
+    pub fn set_selected_face(&mut self, face: Option<usize>) {
+        if face != self.selected_face {
+            self.selected_face = face;
+            self.update_color_overlay();
+        }
+    }
+
+    fn display_mesh(&self) -> &Mesh {
+        match self.view_mode.get() {
+            None | Some(RenderMode::ColorMode) => self.color_overlay.as_ref().unwrap_or(&self.mesh),
+            Some(RenderMode::Shaded | RenderMode::Wireframe | RenderMode::HiddenLine) => &self.mesh,
+        }
+    }
+
+    // Display mode of the viewport being rendered
+    fn active_display_mode(&self) -> DisplayMode {
+        self.view_mode.get().map_or(self.display_mode, RenderMode::display_mode)
+    }
+
+    // Auto-fits the main camera and the split layout's cameras to `bounds`
+    fn fit_cameras(&mut self, bounds: (glam::Vec3, glam::Vec3)) {
+        self.camera.auto_fit_to_model(bounds);
+        for viewport in &mut self.viewports {
+            viewport.fit(bounds);
+        }
+    }
+
+    pub fn handle_input(&mut self, event: &winit::event::WindowEvent) {
+        // The ruler takes over left-dragging
+        let left_press = matches!(
+            event,
+            winit::event::WindowEvent::MouseInput {
+                button: winit::event::MouseButton::Left,
+                state: winit::event::ElementState::Pressed,
+                ..
+            }
+        );
+        match event {
+            winit::event::WindowEvent::CursorMoved { position, .. } => self.cursor_position = *position,
+            winit::event::WindowEvent::MouseInput { state: winit::event::ElementState::Pressed, .. } | winit::event::WindowEvent::MouseWheel { .. } => {
+                self.active_viewport = self.viewport_layout.viewport_at(
+                    self.cursor_position.x as f32 / self.size.width.max(1) as f32,
+                    self.cursor_position.y as f32 / self.size.height.max(1) as f32,
+                );
+            }
+            _ => {}
+        }
+        // Clicks and keys may change the scene in ways the still doesn't track
+        if let Some(still) = &mut self.still {
+            if matches!(
+                event,
+                winit::event::WindowEvent::MouseInput { .. } | winit::event::WindowEvent::MouseWheel { .. } | winit::event::WindowEvent::KeyboardInput { .. }
+            ) {
+                still.reset();
+            }
+        }
+        // Keys typed into text fields don't fly the camera; releases still
+        // pass, so no key is left held
+        let key_press = matches!(event, winit::event::WindowEvent::KeyboardInput { event, .. } if event.state.is_pressed());
+        if key_press && self.wants_keyboard_input() {
+            return;
+        }
+        let split = self.viewport_layout != ViewportLayout::Single;
+        let sensitivity = self.camera_sensitivity;
+        if split && self.active_viewport > 0 {
+            if let Some(viewport) = self.viewports.get_mut(self.active_viewport - 1) {
+                viewport.controller.handle_input(&mut viewport.camera, event, sensitivity);
+            }
+        } else if !(left_press && (self.handles_active() || (self.ruler.enabled && !split))) {
+            self.camera_controller.handle_input(&mut self.camera, event, sensitivity);
+        }
+    }
+
+    /// Switches how input moves the main camera. Path playback takes a copy
+    /// of the timeline's camera path as it is now.
+    pub fn set_camera_controller(&mut self, kind: ControllerKind) {
+        self.camera_controller = match kind {
+            ControllerKind::Orbit => Box::new(OrbitController::default()),
+            ControllerKind::Fly => Box::new(FlyController::default()),
+            ControllerKind::Turntable => Box::new(TurntableController::default()),
+            ControllerKind::PathPlayback => Box::new(PathPlaybackController::new(self.camera_path.clone(), self.timeline.fps)),
+        };
+        info!("Navigation: {}", kind.label());
+    }
+
+    /// Snaps the camera to a standard view direction, refitting the distance
+    /// to the model.
+    pub fn set_view_preset(&mut self, preset: ViewPreset) {
+        self.camera.set_view_preset(preset);
+        if let Some(bounds) = self.model_bounds {
+            self.camera.auto_fit_to_model(bounds);
+        }
+        info!("View: {}", preset.label());
+    }
+
+    pub fn toggle_auto_rotate(&mut self) {
+        self.camera.auto_rotate = !self.camera.auto_rotate;
+        info!("Auto-rotate: {}", self.camera.auto_rotate);
+    }
+
+    /// Moves the camera by the real time elapsed since the last frame: the
+    /// controller's continuous movement, then the auto-rotation.
+    pub fn update_camera(&mut self, delta_time: f32) {
+        self.camera_controller.update(&mut self.camera, delta_time);
+        if !self.camera_controller.is_moving() {
+            self.camera.update_auto_rotate(delta_time);
+        }
+    }
+
+    /// Shows the outliner with the keyboard in its search box.
+    pub fn open_search(&mut self) {
+        self.show_outliner = true;
+        self.focus_search = true;
+    }
+
+    /// Whether a text field has the keyboard, so keys shouldn't act as
+    /// shortcuts.
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.egui_ctx.wants_keyboard_input()
+    }
+
+    // Objects and groups whose names contain the search text, ignoring case
+    fn search_matches(&self) -> Vec<usize> {
+        scene_search(&self.scene, &self.search)
+    }
+
+    fn frame_search_matches(&mut self) {
+        let bounds = self
+            .search_matches()
+            .into_iter()
+            .filter_map(|node| self.object_world_bounds(node))
+            .reduce(|(min, max), (node_min, node_max)| (min.min(node_min), max.max(node_max)));
+        if let Some(bounds) = bounds {
+            self.camera.auto_fit_to_model(bounds);
+        }
+    }
+
+    pub fn toggle_ruler(&mut self) {
+        self.ruler.enabled = !self.ruler.enabled;
+        info!("Ruler: {}", self.ruler.enabled);
+    }
+
+    /// Named section planes, to keep in the session.
+    pub fn sections(&self) -> &[Section] {
+        &self.clip_plane.sections
+    }
+
+    pub fn set_sections(&mut self, sections: Vec<Section>) {
+        self.clip_plane.sections = sections;
+        self.clip_plane.sweep = None;
+    }
+
+    pub fn toggle_measure(&mut self) {
+        self.measure.enabled = !self.measure.enabled;
+        info!("Measure: {}", self.measure.enabled);
+    }
+
+    /// Cycles shaded, wireframe and hidden-line display.
+    pub fn cycle_display_mode(&mut self) {
+        self.display_mode = self.display_mode.next();
+        info!("Display mode: {}", self.display_mode.label());
+    }
+
+    pub fn display_mode(&self) -> DisplayMode {
+        self.display_mode
+    }
+
+    pub fn set_display_mode(&mut self, mode: DisplayMode) {
+        self.display_mode = mode;
+    }
+
+    /// The options kept in the settings file as they are now. The app's own
+    /// are left at their defaults for it to fill in.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            camera_sensitivity: self.camera_sensitivity,
+            display_mode: self.display_mode,
+            vsync: self.vsync,
+            background_colors: self.background_colors,
+            viewport_layout: self.viewport_layout,
+            detail_view: self.detail_view.enabled,
+            show_outliner: self.show_outliner,
+            show_grid: self.overlay_settings.grid,
+            show_axes: self.overlay_settings.axes,
+            show_view_cube: self.view_cube.enabled,
+            show_stats_hud: self.show_stats_hud,
+            ..Settings::default()
+        }
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.camera_sensitivity = settings.camera_sensitivity.clamp(MIN_SENSITIVITY, MAX_SENSITIVITY);
+        self.display_mode = settings.display_mode;
+        self.set_vsync(settings.vsync);
+        self.background_colors = settings.background_colors;
+        if self.viewport_layout != settings.viewport_layout {
+            self.viewport_layout = settings.viewport_layout;
+            self.active_viewport = 0;
+        }
+        self.detail_view.enabled = settings.detail_view;
+        self.show_outliner = settings.show_outliner;
+        self.overlay_settings.grid = settings.show_grid;
+        self.overlay_settings.axes = settings.show_axes;
+        self.view_cube.enabled = settings.show_view_cube;
+        self.show_stats_hud = settings.show_stats_hud;
+        self.scene_stale = true;
+    }
+
+    /// Waits for the display's refresh when presenting, or presents as soon
+    /// as a frame is done where the platform allows it.
+    pub fn set_vsync(&mut self, vsync: bool) {
+        if vsync == self.vsync {
+            return;
+        }
+        self.vsync = vsync;
+        self.config.present_mode = if vsync { wgpu::PresentMode::AutoVsync } else { wgpu::PresentMode::AutoNoVsync };
+        self.surface_configured = false;
+        info!("VSync: {}", vsync);
+    }
+
+    /// Applies a presentation preset's background, light, display mode and
+    /// exposure.
+    pub fn apply_presentation(&mut self, preset: PresentationPreset) {
+        let presentation = preset.presentation();
+        self.background_colors = [presentation.background; 3];
+        // The preset's lighting would be hidden behind the headlight
+        self.headlight = false;
+        self.set_lights(&presentation.lights);
+        self.display_mode = presentation.display_mode;
+        self.post_process.exposure = presentation.exposure;
+        self.set_clay(ClaySettings {
+            enabled: presentation.clay,
+            ..self.clay
+        });
+        info!("Presentation: {}", preset.label());
+    }
+
+    /// Culls `mode`'s side of shaded surfaces, building its pipelines the
+    /// first time it's used.
+    pub fn set_cull_mode(&mut self, mode: CullMode) {
+        if mode == self.cull_mode {
+            return;
+        }
+        self.cull_mode = mode;
+        info!("Face culling: {}", mode.label());
+        self.update_surface_pipelines();
+    }
+
+    // Switches to the pipelines for the culling in effect, which is none
+    // while a capped section plane needs the back faces behind the cut
+    fn update_surface_pipelines(&mut self) {
+        let mode = match self.clip_plane.needs_back_faces() {
+            true => CullMode::None,
+            false => self.cull_mode,
+        };
+        if mode == self.surface_pipelines.cull_mode {
+            return;
+        }
+        let pipelines = self
+            .cached_surface_pipelines
+            .remove(&mode)
+            .unwrap_or_else(|| SurfacePipelines::new(&self.device, &self.render_pipeline_layout, &self.surface_shader, mode));
+        let previous = std::mem::replace(&mut self.surface_pipelines, pipelines);
+        self.cached_surface_pipelines.insert(previous.cull_mode, previous);
+    }
+
+    /// Switches the clay material on or off and sets its colors.
+    pub fn set_clay(&mut self, clay: ClaySettings) {
+        if clay.enabled != self.clay.enabled {
+            info!("Clay: {}", clay.enabled);
+        }
+        self.clay = clay;
+        self.clay_material.set_uniforms(&self.queue, MaterialUniforms::clay(clay.top, clay.bottom, clay.rim()));
+    }
+
+    /// Clears the background to transparent and, for the window, switches the
+    /// surface to a compositing mode that lets it show through.
+    pub fn set_transparent_background(&mut self, transparent: bool) {
+        self.transparent_background = transparent;
+        if transparent && self.config.alpha_mode == wgpu::CompositeAlphaMode::Opaque {
+            let see_through = [wgpu::CompositeAlphaMode::PreMultiplied, wgpu::CompositeAlphaMode::PostMultiplied, wgpu::CompositeAlphaMode::Inherit];
+            match see_through.into_iter().find(|mode| self.surface_alpha_modes.contains(mode)) {
+                Some(mode) => self.config.alpha_mode = mode,
+                None => tracing::warn!("The surface only supports opaque compositing; the window will stay opaque"),
+            }
+        }
+        if !transparent && self.surface_alpha_modes.contains(&wgpu::CompositeAlphaMode::Opaque) {
+            self.config.alpha_mode = wgpu::CompositeAlphaMode::Opaque;
+        }
+        self.surface_configured = false;
+        self.ui_actions.push(UiAction::SetWindowTransparent(transparent));
+        info!("Transparent window: {} ({:?})", transparent, self.config.alpha_mode);
+    }
+
+    pub fn toggle_auto_exposure(&mut self) {
+        if self.safe_mode {
+            info!("Auto exposure is unavailable in safe mode");
+            return;
+        }
+        if !self.post_process.supports_auto_exposure() {
+            info!("Auto exposure needs compute shaders, which the adapter lacks");
+            return;
+        }
+        self.post_process.toggle_auto_exposure();
+        info!("Auto exposure: {}", self.post_process.exposure.auto_exposure);
+    }
+
+    pub fn set_render_scale(&mut self, scale: f32) {
+        let scale = scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+        if scale != self.render_scale {
+            self.render_scale = scale;
+            info!("Render scale: {:.2}", self.render_scale);
+        }
+    }
+
+    fn apply_render_scale(&mut self, scale: f32) {
+        let scale = scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+        if scale != self.effective_render_scale {
+            self.effective_render_scale = scale;
+            self.recreate_scene_targets();
+            debug!(
+                "Scene targets: {}x{} (scale {:.2})",
+                self.scene_size.width, self.scene_size.height, self.effective_render_scale
+            );
+        }
+    }
+
+    // Depth and HDR targets follow the window size multiplied by the render scale
+    fn recreate_scene_targets(&mut self) {
+        self.scene_size = scaled_size(self.size, self.effective_render_scale, self.device.limits().max_texture_dimension_2d);
+        self.depth_texture = create_depth_texture(&self.device, self.scene_size);
+        self.depth_texture_view = self.depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.post_process.resize(&self.device, self.scene_size.width, self.scene_size.height);
+        self.scene_stale = true;
+    }
+
+    pub fn scene_fps_limit(&self) -> Option<u32> {
+        self.scene_fps_limit
+    }
+
+    /// Follows the window's size. A minimized or zero-area window pauses
+    /// drawing, keeping the targets of the last size until it has area again.
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width == 0 || new_size.height == 0 {
+            if !self.minimized {
+                info!("Window has no area, drawing paused");
+                self.minimized = true;
+            }
+            return;
+        }
+        if std::mem::take(&mut self.minimized) {
+            info!("Window restored, drawing resumed");
+        }
+        // Surfaces can't be larger than the biggest texture
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let new_size = winit::dpi::PhysicalSize::new(new_size.width.min(max_dimension), new_size.height.min(max_dimension));
+        self.surface_configured = false;
+        if new_size == self.size {
+            return;
+        }
+        self.size = new_size;
+        self.config.width = new_size.width;
+        self.config.height = new_size.height;
+        self.camera.aspect_ratio = new_size.width as f32 / new_size.height as f32;
+
+        self.recreate_scene_targets();
+    }
+
+    /// Whether there's a surface with area to draw to: not while the window
+    /// is minimized or the app is suspended.
+    pub fn can_draw(&self) -> bool {
+        self.surface.is_some() && !self.minimized
+    }
+
+    /// Drops the surface when the platform suspends the app, as mobile
+    /// platforms do when it goes to the background; `resume` recreates it.
+    pub fn suspend(&mut self) {
+        if self.surface.take().is_some() {
+            info!("Suspended, surface released");
+        }
+        self.surface_configured = false;
+    }
+
+    pub fn resume(&mut self, window: Arc<Window>) -> Result<()> {
+        if self.surface.is_some() {
+            return Ok(());
+        }
+        let size = window.inner_size();
+        self.surface = Some(self.instance.create_surface(window)?);
+        self.surface_configured = false;
+        info!("Resumed, surface recreated");
+        self.resize(size);
+        Ok(())
+    }
+
+    fn write_camera_uniforms(&self, camera: &Camera, viewport_height: u32) {
+        let line = &self.line_style;
+        let own_colors = std::ptr::eq(self.display_mesh(), &self.mesh);
+        let camera_uniforms = CameraUniforms {
+            view_projection: (camera.projection_matrix() * camera.view_matrix()).to_cols_array_2d(),
+            view_matrix: camera.view_matrix().to_cols_array_2d(),
+            camera_position: [camera.position.x, camera.position.y, camera.position.z],
+            morph_weight: self.morph_weight,
+            clip_plane: self.clip_plane.equation(),
+            line_color: [line.color[0], line.color[1], line.color[2], if line.vertex_colors { 0.0 } else { 1.0 }],
+            pixel_size: [2.0 / (viewport_height as f32 * camera.aspect_ratio).max(1.0), 2.0 / viewport_height.max(1) as f32],
+            line_width: line.width.clamp(1, LineStyle::MAX_WIDTH) as f32,
+            // False colors of the analysis overlays are already linear
+            srgb_vertex_colors: (self.vertex_color_space == VertexColorSpace::Srgb && own_colors) as u32 as f32,
+            // and take precedence over the shading modes
+            shading_mode: if own_colors { self.shading_mode as u32 } else { 0 },
+            colormap: self.colormap as u32,
+            value_range: match self.shading_mode {
+                ShadingMode::Distance => [0.0, self.centroid.1],
+                _ => self.model_bounds.map_or([0.0, 1.0], |(min, max)| [min.y, max.y]),
+            },
+            centroid: self.centroid.0.into(),
+            _pad: 0.0,
+            backface_color: [self.backface_color[0], self.backface_color[1], self.backface_color[2], self.backface_tint as u32 as f32],
+            section_cap: self.clip_plane.cap_uniform(),
+            section_contour: self.clip_plane.contour_uniform(),
+        };
+        self.queue.write_buffer(&self.camera_uniform_buffer, 0, bytemuck::cast_slice(&[camera_uniforms]));
+        if self.headlight {
+            self.write_light_uniforms(camera);
+        }
+        self.points.write_uniforms(&self.queue, camera.aspect_ratio, viewport_height);
+        self.sample_points.write_uniforms(&self.queue, camera.aspect_ratio, viewport_height);
+        self.overlay.write_uniforms(&self.queue, camera.aspect_ratio, viewport_height);
+        self.grid.write_uniforms(&self.queue, camera);
+        self.environment.write_uniforms(&self.queue, camera);
+        let transforms = self.scene.world_transforms();
+        let view_projection = camera.projection_matrix() * camera.view_matrix();
+        *self.object_visibility.borrow_mut() = self
+            .scene
+            .nodes
+            .iter()
+            .zip(&transforms)
+            .enumerate()
+            .map(|(index, (node, transform))| self.scene.is_shown(index) && scene::in_frustum(view_projection, *transform, node.bounds))
+            .collect();
+        *self.cluster_visibility.borrow_mut() = self.clusters.as_ref().map_or_else(Vec::new, |clusters| {
+            clusters
+                .iter()
+                .map(|cluster| {
+                    cluster.object < self.scene.nodes.len() && self.scene.is_shown(cluster.object)
+                        && transforms.get(cluster.object).is_some_and(|transform| scene::in_frustum(view_projection, *transform, cluster.bounds))
+                })
+                .collect()
+        });
+        let mesh = self.display_mesh();
+        let position_decode = mesh.quantized().filter(|_| self.has_mesh).map(|quantized| quantized.position_decode());
+        // Shared objects are drawn from their source's vertices, moved to where their own were
+        let transforms: Vec<glam::Mat4> = transforms
+            .iter()
+            .enumerate()
+            .map(|(object, transform)| match mesh.duplicate_of(object) {
+                Some(duplicate) => *transform * glam::Mat4::from_translation(duplicate.offset),
+                None => *transform,
+            })
+            .collect();
+        let materials: Vec<Option<usize>> = mesh.draw_ranges.iter().map(|range| range.material).collect();
+        self.object_bindings.write(&self.queue, &transforms, &materials, position_decode);
+        let model_size = self.model_bounds.map_or(1.0, |(min, max)| (max - min).length());
+        self.normal_lines.write_uniforms(&self.queue, model_size, position_decode);
+    }
+
+    // Rebuilds the overlay content for this frame
+    fn update_overlay(&mut self) {
+        self.overlay.clear();
+        if self.overlay_settings.axes {
+            let length = self.model_bounds.map_or(1.0, |(min, max)| (max - min).max_element() * 0.6).max(1e-3);
+            for (axis, name, color) in [
+                (glam::Vec3::X, "X", [1.0, 0.25, 0.25, 1.0]),
+                (glam::Vec3::Y, "Y", [0.25, 1.0, 0.25, 1.0]),
+                (glam::Vec3::Z, "Z", [0.3, 0.5, 1.0, 1.0]),
+            ] {
+                self.overlay.line(glam::Vec3::ZERO, axis * length, color);
+                self.overlay.label(axis * length, name, color);
+            }
+        }
+        if self.overlay_settings.object_labels {
+            let transforms = self.scene.world_transforms();
+            for (object, (node, transform)) in self.scene.nodes.iter().zip(&transforms).enumerate() {
+                if node.triangles > 0 && self.is_object_visible(object) {
+                    self.overlay.label(transform.transform_point3(node.pivot), &node.name, [1.0, 1.0, 1.0, 1.0]);
+                }
+            }
+        }
+        if self.overlay_settings.camera_frustums {
+            self.add_camera_path_overlay();
+        }
+        if let Some(bounds) = self.model_bounds.filter(|_| self.overlay_settings.bounding_box) {
+            self.overlay.box_edges(bounds, BOUNDING_BOX_COLOR);
+        }
+        if self.clip_plane.enabled {
+            let size = self.model_bounds.map_or(1.0, |(min, max)| (max - min).length()).max(1e-3);
+            self.clip_plane.add_outline(&mut self.overlay, size);
+        }
+        if let Some(snapshot) = self.history.compared() {
+            for (start, end) in snapshot.lines() {
+                self.overlay.line(start, end, HISTORY_COLOR);
+            }
+        }
+        for node in self.search_matches() {
+            if let Some(bounds) = self.object_world_bounds(node) {
+                self.overlay.box_edges(bounds, SEARCH_COLOR);
+            }
+        }
+        if let Some(node) = self.selected_node {
+            if let Some(bounds) = self.object_world_bounds(node) {
+                let color = [SELECTION_COLOR[0], SELECTION_COLOR[1], SELECTION_COLOR[2], 1.0];
+                self.overlay.box_edges(bounds, color);
+            }
+        }
+        self.overlay.upload(&self.device, &self.queue);
+    }
+
+    // Camera path keys as frustums cut off at their orbit target, joined by
+    // the interpolated path
+    fn add_camera_path_overlay(&mut self) {
+        const FRUSTUM_COLOR: [f32; 4] = [0.4, 0.8, 1.0, 1.0];
+        const PATH_COLOR: [f32; 4] = [1.0, 0.85, 0.3, 1.0];
+        let keys = self.camera_path.keys();
+        let mut key_camera = self.camera.clone();
+        for (frame, state) in keys {
+            key_camera.apply_state(state);
+            let apex = key_camera.position;
+            let corners = key_camera.frustum_corners(key_camera.distance);
+            for (i, corner) in corners.iter().enumerate() {
+                self.overlay.line(apex, *corner, FRUSTUM_COLOR);
+                self.overlay.line(*corner, corners[(i + 1) % 4], FRUSTUM_COLOR);
+            }
+            // Triangle above the top edge marks which way is up
+            let top_middle = (corners[0] + corners[1]) * 0.5;
+            let peak = top_middle + (corners[0] - corners[3]) * 0.25;
+            self.overlay.line(corners[0].lerp(top_middle, 0.5), peak, FRUSTUM_COLOR);
+            self.overlay.line(peak, corners[1].lerp(top_middle, 0.5), FRUSTUM_COLOR);
+            self.overlay.label(apex, &format!("{}", frame), FRUSTUM_COLOR);
+        }
+
+        if let (Some((first, _)), Some((last, _))) = (keys.first(), keys.last()) {
+            let mut previous = None;
+            for frame in *first..=*last {
+                let Some(state) = self.camera_path.sample(frame) else {
+                    continue;
+                };
+                key_camera.apply_state(&state);
+                key_camera.update_position();
+                if let Some(previous) = previous {
+                    self.overlay.line(previous, key_camera.position, PATH_COLOR);
+                }
+                previous = Some(key_camera.position);
+            }
+        }
+    }
+
+    // World-space box around an object and its children, None when they
+    // have no geometry
+    fn object_world_bounds(&self, node: usize) -> Option<(glam::Vec3, glam::Vec3)> {
+        if node >= self.scene.nodes.len() {
+            return None;
+        }
+        let transforms = self.scene.world_transforms();
+        self.scene
+            .subtree(node)
+            .filter(|&object| self.scene.nodes[object].triangles > 0)
+            .map(|object| scene::transformed_bounds(transforms[object], self.scene.nodes[object].bounds))
+            .reduce(|(min, max), (object_min, object_max)| (min.min(object_min), max.max(object_max)))
+    }
+
+    // Closest shown object under the point `ndc` of `camera`'s image
+    fn pick_at(&self, camera: &Camera, ndc: glam::Vec2) -> Option<Pick> {
+        let ray = Ray::from_camera(camera, ndc);
+        let transforms = self.scene.world_transforms();
+        self.bvhs
+            .iter()
+            .enumerate()
+            .filter(|&(object, _)| object < transforms.len() && self.scene.is_shown(object))
+            .filter_map(|(object, bvh)| {
+                let (t, face) = bvh.intersect(&self.mesh, &ray.transformed(transforms[object].inverse()))?;
+                Some((t, Pick { object, face, point: ray.at(t) }))
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, pick)| pick)
+    }
+
+    // Selects the object and face under a click in the viewport, or clears
+    // the selection for a click on the background. While measuring, the
+    // click adds the vertex nearest to it to the measurement instead, and a
+    // double-click on the model makes the point under it the orbit pivot
+    fn pick_on_click(&mut self) {
+        let split = self.effective_layout() != ViewportLayout::Single;
+        if !self.has_mesh || (self.ruler.enabled && !split) || self.handles_active() || self.egui_ctx.is_pointer_over_area() {
+            return;
+        }
+        let (clicked, double_clicked, position) = self.egui_ctx.input(|input| {
+            (
+                input.pointer.primary_clicked(),
+                input.pointer.button_double_clicked(egui::PointerButton::Primary),
+                input.pointer.interact_pos(),
+            )
+        });
+        let Some(position) = position.filter(|_| clicked) else {
+            return;
+        };
+        let (viewport, camera, ndc) = self.viewport_under(position);
+
+        // The first click of the two was handled on its own already
+        if double_clicked {
+            if let Some(pick) = self.pick_at(&camera, ndc) {
+                self.set_orbit_pivot(viewport, pick.point);
+            }
+            return;
+        }
+        if self.measure.enabled && !split {
+            if let Some(pick) = self.pick_at(&camera, ndc) {
+                let vertex = self.nearest_vertex(&pick);
+                self.measure.add(vertex);
+                info!("Measuring from {:?}: {:.4}", vertex.to_array(), self.measure.total());
+            }
+            return;
+        }
+        self.pick = self.pick_at(&camera, ndc);
+        match self.pick {
+            Some(pick) => info!("Picked {:?}, face {}", self.scene.nodes[pick.object].name, pick.face),
+            None => info!("Picked nothing"),
+        }
+        self.selected_node = self.pick.map(|pick| pick.object);
+        self.set_selected_face(self.pick.map(|pick| pick.face));
+        if self.inspect {
+            self.inspected = self.pick.and_then(|pick| FaceDetails::new(&self.mesh, &pick));
+        }
+    }
+
+    // Viewport under the window position `position`, with its camera and
+    // the position in that camera's normalized device coordinates
+    fn viewport_under(&self, position: egui::Pos2) -> (usize, Camera, glam::Vec2) {
+        let screen = self.egui_ctx.screen_rect();
+        let (x, y) = ((position.x - screen.min.x) / screen.width(), (position.y - screen.min.y) / screen.height());
+        let layout = self.effective_layout();
+        let viewport = layout.viewport_at(x, y);
+        let rect = layout.rects()[viewport];
+        let ndc = glam::Vec2::new((x - rect[0]) / rect[2] * 2.0 - 1.0, 1.0 - (y - rect[1]) / rect[3] * 2.0);
+        let (camera, _) = self.viewport_camera(viewport);
+        (viewport, camera, ndc)
+    }
+
+    // Makes the camera of `viewport` orbit about `pivot`
+    fn set_orbit_pivot(&mut self, viewport: usize, pivot: glam::Vec3) {
+        match viewport.checked_sub(1).and_then(|index| self.viewports.get_mut(index)) {
+            Some(viewport) => viewport.camera.set_pivot(pivot),
+            None => self.camera.set_pivot(pivot),
+        }
+        info!("Orbit pivot: {:?}", pivot.to_array());
+    }
+
+    // Corner of the picked face closest to where it was hit, in world space
+    fn nearest_vertex(&self, pick: &Pick) -> glam::Vec3 {
+        let transform = self.scene.world_transforms()[pick.object];
+        self.mesh.indices[pick.face * 3..pick.face * 3 + 3]
+            .iter()
+            .map(|&index| transform.transform_point3(glam::Vec3::from(self.mesh.vertices[index as usize].position)))
+            .min_by(|a, b| a.distance_squared(pick.point).total_cmp(&b.distance_squared(pick.point)))
+            .unwrap_or(pick.point)
+    }
+
+    // Whether a left press goes to one of the handle sets drawn over the
+    // viewport rather than the camera or picking
+    fn handles_active(&self) -> bool {
+        self.gizmo.is_active() || self.view_cube.is_active() || self.clip_plane.is_active() || self.light_handles.is_active()
+    }
+
+    // Handles for moving, rotating and scaling the selected object, placed
+    // with the main camera like the ruler
+    fn show_gizmo(&mut self) {
+        let node = self.selected_node.filter(|&node| node < self.scene.nodes.len());
+        let Some(node) = node.filter(|_| !self.ruler.enabled && self.viewport_layout == ViewportLayout::Single) else {
+            self.gizmo.release();
+            return;
+        };
+        let transforms = self.scene.world_transforms();
+        let parent = self.scene.nodes[node].parent.map_or(glam::Mat4::IDENTITY, |parent| transforms[parent]);
+        self.gizmo.show(&self.egui_ctx, &self.camera, &mut self.scene.nodes[node], transforms[node], parent);
+    }
+
+    // Orientation cube over the main camera's view, left out of split layouts
+    fn show_view_cube(&mut self) {
+        if self.viewport_layout != ViewportLayout::Single {
+            self.view_cube.release();
+            return;
+        }
+        let mut rect = self.egui_ctx.available_rect();
+        // Left of the Display window, which is anchored in the same corner
+        let display = self.egui_ctx.memory(|memory| memory.area_rect(egui::Id::new("Display")));
+        if let Some(display) = display.filter(|_| !self.compact_mode) {
+            rect.max.x = rect.max.x.min(display.min.x);
+        }
+        if let Some(direction) = self.view_cube.show(&self.egui_ctx, &self.camera, rect) {
+            self.camera.set_view_direction(direction);
+        }
+    }
+
+    // Arrows moving the selected light along the world axes, and a square
+    // sliding it horizontally
+    fn show_light_handles(&mut self) {
+        let light = self.lights.get(self.selected_light).copied();
+        let Some(light) = light.filter(|_| self.move_light && self.viewport_layout == ViewportLayout::Single) else {
+            self.light_handles.release();
+            self.light_drag_start = None;
+            return;
+        };
+        let position = glam::Vec3::from(light.position);
+        let [x, y, z] = Handle::world_axes(HandleShape::Arrow);
+        let handles = [x, y, z, Handle::new(HandleShape::Plane, glam::Vec3::Y, handles::AXIS_COLORS[1])];
+        let Some(drag) = self.light_handles.show(&self.egui_ctx, &self.camera, position, &handles) else {
+            self.light_drag_start = None;
+            return;
+        };
+        if drag.started || self.light_drag_start.is_none() {
+            self.light_drag_start = Some(position);
+        }
+        let start = self.light_drag_start.unwrap_or(position);
+        let moved = match drag.motion {
+            Motion::Along(distance) => start + handles[drag.handle].axis * distance,
+            Motion::Plane(offset) => start + offset,
+            Motion::Scale(_) | Motion::Rotate(_) => start,
+        };
+        let mut lights = self.lights.clone();
+        lights[self.selected_light].position = moved.to_array();
+        self.set_lights(&lights);
+    }
+
+    /// Cycles the gizmo through moving, rotating, scaling and off.
+    pub fn cycle_gizmo_mode(&mut self) {
+        self.gizmo.mode = self.gizmo.mode.next();
+        info!("Gizmo: {}", self.gizmo.mode.label());
+    }
+
+    /// Points the camera at an object of the scene, framing it.
+    pub fn frame_object(&mut self, node: usize) {
+        if let Some(bounds) = self.object_world_bounds(node) {
+            self.camera.auto_fit_to_model(bounds);
+        }
+    }
+
+    fn is_object_visible(&self, object: usize) -> bool {
+        self.object_visibility.borrow().get(object).copied().unwrap_or(true)
+    }
+
+    // Index ranges of `mesh` to draw for `object`: its visible clusters,
+    // merged where they follow each other, or its whole draw range for
+    // unclustered meshes. A shared object's clusters line up with its
+    // source's, which its indices are drawn from.
+    fn visible_index_ranges(&self, mesh: &Mesh, object: usize) -> Vec<std::ops::Range<u32>> {
+        let gpu_range = mesh.gpu_range(object);
+        let Some(clusters) = &self.clusters else {
+            return std::iter::once(gpu_range).collect();
+        };
+        let shift = |index: u32| index - mesh.draw_ranges[object].start + gpu_range.start;
+        let visibility = self.cluster_visibility.borrow();
+        let (first, object_clusters) = clusters.of_object(object);
+        let mut ranges: Vec<std::ops::Range<u32>> = Vec::new();
+        for (i, cluster) in object_clusters.iter().enumerate() {
+            if !visibility.get(first + i).copied().unwrap_or(true) {
+                continue;
+            }
+            let start = shift(cluster.start);
+            match ranges.last_mut() {
+                Some(last) if last.end == start => last.end += cluster.count,
+                _ => ranges.push(start..start + cluster.count),
+            }
+        }
+        ranges
+    }
+
+    /// Draws the scene into `color_view`; `hud` adds the overlay lines and
+    /// labels, which are left out of exported images. With `viewport` (x, y,
+    /// width, height in pixels) only that part is drawn, over the previous
+    /// content unless `clear`.
+    fn encode_scene(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        color_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        hud: bool,
+        viewport: Option<[f32; 4]>,
+        clear: bool,
+    ) {
+        let background = if self.transparent_background {
+            wgpu::Color::TRANSPARENT
+        } else {
+            let [r, g, b] = self.background_colors[self.active_display_mode() as usize];
+            wgpu::Color {
+                r: r as f64,
+                g: g as f64,
+                b: b as f64,
+                a: 1.0,
+            }
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: if clear { wgpu::LoadOp::Clear(background) } else { wgpu::LoadOp::Load },
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: if clear { wgpu::LoadOp::Clear(1.0) } else { wgpu::LoadOp::Load },
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        if let Some([x, y, width, height]) = viewport {
+            render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        }
+        if self.environment.shows_background() && !self.transparent_background {
+            self.environment.draw_background(&mut render_pass);
+        }
+
+        let display_mode = self.active_display_mode();
+        let quantized = self.display_quantized();
+        if display_mode != DisplayMode::Wireframe {
+            let pipeline = match (display_mode == DisplayMode::HiddenLine, quantized) {
+                (false, false) => &self.surface_pipelines.shaded,
+                (false, true) => &self.surface_pipelines.quantized,
+                (true, false) => &self.surface_pipelines.hidden_line,
+                (true, true) => &self.surface_pipelines.quantized_hidden_line,
+            };
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.light_bind_group, &[]);
+            self.draw_geometry(&mut render_pass, true, 3);
+        }
+        if display_mode != DisplayMode::Shaded {
+            let pipeline = match (display_mode == DisplayMode::HiddenLine, quantized) {
+                (false, false) => &self.wireframe_pipeline,
+                (false, true) => &self.quantized_wireframe_pipeline,
+                (true, false) => &self.hidden_line_edge_pipeline,
+                (true, true) => &self.quantized_hidden_line_edge_pipeline,
+            };
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.light_bind_group, &[]);
+            // Without a mesh (and edges) the wireframe falls back to the plain geometry
+            if !self.draw_edges(&mut render_pass) && display_mode == DisplayMode::Wireframe {
+                self.draw_geometry(&mut render_pass, false, 2);
+            }
+        }
+        self.points.draw(&mut render_pass, &self.camera_bind_group);
+
+        if let Some(arrows) = &self.sample_arrows {
+            if let (Some(vertex_buffer), Some(index_buffer)) = (arrows.get_vertex_buffer(), arrows.get_index_buffer()) {
+                render_pass.set_pipeline(&self.surface_pipelines.shaded);
+                render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+                render_pass.set_bind_group(1, &self.light_bind_group, &[]);
+                render_pass.set_bind_group(2, &self.default_material.bind_group, &[]);
+                self.object_bindings.bind(&mut render_pass, 3, None);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, vertex_buffer.slice(..));
+                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(0..arrows.num_indices, 0, 0..1);
+            }
+        }
+        self.sample_points.draw(&mut render_pass, &self.camera_bind_group);
+        if hud && self.overlay_settings.grid {
+            self.grid.draw(&mut render_pass, &self.camera_bind_group);
+        }
+        if hud && self.has_mesh {
+            self.normal_lines.draw(&mut render_pass, &self.camera_bind_group, &self.object_bindings, |object| self.is_object_visible(object));
+        }
+        if hud {
+            self.overlay.draw(&mut render_pass, &self.camera_bind_group);
+        }
+    }
+
+    // Draws the unique edges of the current mesh on a pass set up with a
+    // wireframe pipeline. Returns false when there are none to draw.
+    fn draw_edges<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) -> bool {
+        if !self.has_mesh {
+            return false;
+        }
+        if self.streamer.is_some() {
+            self.draw_streamed(render_pass, false, 2, true);
+            return true;
+        }
+        let mesh = self.display_mesh();
+        let (Some(vertex_buffer), Some(edge_buffer)) = (mesh.get_vertex_buffer(), mesh.get_edge_buffer()) else {
+            return false;
+        };
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.morph_vertex_buffer(vertex_buffer));
+        render_pass.set_index_buffer(edge_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        // One line list call per object over its unique edges
+        for (object, edges) in mesh.edge_ranges.iter().enumerate() {
+            if !self.is_object_visible(object) {
+                continue;
+            }
+            self.object_bindings.bind(render_pass, 2, Some(object));
+            render_pass.draw_indexed(edges.clone(), 0, 0..self.line_style.instances());
+        }
+        true
+    }
+
+    // Issues the solid draw calls for the current scene on an already configured
+    // pass, binding each object's transform at `object_group`.
+    // With `bind_materials` each draw range binds its material at group 2.
+    fn draw_geometry<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, bind_materials: bool, object_group: u32) {
+        if self.has_mesh && self.streamer.is_some() {
+            self.draw_streamed(render_pass, bind_materials, object_group, false);
+        } else if self.has_mesh {
+            let mesh = self.display_mesh();
+            if let Some(vertex_buffer) = mesh.get_vertex_buffer() {
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, self.morph_vertex_buffer(vertex_buffer));
+
+                if let Some(index_buffer) = mesh.get_index_buffer() {
+                    render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    // False-color overlays replace textures
+                    let textured = self.color_mode == ColorMode::Shaded || std::ptr::eq(mesh, &self.mesh);
+                    for (object, range) in mesh.draw_ranges.iter().enumerate() {
+                        if !self.is_object_visible(object) {
+                            continue;
+                        }
+                        if bind_materials {
+                            render_pass.set_bind_group(2, &self.range_material(range.material, textured).bind_group, &[]);
+                        }
+                        self.object_bindings.bind(render_pass, object_group, Some(object));
+                        for indices in self.visible_index_ranges(mesh, object) {
+                            render_pass.draw_indexed(indices, 0, 0..1);
+                        }
+                    }
+                } else {
+                    self.object_bindings.bind(render_pass, object_group, None);
+                    render_pass.draw(0..mesh.vertices.len() as u32, 0..1);
+                }
+            }
+        } else if self.points.is_empty() {
+            if bind_materials {
+                render_pass.set_bind_group(2, &self.default_material.bind_group, &[]);
+            }
+            self.object_bindings.bind(render_pass, object_group, None);
+            render_pass.set_vertex_buffer(0, self.default_vertex_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, self.default_vertex_buffer.slice(..));
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+    // Material to bind for a draw range using `material`. Clay replaces them
+    // all, unless a false-color overlay is showing (not `textured`).
+    fn range_material(&self, material: Option<usize>, textured: bool) -> &GpuMaterial {
+        if textured && self.clay.enabled {
+            return &self.clay_material;
+        }
+        material
+            .filter(|_| textured)
+            .and_then(|id| self.materials.get(id))
+            // Untextured until the texture has arrived
+            .filter(|material| material.is_uploaded())
+            .unwrap_or(&self.default_material)
+    }
+
+    // Draws the visible resident clusters of a streamed model, then boxes for
+    // the visible ones that aren't loaded. With `edges` it draws their edge
+    // lists for the wireframe pipeline.
+    fn draw_streamed<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, bind_materials: bool, object_group: u32, edges: bool) {
+        let (Some(streamer), Some(clusters)) = (&self.streamer, &self.clusters) else {
+            return;
+        };
+        let visibility = self.cluster_visibility.borrow();
+        let is_visible = |i: usize| visibility.get(i).copied().unwrap_or(true);
+        let textured = self.color_mode == ColorMode::Shaded;
+        let instances = if edges { self.line_style.instances() } else { 1 };
+        for (object, range) in self.mesh.draw_ranges.iter().enumerate() {
+            if !self.is_object_visible(object) {
+                continue;
+            }
+            if bind_materials {
+                render_pass.set_bind_group(2, &self.range_material(range.material, textured).bind_group, &[]);
+            }
+            self.object_bindings.bind(render_pass, object_group, Some(object));
+            let (first, object_clusters) = clusters.of_object(object);
+            for i in (first..first + object_clusters.len()).filter(|&i| is_visible(i)) {
+                if let Some((vertex_buffer, index_buffer, num_indices)) = streamer.resident(i, edges) {
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    render_pass.set_vertex_buffer(1, vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    render_pass.draw_indexed(0..num_indices, 0, 0..instances);
+                }
+            }
+        }
+
+        let Some((vertex_buffer, index_buffer)) = streamer.proxy_buffers(edges) else {
+            return;
+        };
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        if bind_materials {
+            render_pass.set_bind_group(2, &self.default_material.bind_group, &[]);
+        }
+        for object in (0..self.mesh.draw_ranges.len()).filter(|&object| self.is_object_visible(object)) {
+            self.object_bindings.bind(render_pass, object_group, Some(object));
+            let (first, object_clusters) = clusters.of_object(object);
+            for i in (first..first + object_clusters.len()).filter(|&i| is_visible(i) && streamer.resident(i, edges).is_none()) {
+                render_pass.draw_indexed(ClusterStreamer::proxy_indices(i, edges), 0, 0..instances);
+            }
+        }
+    }
+
+    /// Renders normal, linear depth and object ID passes from `camera`.
+    pub fn render_aovs(&self, camera: &Camera, width: u32, height: u32) -> Result<AovImage> {
+        let target = AovTarget::new(&self.device, width, height);
+
+        self.write_camera_uniforms(camera, height);
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("AOV Encoder"),
+        });
+        {
+            let mut render_pass = self.aov_pipeline.begin_pass(&mut encoder, &target, self.display_quantized());
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            self.draw_geometry(&mut render_pass, false, 1);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        target.read(&self.device, &self.queue)
+    }
+
+    /// Renders the scene from `camera` into an offscreen RGBA8 image.
+    pub fn render_to_image(&self, camera: &Camera, width: u32, height: u32) -> Result<Vec<u8>> {
+        self.render_image(camera, width, height, false)
+    }
+
+    // Offscreen render, with the overlay when `hud` is set
+    fn render_image(&self, camera: &Camera, width: u32, height: u32, hud: bool) -> Result<Vec<u8>> {
+        let target = OffscreenTarget::new(&self.device, width, height);
+        let mut post_process = PostProcess::new(&self.device, CAPTURE_FORMAT, width, height, self.post_process.supports_auto_exposure());
+        post_process.exposure = self.post_process.exposure;
+        post_process.color_space = self.post_process.color_space;
+        post_process.update(&self.queue, 0.0);
+
+        self.write_camera_uniforms(camera, height);
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Encoder"),
+        });
+        self.encode_scene(&mut encoder, post_process.hdr_view(), &target.depth_view, hud, None, true);
+        post_process.encode(&mut encoder, &target.color_view);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        target.read_rgba(&self.device, &self.queue)
+    }
+
+    // Offscreen render averaging `samples` jittered frames
+    fn render_still_image(&self, camera: &Camera, width: u32, height: u32, samples: u32) -> Result<Vec<u8>> {
+        let target = OffscreenTarget::new(&self.device, width, height);
+        let mut post_process = PostProcess::new(&self.device, CAPTURE_FORMAT, width, height, self.post_process.supports_auto_exposure());
+        post_process.exposure = self.post_process.exposure;
+        post_process.color_space = self.post_process.color_space;
+        post_process.update(&self.queue, 0.0);
+
+        let mut still = StillAccumulator::new(&self.device, width, height);
+        let mut camera = camera.clone();
+        // Each sample rewrites the camera buffer, so each goes in its own submit
+        for _ in 0..samples.max(1) {
+            camera.jitter = still.jitter();
+            self.write_camera_uniforms(&camera, height);
+            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Still Encoder"),
+            });
+            self.encode_scene(&mut encoder, still.sample_view(), &target.depth_view, false, None, true);
+            still.encode_resolve(&self.queue, &mut encoder, post_process.hdr_view());
+            self.queue.submit(std::iter::once(encoder.finish()));
+        }
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Encoder"),
+        });
+        post_process.encode(&mut encoder, &target.color_view);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        target.read_rgba(&self.device, &self.queue)
+    }
+
+    /// Renders the current view without the UI at the window size times the
+    /// screenshot scale and saves it as a PNG, supersampled in high quality
+    /// still mode. Returns the image size.
+    pub fn capture_screenshot(&mut self, path: &Path) -> Result<(u32, u32)> {
+        self.flush_uploads();
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let width = (self.size.width * self.screenshot_scale).clamp(1, max_dimension);
+        let height = (self.size.height * self.screenshot_scale).clamp(1, max_dimension);
+        let mut camera = self.camera.clone();
+        camera.aspect_ratio = width as f32 / height as f32;
+        let pixels = if self.still_mode {
+            self.render_still_image(&camera, width, height, self.still_samples)?
+        } else {
+            self.render_to_image(&camera, width, height)?
+        };
+        save_png(path, width, height, &pixels, self.color_space())?;
+        Ok((width, height))
+    }
+
+    /// Renders every `ViewPreset` framed on the model and writes them as PNGs into `folder`.
+    pub fn export_standard_views(&mut self, folder: &Path) -> Result<Vec<PathBuf>> {
+        self.flush_uploads();
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let width = self.export_width.clamp(1, max_dimension);
+        let height = self.export_height.clamp(1, max_dimension);
+
+        let mut camera = self.camera.clone();
+        camera.aspect_ratio = width as f32 / height as f32;
+        if let Some(bounds) = self.model_bounds {
+            camera.auto_fit_to_model(bounds);
+        }
+
+        let prefix = self
+            .model_path
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "view".to_string());
+
+        let mut written = Vec::new();
+        for preset in ViewPreset::ALL {
+            camera.set_view_preset(preset);
+            let pixels = self.render_to_image(&camera, width, height)?;
+            let path = folder.join(format!("{}_{}.png", prefix, preset.name()));
+            save_png(&path, width, height, &pixels, self.color_space())?;
+            info!("Exported {:?}", path);
+            written.push(path);
+
+            if self.aov_settings.any() {
+                let aovs = self.render_aovs(&camera, width, height)?;
+                let base = folder.join(format!("{}_{}", prefix, preset.name()));
+                written.extend(aovs.save(&base, &self.aov_settings)?);
+            }
+        }
+        Ok(written)
+    }
+
+    /// Renders the model as orthographic hidden-line top, front, right and
+    /// isometric views with the bounding box dimensions annotated, tiled into
+    /// one PNG. Returns the sheet size.
+    pub fn export_blueprint(&mut self, path: &Path) -> Result<(u32, u32)> {
+        let Some(bounds) = self.model_bounds.filter(|_| self.has_mesh) else {
+            anyhow::bail!("No model loaded");
+        };
+        self.flush_uploads();
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let width = (self.export_width / 2).clamp(1, max_dimension / 2);
+        let height = (self.export_height / 2).clamp(1, max_dimension / 2);
+
+        // The same fit for every view keeps them at one scale
+        let mut camera = self.camera.clone();
+        camera.aspect_ratio = width as f32 / height as f32;
+        camera.orthographic = true;
+        camera.auto_fit_to_model(bounds);
+        // Tighter than the perspective fit, still leaving room for the dimensions
+        camera.distance *= 0.6;
+
+        self.view_mode.set(Some(RenderMode::HiddenLine));
+        let mut views = Vec::new();
+        for preset in blueprint::SHEET_VIEWS {
+            camera.set_view_preset(preset);
+            self.overlay.clear();
+            if preset != ViewPreset::Iso {
+                blueprint::add_dimensions(&mut self.overlay, &camera, bounds);
+            }
+            self.overlay.upload(&self.device, &self.queue);
+            let pixels = self.render_image(&camera, width, height, true);
+            views.push(pixels);
+        }
+        self.view_mode.set(None);
+        // Puts back this frame's overlay
+        self.update_overlay();
+
+        let views = views.into_iter().collect::<Result<Vec<_>>>()?;
+        save_png(path, width * 2, height * 2, &blueprint::compose_sheet(&views, width, height), self.color_space())?;
+        Ok((width * 2, height * 2))
+    }
+
+    /// Writes the loaded model with all in-viewer modifications applied.
+    pub fn export_obj(&self, path: &Path) -> Result<ObjExportStats> {
+        if !self.has_mesh {
+            anyhow::bail!("No model loaded");
+        }
+        let model_dir = self.model_path.as_deref().and_then(Path::parent);
+        obj_export::write_obj(path, &self.mesh, model_dir, self.obj_export_options)
+    }
+
+    /// Writes the parts list of the loaded model as CSV.
+    pub fn export_parts(&self, path: &Path) -> Result<()> {
+        bom::write_csv(path, &self.parts)
+    }
+
+    pub fn take_ui_actions(&mut self) -> Vec<UiAction> {
+        std::mem::take(&mut self.ui_actions)
+    }
+
+    /// Draws a frame. Without `redraw_scene` only the UI is redrawn, over the
+    /// scene kept from the last full frame.
+    pub fn render(&mut self, window: &Window, redraw_scene: bool) -> Result<(), wgpu::SurfaceError> {
+        // Update performance monitor
+        self.performance_monitor.update();
+        self.points.upload_pending(&self.device, self.points.settings.uploads_per_frame);
+        self.upload_pending(upload::DEFAULT_UPLOAD_BUDGET);
+        self.screen_labels.poll(&self.device);
+        self.ruler.poll(&self.device);
+        if self.timeline.tick() {
+            self.apply_timeline_frame(self.timeline.frame());
+        }
+
+        // Begin egui frame
+        let raw_input = match &mut self.egui_winit_state {
+            Some(state) => state.take_egui_input(window),
+            None => egui::RawInput::default(),
+        };
+        self.egui_ctx.begin_frame(raw_input);
+
+        self.show_menu_bar();
+        if self.viewport_layout != ViewportLayout::Single {
+            self.show_viewport_controls();
+        }
+        if self.show_settings {
+            self.show_settings_window();
+        }
+        self.show_load_progress();
+        self.show_toast_popup();
+        self.show_pick_label();
+
+        let stats = self.performance_monitor.get_stats();
+        if !self.compact_mode {
+            self.show_panels(&stats);
+        }
+        if self.show_stats_hud {
+            self.show_stats_hud(&stats);
+        }
+        if self.detail_view.enabled {
+            self.show_detail_view();
+        }
+
+        // Drop resolution while the camera moves below the target frame rate
+        let view_matrix = self.camera.view_matrix();
+        let camera_moving = view_matrix != self.last_view_matrix;
+        self.last_view_matrix = view_matrix;
+        let scale_factor = self.quality_governor.update(stats.fps, camera_moving);
+        self.apply_render_scale(self.render_scale * scale_factor);
+        self.screen_labels.clear();
+        if self.overlay_settings.light {
+            for (i, light) in self.lights.iter().enumerate() {
+                let name = if self.lights.len() > 1 { format!("Light {}", i + 1) } else { "Light".to_string() };
+                self.screen_labels.add(glam::Vec3::from(light.position), name, egui::Color32::from_rgb(255, 210, 90));
+            }
+        }
+        // Before the gizmo, so a click on one of its handles isn't a pick
+        self.pick_on_click();
+        self.show_inspector();
+        self.show_gizmo();
+        self.show_view_cube();
+        self.show_light_handles();
+        if self.viewport_layout == ViewportLayout::Single {
+            self.clip_plane.show(&self.egui_ctx, &self.camera);
+        } else {
+            self.clip_plane.release();
+        }
+        // Labels, the ruler and measurements are placed with the main camera
+        // over the whole window
+        if self.viewport_layout == ViewportLayout::Single {
+            self.screen_labels.paint(&self.egui_ctx, &self.camera);
+            self.ruler.show(&self.egui_ctx, &self.camera);
+            self.measure.paint(&self.egui_ctx, &self.camera);
+        }
+        let egui_output = self.egui_ctx.end_frame();
+        let pixels_per_point = window.scale_factor() as f32;
+        let paint_jobs = self.egui_ctx.tessellate(egui_output.shapes, pixels_per_point);
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [self.size.width, self.size.height],
+            pixels_per_point,
+        };
+
+        let surface = self.surface.as_ref().ok_or(wgpu::SurfaceError::Lost)?;
+        if !self.surface_configured {
+            surface.configure(&self.device, &self.config);
+            self.surface_configured = true;
+        }
+        let output = match surface.get_current_texture() {
+            Ok(output) => output,
+            Err(error) => {
+                // Lost and outdated surfaces come back after reconfiguring
+                if matches!(error, wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) {
+                    self.surface_configured = false;
+                }
+                return Err(error);
+            }
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.post_process.update(&self.queue, stats.frame_time_ms / 1000.0);
+        if redraw_scene || self.scene_stale {
+            self.encode_frame_scene(&mut encoder);
+        }
+
+        // Resolve the HDR scene onto the swapchain image
+        self.post_process.encode(&mut encoder, &view);
+
+        for (id, image_delta) in &egui_output.textures_delta.set {
+            self.egui_renderer.update_texture(&self.device, &self.queue, *id, image_delta);
+        }
+        self.egui_renderer.update_buffers(&self.device, &self.queue, &mut encoder, &paint_jobs, &screen_descriptor);
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("egui Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.egui_renderer.render(&mut rpass, &paint_jobs, &screen_descriptor);
+        }
+
+        for id in &egui_output.textures_delta.free {
+            self.egui_renderer.free_texture(id);
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        self.screen_labels.map_probes();
+        self.ruler.map_probe();
+        output.present();
+
+        Ok(())
+    }
+
+    // Draws the scene, with its overlay and depth probes, into the HDR target(s)
+    fn encode_frame_scene(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.update_overlay();
+        if self.detail_view.enabled {
+            self.encode_detail_view();
+        }
+        match self.post_process.stereo_mode() {
+            StereoMode::Off if self.viewport_layout != ViewportLayout::Single => self.encode_split_scene(encoder, self.post_process.hdr_view(), &self.depth_texture_view, self.scene_size),
+            StereoMode::Off if self.still_mode => self.encode_still_sample(encoder),
+            StereoMode::Off => {
+                self.write_camera_uniforms(&self.camera, self.scene_size.height);
+                self.encode_scene(encoder, self.post_process.hdr_view(), &self.depth_texture_view, true, None, true);
+                self.screen_labels.encode_probes(&self.device, &self.queue, encoder, &self.depth_texture);
+                self.ruler.encode_probe(&self.device, &self.queue, encoder, &self.depth_texture, &self.camera);
+            }
+            stereo_mode => {
+                let aspect_ratio = match stereo_mode {
+                    StereoMode::SideBySide => self.camera.aspect_ratio * 0.5,
+                    _ => self.camera.aspect_ratio,
+                };
+                let half_separation = self.eye_separation * self.camera.distance * 0.5;
+                let left_eye = self.camera.stereo_eye(-half_separation, aspect_ratio);
+                let right_eye = self.camera.stereo_eye(half_separation, aspect_ratio);
+
+                // The left eye is submitted on its own so the camera buffer can be rewritten for the right eye
+                let mut left_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Left Eye Encoder"),
+                });
+                self.write_camera_uniforms(&left_eye, self.scene_size.height);
+                self.encode_scene(&mut left_encoder, self.post_process.hdr_view(), &self.depth_texture_view, true, None, true);
+                self.queue.submit(std::iter::once(left_encoder.finish()));
+
+                self.write_camera_uniforms(&right_eye, self.scene_size.height);
+                self.encode_scene(encoder, self.post_process.hdr_right_view(), &self.depth_texture_view, true, None, true);
+            }
+        }
+        self.scene_stale = false;
+    }
+
+    // Adds a jittered sample of the main view to the high quality still.
+    // Once it has converged nothing is drawn, and the HDR target keeps the
+    // average.
+    fn encode_still_sample(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let size = self.scene_size;
+        if self.still.as_ref().is_none_or(|still| still.size() != (size.width, size.height)) {
+            self.still = Some(StillAccumulator::new(&self.device, size.width, size.height));
+        }
+        let signature = self.still_signature();
+        let Some(mut still) = self.still.take() else {
+            return;
+        };
+        still.follow(signature);
+        if still.samples() < self.still_samples {
+            let mut camera = self.camera.clone();
+            camera.jitter = still.jitter();
+            self.write_camera_uniforms(&camera, size.height);
+            self.encode_scene(encoder, still.sample_view(), &self.depth_texture_view, true, None, true);
+            self.screen_labels.encode_probes(&self.device, &self.queue, encoder, &self.depth_texture);
+            self.ruler.encode_probe(&self.device, &self.queue, encoder, &self.depth_texture, &self.camera);
+            still.encode_resolve(&self.queue, encoder, self.post_process.hdr_view());
+        }
+        self.still = Some(still);
+    }
+
+    // What the main view shows, for noticing when the still has to start over
+    fn still_signature(&self) -> Vec<u8> {
+        let camera = &self.camera;
+        let mut signature = bytemuck::cast_slice(&(camera.projection_matrix() * camera.view_matrix()).to_cols_array()).to_vec();
+        let environment = &self.environment.settings;
+        signature.extend_from_slice(bytemuck::bytes_of(&LightsUniform::new(&self.lights).with_environment(
+            self.environment.lighting_intensity(),
+            environment.rotation,
+            SPECULAR_LEVELS,
+        )));
+        signature.extend_from_slice(&environment.background_blur.to_le_bytes());
+        signature.push(self.environment.shows_background() as u8);
+        signature.extend_from_slice(bytemuck::cast_slice(&self.clip_plane.equation()));
+        signature.extend_from_slice(bytemuck::cast_slice(&[self.clip_plane.cap_uniform(), self.clip_plane.contour_uniform()]));
+        signature.extend_from_slice(bytemuck::cast_slice(&self.background_colors));
+        signature.extend_from_slice(&self.morph_weight.to_le_bytes());
+        signature.push(self.active_display_mode() as u8);
+        signature.push(self.vertex_color_space as u8);
+        signature.extend_from_slice(&[self.shading_mode as u8, self.colormap as u8, self.cull_mode as u8, self.backface_tint as u8]);
+        signature.extend_from_slice(bytemuck::cast_slice(&self.backface_color));
+        signature.push(self.clay.enabled as u8);
+        signature.extend_from_slice(bytemuck::cast_slice(&[self.clay.top, self.clay.bottom, self.clay.rim()]));
+        signature.extend_from_slice(bytemuck::cast_slice(&self.line_style.color));
+        signature.push(self.line_style.vertex_colors as u8);
+        signature.extend_from_slice(&self.line_style.width.to_le_bytes());
+        for (object, transform) in self.scene.world_transforms().iter().enumerate() {
+            signature.extend_from_slice(bytemuck::cast_slice(&transform.to_cols_array()));
+            signature.push(self.scene.is_shown(object) as u8);
+        }
+        signature
+    }
+
+    // The layout in effect: stereo takes the whole window
+    fn effective_layout(&self) -> ViewportLayout {
+        match self.post_process.stereo_mode() {
+            StereoMode::Off => self.viewport_layout,
+            _ => ViewportLayout::Single,
+        }
+    }
+
+    // Camera of viewport `index` of the split layout, with its aspect ratio,
+    // and its render mode (`None` for the main viewport)
+    fn viewport_camera(&self, index: usize) -> (Camera, Option<RenderMode>) {
+        let (mut camera, mode) = match index.checked_sub(1).and_then(|index| self.viewports.get(index)) {
+            Some(viewport) => (viewport.camera.clone(), Some(viewport.mode)),
+            None => (self.camera.clone(), None),
+        };
+        if let Some(rect) = self.effective_layout().rects().get(index) {
+            camera.aspect_ratio = self.camera.aspect_ratio * rect[2] / rect[3];
+        }
+        (camera, mode)
+    }
+
+    // Renders the magnified region around the cursor into the detail view's
+    // targets. It's submitted on its own, before the main view rewrites the
+    // camera buffer.
+    fn encode_detail_view(&self) {
+        let (Some(ndc), Some((post_process, depth_view, color_view))) = (self.detail_view.cursor_ndc, self.detail_view.attachments()) else {
+            return;
+        };
+        let (camera, mode) = self.viewport_camera(self.detail_view.viewport);
+        let camera = camera.magnified(ndc, self.detail_view.magnification, 1.0);
+        post_process.update(&self.queue, 0.0);
+        self.view_mode.set(mode);
+        self.write_camera_uniforms(&camera, DETAIL_SIZE);
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Detail View Encoder"),
+        });
+        self.encode_scene(&mut encoder, post_process.hdr_view(), depth_view, true, None, true);
+        post_process.encode(&mut encoder, color_view);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        self.view_mode.set(None);
+    }
+
+    // Draws each viewport of the split layout with its own camera and render
+    // mode. All but the last are submitted on their own so the camera buffer
+    // can be rewritten for the next one.
+    fn encode_split_scene(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        color_view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        let (width, height) = (size.width as f32, size.height as f32);
+        let rects = self.viewport_layout.rects();
+        let mut cleared = false;
+        for (i, rect) in rects.iter().enumerate() {
+            // Rounding both edges keeps neighbouring viewports from overlapping or leaving gaps
+            let (left, top) = ((rect[0] * width).round(), (rect[1] * height).round());
+            let (right, bottom) = (((rect[0] + rect[2]) * width).round(), ((rect[1] + rect[3]) * height).round());
+            // A window only a few pixels across leaves some viewports without any
+            if right <= left || bottom <= top {
+                continue;
+            }
+            let (mut camera, mode) = self.viewport_camera(i);
+            camera.aspect_ratio = (right - left) / (bottom - top).max(1.0);
+            self.view_mode.set(mode);
+            self.write_camera_uniforms(&camera, (bottom - top) as u32);
+            let viewport = Some([left, top, right - left, bottom - top]);
+            if i + 1 < rects.len() {
+                let mut viewport_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Viewport Encoder"),
+                });
+                self.encode_scene(&mut viewport_encoder, color_view, depth_view, true, viewport, !cleared);
+                self.queue.submit(std::iter::once(viewport_encoder.finish()));
+            } else {
+                self.encode_scene(encoder, color_view, depth_view, true, viewport, !cleared);
+            }
+            cleared = true;
+        }
+        self.view_mode.set(None);
+    }
+
     pub fn get_performance_stats(&self) -> crate::performance::PerformanceStats {
         self.performance_monitor.get_stats()
     }
-} 
\ No newline at end of file
+}
+
+fn scaled_size(size: winit::dpi::PhysicalSize<u32>, scale: f32, max_dimension: u32) -> winit::dpi::PhysicalSize<u32> {
+    let scale_dimension = |dimension: u32| ((dimension as f32 * scale).round() as u32).clamp(1, max_dimension);
+    winit::dpi::PhysicalSize::new(scale_dimension(size.width), scale_dimension(size.height))
+}
+
+// Nodes whose names contain `query`, ignoring case; none for an empty query
+fn scene_search(scene: &Scene, query: &str) -> Vec<usize> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    (0..scene.nodes.len()).filter(|&node| scene.nodes[node].name.to_lowercase().contains(&query)).collect()
+}
+
+/// Pipelines drawing shaded surfaces, one set per cull mode.
+struct SurfacePipelines {
+    cull_mode: CullMode,
+    shaded: wgpu::RenderPipeline,
+    // Fills pushed back in depth for hidden-line mode
+    hidden_line: wgpu::RenderPipeline,
+    // Variants reading `QuantizedVertex` buffers
+    quantized: wgpu::RenderPipeline,
+    quantized_hidden_line: wgpu::RenderPipeline,
+}
+
+impl SurfacePipelines {
+    fn new(device: &Device, layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule, cull_mode: CullMode) -> Self {
+        let create_render_pipeline = |label, entry_point, buffers: &[wgpu::VertexBufferLayout], bias| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point,
+                    buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: HDR_FORMAT,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: cull_mode.face(),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias,
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+        let unbiased = wgpu::DepthBiasState::default();
+        // Pushes the fills back far enough that the edges on top of them pass the depth test
+        let hidden_line_bias = wgpu::DepthBiasState {
+            constant: 4,
+            slope_scale: 2.0,
+            clamp: 0.0,
+        };
+        let mesh_buffers = [Vertex::desc(), Vertex::morph_desc()];
+        let quantized_buffers = [QuantizedVertex::desc()];
+        Self {
+            cull_mode,
+            shaded: create_render_pipeline("Render Pipeline", "vs_main", &mesh_buffers, unbiased),
+            quantized: create_render_pipeline("Quantized Render Pipeline", "vs_quantized", &quantized_buffers, unbiased),
+            hidden_line: create_render_pipeline("Hidden Line Pipeline", "vs_main", &mesh_buffers, hidden_line_bias),
+            quantized_hidden_line: create_render_pipeline("Quantized Hidden Line Pipeline", "vs_quantized", &quantized_buffers, hidden_line_bias),
+        }
+    }
+}
+
+// Lights plus the environment cubes, black without an environment
+fn create_light_bind_group(
+    device: &Device,
+    layout: &wgpu::BindGroupLayout,
+    light_uniform_buffer: &wgpu::Buffer,
+    environment: &Environment,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Light Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(environment.irradiance_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(environment.specular_view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(environment.sampler()),
+            },
+        ],
+    })
+}
+
+fn create_depth_texture(device: &Device, size: winit::dpi::PhysicalSize<u32>) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Depth Texture"),
+        size: wgpu::Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Depth32Float,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
@@ -0,0 +1,205 @@
+use crate::analysis::{self, ColorMode, QualityMetric};
+use crate::renderer::{Renderer, UiAction};
+
+impl Renderer {
+    pub(super) fn show_dimensions_window(&mut self) {
+        if let Some((min, max)) = self.model_bounds {
+            let size = max - min;
+            let show_box = &mut self.overlay_settings.bounding_box;
+            egui::Window::new("Dimensions")
+                .default_open(false)
+                .resizable(false)
+                .show(&self.egui_ctx, |ui| {
+                    egui::Grid::new("dimensions").num_columns(2).show(ui, |ui| {
+                        for (label, value) in [("Width (X)", size.x), ("Height (Y)", size.y), ("Depth (Z)", size.z), ("Diagonal", size.length())] {
+                            ui.label(label);
+                            ui.label(format!("{:.4}", value));
+                            ui.end_row();
+                        }
+                        ui.label("Min");
+                        ui.label(format!("({:.4}, {:.4}, {:.4})", min.x, min.y, min.z));
+                        ui.end_row();
+                        ui.label("Max");
+                        ui.label(format!("({:.4}, {:.4}, {:.4})", max.x, max.y, max.z));
+                        ui.end_row();
+                    });
+                    // The file has no units; spell out both common readings of the largest side
+                    let largest = size.max_element();
+                    ui.label(format!("Largest side: {:.3} m if in meters, {:.3} m if in millimeters", largest, largest * 0.001));
+                    ui.checkbox(show_box, "Show bounding box");
+                });
+        }
+    }
+
+    pub(super) fn show_validation_window(&mut self) {
+        let mut color_mode = self.color_mode;
+        let mut quality = self.quality_settings;
+        let quality_failures = self.quality_failures;
+        if let Some(topology) = &self.topology {
+            egui::Window::new("Validation")
+                .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+                .resizable(false)
+                .collapsible(true)
+                .default_open(false)
+                .show(&self.egui_ctx, |ui| {
+                    egui::Grid::new("topology_totals").num_columns(2).show(ui, |ui| {
+                        ui.label("Vertices");
+                        ui.label(topology.vertices.to_string());
+                        ui.end_row();
+                        ui.label("Edges");
+                        ui.label(topology.edges.to_string());
+                        ui.end_row();
+                        ui.label("Faces");
+                        ui.label(topology.faces.to_string());
+                        ui.end_row();
+                        ui.label("Euler characteristic");
+                        ui.label(topology.euler_characteristic.to_string());
+                        ui.end_row();
+                        ui.label("Components");
+                        ui.label(topology.components.len().to_string());
+                        ui.end_row();
+                        ui.label("Boundary edges");
+                        ui.label(topology.boundary_edges.to_string());
+                        ui.end_row();
+                        ui.label("Non-manifold edges");
+                        ui.label(topology.non_manifold_edges.to_string());
+                        ui.end_row();
+                    });
+                    ui.separator();
+                    egui::ComboBox::from_label("Color by")
+                        .selected_text(color_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in ColorMode::ALL {
+                                ui.selectable_value(&mut color_mode, mode, mode.label());
+                            }
+                        });
+                    if color_mode == ColorMode::Quality {
+                        let previous_metric = quality.metric;
+                        egui::ComboBox::from_label("Metric")
+                            .selected_text(quality.metric.label())
+                            .show_ui(ui, |ui| {
+                                for metric in QualityMetric::ALL {
+                                    ui.selectable_value(&mut quality.metric, metric, metric.label());
+                                }
+                            });
+                        if quality.metric != previous_metric {
+                            quality.threshold = quality.metric.default_threshold();
+                        }
+                        ui.add(
+                            egui::Slider::new(&mut quality.threshold, quality.metric.threshold_range())
+                                .logarithmic(quality.metric == QualityMetric::Area)
+                                .text("Threshold"),
+                        );
+                        ui.label(format!(
+                            "{} of {} triangles past threshold",
+                            quality_failures, topology.faces
+                        ));
+                    }
+                    ui.separator();
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        egui::Grid::new("topology_components").num_columns(4).striped(true).show(ui, |ui| {
+                            ui.strong("Component");
+                            ui.strong("Faces");
+                            ui.strong("Euler");
+                            ui.strong("Genus");
+                            ui.end_row();
+                            for (i, component) in topology.components.iter().enumerate() {
+                                if color_mode == ColorMode::Components {
+                                    let [r, g, b] = analysis::palette_color(i as u32);
+                                    let swatch = egui::Color32::from_rgb((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8);
+                                    ui.colored_label(swatch, i.to_string());
+                                } else {
+                                    ui.label(i.to_string());
+                                }
+                                ui.label(component.faces.to_string());
+                                ui.label(component.euler_characteristic.to_string());
+                                match component.genus() {
+                                    Some(genus) => ui.label(genus.to_string()),
+                                    None if !component.is_manifold() => ui.label(format!("non-manifold ({} edges)", component.non_manifold_edges)),
+                                    None if !component.is_closed() => ui.label(format!("open ({} boundary edges)", component.boundary_edges)),
+                                    None if !component.is_consistently_oriented() => ui.label(format!("flipped faces ({} edges)", component.flipped_edges)),
+                                    None => ui.label(format!("pinched ({} vertices)", component.pinched_vertices)),
+                                };
+                                ui.end_row();
+                            }
+                        });
+                    });
+                });
+        }
+        self.set_color_mode(color_mode, quality);
+    }
+
+    // Legend of the Materials color mode
+    pub(super) fn show_material_ids_window(&mut self) {
+        if self.color_mode == ColorMode::Materials && self.has_mesh {
+            // Triangles per material index, the last entry for faces without one
+            let materials = &self.mesh.materials;
+            let mut faces = vec![0u32; materials.len() + 1];
+            for range in &self.mesh.draw_ranges {
+                let slot = range.material.filter(|&material| material < materials.len()).unwrap_or(materials.len());
+                faces[slot] += range.count / 3;
+            }
+            egui::Window::new("Material IDs")
+                .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -250.0])
+                .resizable(false)
+                .collapsible(true)
+                .show(&self.egui_ctx, |ui| {
+                    egui::ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                        egui::Grid::new("material_legend").num_columns(3).striped(true).show(ui, |ui| {
+                            ui.strong("ID");
+                            ui.strong("Material");
+                            ui.strong("Faces");
+                            ui.end_row();
+                            for (slot, &count) in faces.iter().enumerate().filter(|&(_, &count)| count > 0) {
+                                let material = (slot < materials.len()).then_some(slot);
+                                let [r, g, b] = analysis::material_color(material);
+                                let swatch = egui::Color32::from_rgb((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8);
+                                ui.colored_label(swatch, material.map_or("-".to_string(), |material| material.to_string()));
+                                ui.label(material.map_or("(none)", |material| materials[material].name.as_str()));
+                                ui.label(count.to_string());
+                                ui.end_row();
+                            }
+                        });
+                    });
+                });
+        }
+    }
+
+    pub(super) fn show_parts_window(&mut self) {
+        if self.parts.len() > 1 {
+            let parts = &self.parts;
+            let ui_actions = &mut self.ui_actions;
+            egui::Window::new("Parts")
+                .default_open(false)
+                .show(&self.egui_ctx, |ui| {
+                    let total_triangles: usize = parts.iter().map(|part| part.triangles).sum();
+                    ui.label(format!("{} parts, {} triangles", parts.len(), total_triangles));
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        egui::Grid::new("parts_list").num_columns(4).striped(true).show(ui, |ui| {
+                            ui.strong("Name");
+                            ui.strong("Triangles");
+                            ui.strong("Size");
+                            ui.strong("Volume");
+                            ui.end_row();
+                            for part in parts {
+                                let [x, y, z] = part.size;
+                                ui.label(&part.name);
+                                ui.label(part.triangles.to_string());
+                                ui.label(format!("{:.3} × {:.3} × {:.3}", x, y, z));
+                                if part.closed {
+                                    ui.label(format!("{:.4}", part.volume));
+                                } else {
+                                    ui.label(format!("~{:.4}", part.volume))
+                                        .on_hover_text("Open shell, the volume is only an estimate");
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    if ui.button("Export CSV...").clicked() {
+                        ui_actions.push(UiAction::ExportParts);
+                    }
+                });
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use tracing::info;
+
+use crate::camera::SensorFormat;
+use crate::renderer::Renderer;
+use crate::view_state::ViewState;
+
+impl Renderer {
+    pub(super) fn show_camera_window(&mut self) {
+        // Pivots the Camera window offers, for the selected object if there is one
+        let selected = self.selected_node.filter(|&node| node < self.scene.nodes.len());
+        let object_origin = selected.map(|node| self.scene.world_transforms()[node].transform_point3(self.scene.nodes[node].pivot));
+        let bounds_center = selected.and_then(|node| self.object_world_bounds(node)).or(self.model_bounds).map(|(min, max)| (min + max) * 0.5);
+        let mut pivot = None;
+        let camera = &self.camera;
+        let (mut position, mut target) = (camera.position, camera.target);
+        let (mut yaw, mut pitch) = (camera.yaw.to_degrees(), camera.pitch.to_degrees());
+        let (mut distance, mut fov) = (camera.distance, camera.fov.to_degrees());
+        let mut sensor = self.sensor;
+        let mut focal_length = sensor.focal_length(camera.fov);
+        let mut lens_changed = false;
+        let (mut position_changed, mut orbit_changed) = (false, false);
+        let mut copy_view = false;
+        let mut paste_view = false;
+        let view_text = &mut self.view_text;
+        let view_text_error = &self.view_text_error;
+        egui::Window::new("Camera")
+            .default_open(false)
+            .resizable(false)
+            .show(&self.egui_ctx, |ui| {
+                let speed = (distance * 0.01).max(1e-4);
+                egui::Grid::new("camera_fields").num_columns(2).show(ui, |ui| {
+                    ui.label("Position");
+                    ui.horizontal(|ui| {
+                        for value in [&mut position.x, &mut position.y, &mut position.z] {
+                            position_changed |= ui.add(egui::DragValue::new(value).speed(speed).max_decimals(5)).changed();
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("Target");
+                    ui.horizontal(|ui| {
+                        for value in [&mut target.x, &mut target.y, &mut target.z] {
+                            orbit_changed |= ui.add(egui::DragValue::new(value).speed(speed).max_decimals(5)).changed();
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("Yaw");
+                    orbit_changed |= ui.add(egui::DragValue::new(&mut yaw).speed(0.5).suffix("°").max_decimals(4)).changed();
+                    ui.end_row();
+                    ui.label("Pitch");
+                    orbit_changed |= ui.add(egui::DragValue::new(&mut pitch).speed(0.5).clamp_range(-89.9..=89.9).suffix("°").max_decimals(4)).changed();
+                    ui.end_row();
+                    ui.label("Distance");
+                    orbit_changed |= ui.add(egui::DragValue::new(&mut distance).speed(speed).clamp_range(1e-3..=f32::MAX).max_decimals(5)).changed();
+                    ui.end_row();
+                    ui.label("Field of view");
+                    orbit_changed |= ui.add(egui::DragValue::new(&mut fov).speed(0.5).clamp_range(1.0..=170.0).suffix("°").max_decimals(3)).changed();
+                    ui.end_row();
+                    ui.label("Focal length");
+                    ui.horizontal(|ui| {
+                        lens_changed |= ui.add(egui::DragValue::new(&mut focal_length).speed(0.5).clamp_range(1.0..=2000.0).suffix(" mm").max_decimals(2)).changed();
+                        for preset in SensorFormat::FOCAL_LENGTHS {
+                            if ui.small_button(format!("{}", preset)).clicked() {
+                                focal_length = preset;
+                                lens_changed = true;
+                            }
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("Sensor");
+                    egui::ComboBox::from_id_source("camera_sensor")
+                        .selected_text(sensor.label())
+                        .show_ui(ui, |ui| {
+                            for format in SensorFormat::ALL {
+                                // Keeps the focal length, so the view widens or narrows
+                                lens_changed |= ui.selectable_value(&mut sensor, format, format.label()).changed();
+                            }
+                        })
+                        .response
+                        .on_hover_text("The sensor's height spans the view's; render at the photo's aspect ratio to match its framing");
+                    ui.end_row();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Orbit around").on_hover_text("Or double-click the model to orbit around the point under the cursor");
+                    if ui.add_enabled(object_origin.is_some(), egui::Button::new("Object origin")).on_hover_text("The selected object's center of rotation").clicked() {
+                        pivot = object_origin;
+                    }
+                    if ui.add_enabled(bounds_center.is_some(), egui::Button::new("Bounds center")).on_hover_text("Of the selected object, or else the whole model").clicked() {
+                        pivot = bounds_center;
+                    }
+                    if ui.button("World origin").clicked() {
+                        pivot = Some(glam::Vec3::ZERO);
+                    }
+                });
+                ui.separator();
+                copy_view = ui.button("Copy view as text").clicked();
+                ui.add(egui::TextEdit::multiline(view_text).desired_rows(2).hint_text("Paste a copied view here"));
+                paste_view = ui.add_enabled(!view_text.trim().is_empty(), egui::Button::new("Apply pasted view")).clicked();
+                if let Some(error) = view_text_error {
+                    ui.colored_label(egui::Color32::from_rgb(230, 90, 90), error);
+                }
+            });
+        self.sensor = sensor;
+        if lens_changed {
+            fov = sensor.fov(focal_length).to_degrees().clamp(1.0, 170.0);
+            orbit_changed = true;
+        }
+        if orbit_changed {
+            self.camera.target = target;
+            self.camera.yaw = yaw.to_radians();
+            self.camera.pitch = pitch.to_radians();
+            self.camera.distance = distance;
+            self.camera.fov = fov.to_radians();
+            self.camera.update_position();
+        } else if position_changed {
+            self.camera.set_position(position);
+        }
+        if let Some(pivot) = pivot {
+            self.set_orbit_pivot(0, pivot);
+        }
+        if copy_view {
+            let text = self.view_state().to_text();
+            self.egui_ctx.output_mut(|output| output.copied_text = text);
+            info!("Copied view to the clipboard");
+        }
+        if paste_view {
+            match ViewState::from_text(&self.view_text) {
+                Ok(state) => {
+                    self.apply_view_state(&state);
+                    self.view_text_error = None;
+                    info!("Applied pasted view");
+                }
+                Err(e) => self.view_text_error = Some(format!("{:#}", e)),
+            }
+        }
+    }
+}
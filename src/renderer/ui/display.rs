@@ -0,0 +1,348 @@
+use crate::color::ColorSpace;
+use crate::mesh::Vertex;
+use crate::postprocess::StereoMode;
+use crate::quantize::QuantizedVertex;
+use crate::renderer::{Renderer, UiAction, DEFAULT_BACKGROUND, MAX_RENDER_SCALE, MIN_RENDER_SCALE};
+use crate::shading::{Colormap, CullMode, LineStyle, NormalMode, ShadingMode, VertexColorSpace};
+
+impl Renderer {
+    pub(super) fn show_display_window(&mut self) {
+        let safe_mode = self.safe_mode;
+        let mut render_scale = self.render_scale;
+        let scene_size = self.scene_size;
+        let governor = &mut self.quality_governor;
+        let mut scene_fps_limited = self.scene_fps_limit.is_some();
+        let mut scene_fps = self.scene_fps_limit.unwrap_or(30);
+        let mut quantized = self.mesh.quantized().is_some();
+        let can_quantize = self.has_mesh && self.morph_target.is_none() && self.streamer.is_none();
+        let mut geometry_budget_mb = self.geometry_budget_mb;
+        let mut texture_budget_mb = (self.texture_cache.budget >> 20) as u32;
+        let shading = &mut self.shading_options;
+        let vertex_color_space = &mut self.vertex_color_space;
+        let shading_mode = &mut self.shading_mode;
+        let mut cull_mode = self.cull_mode;
+        let backface_tint = &mut self.backface_tint;
+        let backface_color = &mut self.backface_color;
+        let colormap = &mut self.colormap;
+        let line_style = &mut self.line_style;
+        let model_normals = self.has_mesh.then_some(self.mesh.normals);
+        let mut reshade = false;
+        let position_step = self.mesh.quantized().map(|quantized| quantized.position_step());
+        let vertex_bytes = self.mesh.vertices.len() * if quantized { std::mem::size_of::<QuantizedVertex>() } else { std::mem::size_of::<Vertex>() };
+        let mut stereo_mode = self.post_process.stereo_mode();
+        let eye_separation = &mut self.eye_separation;
+        let auto_exposure_supported = self.post_process.supports_auto_exposure();
+        let exposure = &mut self.post_process.exposure;
+        let overlay_settings = &mut self.overlay_settings;
+        let ruler_enabled = &mut self.ruler.enabled;
+        let measure = &mut self.measure;
+        let inspect = &mut self.inspect;
+        let mut headlight = self.headlight;
+        let move_light = &mut self.move_light;
+        let clip_plane = &mut self.clip_plane;
+        let section_name = &mut self.section_name;
+        let normal_lines = &mut self.normal_lines.settings;
+        let model_bounds = self.model_bounds;
+        let mut transparent = self.transparent_background;
+        let background_mode = self.display_mode;
+        let background_color = &mut self.background_colors[background_mode as usize];
+        let mut alpha_mode = self.config.alpha_mode;
+        let alpha_modes = &self.surface_alpha_modes;
+        let wide_gamut_surface = self.wide_gamut_surface;
+        let color_space = &mut self.post_process.color_space;
+        egui::Window::new("Display")
+            .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+            .resizable(false)
+            .collapsible(true)
+            .show(&self.egui_ctx, |ui| {
+                ui.add(
+                    egui::Slider::new(&mut render_scale, MIN_RENDER_SCALE..=MAX_RENDER_SCALE)
+                        .step_by(0.05)
+                        .text("Render scale"),
+                );
+                ui.label(format!("Scene: {}x{}", scene_size.width, scene_size.height));
+                ui.checkbox(&mut governor.enabled, "Adaptive quality");
+                ui.add_enabled(
+                    governor.enabled,
+                    egui::Slider::new(&mut governor.target_fps, 15.0..=144.0).text("Target FPS"),
+                );
+                ui.checkbox(&mut scene_fps_limited, "Limit scene FPS");
+                ui.add_enabled(scene_fps_limited, egui::Slider::new(&mut scene_fps, 5..=120).text("Scene FPS"));
+                ui.add_enabled(can_quantize, egui::Checkbox::new(&mut quantized, "Quantize vertices"))
+                    .on_hover_text("16-bit positions within the model bounds and compressed normals, for very large models");
+                if can_quantize {
+                    ui.label(format!("Vertices: {:.1} MB", vertex_bytes as f64 / (1 << 20) as f64));
+                }
+                if let Some(step) = position_step {
+                    ui.label(format!("Position error: up to {:.2e}", step));
+                }
+                ui.separator();
+                egui::ComboBox::from_label("Display mode")
+                    .selected_text(shading_mode.label())
+                    .show_ui(ui, |ui| {
+                        for mode in ShadingMode::ALL {
+                            ui.selectable_value(shading_mode, mode, mode.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Color surfaces by their normal, height or distance from the centroid instead of lighting them");
+                ui.add_enabled_ui(shading_mode.uses_colormap(), |ui| {
+                    egui::ComboBox::from_label("Colormap")
+                        .selected_text(colormap.label())
+                        .show_ui(ui, |ui| {
+                            for map in Colormap::ALL {
+                                ui.selectable_value(colormap, map, map.label());
+                            }
+                        });
+                });
+                egui::ComboBox::from_label("Cull faces")
+                    .selected_text(cull_mode.label())
+                    .show_ui(ui, |ui| {
+                        for mode in CullMode::ALL {
+                            ui.selectable_value(&mut cull_mode, mode, mode.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Front faces wind counter-clockwise; culling none draws both sides");
+                ui.horizontal(|ui| {
+                    ui.checkbox(backface_tint, "Tint back faces").on_hover_text("Shows flipped or inside-out faces at a glance");
+                    ui.add_enabled_ui(*backface_tint, |ui| ui.color_edit_button_rgb(backface_color));
+                });
+                egui::ComboBox::from_label("Normals")
+                    .selected_text(shading.normals.label())
+                    .show_ui(ui, |ui| {
+                        for mode in NormalMode::ALL {
+                            ui.selectable_value(&mut shading.normals, mode, mode.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Smooth averages normals per position, flat gives every face its own; applies to models loaded afterwards");
+                ui.add_enabled(
+                    shading.normals == NormalMode::Smooth,
+                    egui::Slider::new(&mut shading.smoothing_angle, 0.0..=180.0).suffix("°").text("Smoothing angle"),
+                )
+                .on_hover_text("Faces meeting at a sharper angle keep a hard edge");
+                if let Some(normals) = model_normals {
+                    reshade = ui
+                        .button("Reload with these normals")
+                        .on_hover_text(format!("The model was loaded with {} normals", normals.label().to_lowercase()))
+                        .clicked();
+                }
+                egui::ComboBox::from_label("Vertex colors")
+                    .selected_text(vertex_color_space.label())
+                    .show_ui(ui, |ui| {
+                        for space in VertexColorSpace::ALL {
+                            ui.selectable_value(vertex_color_space, space, space.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("sRGB decodes the file's vertex colors before shading; try it when they look washed out");
+                ui.horizontal(|ui| {
+                    ui.color_edit_button_rgb(&mut line_style.color);
+                    ui.label("Lines");
+                    ui.checkbox(&mut line_style.vertex_colors, "Vertex colors")
+                        .on_hover_text("Color the wireframe by vertex; hidden-line edges always use the line color");
+                });
+                ui.add(egui::Slider::new(&mut line_style.width, 1..=LineStyle::MAX_WIDTH).suffix(" px").text("Line width"));
+                ui.add(egui::Slider::new(&mut geometry_budget_mb, 64..=16384).logarithmic(true).text("Geometry budget (MB)"))
+                    .on_hover_text("Clustered models needing more GPU memory are streamed; applies to the current streamed model and to models loaded afterwards");
+                ui.add(egui::Slider::new(&mut texture_budget_mb, 0..=8192).text("Texture cache (MB)"))
+                    .on_hover_text("Textures of closed models are kept for reopening them until the cache outgrows this");
+                // Post-processing stays at its defaults in safe mode
+                ui.add_enabled_ui(!safe_mode, |ui| {
+                    ui.separator();
+                    egui::ComboBox::from_label("Stereo")
+                        .selected_text(stereo_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in StereoMode::ALL {
+                                ui.selectable_value(&mut stereo_mode, mode, mode.label());
+                            }
+                        });
+                    ui.add_enabled(
+                        stereo_mode != StereoMode::Off,
+                        egui::Slider::new(eye_separation, 0.0..=0.15).text("Eye separation"),
+                    );
+                    ui.separator();
+                    ui.add_enabled(auto_exposure_supported, egui::Checkbox::new(&mut exposure.auto_exposure, "Auto exposure (E)"))
+                        .on_disabled_hover_text("Needs compute shaders, which the adapter lacks");
+                    ui.add(egui::Slider::new(&mut exposure.exposure_compensation, -4.0..=4.0).text("EV"));
+                    ui.add_enabled(
+                        exposure.auto_exposure,
+                        egui::Slider::new(&mut exposure.adaptation_speed, 0.1..=10.0).text("Adaptation speed"),
+                    );
+                    ui.add_enabled(
+                        exposure.auto_exposure,
+                        egui::Slider::new(&mut exposure.key_value, 0.05..=0.5).text("Key value"),
+                    );
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut overlay_settings.grid, "Grid");
+                    ui.checkbox(&mut overlay_settings.axes, "Axes");
+                    ui.checkbox(&mut overlay_settings.object_labels, "Object labels");
+                    ui.checkbox(&mut overlay_settings.light, "Light");
+                    ui.checkbox(&mut overlay_settings.bounding_box, "Bounds");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Normals");
+                    ui.checkbox(&mut normal_lines.vertex, "Vertex")
+                        .on_hover_text("Red where a normal points against its faces' winding, magenta where it's missing");
+                    ui.checkbox(&mut normal_lines.face, "Face");
+                    ui.add_enabled(
+                        normal_lines.enabled(),
+                        egui::Slider::new(&mut normal_lines.length, 0.1..=10.0).logarithmic(true).suffix("%").text("Length"),
+                    )
+                    .on_hover_text("Percent of the model's diagonal");
+                });
+                ui.checkbox(ruler_enabled, "Ruler (R)");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut measure.enabled, "Measure (M)").on_hover_text("Click the model to measure between the vertices nearest the clicks");
+                    ui.add_enabled_ui(measure.enabled, |ui| {
+                        ui.checkbox(&mut measure.polyline, "Polyline").on_hover_text("Keep adding points instead of starting over after two");
+                        if ui.add_enabled(!measure.points().is_empty(), egui::Button::new("Undo")).clicked() {
+                            measure.undo();
+                        }
+                        if ui.add_enabled(!measure.points().is_empty(), egui::Button::new("Clear")).clicked() {
+                            measure.clear();
+                        }
+                    });
+                });
+                if measure.enabled && measure.points().len() > 1 {
+                    ui.label(format!("Distance: {:.4}", measure.total()));
+                }
+                ui.checkbox(inspect, "Inspect faces").on_hover_text("Hover the model for the face and vertex data under the cursor; click to keep it in a window");
+                ui.checkbox(&mut headlight, "Headlight (L)").on_hover_text("The first light follows the camera");
+                ui.checkbox(move_light, "Move light").on_hover_text("Drag the arrows at the light, or the square to slide it horizontally");
+                ui.horizontal(|ui| {
+                    if ui.checkbox(&mut clip_plane.enabled, "Section plane").changed() && clip_plane.enabled && clip_plane.point == glam::Vec3::ZERO {
+                        clip_plane.reset(model_bounds);
+                    }
+                    ui.add_enabled_ui(clip_plane.enabled, |ui| {
+                        ui.checkbox(&mut clip_plane.editing, "Handles");
+                        if ui.button("Flip").clicked() {
+                            clip_plane.flip();
+                        }
+                        if ui.button("Reset").clicked() {
+                            clip_plane.reset(model_bounds);
+                        }
+                    });
+                });
+                ui.add_enabled_ui(clip_plane.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        for (label, axis) in [("X", glam::Vec3::X), ("Y", glam::Vec3::Y), ("Z", glam::Vec3::Z)] {
+                            if ui.small_button(label).on_hover_text("Face the plane along this axis").clicked() {
+                                clip_plane.align(axis);
+                            }
+                        }
+                        let mut offset = clip_plane.offset();
+                        let size = model_bounds.map_or(1.0, |(min, max)| (max - min).length());
+                        if ui.add(egui::DragValue::new(&mut offset).speed(size * 0.002).prefix("Offset ")).changed() {
+                            clip_plane.set_offset(offset);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut clip_plane.cap, "Cap")
+                            .on_hover_text("Fill the cut in, where the part is closed");
+                        ui.add_enabled_ui(clip_plane.cap, |ui| ui.color_edit_button_rgb(&mut clip_plane.cap_color));
+                        ui.checkbox(&mut clip_plane.contour, "Contour").on_hover_text("Outline where the plane cuts the surface");
+                    });
+                });
+                ui.collapsing(format!("Saved sections ({})", clip_plane.sections.len()), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(section_name).desired_width(120.0).hint_text("Name"));
+                        let name = section_name.trim();
+                        if ui.add_enabled(clip_plane.enabled && !name.is_empty(), egui::Button::new("Save")).on_hover_text("Keep the plane as it is under this name").clicked() {
+                            clip_plane.save_section(name);
+                            section_name.clear();
+                        }
+                    });
+                    let mut applied = None;
+                    let mut removed = None;
+                    for (index, section) in clip_plane.sections.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.button(&section.name).on_hover_text("Cut the model here").clicked() {
+                                applied = Some(index);
+                            }
+                            if ui.small_button("✕").on_hover_text("Delete").clicked() {
+                                removed = Some(index);
+                            }
+                        });
+                    }
+                    if let Some(index) = applied {
+                        clip_plane.apply_section(index);
+                    }
+                    if let Some(index) = removed {
+                        clip_plane.remove_section(index);
+                    }
+                });
+                ui.separator();
+                ui.add_enabled_ui(!transparent, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.color_edit_button_rgb(background_color);
+                        ui.label(format!("Background ({})", background_mode.label()))
+                            .on_hover_text("Each display mode keeps its own background");
+                        if ui.small_button("Reset").clicked() {
+                            *background_color = DEFAULT_BACKGROUND;
+                        }
+                    });
+                });
+                ui.checkbox(&mut transparent, "Transparent window");
+                ui.add_enabled_ui(alpha_modes.len() > 1, |ui| {
+                    egui::ComboBox::from_label("Surface alpha")
+                        .selected_text(format!("{:?}", alpha_mode))
+                        .show_ui(ui, |ui| {
+                            for &mode in alpha_modes {
+                                ui.selectable_value(&mut alpha_mode, mode, format!("{:?}", mode));
+                            }
+                        });
+                });
+                // Offered where the surface suggests a wide-gamut display, or to switch back
+                ui.add_enabled_ui(wide_gamut_surface || *color_space != ColorSpace::Srgb, |ui| {
+                    egui::ComboBox::from_label("Output colors")
+                        .selected_text(color_space.label())
+                        .show_ui(ui, |ui| {
+                            for space in ColorSpace::ALL {
+                                ui.selectable_value(color_space, space, space.label());
+                            }
+                        });
+                })
+                .response
+                .on_hover_text(if wide_gamut_surface {
+                    "Wide-gamut display detected. Display P3 shows colors as intended on displays that don't convert them; screenshots are tagged to match"
+                } else {
+                    "No wide-gamut display detected"
+                });
+            });
+        if alpha_mode != self.config.alpha_mode {
+            self.config.alpha_mode = alpha_mode;
+            self.surface_configured = false;
+        }
+        // Built when first shown rather than for every model loaded
+        if self.normal_lines.settings.enabled() && !self.normal_lines.is_built() && self.has_mesh {
+            self.normal_lines.build(&self.device, &self.mesh);
+        }
+        if headlight != self.headlight {
+            self.toggle_headlight();
+        }
+        if transparent != self.transparent_background {
+            self.set_transparent_background(transparent);
+        }
+        self.set_render_scale(render_scale);
+        self.set_cull_mode(cull_mode);
+        self.update_surface_pipelines();
+        self.scene_fps_limit = scene_fps_limited.then_some(scene_fps);
+        self.set_vertex_quantization(quantized);
+        self.geometry_budget_mb = geometry_budget_mb;
+        if texture_budget_mb as usize != self.texture_cache.budget >> 20 {
+            self.texture_cache.budget = texture_budget_mb as usize * (1 << 20);
+            self.texture_cache.trim();
+        }
+        if let Some(streamer) = &mut self.streamer {
+            streamer.budget = geometry_budget_mb as usize * (1 << 20);
+        }
+        self.post_process.set_stereo_mode(&self.device, stereo_mode);
+        if reshade {
+            self.ui_actions.push(UiAction::ReparseModel);
+        }
+    }
+}
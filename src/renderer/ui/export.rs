@@ -0,0 +1,72 @@
+use crate::aov::AovFormat;
+use crate::renderer::{Renderer, UiAction};
+use crate::still::StillAccumulator;
+
+impl Renderer {
+    pub(super) fn show_export_window(&mut self) {
+        let export_width = &mut self.export_width;
+        let export_height = &mut self.export_height;
+        let screenshot_scale = &mut self.screenshot_scale;
+        let mut still_mode = self.still_mode;
+        let still_samples = &mut self.still_samples;
+        let still_progress = self.still.as_ref().map_or(0, StillAccumulator::samples);
+        let window_size = self.size;
+        let aov_settings = &mut self.aov_settings;
+        let obj_export_options = &mut self.obj_export_options;
+        let ui_actions = &mut self.ui_actions;
+        egui::Window::new("Export")
+            .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+            .resizable(false)
+            .collapsible(true)
+            .default_open(false)
+            .show(&self.egui_ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Size");
+                    ui.add(egui::DragValue::new(export_width).clamp_range(16..=8192).suffix(" px"));
+                    ui.label("x");
+                    ui.add(egui::DragValue::new(export_height).clamp_range(16..=8192).suffix(" px"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Passes");
+                    ui.checkbox(&mut aov_settings.normal, "Normal");
+                    ui.checkbox(&mut aov_settings.depth, "Depth");
+                    ui.checkbox(&mut aov_settings.object_id, "Object ID");
+                });
+                ui.add_enabled_ui(aov_settings.any(), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Pass format");
+                        ui.radio_value(&mut aov_settings.format, AovFormat::Png, "PNG");
+                        ui.radio_value(&mut aov_settings.format, AovFormat::Exr, "EXR");
+                    });
+                });
+                if ui.button("Export standard views...").clicked() {
+                    ui_actions.push(UiAction::ExportStandardViews);
+                }
+                if ui.button("Export blueprint...").clicked() {
+                    ui_actions.push(UiAction::ExportBlueprint);
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(egui::Slider::new(screenshot_scale, 1..=4).text("Screenshot scale"));
+                    ui.label(format!("{}x{}", window_size.width * *screenshot_scale, window_size.height * *screenshot_scale));
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut still_mode, "High quality still")
+                        .on_hover_text("Averages jittered frames while the view holds still; screenshots take as many samples");
+                    ui.add(egui::Slider::new(still_samples, 4..=256).logarithmic(true).text("samples"));
+                });
+                if still_mode {
+                    ui.label(format!("{} of {} samples", still_progress.min(*still_samples), still_samples));
+                }
+                if ui.button("Save screenshot... (S)").clicked() {
+                    ui_actions.push(UiAction::SaveScreenshot);
+                }
+                ui.separator();
+                ui.checkbox(&mut obj_export_options.weld, "Weld duplicate vertices on OBJ export");
+            });
+        if still_mode != self.still_mode {
+            self.still_mode = still_mode;
+            self.still = None;
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::light::{Light, LightKind, MAX_LIGHTS};
+use crate::presentation::ClaySettings;
+use crate::renderer::{Renderer, UiAction};
+
+impl Renderer {
+    pub(super) fn show_lights_window(&mut self) {
+        let mut lights = self.lights.clone();
+        let mut selected = self.selected_light;
+        let position_speed = self.model_bounds.map_or(0.05, |(min, max)| (max - min).length() * 0.005).max(1e-4);
+        let mut environment = self.environment.settings;
+        let environment_name = self.environment.path().map(|path| path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned());
+        let mut load_environment = false;
+        let mut clear_environment = false;
+        let mut clay = self.clay;
+        egui::Window::new("Lights")
+            .default_open(false)
+            .resizable(false)
+            .show(&self.egui_ctx, |ui| {
+                ui.collapsing("Environment", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(environment_name.as_deref().unwrap_or("None"));
+                        load_environment = ui.button("Load...").on_hover_text("Light the scene with an HDR image").clicked();
+                        clear_environment = ui.add_enabled(environment_name.is_some(), egui::Button::new("Clear")).clicked();
+                    });
+                    ui.add_enabled_ui(environment_name.is_some(), |ui| {
+                        ui.add(egui::Slider::new(&mut environment.intensity, 0.0..=4.0).text("Intensity"));
+                        ui.add(egui::Slider::new(&mut environment.rotation, -180.0..=180.0).suffix("°").text("Rotation"));
+                        ui.checkbox(&mut environment.background, "Show as background");
+                        ui.add_enabled(
+                            environment.background,
+                            egui::Slider::new(&mut environment.background_blur, 0.0..=1.0).text("Background blur"),
+                        );
+                    });
+                });
+                ui.collapsing("Clay", |ui| {
+                    ui.checkbox(&mut clay.enabled, "Clay material")
+                        .on_hover_text("Replaces materials, textures and vertex colors with two-tone clay");
+                    ui.add_enabled_ui(clay.enabled, |ui| {
+                        egui::Grid::new("clay_fields").num_columns(2).show(ui, |ui| {
+                            ui.label("Top");
+                            ui.color_edit_button_rgb(&mut clay.top);
+                            ui.end_row();
+                            ui.label("Bottom");
+                            ui.color_edit_button_rgb(&mut clay.bottom);
+                            ui.end_row();
+                            ui.label("Rim light");
+                            ui.color_edit_button_rgb(&mut clay.rim_color);
+                            ui.end_row();
+                        });
+                        ui.add(egui::Slider::new(&mut clay.rim_strength, 0.0..=2.0).text("Rim strength"));
+                        if ui.button("Reset").clicked() {
+                            clay = ClaySettings { enabled: true, ..ClaySettings::default() };
+                        }
+                    });
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    for i in 0..lights.len() {
+                        ui.selectable_value(&mut selected, i, format!("{}", i + 1));
+                    }
+                    if ui.add_enabled(lights.len() < MAX_LIGHTS, egui::Button::new("+")).on_hover_text("Add a light").clicked() {
+                        // A dimmer fill light from the other side, without a second ambient term
+                        let light = lights.get(selected).copied().unwrap_or_default();
+                        let [x, y, z] = light.position;
+                        lights.push(Light { position: [-x, y, -z], intensity: 0.5, ambient_strength: 0.0, ..light });
+                        selected = lights.len() - 1;
+                    }
+                    if ui.add_enabled(lights.len() > 1, egui::Button::new("-")).on_hover_text("Remove this light").clicked() {
+                        lights.remove(selected);
+                        selected = selected.min(lights.len() - 1);
+                    }
+                });
+                let Some(light) = lights.get_mut(selected) else {
+                    return;
+                };
+                egui::Grid::new("light_fields").num_columns(2).show(ui, |ui| {
+                    ui.label("Type");
+                    egui::ComboBox::from_id_source("light_kind")
+                        .selected_text(light.kind.label())
+                        .show_ui(ui, |ui| {
+                            for kind in LightKind::ALL {
+                                ui.selectable_value(&mut light.kind, kind, kind.label());
+                            }
+                        });
+                    ui.end_row();
+                    ui.label(if light.kind == LightKind::Directional { "From" } else { "Position" });
+                    ui.horizontal(|ui| {
+                        for value in &mut light.position {
+                            ui.add(egui::DragValue::new(value).speed(position_speed).max_decimals(3));
+                        }
+                    });
+                    ui.end_row();
+                    ui.label("Color");
+                    ui.color_edit_button_rgb(&mut light.color);
+                    ui.end_row();
+                });
+                ui.add(egui::Slider::new(&mut light.intensity, 0.0..=10.0).text("Intensity"));
+                ui.add(egui::Slider::new(&mut light.ambient_strength, 0.0..=1.0).text("Ambient"));
+                ui.add(egui::Slider::new(&mut light.diffuse_strength, 0.0..=1.0).text("Diffuse"));
+                ui.add(egui::Slider::new(&mut light.specular_strength, 0.0..=1.0).text("Specular"));
+                ui.add(egui::Slider::new(&mut light.shininess, 1.0..=256.0).logarithmic(true).text("Shininess"));
+                if ui.button("Reset").clicked() {
+                    *light = Light::default();
+                }
+            });
+        self.selected_light = selected;
+        if lights != self.lights {
+            self.set_lights(&lights);
+        }
+        if clay != self.clay {
+            self.set_clay(clay);
+        }
+        if load_environment {
+            self.ui_actions.push(UiAction::LoadEnvironment);
+        }
+        if clear_environment {
+            self.clear_environment();
+        } else if environment != self.environment.settings {
+            self.environment.settings = environment;
+            self.write_light_uniforms(&self.camera);
+        }
+    }
+}
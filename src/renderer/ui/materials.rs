@@ -0,0 +1,174 @@
+use crate::material::{self, PbrParams, TextureSlot};
+use crate::renderer::Renderer;
+
+impl Renderer {
+    pub(super) fn show_texture_warnings(&mut self) {
+        let mut reload_textures = false;
+        if !self.texture_warnings.is_empty() {
+            let warnings = &self.texture_warnings;
+            egui::Window::new("Warnings")
+                .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -10.0])
+                .resizable(false)
+                .collapsible(true)
+                .show(&self.egui_ctx, |ui| {
+                    ui.label("Missing or unreadable textures (shown as a magenta checker):");
+                    egui::ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+                        for warning in warnings {
+                            let material = if warning.material.is_empty() {
+                                String::new()
+                            } else {
+                                format!("[{}] ", warning.material)
+                            };
+                            ui.label(format!("{}{} - {}", material, warning.path, warning.reason));
+                        }
+                    });
+                    if ui.button("Reload textures").clicked() {
+                        reload_textures = true;
+                    }
+                });
+        }
+        if reload_textures {
+            self.reload_textures();
+        }
+    }
+
+    pub(super) fn show_materials_window(&mut self) {
+        let mut material_assignment = None;
+        let mut edited_material = None;
+        let mut make_unique = None;
+        if self.has_mesh && !self.mesh.materials.is_empty() {
+            let mesh = &self.mesh;
+            let selected_range = self.selected_node.filter(|&node| node < mesh.draw_ranges.len());
+            egui::Window::new("Materials")
+                .default_open(false)
+                .resizable(false)
+                .show(&self.egui_ctx, |ui| {
+                    let material_name = |material: Option<usize>| match material {
+                        Some(id) => mesh.materials[id].name.as_str(),
+                        None => "(default)",
+                    };
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        egui::Grid::new("material_slots").num_columns(2).striped(true).show(ui, |ui| {
+                            for (i, range) in mesh.draw_ranges.iter().enumerate() {
+                                let name = if range.name.is_empty() { "(unnamed)" } else { range.name.as_str() };
+                                ui.label(format!("{} ({} tris)", name, range.count / 3));
+                                let mut material = range.material;
+                                egui::ComboBox::from_id_source(("material_slot", i))
+                                    .selected_text(material_name(material))
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(&mut material, None, material_name(None));
+                                        for id in 0..mesh.materials.len() {
+                                            ui.selectable_value(&mut material, Some(id), material_name(Some(id)));
+                                        }
+                                    });
+                                if material != range.material {
+                                    material_assignment = Some((i, material));
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+                    ui.separator();
+                    let Some(range) = selected_range else {
+                        ui.label("Select an object to edit its material");
+                        return;
+                    };
+                    let material = mesh.draw_ranges[range].material;
+                    ui.strong(format!("Material of {}", material_name(material)));
+                    let Some(index) = material else {
+                        ui.label("The object uses the default material.");
+                        if ui.button("Add material").clicked() {
+                            make_unique = Some(range);
+                        }
+                        return;
+                    };
+                    let users = mesh.draw_ranges.iter().filter(|other| other.material == material && other.count > 0).count();
+                    if users > 1 {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Shared by {} objects", users));
+                            if ui.button("Make unique").on_hover_text("Give this object its own copy to edit").clicked() {
+                                make_unique = Some(range);
+                            }
+                        });
+                    }
+
+                    let mut mtl = mesh.materials[index].clone();
+                    let mut changed = false;
+                    let mut pbr = PbrParams::from_mtl(&mtl);
+                    let mut physically_based = pbr.is_some();
+                    if ui.checkbox(&mut physically_based, "Physically based").on_hover_text("Metallic-roughness shading instead of Phong").changed() {
+                        pbr = physically_based.then(PbrParams::default);
+                        PbrParams::store(pbr, &mut mtl);
+                        pbr = PbrParams::from_mtl(&mtl);
+                        changed = true;
+                    }
+                    egui::Grid::new("material_editor").num_columns(2).show(ui, |ui| {
+                        let mut diffuse = mtl.diffuse.unwrap_or([0.8; 3]);
+                        ui.label(if pbr.is_some() { "Base color" } else { "Diffuse" });
+                        if ui.color_edit_button_rgb(&mut diffuse).changed() {
+                            mtl.diffuse = Some(diffuse);
+                            changed = true;
+                        }
+                        ui.end_row();
+                        match &mut pbr {
+                            Some(params) => {
+                                ui.label("Metallic");
+                                let metallic = ui.add(egui::Slider::new(&mut params.metallic, 0.0..=1.0)).changed();
+                                ui.end_row();
+                                ui.label("Roughness");
+                                let roughness = ui.add(egui::Slider::new(&mut params.roughness, 0.0..=1.0)).changed();
+                                ui.end_row();
+                                if metallic || roughness {
+                                    PbrParams::store(Some(*params), &mut mtl);
+                                    changed = true;
+                                }
+                                if let Some((_, mut scale)) = material::texture_statement(&mtl, TextureSlot::Normal) {
+                                    ui.label("Normal strength");
+                                    if ui.add(egui::Slider::new(&mut scale, 0.0..=2.0)).changed() {
+                                        material::set_normal_scale(&mut mtl, scale);
+                                        changed = true;
+                                    }
+                                    ui.end_row();
+                                }
+                            }
+                            None => {
+                                let mut specular = mtl.specular.unwrap_or([0.0; 3]);
+                                ui.label("Specular");
+                                if ui.color_edit_button_rgb(&mut specular).changed() {
+                                    mtl.specular = Some(specular);
+                                    changed = true;
+                                }
+                                ui.end_row();
+                                let mut shininess = mtl.shininess.unwrap_or(32.0);
+                                ui.label("Shininess");
+                                if ui.add(egui::Slider::new(&mut shininess, 1.0..=1000.0).logarithmic(true)).changed() {
+                                    mtl.shininess = Some(shininess);
+                                    changed = true;
+                                }
+                                ui.end_row();
+                            }
+                        }
+                    });
+                    let maps: Vec<_> = TextureSlot::ALL
+                        .into_iter()
+                        .filter_map(|slot| material::texture_statement(&mtl, slot).map(|_| slot.label()))
+                        .collect();
+                    if !maps.is_empty() {
+                        ui.label(format!("Maps: {}", maps.join(", ")));
+                    }
+                    if changed {
+                        edited_material = Some((index, mtl));
+                    }
+                });
+        }
+        if let Some((range, material)) = material_assignment {
+            self.assign_material(range, material);
+        }
+        if let Some((index, mtl)) = edited_material {
+            self.update_material(index, mtl);
+        }
+        if let Some(range) = make_unique {
+            self.make_material_unique(range);
+        }
+    }
+}
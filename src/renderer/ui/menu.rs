@@ -0,0 +1,173 @@
+use tracing::info;
+
+use crate::camera::ViewPreset;
+use crate::camera_controller::ControllerKind;
+use crate::presentation::PresentationPreset;
+use crate::renderer::{Renderer, UiAction};
+use crate::viewports::{DisplayMode, ViewportLayout};
+
+impl Renderer {
+    /// Draws the File, View and Export menus and applies what changed in them.
+    pub(in crate::renderer) fn show_menu_bar(&mut self) {
+    let ui_actions = &mut self.ui_actions;
+    let reopen_last_session = &mut self.reopen_last_session;
+    let safe_mode = self.safe_mode;
+    let compact_mode = &mut self.compact_mode;
+    let camera = &mut self.camera;
+    let mut controller = self.camera_controller.kind();
+    let has_camera_path = !self.camera_path.is_empty();
+    let viewport_layout = &mut self.viewport_layout;
+    let previous_layout = *viewport_layout;
+    let display_mode = &mut self.display_mode;
+    let previous_display_mode = *display_mode;
+    let mut presentation = None;
+    let detail_view_enabled = &mut self.detail_view.enabled;
+    let show_outliner = &mut self.show_outliner;
+    let show_stats_hud = &mut self.show_stats_hud;
+    let show_view_cube = &mut self.view_cube.enabled;
+    let show_grid = &mut self.overlay_settings.grid;
+    let show_axes = &mut self.overlay_settings.axes;
+    let show_settings = &mut self.show_settings;
+    let has_mesh = self.has_mesh;
+    let has_model = self.model_path.is_some();
+    let reload_on_change = &mut self.reload_on_change;
+    egui::TopBottomPanel::top("menu_bar").show(&self.egui_ctx, |ui| {
+        egui::menu::bar(ui, |ui| {
+            ui.menu_button("File", |ui| {
+                if ui.button("Open... (O)").clicked() {
+                    ui_actions.push(UiAction::OpenModel);
+                    ui.close_menu();
+                }
+                if ui.add_enabled(has_model, egui::Button::new("Close model")).clicked() {
+                    ui_actions.push(UiAction::CloseModel);
+                    ui.close_menu();
+                }
+                ui.checkbox(reload_on_change, "Reload when changed on disk");
+                ui.separator();
+                if ui.button("Import camera... (I)").clicked() {
+                    ui_actions.push(UiAction::ImportView);
+                    ui.close_menu();
+                }
+                if ui.button("Export camera... (C)").clicked() {
+                    ui_actions.push(UiAction::ExportView);
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Import samples (CSV/NPY)...").clicked() {
+                    ui_actions.push(UiAction::ImportSamples);
+                    ui.close_menu();
+                }
+                if ui.add_enabled(has_mesh, egui::Button::new("Load morph target...")).clicked() {
+                    ui_actions.push(UiAction::LoadMorphTarget);
+                    ui.close_menu();
+                }
+                if ui.button("Load environment (HDR)...").clicked() {
+                    ui_actions.push(UiAction::LoadEnvironment);
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Save screenshot... (S)").clicked() {
+                    ui_actions.push(UiAction::SaveScreenshot);
+                    ui.close_menu();
+                }
+                ui.separator();
+                ui.add_enabled(!safe_mode, egui::Checkbox::new(reopen_last_session, "Reopen last session on launch"));
+                ui.separator();
+                if ui.button("Quit (Q)").clicked() {
+                    ui_actions.push(UiAction::Quit);
+                    ui.close_menu();
+                }
+            });
+            ui.menu_button("View", |ui| {
+                for preset in ViewPreset::ALL {
+                    if ui.button(format!("{} ({})", preset.label(), preset.shortcut())).clicked() {
+                        ui_actions.push(UiAction::SetViewPreset(preset));
+                        ui.close_menu();
+                    }
+                }
+                ui.separator();
+                egui::ComboBox::from_label("Display (W)")
+                    .selected_text(display_mode.label())
+                    .show_ui(ui, |ui| {
+                        for mode in DisplayMode::ALL {
+                            ui.selectable_value(display_mode, mode, mode.label());
+                        }
+                    });
+                ui.menu_button("Presentation", |ui| {
+                    for preset in PresentationPreset::ALL {
+                        if ui.button(preset.label()).clicked() {
+                            presentation = Some(preset);
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.separator();
+                for layout in ViewportLayout::ALL {
+                    ui.radio_value(viewport_layout, layout, layout.label());
+                }
+                ui.checkbox(detail_view_enabled, "Detail view");
+                ui.checkbox(show_outliner, "Outliner");
+                ui.checkbox(show_stats_hud, "Stats HUD");
+                ui.checkbox(show_grid, "Ground grid");
+                ui.checkbox(show_axes, "World axes");
+                ui.checkbox(show_view_cube, "View cube");
+                ui.separator();
+                if ui.button("Settings...").clicked() {
+                    *show_settings = true;
+                    ui.close_menu();
+                }
+                ui.separator();
+                ui.menu_button("Navigation", |ui| {
+                    for kind in ControllerKind::ALL {
+                        let enabled = kind != ControllerKind::PathPlayback || has_camera_path;
+                        if ui.add_enabled(enabled, egui::RadioButton::new(controller == kind, kind.label())).on_hover_text(kind.hint()).clicked() {
+                            controller = kind;
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.checkbox(&mut camera.auto_rotate, "Auto-rotate (T)");
+                ui.add_enabled(
+                    camera.auto_rotate,
+                    egui::Slider::new(&mut camera.auto_rotate_speed, -180.0..=180.0).suffix("°/s").text("Speed"),
+                );
+                ui.separator();
+                if ui.checkbox(compact_mode, "Compact mode").clicked() {
+                    ui_actions.push(UiAction::SetCompactMode(*compact_mode));
+                    ui.close_menu();
+                }
+            });
+            ui.menu_button("Export", |ui| {
+                if ui.add_enabled(has_mesh, egui::Button::new("OBJ (apply modifications)...")).clicked() {
+                    ui_actions.push(UiAction::ExportObj);
+                    ui.close_menu();
+                }
+                if ui.button("Standard views...").clicked() {
+                    ui_actions.push(UiAction::ExportStandardViews);
+                    ui.close_menu();
+                }
+                if ui.button("Blueprint...").clicked() {
+                    ui_actions.push(UiAction::ExportBlueprint);
+                    ui.close_menu();
+                }
+            });
+        });
+    });
+
+    if let Some(preset) = presentation {
+        self.apply_presentation(preset);
+    }
+    if self.display_mode != previous_display_mode {
+        info!("Display mode: {}", self.display_mode.label());
+        self.scene_stale = true;
+    }
+    if self.viewport_layout != previous_layout {
+        info!("Viewport layout: {}", self.viewport_layout.label());
+        self.active_viewport = 0;
+        self.scene_stale = true;
+    }
+    if controller != self.camera_controller.kind() {
+        self.set_camera_controller(controller);
+    }
+    }
+}
@@ -0,0 +1,51 @@
+//! The menu bar, tool windows and viewport overlays, one method on
+//! `Renderer` per window, kept next to the renderer so they can reach its
+//! state.
+
+mod analysis;
+mod camera;
+mod display;
+mod export;
+mod lights;
+mod materials;
+mod menu;
+mod model;
+mod outliner;
+mod overlays;
+mod performance;
+mod settings;
+mod timeline;
+
+use crate::performance::PerformanceStats;
+use crate::renderer::Renderer;
+use crate::uv_layout;
+
+impl Renderer {
+    /// Draws the tool windows around the viewport; left out in compact mode.
+    pub(super) fn show_panels(&mut self, stats: &PerformanceStats) {
+        self.show_performance_window(stats);
+        self.show_display_window();
+        self.show_export_window();
+        self.show_camera_window();
+        self.show_lights_window();
+        self.show_dimensions_window();
+        self.show_validation_window();
+        self.show_material_ids_window();
+        self.show_texture_warnings();
+        self.show_materials_window();
+        self.show_terrain_window();
+        self.show_point_cloud_window();
+        self.show_timeline_window();
+        self.show_parts_window();
+        self.show_outliner();
+        self.show_history_window();
+        self.show_morph_window();
+        self.show_samples_window();
+
+        let mut selected_face = self.selected_face;
+        if self.has_mesh && self.mesh.has_uvs {
+            uv_layout::show(&self.egui_ctx, &self.mesh, &mut selected_face);
+        }
+        self.set_selected_face(selected_face);
+    }
+}
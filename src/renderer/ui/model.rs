@@ -0,0 +1,165 @@
+use crate::renderer::{Renderer, UiAction};
+use crate::terrain;
+
+impl Renderer {
+    pub(super) fn show_terrain_window(&mut self) {
+        let mut regenerate_terrain = false;
+        if let Some(path) = self.model_path.as_deref().filter(|path| self.has_mesh && terrain::is_heightmap(path)) {
+            let options = &mut self.terrain_options;
+            egui::Window::new("Terrain")
+                .resizable(false)
+                .show(&self.egui_ctx, |ui| {
+                    ui.label(format!("Height map: {}", path.file_name().unwrap_or_default().to_string_lossy()));
+                    ui.add(
+                        egui::Slider::new(&mut options.resolution, 16..=4096)
+                            .logarithmic(true)
+                            .text("Resolution"),
+                    )
+                    .on_hover_text("Grid samples along the longer edge, up to the image size");
+                    ui.add(egui::Slider::new(&mut options.vertical_scale, 0.0..=1.0).text("Vertical scale"))
+                        .on_hover_text("Height of white relative to the terrain width");
+                    if ui.button("Regenerate").clicked() {
+                        regenerate_terrain = true;
+                    }
+                });
+        }
+        if regenerate_terrain {
+            self.ui_actions.push(UiAction::ReparseModel);
+        }
+    }
+
+    pub(super) fn show_point_cloud_window(&mut self) {
+        if !self.points.is_empty() {
+            let points = &mut self.points;
+            egui::Window::new("Point Cloud")
+                .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+                .resizable(false)
+                .show(&self.egui_ctx, |ui| {
+                    let total = points.total_points();
+                    let uploaded = points.uploaded_points();
+                    egui::Grid::new("point_cloud_stats").num_columns(2).show(ui, |ui| {
+                        ui.label("Points");
+                        ui.label(total.to_string());
+                        ui.end_row();
+                        ui.label("On GPU");
+                        if uploaded < total {
+                            ui.label(format!("{} ({:.0}%)", uploaded, uploaded as f64 * 100.0 / total as f64));
+                        } else {
+                            ui.label(uploaded.to_string());
+                        }
+                        ui.end_row();
+                        ui.label("Drawn");
+                        ui.label(points.drawn_points().to_string());
+                        ui.end_row();
+                    });
+                    let [x, y, z] = points.origin();
+                    ui.label(format!("Origin: {:.3}, {:.3}, {:.3}", x, y, z))
+                        .on_hover_text("Coordinates are shown relative to the first point");
+                    ui.separator();
+                    ui.add(egui::Slider::new(&mut points.settings.point_size, 1.0..=10.0).text("Point size (px)"));
+                    ui.add(
+                        egui::Slider::new(&mut points.settings.budget, 100_000..=100_000_000)
+                            .logarithmic(true)
+                            .text("Point budget"),
+                    )
+                    .on_hover_text("Larger clouds are thinned evenly down to this many points");
+                });
+        }
+    }
+
+    pub(super) fn show_history_window(&mut self) {
+        if self.history.snapshots.len() > 1 {
+            let history = &mut self.history;
+            egui::Window::new("History")
+                .default_open(false)
+                .show(&self.egui_ctx, |ui| {
+                    let latest = history.snapshots.len() - 1;
+                    let mut version = history.compare.unwrap_or(latest);
+                    ui.add(
+                        egui::Slider::new(&mut version, 0..=latest)
+                            .custom_formatter(|version, _| format!("{}", version as usize + 1))
+                            .text("Compare with version"),
+                    );
+                    egui::Grid::new("history_versions").num_columns(4).striped(true).show(ui, |ui| {
+                        for (index, snapshot) in history.snapshots.iter().enumerate().rev() {
+                            let label = if index == latest { format!("{} (current)", index + 1) } else { (index + 1).to_string() };
+                            ui.selectable_value(&mut version, index, label);
+                            ui.label(snapshot.age());
+                            ui.label(format!("{} vertices, {} faces", snapshot.summary.vertices, snapshot.summary.faces));
+                            ui.label(format!("{:08x}", snapshot.summary.checksum >> 32))
+                                .on_hover_text(format!("Checksum {:016x}, {} edges kept", snapshot.summary.checksum, snapshot.edge_count()));
+                            ui.end_row();
+                        }
+                    });
+                    history.compare = (version < latest).then_some(version);
+                    ui.weak("The version compared with is outlined in cyan over the current model.");
+                    if ui.button("Clear history").clicked() {
+                        history.keep_latest();
+                    }
+                });
+        }
+    }
+
+    pub(super) fn show_morph_window(&mut self) {
+        let mut clear_morph_target = false;
+        if let Some(target) = &self.morph_target {
+            let morph_weight = &mut self.morph_weight;
+            egui::Window::new("Morph")
+                .resizable(false)
+                .show(&self.egui_ctx, |ui| {
+                    ui.label(format!("Target: {}", target.path.file_name().unwrap_or_default().to_string_lossy()));
+                    ui.label(format!(
+                        "{} vertices moved, up to {:.4}",
+                        target.changed_vertices, target.max_displacement
+                    ));
+                    ui.add(egui::Slider::new(morph_weight, 0.0..=1.0).text("Model → target"));
+                    ui.horizontal(|ui| {
+                        if ui.button("Model").clicked() {
+                            *morph_weight = 0.0;
+                        }
+                        if ui.button("Target").clicked() {
+                            *morph_weight = 1.0;
+                        }
+                        if ui.button("Clear").clicked() {
+                            clear_morph_target = true;
+                        }
+                    });
+                });
+        }
+        if clear_morph_target {
+            self.morph_target = None;
+            self.morph_weight = 0.0;
+        }
+    }
+
+    pub(super) fn show_samples_window(&mut self) {
+        let mut clear_samples = false;
+        let mut arrow_scale = self.arrow_scale;
+        if let Some(samples) = &self.samples {
+            let sample_points = &mut self.sample_points;
+            egui::Window::new("Samples")
+                .resizable(false)
+                .show(&self.egui_ctx, |ui| {
+                    ui.label(format!("{}: {} points", samples.name, samples.positions.len()));
+                    if samples.vectors.is_some() {
+                        ui.label(format!("Max vector magnitude: {:.4}", samples.max_magnitude()));
+                        ui.add(
+                            egui::Slider::new(&mut arrow_scale, 0.1..=10.0)
+                                .logarithmic(true)
+                                .text("Arrow scale"),
+                        );
+                    }
+                    ui.add(egui::Slider::new(&mut sample_points.settings.point_size, 1.0..=10.0).text("Point size (px)"));
+                    if ui.button("Clear samples").clicked() {
+                        clear_samples = true;
+                    }
+                });
+        }
+        if clear_samples {
+            self.clear_samples();
+        } else if arrow_scale != self.arrow_scale {
+            self.arrow_scale = arrow_scale;
+            self.update_sample_arrows();
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use tracing::error;
+
+use crate::gizmo::GizmoMode;
+use crate::renderer::{scene_search, Renderer};
+use crate::scene;
+
+impl Renderer {
+    pub(super) fn show_outliner(&mut self) {
+        let mut frame_node = None;
+        let mut frame_matches = false;
+        if self.scene.nodes.len() > 1 && self.show_outliner {
+            let matches = scene_search(&self.scene, &self.search);
+            // Rows kept by the search: matches and the groups above them
+            let listed: Vec<bool> = (0..self.scene.nodes.len())
+                .map(|node| self.search.trim().is_empty() || self.scene.subtree(node).any(|part| matches.contains(&part)))
+                .collect();
+            let search = &mut self.search;
+            let focus_search = std::mem::take(&mut self.focus_search);
+            let scene = &mut self.scene;
+            let selected = &mut self.selected_node;
+            let gizmo_mode = &mut self.gizmo.mode;
+            let visibility = self.object_visibility.borrow();
+            let step = self.model_bounds.map_or(0.01, |(min, max)| (max - min).length() * 0.005);
+            let shared_with: Vec<Option<usize>> = (0..scene.nodes.len()).map(|node| self.mesh.duplicate_of(node).map(|duplicate| duplicate.source)).collect();
+            let shared_bytes = self.mesh.shared_bytes();
+            egui::SidePanel::left("outliner")
+                .default_width(300.0)
+                .show(&self.egui_ctx, |ui| {
+                    ui.heading("Outliner");
+                    ui.horizontal(|ui| {
+                        let response = ui.add(egui::TextEdit::singleline(search).hint_text("Search (Ctrl+F)").desired_width(180.0));
+                        if focus_search {
+                            response.request_focus();
+                        }
+                        // Enter frames what was found
+                        if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                            frame_matches = true;
+                        }
+                        if !search.is_empty() {
+                            if ui.small_button("x").on_hover_text("Clear the search").clicked() {
+                                search.clear();
+                            }
+                            ui.label(format!("{} found", matches.len()));
+                            frame_matches |= ui.add_enabled(!matches.is_empty(), egui::Button::new("Frame")).clicked();
+                        }
+                    });
+                    let visible = |node: usize| visibility.get(node).copied().unwrap_or(true);
+                    // Nodes that only group others aren't counted
+                    let objects = scene.nodes.iter().filter(|node| node.triangles > 0).count();
+                    let drawn: Vec<&scene::SceneNode> = (0..scene.nodes.len())
+                        .filter(|&node| visible(node) && scene.nodes[node].triangles > 0)
+                        .map(|node| &scene.nodes[node])
+                        .collect();
+                    ui.label(format!(
+                        "Drawn {} of {} objects, {} triangles",
+                        drawn.len(),
+                        objects,
+                        drawn.iter().map(|node| node.triangles as u64).sum::<u64>()
+                    ));
+                    let copies = shared_with.iter().flatten().count();
+                    if copies > 0 {
+                        ui.label(format!("{} objects share geometry, saving {:.1} MiB", copies, shared_bytes as f64 / (1024.0 * 1024.0)))
+                            .on_hover_text("Objects that are moved copies of another are drawn from its buffers");
+                    }
+                    egui::ScrollArea::vertical().max_height(ui.available_height() * 0.6).show(ui, |ui| {
+                        egui::Grid::new("scene_nodes").num_columns(4).striped(true).show(ui, |ui| {
+                            for (node, depth) in scene.depth_first() {
+                                if !listed[node] {
+                                    continue;
+                                }
+                                // Groups count everything under them
+                                let (triangles, gpu_bytes) = scene
+                                    .subtree(node)
+                                    .fold((0, 0), |(triangles, bytes), part| {
+                                        let bytes = if shared_with[part].is_some() { bytes } else { bytes + scene.nodes[part].gpu_bytes };
+                                        (triangles + scene.nodes[part].triangles as u64, bytes)
+                                    });
+                                let source = shared_with[node].map(|source| scene.nodes[source].name.clone());
+                                let parent_shown = scene.nodes[node].parent.is_none_or(|parent| scene.is_shown(parent));
+                                let object = &mut scene.nodes[node];
+                                ui.horizontal(|ui| {
+                                    ui.add_space(depth as f32 * 12.0);
+                                    ui.checkbox(&mut object.visible, "").on_hover_text("Show in the viewport");
+                                    let label = if object.is_identity() { object.name.clone() } else { format!("{} *", object.name) };
+                                    if ui.selectable_value(selected, Some(node), label).on_hover_text("Click to frame").clicked() {
+                                        frame_node = Some(node);
+                                    }
+                                });
+                                ui.label(format!("{} tris", triangles));
+                                let kib = gpu_bytes as f64 / 1024.0;
+                                if let Some(source) = source {
+                                    ui.weak("shared").on_hover_text(format!("Same geometry as {}, drawn from its buffers", source));
+                                } else if kib < 1024.0 {
+                                    ui.label(format!("{:.1} KiB", kib));
+                                } else {
+                                    ui.label(format!("{:.1} MiB", kib / 1024.0));
+                                }
+                                if !object.visible || !parent_shown {
+                                    ui.weak("hidden");
+                                } else if object.triangles == 0 {
+                                    ui.weak("group");
+                                } else if visible(node) {
+                                    ui.label("drawn");
+                                } else {
+                                    ui.weak("culled").on_hover_text("Outside the view in the last frame");
+                                }
+                                ui.end_row();
+                            }
+                        });
+                    });
+
+                    let Some(node) = selected.filter(|&node| node < scene.nodes.len()) else {
+                        ui.label("Select an object to parent or move it");
+                        return;
+                    };
+                    ui.separator();
+                    let node_name = |node: Option<usize>| node.map_or("(none)", |node| scene.nodes[node].name.as_str()).to_string();
+                    let mut parent = scene.nodes[node].parent;
+                    egui::ComboBox::from_label("Parent")
+                        .selected_text(node_name(parent))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut parent, None, "(none)");
+                            for candidate in 0..scene.nodes.len() {
+                                if candidate != node && !scene.is_ancestor(node, candidate) {
+                                    ui.selectable_value(&mut parent, Some(candidate), node_name(Some(candidate)));
+                                }
+                            }
+                        });
+                    if parent != scene.nodes[node].parent {
+                        if let Err(e) = scene.set_parent(node, parent) {
+                            error!("{:#}", e);
+                        }
+                    }
+
+                    let object = &mut scene.nodes[node];
+                    ui.horizontal(|ui| {
+                        ui.label("Move");
+                        for axis in 0..3 {
+                            ui.add(egui::DragValue::new(&mut object.translation[axis]).speed(step));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Rotate");
+                        for axis in 0..3 {
+                            ui.add(egui::DragValue::new(&mut object.rotation[axis]).speed(1.0).suffix("°"));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Scale");
+                        ui.add(egui::DragValue::new(&mut object.scale).speed(0.01).clamp_range(0.001..=1000.0));
+                    });
+                    if ui.add_enabled(!object.is_identity(), egui::Button::new("Reset transform")).clicked() {
+                        object.reset_transform();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Gizmo (G)");
+                        for mode in GizmoMode::ALL {
+                            ui.selectable_value(gizmo_mode, mode, mode.label());
+                        }
+                    });
+                });
+        }
+        if let Some(node) = frame_node {
+            self.frame_object(node);
+        }
+        if frame_matches {
+            self.frame_search_matches();
+        }
+    }
+}
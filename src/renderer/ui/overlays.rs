@@ -0,0 +1,202 @@
+use crate::detail_view::DETAIL_SIZE;
+use crate::inspector::FaceDetails;
+use crate::performance::PerformanceStats;
+use crate::renderer::Renderer;
+use crate::viewports::RenderMode;
+
+impl Renderer {
+    // The progress of a model loading in the background
+    pub(in crate::renderer) fn show_load_progress(&self) {
+        if let Some(progress) = &self.load_progress {
+            let name = progress.path.file_name().map_or_else(|| progress.path.display().to_string(), |name| name.to_string_lossy().into_owned());
+            egui::Window::new("Loading")
+                .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -40.0])
+                .resizable(false)
+                .collapsible(false)
+                .show(&self.egui_ctx, |ui| {
+                    ui.label(name);
+                    const MB: f64 = 1024.0 * 1024.0;
+                    ui.add(
+                        egui::ProgressBar::new(progress.fraction())
+                            .desired_width(240.0)
+                            .text(format!("{:.1}/{:.1} MB", progress.bytes_read() as f64 / MB, progress.total_bytes() as f64 / MB)),
+                    );
+                    ui.label(format!("{} vertices", progress.vertices()));
+                });
+        }
+    }
+
+    // The last toast, until it is clicked away or times out
+    pub(in crate::renderer) fn show_toast_popup(&mut self) {
+        const TOAST_DURATION: f32 = 8.0;
+        if let Some((title, lines, shown)) = self.toast.as_ref() {
+            let response = egui::Area::new(egui::Id::new("toast"))
+                .anchor(egui::Align2::RIGHT_TOP, [-10.0, 40.0])
+                .interactable(true)
+                .show(&self.egui_ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.strong(title);
+                        for line in lines {
+                            ui.label(line);
+                        }
+                    });
+                })
+                .response;
+            if response.clicked() || shown.elapsed().as_secs_f32() > TOAST_DURATION {
+                self.toast = None;
+            }
+        }
+    }
+
+    // Name and position of the picked face, when its object is selected
+    pub(in crate::renderer) fn show_pick_label(&self) {
+        if let Some(pick) = self.pick.filter(|pick| Some(pick.object) == self.selected_node) {
+            let [x, y, z] = pick.point.to_array();
+            egui::Area::new(egui::Id::new("pick"))
+                .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+                .interactable(false)
+                .show(&self.egui_ctx, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.strong(&self.scene.nodes[pick.object].name);
+                        ui.label(format!("Face {} at ({:.4}, {:.4}, {:.4})", pick.face, x, y, z));
+                    });
+                });
+        }
+    }
+
+    // While inspecting, a tooltip with the face under the cursor, and a
+    // window with the one clicked last
+    pub(in crate::renderer) fn show_inspector(&mut self) {
+        if !self.inspect || !self.has_mesh {
+            self.inspected = None;
+            return;
+        }
+        let hover = self.egui_ctx.input(|input| input.pointer.hover_pos());
+        if let Some(position) = hover.filter(|_| !self.egui_ctx.is_pointer_over_area() && !self.handles_active()) {
+            let (_, camera, ndc) = self.viewport_under(position);
+            let details = self.pick_at(&camera, ndc).and_then(|pick| FaceDetails::new(&self.mesh, &pick));
+            if let Some(details) = details.filter(|details| self.inspected.as_ref().map(FaceDetails::face) != Some(details.face())) {
+                egui::show_tooltip_at_pointer(&self.egui_ctx, egui::Id::new("face_inspector"), |ui| details.ui(ui));
+            }
+        }
+        let mut open = self.inspected.is_some();
+        if let Some(details) = &self.inspected {
+            egui::Window::new("Inspector").open(&mut open).resizable(false).show(&self.egui_ctx, |ui| details.ui(ui));
+        }
+        if !open {
+            self.inspected = None;
+        }
+    }
+
+    // The detail view window, and a frame around the region it magnifies
+    // while the cursor is over the scene
+    pub(in crate::renderer) fn show_detail_view(&mut self) {
+        self.detail_view.ensure_target(&self.device, &mut self.egui_renderer, self.post_process.supports_auto_exposure());
+        self.detail_view.set_output(self.post_process.exposure, self.post_process.color_space);
+        let screen = self.egui_ctx.screen_rect();
+        let hover = self.egui_ctx.input(|input| input.pointer.hover_pos());
+        let layout = self.effective_layout();
+        match hover {
+            Some(position) if !self.egui_ctx.is_pointer_over_area() => {
+                let (x, y) = ((position.x - screen.min.x) / screen.width(), (position.y - screen.min.y) / screen.height());
+                let viewport = layout.viewport_at(x, y);
+                let rect = layout.rects()[viewport];
+                let (local_x, local_y) = ((x - rect[0]) / rect[2], (y - rect[1]) / rect[3]);
+                self.detail_view.viewport = viewport;
+                self.detail_view.cursor_ndc = Some(glam::Vec2::new(local_x * 2.0 - 1.0, 1.0 - local_y * 2.0));
+                // The detail is square and spans 1/magnification of the viewport's height
+                let side = rect[3] * screen.height() / self.detail_view.magnification;
+                self.egui_ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("detail_frame"))).rect_stroke(
+                    egui::Rect::from_center_size(position, egui::vec2(side, side)),
+                    0.0,
+                    egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 210, 90)),
+                );
+            }
+            // Keeps showing the last region while the cursor is over the UI
+            Some(_) => {}
+            None => self.detail_view.cursor_ndc = None,
+        }
+
+        let Some(texture_id) = self.detail_view.texture_id() else {
+            return;
+        };
+        let has_region = self.detail_view.cursor_ndc.is_some();
+        let magnification = &mut self.detail_view.magnification;
+        egui::Window::new("Detail")
+            .anchor(egui::Align2::LEFT_BOTTOM, [10.0, -10.0])
+            .resizable(false)
+            .collapsible(true)
+            .show(&self.egui_ctx, |ui| {
+                let size = egui::vec2(DETAIL_SIZE as f32, DETAIL_SIZE as f32) / ui.ctx().pixels_per_point();
+                if has_region {
+                    let response = ui.add(egui::Image::new(egui::load::SizedTexture::new(texture_id, size)));
+                    let center = response.rect.center();
+                    let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 210, 90));
+                    ui.painter().line_segment([center - egui::vec2(6.0, 0.0), center + egui::vec2(6.0, 0.0)], stroke);
+                    ui.painter().line_segment([center - egui::vec2(0.0, 6.0), center + egui::vec2(0.0, 6.0)], stroke);
+                } else {
+                    ui.allocate_ui(size, |ui| ui.label("Move the cursor over the model"));
+                }
+                ui.add(egui::Slider::new(magnification, 2.0..=16.0).logarithmic(true).suffix("x").text("Magnification"));
+            });
+    }
+
+    // Separator lines between the split viewports and a render mode picker
+    // in the corner of each secondary one
+    pub(in crate::renderer) fn show_viewport_controls(&mut self) {
+        let screen = self.egui_ctx.screen_rect();
+        let painter = self.egui_ctx.layer_painter(egui::LayerId::background());
+        let stroke = egui::Stroke::new(1.0, egui::Color32::from_gray(20));
+        let mut mode_changed = false;
+        for (i, rect) in self.viewport_layout.rects().iter().enumerate() {
+            let min = screen.min + egui::vec2(rect[0] * screen.width(), rect[1] * screen.height());
+            let max = min + egui::vec2(rect[2] * screen.width(), rect[3] * screen.height());
+            painter.rect_stroke(egui::Rect::from_min_max(min, max), 0.0, stroke);
+            let corner = egui::pos2(min.x + 8.0, max.y - 8.0);
+            egui::Area::new(egui::Id::new(("viewport_mode", i)))
+                .fixed_pos(corner)
+                .pivot(egui::Align2::LEFT_BOTTOM)
+                .show(&self.egui_ctx, |ui| match i.checked_sub(1).and_then(|index| self.viewports.get_mut(index)) {
+                    Some(viewport) => {
+                        egui::ComboBox::from_id_source(("viewport_mode_combo", i))
+                            .selected_text(viewport.mode.label())
+                            .show_ui(ui, |ui| {
+                                for mode in RenderMode::ALL {
+                                    mode_changed |= ui.selectable_value(&mut viewport.mode, mode, mode.label()).changed();
+                                }
+                            });
+                    }
+                    None => {
+                        ui.label("Main");
+                    }
+                });
+        }
+        if mode_changed {
+            self.scene_stale = true;
+        }
+    }
+
+    // Minimal stats for recordings, where the performance window is too busy
+    pub(in crate::renderer) fn show_stats_hud(&self, stats: &PerformanceStats) {
+        let file = self.model_path.as_ref().map_or_else(
+            || "No model".to_string(),
+            |path| path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned(),
+        );
+        let count = if self.points.is_empty() {
+            let triangles: u64 = self.scene.nodes.iter().map(|node| node.triangles as u64).sum();
+            format!("{} triangles", triangles)
+        } else {
+            format!("{} points", self.points.total_points())
+        };
+        egui::Area::new(egui::Id::new("stats_hud"))
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 30.0])
+            .interactable(false)
+            .show(&self.egui_ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.strong(format!("{:.0} FPS", stats.fps));
+                    ui.label(count);
+                    ui.label(file);
+                });
+            });
+    }
+}
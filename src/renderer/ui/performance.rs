@@ -0,0 +1,61 @@
+use crate::performance::PerformanceStats;
+use crate::renderer::Renderer;
+
+impl Renderer {
+    pub(super) fn show_performance_window(&mut self, stats: &PerformanceStats) {
+        let safe_mode = self.safe_mode;
+        let adapter_info = &self.adapter_info;
+        let pending_upload = self.pending_upload_bytes();
+        let decoding = self.texture_decoder.in_flight();
+        let gpu_resources = self.gpu_resource_counts();
+        let streaming_stats = self
+            .streamer
+            .as_ref()
+            .map(|streamer| (streamer.resident_count(), streamer.len(), streamer.resident_bytes()));
+        let cluster_counts = self
+            .clusters
+            .as_ref()
+            .map(|clusters| (self.cluster_visibility.borrow().iter().filter(|&&visible| visible).count(), clusters.len()));
+        let cache = &self.texture_cache;
+        let texture_cache_stats = (cache.len(), cache.bytes(), cache.unused_bytes(), cache.hits, cache.misses);
+        egui::Window::new("Performance")
+            .anchor(egui::Align2::LEFT_TOP, [10.0, 10.0])
+            .resizable(false)
+            .collapsible(false)
+            .show(&self.egui_ctx, |ui| {
+                ui.label(format!("CPU: {:.1}%", stats.cpu_usage));
+                ui.label(format!("RAM: {:.1}% ({:.0}MB/{:.0}MB)", stats.memory_usage, stats.memory_used_mb, stats.memory_total_mb));
+                ui.label(format!("FPS: {:.1}", stats.fps));
+                ui.label(format!("Frame: {:.1}ms", stats.frame_time_ms));
+                ui.label(format!("Frames: {}", stats.frame_count));
+                if pending_upload > 0 {
+                    ui.label(format!("Uploading: {:.1}MB left", pending_upload as f64 / (1024.0 * 1024.0)));
+                }
+                if decoding > 0 {
+                    ui.label(format!("Decoding: {} textures", decoding));
+                }
+                if let Some((visible, total)) = cluster_counts {
+                    ui.label(format!("Clusters: {}/{}", visible, total));
+                }
+                if let Some(counts) = gpu_resources {
+                    ui.label(counts.to_string());
+                }
+                if let Some((resident, total, bytes)) = streaming_stats {
+                    ui.label(format!("Streamed: {}/{} ({:.0}MB)", resident, total, bytes as f64 / (1024.0 * 1024.0)));
+                }
+                let (textures, bytes, unused_bytes, hits, misses) = texture_cache_stats;
+                if textures > 0 {
+                    ui.label(format!("Textures: {} ({:.0}MB, {:.0}MB unused)", textures, bytes as f64 / (1024.0 * 1024.0), unused_bytes as f64 / (1024.0 * 1024.0)))
+                        .on_hover_text(format!("Texture cache: {} hits, {} misses", hits, misses));
+                }
+                if safe_mode {
+                    ui.separator();
+                    ui.colored_label(egui::Color32::YELLOW, "Safe mode");
+                    if let Some(info) = adapter_info {
+                        ui.label(format!("{} ({:?})", info.name, info.backend));
+                        ui.label(format!("Driver: {} {}", info.driver, info.driver_info));
+                    }
+                }
+            });
+    }
+}
@@ -0,0 +1,36 @@
+use crate::renderer::{Renderer, UiAction, MAX_SENSITIVITY, MIN_SENSITIVITY};
+use crate::settings::Settings;
+
+impl Renderer {
+    // Options that aren't in the menus, plus resetting everything the
+    // settings file keeps
+    pub(in crate::renderer) fn show_settings_window(&mut self) {
+        let mut open = true;
+        let mut sensitivity = self.camera_sensitivity;
+        let mut vsync = self.vsync;
+        let ui_actions = &mut self.ui_actions;
+        egui::Window::new("Settings").open(&mut open).resizable(false).show(&self.egui_ctx, |ui| {
+            ui.add(
+                egui::Slider::new(&mut sensitivity, MIN_SENSITIVITY..=MAX_SENSITIVITY)
+                    .logarithmic(true)
+                    .text("Camera sensitivity"),
+            )
+            .on_hover_text("Speed of mouse rotation, panning and wheel zoom");
+            ui.checkbox(&mut vsync, "VSync");
+            ui.separator();
+            if let Some(path) = Settings::path() {
+                ui.label(format!("Saved on exit to {}", path.display()));
+            }
+            if ui
+                .button("Reset to defaults")
+                .on_hover_text("Sensitivity, vsync, display mode, backgrounds, layout and panels")
+                .clicked()
+            {
+                ui_actions.push(UiAction::ResetSettings);
+            }
+        });
+        self.show_settings = open;
+        self.camera_sensitivity = sensitivity;
+        self.set_vsync(vsync);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::renderer::{Renderer, UiAction};
+
+impl Renderer {
+    pub(super) fn show_timeline_window(&mut self) {
+        let mut timeline_changed = false;
+        let mut key_removed = false;
+        let mut turntable = self.turntable;
+        let mut sweep = self.clip_plane.sweep;
+        {
+            let timeline = &mut self.timeline;
+            let camera_path = &mut self.camera_path;
+            let sections = &self.clip_plane.sections;
+            let show_frustums = &mut self.overlay_settings.camera_frustums;
+            let sequence = &self.frame_sequence;
+            let ui_actions = &mut self.ui_actions;
+            let camera_state = self.camera.state();
+            egui::Window::new("Timeline")
+                .default_open(false)
+                .resizable(false)
+                .show(&self.egui_ctx, |ui| {
+                    timeline_changed = timeline.show(ui);
+                    let frame = timeline.frame();
+                    ui.separator();
+
+                    match sequence {
+                        Some(sequence) => ui.label(format!("Sequence: {} files", sequence.len())),
+                        None => ui.label("No model sequence"),
+                    };
+                    if ui.button("Load sequence...").on_hover_text("Pick any file of a numbered series").clicked() {
+                        ui_actions.push(UiAction::LoadSequence);
+                    }
+                    ui.separator();
+
+                    ui.label(format!("Camera path: {} keys", camera_path.len()));
+                    ui.horizontal(|ui| {
+                        if ui.button("Set key").on_hover_text("Store the current view at this frame").clicked() {
+                            camera_path.set_key(frame, camera_state);
+                        }
+                        if ui.add_enabled(camera_path.has_key(frame), egui::Button::new("Remove key")).clicked() {
+                            camera_path.remove_key(frame);
+                            key_removed = true;
+                        }
+                        if ui.add_enabled(!camera_path.is_empty(), egui::Button::new("Clear")).clicked() {
+                            camera_path.clear();
+                        }
+                    });
+                    ui.add_enabled(camera_path.is_empty(), egui::Checkbox::new(&mut turntable, "Turntable"))
+                        .on_hover_text("Orbit once around the model over the timeline");
+                    ui.checkbox(show_frustums, "Show camera frustums")
+                        .on_hover_text("Draw each key's view and the path between keys in the scene");
+                    ui.separator();
+
+                    let mut sweeping = sweep.is_some();
+                    ui.add_enabled(sections.len() >= 2, egui::Checkbox::new(&mut sweeping, "Section sweep"))
+                        .on_hover_text("Move the section plane from one saved section to another over the timeline")
+                        .on_disabled_hover_text("Save two sections in the Display panel to sweep between");
+                    sweep = match (sweeping, sweep) {
+                        (true, None) => Some((0, sections.len().saturating_sub(1))),
+                        (true, sweep) => sweep,
+                        (false, _) => None,
+                    };
+                    if let Some((from, to)) = &mut sweep {
+                        ui.horizontal(|ui| {
+                            for (label, index) in [("From", from), ("to", to)] {
+                                ui.label(label);
+                                egui::ComboBox::from_id_source(("section_sweep", label))
+                                    .selected_text(sections.get(*index).map_or("", |section| section.name.as_str()))
+                                    .show_ui(ui, |ui| {
+                                        for (option, section) in sections.iter().enumerate() {
+                                            ui.selectable_value(index, option, &section.name);
+                                        }
+                                    });
+                            }
+                        });
+                    }
+                    ui.separator();
+
+                    if ui.button("Render frames...").on_hover_text("Uses the export size").clicked() {
+                        ui_actions.push(UiAction::CaptureTimeline);
+                    }
+                });
+        }
+        if turntable != self.turntable {
+            self.turntable = turntable;
+            self.turntable_yaw = self.camera.yaw - std::f32::consts::TAU * self.timeline.progress(self.timeline.frame());
+        }
+        let sweep_changed = sweep != self.clip_plane.sweep;
+        self.clip_plane.sweep = sweep;
+        if timeline_changed || key_removed || sweep_changed {
+            self.apply_timeline_frame(self.timeline.frame());
+        }
+    }
+}
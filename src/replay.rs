@@ -0,0 +1,318 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::camera::CameraState;
+use crate::capture::save_png;
+use crate::material::decode_rgba;
+use crate::renderer::Renderer;
+use crate::view_state::ViewState;
+use crate::viewports::DisplayMode;
+
+const REPLAY_VERSION: u32 = 1;
+
+/// Shortcut keys a replay repeats. Keys that open dialogs or write files
+/// aren't part of it, and neither are the camera keys, whose effect is in
+/// the recorded camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyAction {
+    CycleDisplayMode,
+    CycleGizmoMode,
+    ToggleAutoExposure,
+    ToggleHeadlight,
+    ToggleRuler,
+    ToggleMeasure,
+    ToggleAutoRotate,
+}
+
+impl KeyAction {
+    /// The action bound to the character `key`.
+    pub fn from_key(key: &str) -> Option<KeyAction> {
+        match key.to_ascii_lowercase().as_str() {
+            "w" => Some(KeyAction::CycleDisplayMode),
+            "g" => Some(KeyAction::CycleGizmoMode),
+            "e" => Some(KeyAction::ToggleAutoExposure),
+            "l" => Some(KeyAction::ToggleHeadlight),
+            "r" => Some(KeyAction::ToggleRuler),
+            "m" => Some(KeyAction::ToggleMeasure),
+            "t" => Some(KeyAction::ToggleAutoRotate),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, renderer: &mut Renderer) {
+        match self {
+            KeyAction::CycleDisplayMode => renderer.cycle_display_mode(),
+            KeyAction::CycleGizmoMode => renderer.cycle_gizmo_mode(),
+            KeyAction::ToggleAutoExposure => renderer.toggle_auto_exposure(),
+            KeyAction::ToggleHeadlight => renderer.toggle_headlight(),
+            KeyAction::ToggleRuler => renderer.toggle_ruler(),
+            KeyAction::ToggleMeasure => renderer.toggle_measure(),
+            KeyAction::ToggleAutoRotate => renderer.toggle_auto_rotate(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// Seconds since the previous frame.
+    pub delta_time: f32,
+    /// Model opened since the previous frame.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<PathBuf>,
+    /// Shortcut keys pressed since the previous frame, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<KeyAction>,
+    /// Where mouse and keyboard input left the camera for this frame. Taking
+    /// the camera rather than the events that moved it keeps playback
+    /// independent of controller tuning and frame timing.
+    pub camera: CameraState,
+}
+
+/// Recorded viewer session that plays back frame for frame: the state it
+/// started from and each frame's camera and shortcut keys. Changes made in
+/// the panels aren't recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    /// Scene size when recording started, which headless playback renders at.
+    pub width: u32,
+    pub height: u32,
+    pub view: ViewState,
+    pub display_mode: DisplayMode,
+    pub headlight: bool,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {:?}", path))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        let replay: Self = serde_json::from_str(&json).with_context(|| format!("Invalid replay file {:?}", path))?;
+        if replay.version > REPLAY_VERSION {
+            anyhow::bail!("Replay version {} is newer than supported ({})", replay.version, REPLAY_VERSION);
+        }
+        Ok(replay)
+    }
+}
+
+/// Collects the frames of a replay while the viewer runs.
+pub struct Recorder {
+    path: PathBuf,
+    replay: Replay,
+    actions: Vec<KeyAction>,
+    model: Option<PathBuf>,
+}
+
+impl Recorder {
+    /// Starts recording from the renderer's current state, to be written to
+    /// `path` by `finish`.
+    pub fn new(path: PathBuf, renderer: &Renderer, width: u32, height: u32) -> Self {
+        Self {
+            path,
+            replay: Replay {
+                version: REPLAY_VERSION,
+                width,
+                height,
+                view: renderer.view_state(),
+                display_mode: renderer.display_mode(),
+                headlight: renderer.headlight(),
+                frames: Vec::new(),
+            },
+            actions: Vec::new(),
+            model: None,
+        }
+    }
+
+    pub fn record_action(&mut self, action: KeyAction) {
+        self.actions.push(action);
+    }
+
+    /// Adds a frame with the camera as it's about to be drawn.
+    pub fn record_frame(&mut self, renderer: &Renderer, delta_time: f32) {
+        let model = renderer.model_path().map(Path::to_path_buf);
+        let opened = model.clone().filter(|_| model != self.model);
+        self.model = model;
+        self.replay.frames.push(ReplayFrame {
+            delta_time,
+            model: opened,
+            actions: std::mem::take(&mut self.actions),
+            camera: renderer.camera().state(),
+        });
+    }
+
+    pub fn finish(&self) -> Result<()> {
+        self.replay.save(&self.path)?;
+        info!("Recorded {} frames to {:?}", self.replay.frames.len(), self.path);
+        Ok(())
+    }
+}
+
+/// Steps through a replay, one frame per call to `step`.
+pub struct Player {
+    replay: Replay,
+    next: usize,
+}
+
+impl Player {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, next: 0 }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.replay.width, self.replay.height)
+    }
+
+    /// Puts `renderer` in the state of the next frame, loading its model and
+    /// repeating its keys. Returns false once every frame has been played.
+    pub fn step(&mut self, renderer: &mut Renderer) -> Result<bool> {
+        let Some(frame) = self.replay.frames.get(self.next) else {
+            return Ok(false);
+        };
+        if self.next == 0 {
+            renderer.apply_view_state(&self.replay.view);
+            renderer.set_display_mode(self.replay.display_mode);
+            if renderer.headlight() != self.replay.headlight {
+                renderer.toggle_headlight();
+            }
+        }
+        if let Some(path) = &frame.model {
+            renderer.load_mesh(path)?;
+            renderer.flush_uploads();
+        }
+        for action in &frame.actions {
+            action.apply(renderer);
+        }
+        renderer.set_camera_state(&frame.camera);
+        self.next += 1;
+        Ok(true)
+    }
+}
+
+/// Plays `replay` headlessly, handing the renderer and each frame's RGBA
+/// pixels to `frame` in turn. Returns the number of frames played.
+fn play(replay: Replay, mut frame: impl FnMut(usize, &Renderer, &[u8]) -> Result<()>) -> Result<usize> {
+    let mut player = Player::new(replay);
+    let (width, height) = player.size();
+    let mut renderer = pollster::block_on(Renderer::new_headless(width, height))?;
+    let mut index = 0;
+    while player.step(&mut renderer)? {
+        let mut camera = renderer.camera().clone();
+        camera.aspect_ratio = width as f32 / height as f32;
+        let pixels = renderer.render_to_image(&camera, width, height)?;
+        frame(index, &renderer, &pixels)?;
+        index += 1;
+    }
+    Ok(index)
+}
+
+/// Plays the replay at `path` headlessly and writes every frame into
+/// `output` as a numbered PNG, for comparing renders between builds.
+pub fn render(path: &Path, output: &Path) -> Result<()> {
+    let replay = Replay::load(path)?;
+    let (width, height) = (replay.width, replay.height);
+    std::fs::create_dir_all(output).with_context(|| format!("Failed to create output folder {:?}", output))?;
+
+    let count = play(replay, |index, renderer, pixels| {
+        save_png(&output.join(format!("frame_{:05}.png", index)), width, height, pixels, renderer.color_space())
+    })?;
+    info!("Replayed {} frames into {:?}", count, output);
+    Ok(())
+}
+
+/// Set to re-record the reference frames `check` compares against.
+pub const BLESS_VARIABLE: &str = "DOTOBJVIEWER_BLESS";
+// Largest difference in any channel for a pixel to still match, absorbing
+// rounding and filtering differences between GPUs and drivers
+const CHANNEL_TOLERANCE: u8 = 8;
+// Share of a frame's pixels allowed to differ by more, for triangle edges
+// that rasterize a pixel apart
+const MAX_DIFFERING_PIXELS: f32 = 0.01;
+
+/// Whether wgpu can find an adapter to play replays headlessly with; CI
+/// machines often have none unless a software one is installed.
+pub fn adapter_available() -> bool {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).is_some()
+}
+
+// Share of the pixels of `actual` that differ from `expected` by more than
+// the tolerance; 1.0 when the sizes don't match
+fn differing_pixels(expected: &[u8], actual: &[u8]) -> f32 {
+    if expected.len() != actual.len() || actual.is_empty() {
+        return 1.0;
+    }
+    let differing = expected
+        .chunks_exact(4)
+        .zip(actual.chunks_exact(4))
+        .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+        .count();
+    differing as f32 / (actual.len() / 4) as f32
+}
+
+/// Visual regression check, the entry point for tests: plays the replay at
+/// `path` headlessly and compares each frame with `frame_NNNNN.png` in
+/// `expected`, pixel by pixel within a small tolerance. Frames that differ
+/// are saved into `failures` as PNGs to look at, and the check fails naming
+/// them. Missing reference frames fail the check too; they're only written
+/// when `BLESS_VARIABLE` is set.
+pub fn check(path: &Path, expected: &Path, failures: &Path) -> Result<()> {
+    let replay = Replay::load(path)?;
+    let (width, height) = (replay.width, replay.height);
+    let frame_name = |index: usize| format!("frame_{:05}.png", index);
+    if std::env::var_os(BLESS_VARIABLE).is_some() {
+        // Frames left from a longer earlier version would never be compared
+        let _ = std::fs::remove_dir_all(expected);
+        std::fs::create_dir_all(expected).with_context(|| format!("Failed to create {:?}", expected))?;
+        let count = play(replay, |index, renderer, pixels| {
+            save_png(&expected.join(frame_name(index)), width, height, pixels, renderer.color_space())
+        })?;
+        info!("Recorded {} reference frames to {:?}", count, expected);
+        return Ok(());
+    }
+    if !expected.is_dir() {
+        anyhow::bail!("{:?} has no reference frames in {:?}; set {} to record them", path, expected, BLESS_VARIABLE);
+    }
+
+    // Left over from an earlier failing run
+    let _ = std::fs::remove_dir_all(failures);
+    let mut mismatched = Vec::new();
+    let count = play(replay, |index, renderer, pixels| {
+        let reference = expected.join(frame_name(index));
+        let bytes = std::fs::read(&reference).with_context(|| format!("Missing reference frame {:?}", reference))?;
+        let (reference_width, reference_height, reference_pixels) =
+            decode_rgba(&bytes).with_context(|| format!("Invalid reference frame {:?}", reference))?;
+        let differing = match (reference_width, reference_height) == (width, height) {
+            true => differing_pixels(&reference_pixels, pixels),
+            false => 1.0,
+        };
+        if differing > MAX_DIFFERING_PIXELS {
+            std::fs::create_dir_all(failures).with_context(|| format!("Failed to create {:?}", failures))?;
+            save_png(&failures.join(frame_name(index)), width, height, pixels, renderer.color_space())?;
+            mismatched.push((index, format!("{:.1}%", differing * 100.0)));
+        }
+        Ok(())
+    })?;
+
+    if expected.join(frame_name(count)).exists() {
+        anyhow::bail!("{:?} played {} frames, but {:?} has more reference frames", path, count, expected);
+    }
+    if !mismatched.is_empty() {
+        anyhow::bail!(
+            "{:?}: frames {:?} (index, pixels differing) differ from {:?}; the new renders are in {:?}, set {} to accept them",
+            path,
+            mismatched,
+            expected,
+            failures,
+            BLESS_VARIABLE
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use glam::Vec2;
+
+use crate::camera::Camera;
+use crate::depth_probe::DepthProbes;
+
+const RULER_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 220, 60);
+
+/// Screen-space ruler: dragging in the viewport measures the distance in
+/// pixels, converted to an approximate world length at the depth of the
+/// surface under the start point. The conversion assumes the measured span
+/// faces the camera, so it's a sanity check rather than a measurement.
+pub struct Ruler {
+    pub enabled: bool,
+    start: Option<egui::Pos2>,
+    end: Option<egui::Pos2>,
+    dragging: bool,
+    // View distance of the surface under `start`, infinite over background
+    start_distance: Option<f32>,
+    screen: egui::Rect,
+    depth: DepthProbes,
+    // Start point of the depth request in flight
+    probed: Option<egui::Pos2>,
+}
+
+impl Ruler {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            enabled: false,
+            start: None,
+            end: None,
+            dragging: false,
+            start_distance: None,
+            screen: egui::Rect::NOTHING,
+            depth: DepthProbes::new(device),
+            probed: None,
+        }
+    }
+
+    /// Handles dragging outside egui windows and paints the ruler. The last
+    /// measurement is dropped when the ruler is turned off.
+    pub fn show(&mut self, ctx: &egui::Context, camera: &Camera) {
+        if !self.enabled {
+            self.start = None;
+            self.end = None;
+            self.dragging = false;
+            return;
+        }
+        self.screen = ctx.screen_rect();
+        let (pressed, down, position) = ctx.input(|input| (input.pointer.primary_pressed(), input.pointer.primary_down(), input.pointer.interact_pos()));
+        if pressed && !ctx.is_pointer_over_area() {
+            if let Some(position) = position {
+                self.start = Some(position);
+                self.end = Some(position);
+                self.start_distance = None;
+                self.dragging = true;
+            }
+        }
+        if self.dragging {
+            match position {
+                Some(position) if down => self.end = Some(position),
+                _ => self.dragging = false,
+            }
+        }
+
+        let (Some(start), Some(end)) = (self.start, self.end) else {
+            return;
+        };
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let stroke = egui::Stroke::new(2.0, RULER_COLOR);
+        painter.line_segment([start, end], stroke);
+        let across = (end - start).normalized().rot90() * 6.0;
+        for point in [start, end] {
+            painter.line_segment([point - across, point + across], stroke);
+        }
+
+        let length = (end - start).length();
+        let pixels = length * ctx.pixels_per_point();
+        let text = match self.start_distance {
+            Some(distance) if distance.is_finite() => {
+                // Size of one point at that distance from the camera
+                let world_per_point = 2.0 * distance * (camera.fov * 0.5).tan() / self.screen.height().max(1.0);
+                format!("{:.0} px ≈ {:.4}", pixels, length * world_per_point)
+            }
+            Some(_) => format!("{:.0} px (no surface under start)", pixels),
+            None => format!("{:.0} px", pixels),
+        };
+        let anchor = start + (end - start) * 0.5 + egui::vec2(0.0, -8.0);
+        let font = egui::FontId::proportional(14.0);
+        painter.text(anchor + egui::vec2(1.0, 1.0), egui::Align2::CENTER_BOTTOM, &text, font.clone(), egui::Color32::BLACK);
+        painter.text(anchor, egui::Align2::CENTER_BOTTOM, text, font, RULER_COLOR);
+    }
+
+    /// Requests the depth under the start point from `depth_texture`.
+    pub fn encode_probe(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, depth_texture: &wgpu::Texture, camera: &Camera) {
+        let Some(start) = self.start.filter(|_| self.enabled) else {
+            return;
+        };
+        let relative = (start - self.screen.min) / self.screen.size();
+        let ndc = Vec2::new(relative.x * 2.0 - 1.0, 1.0 - relative.y * 2.0);
+        if self.depth.encode(device, queue, encoder, depth_texture, &[ndc], (camera.near, camera.far)) {
+            self.probed = Some(start);
+        }
+    }
+
+    /// Starts reading back the request encoded this frame. Call after the
+    /// encoder has been submitted.
+    pub fn map_probe(&mut self) {
+        self.depth.map();
+    }
+
+    /// Picks up a finished depth readback, without waiting for the GPU.
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        if let Some(distances) = self.depth.poll(device) {
+            // Ignore results for a start point that has since moved
+            if self.probed == self.start {
+                self.start_distance = distances.first().copied();
+            }
+        }
+    }
+}
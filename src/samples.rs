@@ -0,0 +1,243 @@
+use anyhow::{bail, ensure, Context, Result};
+use glam::Vec3;
+use std::path::Path;
+
+use crate::analysis;
+use crate::mesh::MeshData;
+use crate::point_cloud::{PointCloud, PointVertex};
+
+/// Arrow glyphs beyond this are thinned out evenly.
+const MAX_ARROWS: usize = 100_000;
+const ARROW_SIDES: usize = 6;
+const POINT_COLOR: [u8; 4] = [255, 200, 40, 255];
+
+/// Sample points with optional per-point vectors, overlaid on the model.
+pub struct SampleSet {
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+    pub vectors: Option<Vec<[f32; 3]>>,
+}
+
+/// Loads a CSV/text table or a NumPy `.npy` array. Columns are x, y, z and
+/// optionally a vector (three more columns).
+pub fn load(path: &Path) -> Result<SampleSet> {
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    let (positions, vectors) = match extension.as_deref() {
+        Some("npy") => load_npy(path)?,
+        _ => load_csv(path)?,
+    };
+    if positions.is_empty() {
+        bail!("No samples found in {:?}", path);
+    }
+    Ok(SampleSet {
+        name: path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+        positions,
+        vectors,
+    })
+}
+
+type Columns = (Vec<[f32; 3]>, Option<Vec<[f32; 3]>>);
+
+// Vector columns are recognized by these header names, in order of preference
+const VECTOR_NAMES: [[&str; 3]; 4] = [["vx", "vy", "vz"], ["u", "v", "w"], ["dx", "dy", "dz"], ["nx", "ny", "nz"]];
+
+fn load_csv(path: &Path) -> Result<Columns> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let split = |line: &str| -> Vec<String> {
+        line.split([',', ';', '\t', ' '])
+            .map(|field| field.trim().trim_matches('"').to_string())
+            .filter(|field| !field.is_empty())
+            .collect()
+    };
+    let mut lines = text.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#')).peekable();
+
+    // Without a header the first three columns are the position and the next three the vector
+    let mut position_columns = [0, 1, 2];
+    let mut vector_columns = None;
+    let first = split(lines.peek().context("Empty sample file")?);
+    if first.iter().any(|field| field.parse::<f64>().is_err()) {
+        let header: Vec<String> = first.iter().map(|name| name.to_lowercase()).collect();
+        let find = |names: [&str; 3]| -> Option<[usize; 3]> {
+            let index = |name: &str| header.iter().position(|column| column == name);
+            Some([index(names[0])?, index(names[1])?, index(names[2])?])
+        };
+        position_columns = find(["x", "y", "z"]).context("Sample header has no x, y, z columns")?;
+        vector_columns = VECTOR_NAMES.into_iter().find_map(find);
+        lines.next();
+    } else if first.len() >= 6 {
+        vector_columns = Some([3, 4, 5]);
+    }
+
+    let mut positions = Vec::new();
+    let mut vectors = Vec::new();
+    for (row, line) in lines.enumerate() {
+        let fields = split(line);
+        let read = |columns: [usize; 3]| -> Result<[f32; 3]> {
+            let mut value = [0.0; 3];
+            for (axis, column) in columns.into_iter().enumerate() {
+                let field = fields.get(column).with_context(|| format!("Row {} has too few columns", row + 1))?;
+                value[axis] = field.parse().with_context(|| format!("Invalid number {:?} in row {}", field, row + 1))?;
+            }
+            Ok(value)
+        };
+        positions.push(read(position_columns)?);
+        if let Some(columns) = vector_columns {
+            vectors.push(read(columns)?);
+        }
+    }
+    Ok((positions, vector_columns.map(|_| vectors)))
+}
+
+/// Reads a C-ordered `(N, 3)` or `(N, 6)` float or integer array.
+fn load_npy(path: &Path) -> Result<Columns> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    ensure!(bytes.starts_with(b"\x93NUMPY") && bytes.len() > 10, "Not a NumPy .npy file");
+    let (header_start, header_len) = match bytes[6] {
+        1 => (10, u16::from_le_bytes([bytes[8], bytes[9]]) as usize),
+        _ => (12, u32::from_le_bytes(bytes.get(8..12).context("Truncated .npy header")?.try_into()?) as usize),
+    };
+    let header = std::str::from_utf8(bytes.get(header_start..header_start + header_len).context("Truncated .npy header")?)?;
+    let data = &bytes[header_start + header_len..];
+
+    let value_of = |key: &str| -> Result<&str> {
+        let start = header.find(&format!("'{}'", key)).with_context(|| format!(".npy header has no {}", key))?;
+        let rest = header[start + key.len() + 2..].trim_start().trim_start_matches(':').trim_start();
+        Ok(rest)
+    };
+    ensure!(value_of("fortran_order")?.starts_with("False"), "Fortran-ordered .npy arrays are not supported");
+    let descr = value_of("descr")?.trim_start_matches('\'');
+    let descr = &descr[..descr.find('\'').context("Invalid .npy descr")?];
+    let shape = value_of("shape")?;
+    let shape: Vec<usize> = shape[1..shape.find(')').context("Invalid .npy shape")?]
+        .split(',')
+        .map(str::trim)
+        .filter(|dimension| !dimension.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    let [rows, columns] = shape[..] else {
+        bail!("Expected a 2D array, found shape {:?}", shape);
+    };
+    ensure!(columns == 3 || columns == 6, "Expected 3 or 6 columns (x, y, z[, vx, vy, vz]), found {}", columns);
+
+    let (size, read): (usize, fn(&[u8]) -> f32) = match descr {
+        "<f4" => (4, |b| f32::from_le_bytes(b.try_into().unwrap())),
+        "<f8" => (8, |b| f64::from_le_bytes(b.try_into().unwrap()) as f32),
+        "<i4" => (4, |b| i32::from_le_bytes(b.try_into().unwrap()) as f32),
+        "<i8" => (8, |b| i64::from_le_bytes(b.try_into().unwrap()) as f32),
+        other => bail!("Unsupported .npy dtype {:?}", other),
+    };
+    ensure!(data.len() >= rows * columns * size, "Truncated .npy data");
+
+    let values: Vec<f32> = data[..rows * columns * size].chunks_exact(size).map(read).collect();
+    let positions = values.chunks_exact(columns).map(|row| [row[0], row[1], row[2]]).collect();
+    let vectors = (columns == 6).then(|| values.chunks_exact(columns).map(|row| [row[3], row[4], row[5]]).collect());
+    Ok((positions, vectors))
+}
+
+impl SampleSet {
+    pub fn bounds(&self) -> (Vec3, Vec3) {
+        self.positions.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), &position| (min.min(position.into()), max.max(position.into())),
+        )
+    }
+
+    pub fn max_magnitude(&self) -> f32 {
+        self.vectors
+            .iter()
+            .flatten()
+            .map(|&vector| Vec3::from(vector).length())
+            .filter(|length| length.is_finite())
+            .fold(0.0, f32::max)
+    }
+
+    /// The samples as points, colored by vector magnitude when there are vectors.
+    pub fn point_cloud(&self) -> PointCloud {
+        let max_magnitude = self.max_magnitude();
+        let points = self
+            .positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| {
+                let color = match &self.vectors {
+                    Some(vectors) if max_magnitude > 0.0 => {
+                        let [r, g, b] = analysis::ramp_color(Vec3::from(vectors[i]).length() / max_magnitude);
+                        [r, g, b].map(|channel| (channel * 255.0) as u8)
+                    }
+                    _ => [POINT_COLOR[0], POINT_COLOR[1], POINT_COLOR[2]],
+                };
+                PointVertex { position, color: [color[0], color[1], color[2], 255] }
+            })
+            .collect();
+        PointCloud {
+            points,
+            origin: [0.0; 3],
+            bounds: self.bounds(),
+        }
+    }
+
+    /// Arrow glyphs for the vectors, colored by magnitude. The longest arrow
+    /// is 5% of the samples' bounding box diagonal times `scale`.
+    pub fn arrow_mesh(&self, scale: f32) -> Option<MeshData> {
+        let vectors = self.vectors.as_ref()?;
+        let max_magnitude = self.max_magnitude();
+        if max_magnitude <= 0.0 {
+            return None;
+        }
+        let (min, max) = self.bounds();
+        let diagonal = (max - min).length().max(1e-6);
+        let length_scale = diagonal * 0.05 * scale / max_magnitude;
+
+        let mut arrows = MeshData {
+            name: format!("{} vectors", self.name),
+            face_colors: Some(Vec::new()),
+            ..Default::default()
+        };
+        let step = vectors.len().div_ceil(MAX_ARROWS).max(1);
+        for (position, vector) in self.positions.iter().zip(vectors).step_by(step) {
+            let vector = Vec3::from(*vector);
+            let magnitude = vector.length();
+            if !(magnitude > 0.0 && magnitude.is_finite()) {
+                continue;
+            }
+            let color = analysis::ramp_color(magnitude / max_magnitude);
+            push_arrow(&mut arrows, Vec3::from(*position), vector * length_scale, color);
+        }
+        Some(arrows)
+    }
+}
+
+// Hexagonal shaft plus cone head, proportioned to the arrow's length
+fn push_arrow(mesh: &mut MeshData, origin: Vec3, vector: Vec3, color: [f32; 3]) {
+    let length = vector.length();
+    let direction = vector / length;
+    let u = direction.any_orthonormal_vector();
+    let v = direction.cross(u);
+    let head_base = origin + vector * 0.7;
+    let ring = |center: Vec3, radius: f32| {
+        (0..ARROW_SIDES).map(move |k| {
+            let angle = k as f32 / ARROW_SIDES as f32 * std::f32::consts::TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        })
+    };
+
+    let base = mesh.positions.len() as u32;
+    mesh.positions.extend(ring(origin, length * 0.04).map(|point| point.to_array()));
+    mesh.positions.extend(ring(head_base, length * 0.04).map(|point| point.to_array()));
+    mesh.positions.extend(ring(head_base, length * 0.1).map(|point| point.to_array()));
+    mesh.positions.push((origin + vector).to_array());
+    mesh.positions.push(head_base.to_array());
+
+    let sides = ARROW_SIDES as u32;
+    let (shaft_bottom, shaft_top, head, tip, head_center) = (base, base + sides, base + 2 * sides, base + 3 * sides, base + 3 * sides + 1);
+    for k in 0..sides {
+        let next = (k + 1) % sides;
+        mesh.triangles.push([shaft_bottom + k, shaft_bottom + next, shaft_top + next]);
+        mesh.triangles.push([shaft_bottom + k, shaft_top + next, shaft_top + k]);
+        mesh.triangles.push([head + k, head + next, tip]);
+        mesh.triangles.push([head_center, head + next, head + k]);
+    }
+    if let Some(face_colors) = &mut mesh.face_colors {
+        face_colors.extend(std::iter::repeat_n(color, 4 * ARROW_SIDES));
+    }
+}
@@ -0,0 +1,395 @@
+use anyhow::{bail, Result};
+use glam::{EulerRot, Mat3, Mat4, Quat, Vec3, Vec4};
+use wgpu::util::DeviceExt;
+
+use crate::mesh::{DrawRange, Mesh, Vertex};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ObjectUniforms {
+    model: [[f32; 4]; 4],
+    // Inverse transpose of the model matrix, for normals
+    normal: [[f32; 4]; 4],
+    object_id: u32,
+    // Index into the mesh's materials, `NO_MATERIAL` for none
+    material_index: u32,
+    _pad: [u32; 2],
+}
+
+const NO_MATERIAL: u32 = u32::MAX;
+
+impl ObjectUniforms {
+    // `decode` maps the vertex positions into model space first; it doesn't
+    // apply to normals
+    fn new(transform: Mat4, decode: Mat4, object_id: u32, material: Option<usize>) -> Self {
+        let normal = Mat3::from_mat4(transform).inverse().transpose();
+        Self {
+            model: (transform * decode).to_cols_array_2d(),
+            normal: Mat4::from_mat3(normal).to_cols_array_2d(),
+            object_id,
+            material_index: material.map_or(NO_MATERIAL, |material| material as u32),
+            _pad: [0; 2],
+        }
+    }
+}
+
+/// One object of the scene (a group of the loaded mesh) with its transform
+/// relative to its parent.
+#[derive(Debug, Clone)]
+pub struct SceneNode {
+    pub name: String,
+    pub parent: Option<usize>,
+    /// Transform from the file, e.g. a glTF node's. Translation, rotation
+    /// and scale are edits applied on top of it, in the parent's space.
+    pub placement: Mat4,
+    pub translation: Vec3,
+    /// XYZ Euler angles in degrees.
+    pub rotation: Vec3,
+    pub scale: f32,
+    /// Center of the object's geometry, which it rotates and scales about.
+    pub pivot: Vec3,
+    /// Model-space bounding box, used for frustum culling.
+    pub bounds: (Vec3, Vec3),
+    pub triangles: u32,
+    /// Size of the object's share of the vertex and index buffers.
+    pub gpu_bytes: u64,
+    /// Unchecked in the outliner; hidden objects are skipped like culled ones.
+    pub visible: bool,
+}
+
+impl SceneNode {
+    pub fn local_transform(&self) -> Mat4 {
+        let [x, y, z] = self.rotation.to_array().map(f32::to_radians);
+        let pivot = self.placement.transform_point3(self.pivot);
+        Mat4::from_translation(self.translation + pivot)
+            * Mat4::from_quat(Quat::from_euler(EulerRot::XYZ, x, y, z))
+            * Mat4::from_scale(Vec3::splat(self.scale))
+            * Mat4::from_translation(-pivot)
+            * self.placement
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.translation == Vec3::ZERO && self.rotation == Vec3::ZERO && self.scale == 1.0
+    }
+
+    pub fn reset_transform(&mut self) {
+        self.translation = Vec3::ZERO;
+        self.rotation = Vec3::ZERO;
+        self.scale = 1.0;
+    }
+}
+
+/// Object hierarchy of the loaded model, one node per draw range. Geometry
+/// stays in model space; world transforms are applied in the shaders.
+#[derive(Debug, Clone, Default)]
+pub struct Scene {
+    pub nodes: Vec<SceneNode>,
+}
+
+impl Scene {
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        let nodes = mesh
+            .draw_ranges
+            .iter()
+            .map(|range| {
+                let (min, max) = range_bounds(mesh, range);
+                let mut vertices = range_indices(mesh, range).to_vec();
+                vertices.sort_unstable();
+                vertices.dedup();
+                // Parts of a group with several materials go by their material
+                let is_part = range.parent.is_some_and(|parent| mesh.draw_ranges[parent].name == range.name);
+                let name = match range.material.and_then(|material| mesh.materials.get(material)) {
+                    Some(material) if is_part => material.name.clone(),
+                    None if is_part => "(no material)".to_string(),
+                    _ if range.name.is_empty() => "(unnamed)".to_string(),
+                    _ => range.name.clone(),
+                };
+                SceneNode {
+                    name,
+                    parent: range.parent,
+                    placement: range.transform,
+                    translation: Vec3::ZERO,
+                    rotation: Vec3::ZERO,
+                    scale: 1.0,
+                    pivot: if range.count > 0 { (min + max) * 0.5 } else { Vec3::ZERO },
+                    bounds: (min, max),
+                    triangles: range.count / 3,
+                    gpu_bytes: (vertices.len() * std::mem::size_of::<Vertex>()) as u64 + range.count as u64 * 4,
+                    visible: true,
+                }
+            })
+            .collect();
+        Self { nodes }
+    }
+
+    /// Grows the culling bounds to also cover `mesh`, a morph target of the
+    /// same topology.
+    pub fn include_bounds(&mut self, mesh: &Mesh) {
+        for (node, range) in self.nodes.iter_mut().zip(&mesh.draw_ranges) {
+            let (min, max) = range_bounds(mesh, range);
+            node.bounds = (node.bounds.0.min(min), node.bounds.1.max(max));
+        }
+    }
+
+    /// Carries parents, transforms and visibility over from `previous` when
+    /// it has the same objects, e.g. between frames of a sequence.
+    pub fn keep_hierarchy(&mut self, previous: &Scene) {
+        if self.nodes.len() != previous.nodes.len() || self.nodes.iter().zip(&previous.nodes).any(|(node, old)| node.name != old.name) {
+            return;
+        }
+        for (node, old) in self.nodes.iter_mut().zip(&previous.nodes) {
+            node.parent = old.parent;
+            node.translation = old.translation;
+            node.rotation = old.rotation;
+            node.scale = old.scale;
+            node.visible = old.visible;
+        }
+    }
+
+    pub fn children(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nodes.len()).filter(move |&child| self.nodes[child].parent == Some(node))
+    }
+
+    /// Nodes in tree order with their depth, roots first.
+    pub fn depth_first(&self) -> Vec<(usize, usize)> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<(usize, usize)> = (0..self.nodes.len()).rev().filter(|&node| self.nodes[node].parent.is_none()).map(|node| (node, 0)).collect();
+        while let Some((node, depth)) = stack.pop() {
+            order.push((node, depth));
+            let children: Vec<usize> = self.children(node).collect();
+            stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+        }
+        order
+    }
+
+    pub fn is_ancestor(&self, ancestor: usize, mut node: usize) -> bool {
+        while let Some(parent) = self.nodes[node].parent {
+            if parent == ancestor {
+                return true;
+            }
+            node = parent;
+        }
+        false
+    }
+
+    /// Whether `node` and all its ancestors are visible.
+    pub fn is_shown(&self, mut node: usize) -> bool {
+        loop {
+            if !self.nodes[node].visible {
+                return false;
+            }
+            match self.nodes[node].parent {
+                Some(parent) => node = parent,
+                None => return true,
+            }
+        }
+    }
+
+    /// `node` and everything parented under it, at any depth.
+    pub fn subtree(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nodes.len()).filter(move |&other| other == node || self.is_ancestor(node, other))
+    }
+
+    /// Reparents `node`, keeping its local transform.
+    pub fn set_parent(&mut self, node: usize, parent: Option<usize>) -> Result<()> {
+        if let Some(parent) = parent {
+            if parent == node || self.is_ancestor(node, parent) {
+                bail!("{:?} can't be parented to its own descendant", self.nodes[node].name);
+            }
+        }
+        self.nodes[node].parent = parent;
+        Ok(())
+    }
+
+    /// World-space box around every object with geometry, as placed now.
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let transforms = self.world_transforms();
+        self.nodes
+            .iter()
+            .zip(&transforms)
+            .filter(|(node, _)| node.triangles > 0)
+            .map(|(node, transform)| transformed_bounds(*transform, node.bounds))
+            .reduce(|(min, max), (node_min, node_max)| (min.min(node_min), max.max(node_max)))
+    }
+
+    /// World transform of every node: its parents' transforms applied to its own.
+    pub fn world_transforms(&self) -> Vec<Mat4> {
+        let mut world: Vec<Option<Mat4>> = vec![None; self.nodes.len()];
+        for node in 0..self.nodes.len() {
+            // Walk up to the nearest resolved ancestor, then resolve back down
+            let mut chain = vec![node];
+            while let Some(parent) = self.nodes[*chain.last().unwrap()].parent {
+                if world[parent].is_some() {
+                    break;
+                }
+                chain.push(parent);
+            }
+            for &current in chain.iter().rev() {
+                if world[current].is_none() {
+                    let parent = self.nodes[current].parent.and_then(|parent| world[parent]).unwrap_or(Mat4::IDENTITY);
+                    world[current] = Some(parent * self.nodes[current].local_transform());
+                }
+            }
+        }
+        world.into_iter().map(Option::unwrap_or_default).collect()
+    }
+}
+
+/// Axis-aligned box around `bounds` placed by `transform`.
+pub fn transformed_bounds(transform: Mat4, (min, max): (Vec3, Vec3)) -> (Vec3, Vec3) {
+    (0..8).fold((Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)), |(low, high), corner| {
+        let point = transform.transform_point3(Vec3::select(
+            glam::BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0),
+            max,
+            min,
+        ));
+        (low.min(point), high.max(point))
+    })
+}
+
+fn range_indices<'a>(mesh: &'a Mesh, range: &DrawRange) -> &'a [u32] {
+    &mesh.indices[range.start as usize..(range.start + range.count) as usize]
+}
+
+fn range_bounds(mesh: &Mesh, range: &DrawRange) -> (Vec3, Vec3) {
+    range_indices(mesh, range).iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), &index| {
+            let position = Vec3::from(mesh.vertices[index as usize].position);
+            (min.min(position), max.max(position))
+        },
+    )
+}
+
+/// Whether the box `bounds`, placed by `transform`, may be visible through
+/// `view_projection`. Boxes are culled when all their corners are outside
+/// the same clip plane.
+pub fn in_frustum(view_projection: Mat4, transform: Mat4, (min, max): (Vec3, Vec3)) -> bool {
+    if min.cmpgt(max).any() {
+        return false;
+    }
+    let clip_from_model = view_projection * transform;
+    let mut outside = [true; 6];
+    for corner in 0..8 {
+        let pick = |bit: usize, axis: usize| if corner & bit != 0 { max[axis] } else { min[axis] };
+        let clip = clip_from_model * Vec4::new(pick(1, 0), pick(2, 1), pick(4, 2), 1.0);
+        // wgpu clip space has depth 0..w
+        let planes = [clip.x < -clip.w, clip.x > clip.w, clip.y < -clip.w, clip.y > clip.w, clip.z < 0.0, clip.z > clip.w];
+        for (outside, beyond) in outside.iter_mut().zip(planes) {
+            *outside &= beyond;
+        }
+    }
+    !outside.contains(&true)
+}
+
+/// Per-object model matrices, IDs and material indices, all in one uniform buffer rewritten
+/// once per frame and bound with a dynamic offset per draw instead of a bind
+/// group per object. Slot 0 holds the identity transform for geometry outside
+/// the scene (default triangle, glyphs); object `i` lives in slot `i + 1`.
+pub struct ObjectBindings {
+    layout: wgpu::BindGroupLayout,
+    // Bytes between slots, padded to the device's uniform offset alignment
+    stride: usize,
+    capacity: usize,
+    count: usize,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ObjectBindings {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Object Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniforms>() as u64),
+                },
+                count: None,
+            }],
+        });
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as usize;
+        let stride = std::mem::size_of::<ObjectUniforms>().div_ceil(alignment) * alignment;
+        let capacity = 16;
+        let (buffer, bind_group) = Self::create_buffer(device, &layout, stride, capacity);
+        Self {
+            layout,
+            stride,
+            capacity,
+            count: 0,
+            buffer,
+            bind_group,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, stride: usize, capacity: usize) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Object Buffer"),
+            contents: &Self::slots(stride, capacity, &[], &[], Mat4::IDENTITY),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Object Bind Group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<ObjectUniforms>() as u64),
+                }),
+            }],
+        });
+        (buffer, bind_group)
+    }
+
+    // Buffer contents for `slots` slots: the identity, then `transforms`,
+    // then identities for unused slots
+    fn slots(stride: usize, slots: usize, transforms: &[Mat4], materials: &[Option<usize>], decode: Mat4) -> Vec<u8> {
+        let mut bytes = vec![0; stride * slots];
+        for slot in 0..slots {
+            let object = slot.checked_sub(1);
+            let transform = object.and_then(|i| transforms.get(i)).copied().unwrap_or(Mat4::IDENTITY);
+            let material = object.and_then(|i| materials.get(i)).copied().flatten();
+            // Object IDs are numbered from 1 in draw order; 0 is no object,
+            // as for the background and geometry outside the scene in the ID pass
+            let object_id = object.map_or(0, |i| i as u32 + 1);
+            let decode = if slot == 0 { Mat4::IDENTITY } else { decode };
+            let uniforms = ObjectUniforms::new(transform, decode, object_id, material);
+            bytes[slot * stride..][..std::mem::size_of::<ObjectUniforms>()].copy_from_slice(bytemuck::bytes_of(&uniforms));
+        }
+        bytes
+    }
+
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    /// Makes room for `count` objects, growing the buffer when needed.
+    pub fn resize(&mut self, device: &wgpu::Device, count: usize) {
+        if count + 1 > self.capacity {
+            self.capacity = (count + 1).next_power_of_two();
+            (self.buffer, self.bind_group) = Self::create_buffer(device, &self.layout, self.stride, self.capacity);
+        }
+        self.count = count;
+    }
+
+    /// Uploads the transforms and material indices of all objects in a
+    /// single write. `position_decode` goes in front of each object's
+    /// transform for meshes with quantized positions.
+    pub fn write(&self, queue: &wgpu::Queue, transforms: &[Mat4], materials: &[Option<usize>], position_decode: Option<Mat4>) {
+        let transforms = &transforms[..transforms.len().min(self.count)];
+        let decode = position_decode.unwrap_or(Mat4::IDENTITY);
+        queue.write_buffer(&self.buffer, 0, &Self::slots(self.stride, self.count + 1, transforms, materials, decode));
+    }
+
+    /// Binds object `index` at `group`, or the identity for `None` and
+    /// objects past the end.
+    pub fn bind<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, group: u32, index: Option<usize>) {
+        let slot = index.filter(|&index| index < self.count).map_or(0, |index| index + 1);
+        render_pass.set_bind_group(group, &self.bind_group, &[(slot * self.stride) as u32]);
+    }
+}
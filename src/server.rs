@@ -0,0 +1,285 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use crate::camera::ViewPreset;
+use crate::capture::write_png;
+use crate::cli;
+use crate::renderer::Renderer;
+
+const DEFAULT_SIZE: u32 = 512;
+// Longest request line and header line accepted, and the most header lines
+const MAX_LINE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/// Serves `GET /render?model=<path>&view=<preset>&size=<n or WxH>` on
+/// `address`, answering each request with a PNG preview rendered headlessly.
+/// Model paths are resolved inside `root`; anything outside it is reported
+/// as not found. Requests are handled one at a time on a single renderer.
+pub fn run(address: &str, root: &Path) -> Result<()> {
+    let root = root.canonicalize().with_context(|| format!("Failed to open the serve root {:?}", root))?;
+    let listener = TcpListener::bind(address).with_context(|| format!("Failed to listen on {}", address))?;
+    let mut server = Server {
+        renderer: pollster::block_on(Renderer::new_headless(DEFAULT_SIZE, DEFAULT_SIZE))?,
+        root,
+        loaded: None,
+    };
+    info!("Serving previews of {:?} on http://{}/render", server.root, listener.local_addr()?);
+    for stream in listener.incoming() {
+        let result = stream.map_err(anyhow::Error::from).and_then(|stream| server.handle_connection(stream));
+        if let Err(e) = result {
+            warn!("Request failed: {:#}", e);
+        }
+    }
+    Ok(())
+}
+
+struct Server {
+    renderer: Renderer,
+    // Canonical folder that requested models must lie in
+    root: PathBuf,
+    // Model currently in the renderer with its modification time, reloaded
+    // when a request names another file or the file changed
+    loaded: Option<(PathBuf, Option<SystemTime>)>,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn text(status: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: message.into().into_bytes(),
+        }
+    }
+
+    fn not_found() -> Self {
+        Self::text("404 Not Found", "Model not found")
+    }
+}
+
+/// Parsed query of a `/render` request.
+struct RenderRequest {
+    model: PathBuf,
+    view: ViewPreset,
+    width: u32,
+    height: u32,
+}
+
+impl RenderRequest {
+    fn parse(query: &str, max_dimension: u32) -> Result<Self> {
+        let mut model = None;
+        let mut view = ViewPreset::Front;
+        let (mut width, mut height) = (DEFAULT_SIZE, DEFAULT_SIZE);
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value)?;
+            match percent_decode(key)?.as_str() {
+                "model" => model = Some(PathBuf::from(value)),
+                "view" => view = ViewPreset::from_name(&value).ok_or_else(|| anyhow!("Unknown view '{}'", value))?,
+                "size" => {
+                    (width, height) = match value.parse::<u32>() {
+                        Ok(side) if side > 0 => (side, side),
+                        _ => cli::parse_size(&value)?,
+                    }
+                }
+                other => bail!("Unknown parameter '{}'", other),
+            }
+        }
+        if width > max_dimension || height > max_dimension {
+            bail!("Size {}x{} exceeds the {} pixel limit", width, height, max_dimension);
+        }
+        Ok(Self {
+            model: model.ok_or_else(|| anyhow!("Missing 'model' parameter"))?,
+            view,
+            width,
+            height,
+        })
+    }
+}
+
+impl Server {
+    fn handle_connection(&mut self, stream: TcpStream) -> Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+        let mut reader = BufReader::new(&stream);
+        let (request_line, response) = match read_request(&mut reader)? {
+            Ok(request_line) => (request_line.trim().to_string(), self.respond(request_line.trim())),
+            Err(response) => ("(oversized request)".to_string(), response),
+        };
+        info!("{} -> {}", request_line, response.status);
+        let mut stream = &stream;
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.status,
+            response.content_type,
+            response.body.len()
+        )?;
+        stream.write_all(&response.body)?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn respond(&mut self, request_line: &str) -> Response {
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Response::text("400 Bad Request", "Malformed request line");
+        };
+        if method != "GET" {
+            return Response::text("405 Method Not Allowed", "Only GET is supported");
+        }
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        if path != "/render" {
+            return Response::text("404 Not Found", "Use /render?model=<path>&view=<view>&size=<size>");
+        }
+        let mut request = match RenderRequest::parse(query, self.renderer.max_image_dimension()) {
+            Ok(request) => request,
+            Err(e) => return Response::text("400 Bad Request", format!("{:#}", e)),
+        };
+        // The same answer for missing files and paths outside the root, so
+        // requests can't probe the rest of the file system
+        match resolve(&self.root, &request.model) {
+            Some(model) => request.model = model,
+            None => return Response::not_found(),
+        }
+        match self.render(&request) {
+            Ok(png) => Response {
+                status: "200 OK",
+                content_type: "image/png",
+                body: png,
+            },
+            Err(e) => Response::text("500 Internal Server Error", format!("{:#}", e)),
+        }
+    }
+
+    fn render(&mut self, request: &RenderRequest) -> Result<Vec<u8>> {
+        let modified = std::fs::metadata(&request.model)
+            .with_context(|| format!("Failed to read {:?}", request.model))?
+            .modified()
+            .ok();
+        if self.loaded.as_ref() != Some(&(request.model.clone(), modified)) {
+            self.loaded = None;
+            self.renderer.load_mesh(&request.model)?;
+            self.renderer.flush_uploads();
+            self.loaded = Some((request.model.clone(), modified));
+        }
+        let bounds = self
+            .renderer
+            .model_bounds()
+            .ok_or_else(|| anyhow!("Model {:?} has no vertices", request.model))?;
+
+        let mut camera = self.renderer.camera().clone();
+        camera.aspect_ratio = request.width as f32 / request.height as f32;
+        camera.auto_fit_to_model(bounds);
+        camera.set_view_preset(request.view);
+        let pixels = self.renderer.render_to_image(&camera, request.width, request.height)?;
+
+        let mut png = Vec::new();
+        write_png(&mut png, request.width, request.height, &pixels, self.renderer.color_space())?;
+        Ok(png)
+    }
+}
+
+// Reads the request line and skips the headers, answering with an error
+// response instead when a line or the header block is too long
+fn read_request(reader: &mut impl BufRead) -> Result<std::result::Result<String, Response>> {
+    let Some(request_line) = read_line(reader)? else {
+        return Ok(Err(Response::text("414 URI Too Long", "Request line is too long")));
+    };
+    for _ in 0..MAX_HEADERS {
+        match read_line(reader)? {
+            Some(header) if header.trim().is_empty() => return Ok(Ok(request_line)),
+            Some(_) => {}
+            None => break,
+        }
+    }
+    Ok(Err(Response::text("431 Request Header Fields Too Large", "Request headers are too large")))
+}
+
+// One line of at most `MAX_LINE` bytes, `None` if it's longer; the end of
+// the stream counts as the end of the line
+fn read_line(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = Vec::new();
+    reader.by_ref().take(MAX_LINE as u64 + 1).read_until(b'\n', &mut line)?;
+    if line.len() > MAX_LINE {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+// Canonical path of `model` relative to `root`, or `None` if it doesn't
+// exist or lies outside the root, also through `..` or symbolic links
+fn resolve(root: &Path, model: &Path) -> Option<PathBuf> {
+    let path = root.join(model.strip_prefix("/").unwrap_or(model)).canonicalize().ok()?;
+    (path.starts_with(root) && path.is_file()).then_some(path)
+}
+
+// Decodes `%XX` escapes and `+` for spaces in a query string component
+fn percent_decode(text: &str) -> Result<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut input = text.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [input.next(), input.next()];
+                let value = match hex {
+                    [Some(high), Some(low)] => std::str::from_utf8(&[high, low]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+                    _ => None,
+                };
+                bytes.push(value.ok_or_else(|| anyhow!("Invalid escape in '{}'", text))?);
+            }
+            other => bytes.push(other),
+        }
+    }
+    String::from_utf8(bytes).with_context(|| format!("Invalid UTF-8 in '{}'", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_models_inside_the_root_only() {
+        let folder = std::env::temp_dir().join(format!("dotobjviewer-serve-{}", std::process::id()));
+        let root = folder.join("root");
+        std::fs::create_dir_all(root.join("parts")).unwrap();
+        std::fs::write(root.join("parts/cube.obj"), "").unwrap();
+        std::fs::write(folder.join("secret.obj"), "").unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let cube = root.join("parts/cube.obj");
+        assert_eq!(resolve(&root, Path::new("parts/cube.obj")), Some(cube.clone()));
+        assert_eq!(resolve(&root, Path::new("/parts/cube.obj")), Some(cube));
+        assert_eq!(resolve(&root, Path::new("../secret.obj")), None);
+        assert_eq!(resolve(&root, Path::new("parts/../../secret.obj")), None);
+        assert_eq!(resolve(&root, &folder.join("secret.obj")), None);
+        assert_eq!(resolve(&root, Path::new("missing.obj")), None);
+        assert_eq!(resolve(&root, Path::new("parts")), None);
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn caps_request_line_and_headers() {
+        let request = b"GET /render?model=a.obj HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        assert_eq!(read_request(&mut &request[..]).unwrap().ok().unwrap(), "GET /render?model=a.obj HTTP/1.1\r\n");
+
+        let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(read_request(&mut long_line.as_bytes()).unwrap().err().unwrap().status, "414 URI Too Long");
+
+        let long_header = format!("GET / HTTP/1.1\r\nX: {}\r\n\r\n", "a".repeat(MAX_LINE));
+        let many_headers = format!("GET / HTTP/1.1\r\n{}\r\n", "X: a\r\n".repeat(MAX_HEADERS + 1));
+        for request in [long_header, many_headers] {
+            let response = read_request(&mut request.as_bytes()).unwrap().err().unwrap();
+            assert_eq!(response.status, "431 Request Header Fields Too Large");
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::clip_plane::Section;
+use crate::view_state::ViewState;
+
+const SESSION_VERSION: u32 = 1;
+
+/// Workspace remembered between launches: loaded models, camera/lights and
+/// window size, plus the named section planes, which are kept whether or not
+/// the rest is reopened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub reopen_on_launch: bool,
+    pub models: Vec<PathBuf>,
+    pub view: Option<ViewState>,
+    pub window_size: Option<[u32; 2]>,
+    #[serde(default)]
+    pub sections: Vec<Section>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            version: SESSION_VERSION,
+            reopen_on_launch: false,
+            models: Vec::new(),
+            view: None,
+            window_size: None,
+            sections: Vec::new(),
+        }
+    }
+}
+
+impl Session {
+    pub fn path() -> Option<PathBuf> {
+        dirs_next::config_dir().map(|dir| dir.join("dotobjviewer").join("session.json"))
+    }
+
+    /// Loads the last session, falling back to defaults if there is none or it
+    /// can't be read.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        if !path.exists() {
+            return Self::default();
+        }
+
+        let session = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {:?}", path))
+            .and_then(|json| {
+                serde_json::from_str::<Self>(&json).with_context(|| format!("Invalid session file {:?}", path))
+            });
+        match session {
+            Ok(session) if session.version <= SESSION_VERSION => session,
+            Ok(session) => {
+                tracing::warn!("Session file version {} is newer than supported, ignoring it", session.version);
+                Self::default()
+            }
+            Err(e) => {
+                tracing::warn!("{:#}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().context("No config directory available")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json).with_context(|| format!("Failed to write {:?}", path))?;
+        Ok(())
+    }
+}
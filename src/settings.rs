@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::renderer::DEFAULT_BACKGROUND;
+use crate::viewports::{DisplayMode, ViewportLayout};
+
+/// Viewer preferences, saved to `settings.toml` on exit and restored on
+/// startup. Unlike the session, these aren't about what was being looked
+/// at. Missing keys take their defaults, so the file can be edited by hand
+/// down to the options that matter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Multiplier on mouse rotation, panning and wheel zoom.
+    pub camera_sensitivity: f32,
+    pub display_mode: DisplayMode,
+    pub vsync: bool,
+    /// Background per display mode, linear RGB.
+    pub background_colors: [[f32; 3]; 3],
+    pub viewport_layout: ViewportLayout,
+    pub detail_view: bool,
+    pub show_outliner: bool,
+    pub show_grid: bool,
+    pub show_axes: bool,
+    pub show_view_cube: bool,
+    pub show_stats_hud: bool,
+    /// Per-pass timings in the log, toggled with P.
+    pub show_detailed_stats: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            camera_sensitivity: 1.0,
+            display_mode: DisplayMode::Shaded,
+            vsync: true,
+            background_colors: [DEFAULT_BACKGROUND; 3],
+            viewport_layout: ViewportLayout::Single,
+            detail_view: false,
+            show_outliner: true,
+            show_grid: false,
+            show_axes: false,
+            show_view_cube: true,
+            show_stats_hud: false,
+            show_detailed_stats: false,
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        dirs_next::config_dir().map(|dir| dir.join("dotobjviewer").join("settings.toml"))
+    }
+
+    /// Loads the saved settings, falling back to defaults if there are none
+    /// or they can't be read.
+    pub fn load() -> Self {
+        let Some(path) = Self::path().filter(|path| path.exists()) else {
+            return Self::default();
+        };
+        let settings = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {:?}", path))
+            .and_then(|text| toml::from_str::<Self>(&text).with_context(|| format!("Invalid settings file {:?}", path)));
+        settings.unwrap_or_else(|e| {
+            tracing::warn!("{:#}", e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().context("No config directory available")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        }
+        let text = toml::to_string(self)?;
+        std::fs::write(&path, text).with_context(|| format!("Failed to write {:?}", path))?;
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
 use wgpu::ShaderModule;
 
-pub fn create_shader_module(device: &wgpu::Device, source: &str) -> ShaderModule {
+pub fn create_shader_module(device: &wgpu::Device, label: &str, source: &str) -> ShaderModule {
     device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some("Shader"),
+        label: Some(label),
         source: wgpu::ShaderSource::Wgsl(source.into()),
     })
-} 
\ No newline at end of file
+}
@@ -0,0 +1,71 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @location(4) target_position: vec3<f32>,
+    @location(5) target_normal: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) view_depth: f32,
+}
+
+struct CameraUniforms {
+    view_projection: mat4x4<f32>,
+    view_matrix: mat4x4<f32>,
+    camera_position: vec3<f32>,
+    // 0 shows the model, 1 its morph target
+    morph_weight: f32,
+}
+
+struct ObjectUniforms {
+    model: mat4x4<f32>,
+    normal_matrix: mat4x4<f32>,
+    object_id: u32,
+    // Index into the model's materials, 0xffffffff for none
+    material_index: u32,
+}
+
+struct FragmentOutput {
+    @location(0) normal_depth: vec4<f32>,
+    @location(1) object_id: u32,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraUniforms;
+@group(1) @binding(0) var<uniform> object: ObjectUniforms;
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    let morphed = mix(model.position, model.target_position, camera.morph_weight);
+    let normal = mix(model.normal, model.target_normal, camera.morph_weight);
+    return transform_vertex(morphed, normal);
+}
+
+@vertex
+fn vs_quantized(
+    model: QuantizedVertexInput,
+) -> VertexOutput {
+    return transform_vertex(model.position.xyz, oct_decode(model.normal));
+}
+
+fn transform_vertex(local_position: vec3<f32>, normal: vec3<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    let position = object.model * vec4<f32>(local_position, 1.0);
+    let view_position = camera.view_matrix * position;
+    out.normal = (object.normal_matrix * vec4<f32>(normal, 0.0)).xyz;
+    out.view_depth = -view_position.z;
+    out.clip_position = camera.view_projection * position;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    out.normal_depth = vec4<f32>(normalize(in.normal), in.view_depth);
+    out.object_id = object.object_id;
+    return out;
+}
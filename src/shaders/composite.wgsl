@@ -0,0 +1,66 @@
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+struct CompositeParams {
+    exposure_compensation: f32,
+    auto_exposure: u32,
+    key_value: f32,
+    stereo_mode: u32,
+    // Linear sRGB to the output color space
+    output_gamut: mat3x3<f32>,
+}
+
+struct ExposureState {
+    average_luminance: f32,
+    adapted_luminance: f32,
+}
+
+@group(0) @binding(0) var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1) var hdr_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: CompositeParams;
+@group(0) @binding(3) var<storage, read> exposure_state: ExposureState;
+@group(0) @binding(4) var hdr_right_texture: texture_2d<f32>;
+
+const STEREO_ANAGLYPH: u32 = 1u;
+const STEREO_SIDE_BY_SIDE: u32 = 2u;
+
+// Fullscreen triangle generated from the vertex index
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color: vec3<f32>;
+    // Stereo views stay opaque; the plain view keeps the scene's coverage for transparent windows
+    var alpha = 1.0;
+    if (params.stereo_mode == STEREO_ANAGLYPH) {
+        // Half-color anaglyph: luminance of the left eye in red, right eye in green/blue
+        let left = textureSample(hdr_texture, hdr_sampler, in.uv).rgb;
+        let right = textureSample(hdr_right_texture, hdr_sampler, in.uv).rgb;
+        color = vec3<f32>(dot(left, vec3<f32>(0.299, 0.587, 0.114)), right.g, right.b);
+    } else if (params.stereo_mode == STEREO_SIDE_BY_SIDE) {
+        let eye_uv = vec2<f32>(fract(in.uv.x * 2.0), in.uv.y);
+        let left = textureSample(hdr_texture, hdr_sampler, eye_uv).rgb;
+        let right = textureSample(hdr_right_texture, hdr_sampler, eye_uv).rgb;
+        color = select(right, left, in.uv.x < 0.5);
+    } else {
+        let scene = textureSample(hdr_texture, hdr_sampler, in.uv);
+        color = scene.rgb;
+        alpha = scene.a;
+    }
+
+    var exposure = exp2(params.exposure_compensation);
+    if (params.auto_exposure != 0u && exposure_state.adapted_luminance > 0.0) {
+        exposure = exposure * params.key_value / exposure_state.adapted_luminance;
+    }
+
+    return vec4<f32>(params.output_gamut * (color * exposure), alpha);
+}
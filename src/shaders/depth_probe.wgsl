@@ -0,0 +1,14 @@
+@group(0) @binding(0) var depth_texture: texture_2d<f32>;
+@group(0) @binding(1) var<storage, read> probes: array<vec2<u32>>;
+@group(0) @binding(2) var<storage, read_write> depths: array<f32>;
+
+// Depth textures can only be copied whole, so the requested texels
+// are gathered into a buffer instead
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= arrayLength(&probes)) {
+        return;
+    }
+    let dims = textureDimensions(depth_texture);
+    depths[id.x] = textureLoad(depth_texture, min(probes[id.x], dims - 1u), 0).r;
+}
@@ -0,0 +1,150 @@
+struct EnvironmentUniforms {
+    // Camera rotation and projection only, so the far plane maps to directions
+    inverse_view_projection: mat4x4<f32>,
+    // Radians about the world Y axis
+    rotation: f32,
+    intensity: f32,
+    // Mip level the background is sampled at, for a blurred backdrop
+    background_lod: f32,
+    // Roughness the specular level being filtered is for
+    roughness: f32,
+    // Cube face being filtered, in wgpu's +X, -X, +Y, -Y, +Z, -Z order
+    face: u32,
+    // Mip levels of the source and the width of its largest
+    source_levels: f32,
+    source_width: f32,
+    _pad: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: EnvironmentUniforms;
+
+const PI: f32 = 3.14159265;
+const SPECULAR_SAMPLES: u32 = 128u;
+
+// Fullscreen triangle generated from the vertex index
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    // On the far plane, behind everything the scene draws
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 1.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+fn rotate_y(direction: vec3<f32>, angle: f32) -> vec3<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return vec3<f32>(c * direction.x + s * direction.z, direction.y, -s * direction.x + c * direction.z);
+}
+
+// Longitude along U and latitude down V, with +Y up
+fn sample_source(direction: vec3<f32>, lod: f32) -> vec3<f32> {
+    let d = normalize(direction);
+    let uv = vec2<f32>(atan2(d.z, d.x) / (2.0 * PI) + 0.5, acos(clamp(d.y, -1.0, 1.0)) / PI);
+    return textureSampleLevel(source_texture, source_sampler, uv, lod).rgb;
+}
+
+// Direction through `uv` of the cube face being filtered
+fn face_direction(uv: vec2<f32>) -> vec3<f32> {
+    let u = uv.x * 2.0 - 1.0;
+    let v = uv.y * 2.0 - 1.0;
+    switch params.face {
+        case 0u: { return normalize(vec3<f32>(1.0, -v, -u)); }
+        case 1u: { return normalize(vec3<f32>(-1.0, -v, u)); }
+        case 2u: { return normalize(vec3<f32>(u, 1.0, v)); }
+        case 3u: { return normalize(vec3<f32>(u, -1.0, -v)); }
+        case 4u: { return normalize(vec3<f32>(u, -v, 1.0)); }
+        default: { return normalize(vec3<f32>(-u, -v, -1.0)); }
+    }
+}
+
+fn tangent_frame(normal: vec3<f32>) -> mat3x3<f32> {
+    let up = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(normal.y) < 0.999);
+    let tangent = normalize(cross(up, normal));
+    return mat3x3<f32>(tangent, cross(normal, tangent), normal);
+}
+
+@fragment
+fn fs_skybox(in: VertexOutput) -> @location(0) vec4<f32> {
+    let ndc = vec2<f32>(in.uv.x * 2.0 - 1.0, 1.0 - in.uv.y * 2.0);
+    let far = params.inverse_view_projection * vec4<f32>(ndc, 1.0, 1.0);
+    let direction = rotate_y(far.xyz / far.w, params.rotation);
+    return vec4<f32>(sample_source(direction, params.background_lod) * params.intensity, 1.0);
+}
+
+// Cosine-weighted average of the incoming light over the hemisphere, so
+// that albedo times it gives the diffusely reflected light
+@fragment
+fn fs_irradiance(in: VertexOutput) -> @location(0) vec4<f32> {
+    let frame = tangent_frame(face_direction(in.uv));
+    // About one source texel per step at this level
+    let lod = max(params.source_levels - 7.0, 0.0);
+    var sum = vec3<f32>(0.0);
+    var weight = 0.0;
+    let steps = vec2<u32>(64u, 16u);
+    for (var i = 0u; i < steps.x; i = i + 1u) {
+        let phi = (f32(i) + 0.5) / f32(steps.x) * 2.0 * PI;
+        for (var j = 0u; j < steps.y; j = j + 1u) {
+            let theta = (f32(j) + 0.5) / f32(steps.y) * 0.5 * PI;
+            let local = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            // cos for Lambert, sin for the solid angle of the step
+            let w = cos(theta) * sin(theta);
+            sum += sample_source(frame * local, lod) * w;
+            weight += w;
+        }
+    }
+    return vec4<f32>(sum / weight, 1.0);
+}
+
+fn radical_inverse(index: u32) -> f32 {
+    var bits = index;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return f32(bits) * 2.3283064365386963e-10;
+}
+
+// GGX-filtered reflections for one roughness, assuming the view along the
+// normal, with each sample read from a mip level matching its footprint
+@fragment
+fn fs_specular(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = face_direction(in.uv);
+    if (params.roughness <= 0.0) {
+        return vec4<f32>(sample_source(normal, 0.0), 1.0);
+    }
+    let frame = tangent_frame(normal);
+    let alpha = params.roughness * params.roughness;
+    let texel_solid_angle = 4.0 * PI / (params.source_width * params.source_width * 0.5);
+    var sum = vec3<f32>(0.0);
+    var weight = 0.0;
+    for (var i = 0u; i < SPECULAR_SAMPLES; i = i + 1u) {
+        let xi = vec2<f32>((f32(i) + 0.5) / f32(SPECULAR_SAMPLES), radical_inverse(i));
+        let phi = 2.0 * PI * xi.x;
+        let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y));
+        let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+        let half_dir = frame * vec3<f32>(sin_theta * cos(phi), sin_theta * sin(phi), cos_theta);
+        let light_dir = 2.0 * dot(normal, half_dir) * half_dir - normal;
+        let n_dot_l = dot(normal, light_dir);
+        if (n_dot_l > 0.0) {
+            let d_denom = cos_theta * cos_theta * (alpha * alpha - 1.0) + 1.0;
+            let distribution = alpha * alpha / (PI * d_denom * d_denom);
+            // With the view along the normal the pdf reduces to D / 4
+            let pdf = distribution / 4.0;
+            let sample_solid_angle = 1.0 / (f32(SPECULAR_SAMPLES) * pdf + 1e-4);
+            let lod = clamp(0.5 * log2(sample_solid_angle / texel_solid_angle) + 1.0, 0.0, params.source_levels - 1.0);
+            sum += sample_source(light_dir, lod) * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+    return vec4<f32>(sum / max(weight, 1e-4), 1.0);
+}
@@ -0,0 +1,61 @@
+struct CameraUniforms {
+    view_projection: mat4x4<f32>,
+}
+
+struct GridUniforms {
+    center: vec2<f32>,
+    extent: f32,
+    cell_size: f32,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraUniforms;
+@group(1) @binding(0) var<uniform> grid: GridUniforms;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) plane_position: vec2<f32>,
+}
+
+// One quad on y = 0 around the center, made up from the vertex index
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0),
+    );
+    let position = grid.center + corners[index] * grid.extent;
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_projection * vec4<f32>(position.x, 0.0, position.y, 1.0);
+    out.plane_position = position;
+    return out;
+}
+
+// Coverage of the lines every `spacing` units, about a pixel wide
+fn line_coverage(coordinate: vec2<f32>, spacing: f32) -> f32 {
+    let scaled = coordinate / spacing;
+    let distance = abs(fract(scaled - 0.5) - 0.5) / max(fwidth(scaled), vec2<f32>(1e-6));
+    return 1.0 - min(min(distance.x, distance.y), 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let minor = line_coverage(in.plane_position, grid.cell_size);
+    let major = line_coverage(in.plane_position, grid.cell_size * 10.0);
+    var color = vec4<f32>(0.5, 0.5, 0.5, max(minor * 0.25, major * 0.5));
+
+    // World X runs along z = 0, world Z along x = 0
+    let axis_distance = abs(in.plane_position) / max(fwidth(in.plane_position), vec2<f32>(1e-6));
+    let x_axis = 1.0 - min(axis_distance.y * 0.5, 1.0);
+    let z_axis = 1.0 - min(axis_distance.x * 0.5, 1.0);
+    color = mix(color, vec4<f32>(0.9, 0.2, 0.2, 0.9), x_axis);
+    color = mix(color, vec4<f32>(0.25, 0.45, 1.0, 0.9), z_axis);
+
+    // Faded out towards the edge of the quad so it reads as endless
+    let fade = 1.0 - smoothstep(0.3, 1.0, length(in.plane_position - grid.center) / grid.extent);
+    color.a *= fade;
+    if (color.a <= 0.001) {
+        discard;
+    }
+    return color;
+}
@@ -0,0 +1,62 @@
+struct ExposureParams {
+    delta_time: f32,
+    adaptation_speed: f32,
+    min_luminance: f32,
+    max_luminance: f32,
+}
+
+struct ExposureState {
+    average_luminance: f32,
+    adapted_luminance: f32,
+}
+
+@group(0) @binding(0) var hdr_texture: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: ExposureParams;
+@group(0) @binding(2) var<storage, read_write> state: ExposureState;
+
+const WORKGROUP_SIZE: u32 = 256u;
+const SAMPLE_GRID: u32 = 64u;
+
+var<workgroup> partial_sums: array<f32, 256>;
+
+@compute @workgroup_size(256)
+fn cs_main(@builtin(local_invocation_index) index: u32) {
+    let dims = textureDimensions(hdr_texture);
+
+    // Each thread accumulates log-luminance over a share of a fixed sample grid
+    var sum = 0.0;
+    for (var i = index; i < SAMPLE_GRID * SAMPLE_GRID; i += WORKGROUP_SIZE) {
+        let cell = vec2<u32>(i % SAMPLE_GRID, i / SAMPLE_GRID);
+        let coord = min((cell * 2u + 1u) * dims / (2u * SAMPLE_GRID), dims - 1u);
+        let color = textureLoad(hdr_texture, coord, 0).rgb;
+        let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+        sum += log(max(luminance, 0.0001));
+    }
+    partial_sums[index] = sum;
+    workgroupBarrier();
+
+    // Parallel reduction in shared memory
+    for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride = stride / 2u) {
+        if (index < stride) {
+            partial_sums[index] += partial_sums[index + stride];
+        }
+        workgroupBarrier();
+    }
+
+    if (index == 0u) {
+        let average = clamp(
+            exp(partial_sums[0] / f32(SAMPLE_GRID * SAMPLE_GRID)),
+            params.min_luminance,
+            params.max_luminance,
+        );
+        state.average_luminance = average;
+
+        // Exponential adaptation towards the current average, seeded on first use
+        if (state.adapted_luminance <= 0.0) {
+            state.adapted_luminance = average;
+        } else {
+            let blend = 1.0 - exp(-params.delta_time * params.adaptation_speed);
+            state.adapted_luminance = mix(state.adapted_luminance, average, blend);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+struct CameraUniforms {
+    view_projection: mat4x4<f32>,
+}
+
+struct NormalLineUniforms {
+    encode: mat4x4<f32>,
+    length: f32,
+}
+
+struct ObjectUniforms {
+    model: mat4x4<f32>,
+    normal_matrix: mat4x4<f32>,
+    object_id: u32,
+    // Index into the model's materials, 0xffffffff for none
+    material_index: u32,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraUniforms;
+@group(1) @binding(0) var<uniform> params: NormalLineUniforms;
+@group(2) @binding(0) var<uniform> object: ObjectUniforms;
+
+struct LineInput {
+    @location(0) position: vec3<f32>,
+    @location(1) direction: vec3<f32>,
+    @location(2) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+}
+
+// Lines run from the surface along the model-space normal, so they scale
+// with the object like its geometry does
+@vertex
+fn vs_main(in: LineInput) -> VertexOutput {
+    let local = params.encode * vec4<f32>(in.position + in.direction * params.length, 1.0);
+    var out: VertexOutput;
+    out.clip_position = camera.view_projection * object.model * local;
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
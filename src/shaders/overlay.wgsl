@@ -0,0 +1,55 @@
+struct CameraUniforms {
+    view_projection: mat4x4<f32>,
+}
+
+struct OverlayUniforms {
+    viewport_size: vec2<f32>,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraUniforms;
+@group(1) @binding(0) var<uniform> params: OverlayUniforms;
+@group(1) @binding(1) var font_texture: texture_2d<f32>;
+@group(1) @binding(2) var font_sampler: sampler;
+
+struct OverlayInput {
+    @location(0) position: vec3<f32>,
+    @location(1) offset: vec2<f32>,
+    @location(2) tex_coords: vec2<f32>,
+    @location(3) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+// Vertices sit at a world anchor, moved by a pixel offset in screen space
+@vertex
+fn vs_main(in: OverlayInput) -> VertexOutput {
+    var clip = camera.view_projection * vec4<f32>(in.position, 1.0);
+    let offset = in.offset * 2.0 / max(params.viewport_size, vec2<f32>(1.0));
+    // Pulled slightly towards the camera so labels on a surface aren't hidden by it
+    clip = vec4<f32>(clip.xy + offset * clip.w, clip.z * 0.999, clip.w);
+
+    var out: VertexOutput;
+    out.clip_position = clip;
+    out.tex_coords = in.tex_coords;
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_line(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
+
+@fragment
+fn fs_text(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coverage = textureSample(font_texture, font_sampler, in.tex_coords).r;
+    if (coverage < 0.5) {
+        discard;
+    }
+    return in.color;
+}
@@ -0,0 +1,57 @@
+struct CameraUniforms {
+    view_projection: mat4x4<f32>,
+}
+
+struct PointUniforms {
+    point_size: f32,
+    viewport_height: f32,
+    aspect_ratio: f32,
+    _padding: f32,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraUniforms;
+@group(1) @binding(0) var<uniform> params: PointUniforms;
+
+struct PointInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+    @location(1) corner: vec2<f32>,
+}
+
+// Each point instance is expanded into a screen-aligned square of `point_size` pixels
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, point: PointInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+
+    var clip = camera.view_projection * vec4<f32>(point.position, 1.0);
+    let half_size = params.point_size / max(params.viewport_height, 1.0);
+    clip = vec4<f32>(clip.xy + corner * vec2<f32>(half_size / params.aspect_ratio, half_size) * clip.w, clip.zw);
+
+    var out: VertexOutput;
+    out.clip_position = clip;
+    // Scan colors are stored as sRGB bytes; the scene is shaded in linear space
+    out.color = pow(point.color.rgb, vec3<f32>(2.2));
+    out.corner = corner;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if (dot(in.corner, in.corner) > 1.0) {
+        discard;
+    }
+    return vec4<f32>(in.color, 1.0);
+}
@@ -0,0 +1,17 @@
+// Compressed vertex layout shared by the vs_quantized entry points. Positions
+// are relative to the model bounds; object.model maps them back.
+struct QuantizedVertexInput {
+    @location(0) position: vec4<f32>,
+    @location(1) normal: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) tex_coords: vec2<f32>,
+}
+
+// Octahedral normal encoding back to a unit vector
+fn oct_decode(encoded: vec2<f32>) -> vec3<f32> {
+    var normal = vec3<f32>(encoded.x, encoded.y, 1.0 - abs(encoded.x) - abs(encoded.y));
+    let fold = max(-normal.z, 0.0);
+    normal.x += select(fold, -fold, normal.x >= 0.0);
+    normal.y += select(fold, -fold, normal.y >= 0.0);
+    return normalize(normal);
+}
@@ -0,0 +1,39 @@
+struct StillParams {
+    // Share of the new sample in the average, 1/n for the nth sample
+    weight: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+}
+
+struct StillOutput {
+    @location(0) average: vec4<f32>,
+    @location(1) hdr: vec4<f32>,
+}
+
+@group(0) @binding(0) var sample_texture: texture_2d<f32>;
+@group(0) @binding(1) var history_texture: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> params: StillParams;
+
+// Fullscreen triangle generated from the vertex index
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> StillOutput {
+    let coords = vec2<i32>(position.xy);
+    let sample = textureLoad(sample_texture, coords, 0);
+    var average = sample;
+    // The first sample replaces whatever the history held
+    if params.weight < 1.0 {
+        let history = textureLoad(history_texture, coords, 0);
+        average = history + (sample - history) * params.weight;
+    }
+    var out: StillOutput;
+    out.average = average;
+    out.hdr = average;
+    return out;
+}
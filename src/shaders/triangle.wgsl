@@ -2,6 +2,9 @@ struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) color: vec3<f32>,
+    @location(3) tex_coords: vec2<f32>,
+    @location(4) target_position: vec3<f32>,
+    @location(5) target_normal: vec3<f32>,
 }
 
 struct VertexOutput {
@@ -9,15 +12,39 @@ struct VertexOutput {
     @location(0) world_position: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) color: vec3<f32>,
+    @location(3) tex_coords: vec2<f32>,
 }
 
 struct CameraUniforms {
     view_projection: mat4x4<f32>,
     view_matrix: mat4x4<f32>,
     camera_position: vec3<f32>,
+    // 0 shows the model, 1 its morph target
+    morph_weight: f32,
+    // Fragments in front of the section plane (positive side) are cut away
+    clip_plane: vec4<f32>,
+    // Wireframe settings, unused here
+    line_color: vec4<f32>,
+    pixel_size: vec2<f32>,
+    line_width: f32,
+    // 1 when vertex colors are sRGB encoded and need decoding to shade
+    srgb_vertex_colors: f32,
+    // 0 lit, 1 world normal as RGB, 2 height, 3 distance from the centroid
+    shading_mode: u32,
+    // 0 viridis, 1 turbo, 2 rainbow, 3 grayscale
+    colormap: u32,
+    // Height or distance mapped to the ends of the colormap
+    value_range: vec2<f32>,
+    centroid: vec3<f32>,
+    // Mixed into back faces when w is 1
+    backface_color: vec4<f32>,
+    // Fill for the inside of the cut and outline along it, each shown when w is 1
+    section_cap: vec4<f32>,
+    section_contour: vec4<f32>,
 }
 
 struct LightUniforms {
+    // w is 1 for point lights and 0 for directional ones, which shine from xyz
     position: vec4<f32>,
     color: vec4<f32>,
     intensity: f32,
@@ -27,41 +54,320 @@ struct LightUniforms {
     shininess: f32,
 }
 
+struct Lights {
+    lights: array<LightUniforms, 8>,
+    count: u32,
+    // 0 without an environment map, which leaves ambient light to the lights
+    environment_intensity: f32,
+    // Radians about the world Y axis
+    environment_rotation: f32,
+    // Mip levels of the specular cube, one roughness each
+    specular_levels: f32,
+}
+
+struct ObjectUniforms {
+    model: mat4x4<f32>,
+    normal_matrix: mat4x4<f32>,
+    object_id: u32,
+    // Index into the model's materials, 0xffffffff for none
+    material_index: u32,
+}
+
+// MTL colors; diffuse.w is 1 when they replace the vertex colors
+struct MaterialUniforms {
+    diffuse: vec4<f32>,
+    // w is 1 for two-tone clay, this being the color of downward faces
+    ambient: vec4<f32>,
+    // w is the shininess, 0 to use the light's; clay's rim light color
+    specular: vec4<f32>,
+    // Metallic, roughness and normal map strength; w is 1 for physically
+    // based shading
+    pbr: vec4<f32>,
+}
+
 @group(0) @binding(0) var<uniform> camera: CameraUniforms;
-@group(1) @binding(0) var<uniform> light: LightUniforms;
+@group(1) @binding(0) var<uniform> lights: Lights;
+@group(1) @binding(1) var irradiance_texture: texture_cube<f32>;
+@group(1) @binding(2) var specular_texture: texture_cube<f32>;
+@group(1) @binding(3) var environment_sampler: sampler;
+@group(2) @binding(0) var diffuse_texture: texture_2d<f32>;
+@group(2) @binding(1) var diffuse_sampler: sampler;
+@group(2) @binding(2) var<uniform> material: MaterialUniforms;
+@group(2) @binding(3) var metallic_texture: texture_2d<f32>;
+@group(2) @binding(4) var roughness_texture: texture_2d<f32>;
+@group(2) @binding(5) var normal_texture: texture_2d<f32>;
+@group(2) @binding(6) var occlusion_texture: texture_2d<f32>;
+
+const PI: f32 = 3.14159265;
+@group(3) @binding(0) var<uniform> object: ObjectUniforms;
 
 @vertex
 fn vs_main(
     model: VertexInput,
 ) -> VertexOutput {
+    let morphed = mix(model.position, model.target_position, camera.morph_weight);
+    let normal = mix(model.normal, model.target_normal, camera.morph_weight);
+    return transform_vertex(morphed, normal, model.color, model.tex_coords);
+}
+
+@vertex
+fn vs_quantized(
+    model: QuantizedVertexInput,
+) -> VertexOutput {
+    return transform_vertex(model.position.xyz, oct_decode(model.normal), model.color.rgb, model.tex_coords);
+}
+
+fn transform_vertex(local_position: vec3<f32>, normal: vec3<f32>, color: vec3<f32>, tex_coords: vec2<f32>) -> VertexOutput {
     var out: VertexOutput;
-    out.world_position = model.position;
-    out.normal = model.normal;
-    out.color = model.color;
-    out.clip_position = camera.view_projection * vec4<f32>(model.position, 1.0);
+    let position = (object.model * vec4<f32>(local_position, 1.0)).xyz;
+    out.world_position = position;
+    out.normal = (object.normal_matrix * vec4<f32>(normal, 0.0)).xyz;
+    out.color = select(color, pow(color, vec3<f32>(2.2)), camera.srgb_vertex_colors > 0.5);
+    // OBJ texture coordinates have V pointing up
+    out.tex_coords = vec2<f32>(tex_coords.x, 1.0 - tex_coords.y);
+    out.clip_position = camera.view_projection * vec4<f32>(position, 1.0);
     return out;
 }
 
 @fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let normal = normalize(in.normal);
-    let light_dir = normalize(light.position.xyz - in.world_position);
+fn fs_main(vertex: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4<f32> {
+    let side = dot(camera.clip_plane.xyz, vertex.world_position) + camera.clip_plane.w;
+    // How far the plane distance changes across a pixel, for the contour
+    let side_width = fwidth(side);
+    if (side > 0.0) {
+        discard;
+    }
+    // Back faces, drawn when they aren't culled, are lit from the side the
+    // camera sees
+    var in = vertex;
+    if (!front_facing) {
+        in.normal = -in.normal;
+    }
+    var color: vec3<f32>;
+    if (!front_facing && camera.section_cap.w > 0.5) {
+        // The inside of the part seen through the cut, filled in flat so
+        // it reads as a solid face on the plane
+        color = camera.section_cap.rgb;
+    } else {
+        color = shade(in);
+        if (!front_facing && camera.backface_color.w > 0.5) {
+            color = mix(color, camera.backface_color.rgb, 0.6);
+        }
+    }
+    if (camera.section_contour.w > 0.5 && side > -1.5 * side_width) {
+        color = camera.section_contour.rgb;
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+fn shade(in: VertexOutput) -> vec3<f32> {
     let view_dir = normalize(camera.camera_position - in.world_position);
-    let reflect_dir = reflect(-light_dir, normal);
-    
-    // Ambient lighting
-    let ambient = light.ambient_strength * light.color.xyz;
-    
-    // Diffuse lighting
-    let diff = max(dot(normal, light_dir), 0.0);
-    let diffuse = light.diffuse_strength * diff * light.color.xyz;
-    
-    // Specular lighting
-    let spec = pow(max(dot(view_dir, reflect_dir), 0.0), light.shininess);
-    let specular = light.specular_strength * spec * light.color.xyz;
-    
+    if (camera.shading_mode > 0u) {
+        return false_color(in, view_dir);
+    }
+    if (material.pbr.w > 0.5) {
+        return shade_pbr(in, view_dir);
+    }
+    let normal = normalize(in.normal);
+
+    var ambient = vec3<f32>(0.0);
+    var diffuse = vec3<f32>(0.0);
+    var specular = vec3<f32>(0.0);
+    for (var i = 0u; i < lights.count; i = i + 1u) {
+        let light = lights.lights[i];
+        let light_color = light.color.xyz * light.intensity;
+        let light_dir = normalize(light.position.xyz - in.world_position * light.position.w);
+        let reflect_dir = reflect(-light_dir, normal);
+
+        // Ambient lighting
+        ambient += light.ambient_strength * light_color;
+
+        // Diffuse lighting
+        let diff = max(dot(normal, light_dir), 0.0);
+        diffuse += light.diffuse_strength * diff * light_color;
+
+        // Specular lighting
+        let shininess = select(light.shininess, material.specular.w, material.specular.w > 0.0);
+        let spec = pow(max(dot(view_dir, reflect_dir), 0.0), shininess);
+        specular += light.specular_strength * spec * light_color;
+    }
+
+    var environment = vec3<f32>(0.0);
+    var reflection = vec3<f32>(0.0);
+    if (lights.environment_intensity > 0.0) {
+        // The environment replaces the flat ambient term and adds a faint
+        // reflection, roughly as rough as the highlights are wide
+        let shininess = select(select(32.0, lights.lights[0].shininess, lights.count > 0u), material.specular.w, material.specular.w > 0.0);
+        let roughness = clamp(sqrt(2.0 / (shininess + 2.0)), 0.0, 1.0);
+        let n_dot_v = max(dot(normal, view_dir), 0.0);
+        let fresnel = 0.04 + 0.96 * pow(1.0 - n_dot_v, 5.0);
+        ambient = vec3<f32>(0.0);
+        environment = environment_irradiance(normal);
+        reflection = environment_specular(reflect(-view_dir, normal), roughness) * fresnel;
+    }
+
+    if (material.ambient.w > 0.5) {
+        // Clay: no textures, a color blended from the bottom one to the top
+        // one as the surface turns up, and a rim light at grazing angles
+        let albedo = mix(material.ambient.rgb, material.diffuse.rgb, normal.y * 0.5 + 0.5);
+        let rim = pow(1.0 - max(dot(normal, view_dir), 0.0), 3.0) * material.specular.rgb;
+        return (ambient + diffuse + environment) * albedo + specular + reflection + rim;
+    }
+
     // Combine lighting
-    let result = (ambient + diffuse + specular) * in.color;
-    
-    return vec4<f32>(result, 1.0);
-} 
\ No newline at end of file
+    let texel = textureSample(diffuse_texture, diffuse_sampler, in.tex_coords).rgb;
+    var result = (ambient + diffuse + specular + environment) * in.color * texel + reflection;
+    if (material.diffuse.w > 0.5) {
+        // MTL: Ka and Kd tint their terms, Ks gives untinted highlights
+        let albedo = material.diffuse.rgb * texel;
+        result = ambient * material.ambient.rgb * texel + (diffuse + environment) * albedo + specular * material.specular.rgb + reflection;
+    }
+
+    return result;
+}
+
+// Tangent frame from screen-space derivatives, so normal maps work without
+// per-vertex tangents
+fn perturb_normal(normal: vec3<f32>, position: vec3<f32>, uv: vec2<f32>, map: vec3<f32>) -> vec3<f32> {
+    let dp1 = dpdx(position);
+    let dp2 = dpdy(position);
+    let duv1 = dpdx(uv);
+    let duv2 = dpdy(uv);
+    let dp2perp = cross(dp2, normal);
+    let dp1perp = cross(normal, dp1);
+    // Texture V runs down here, opposite to the tangent-space convention
+    let tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    let bitangent = -(dp2perp * duv1.y + dp1perp * duv2.y);
+    let scale = inverseSqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+    let perturbed = map.x * tangent * scale + map.y * bitangent * scale + map.z * normal;
+    return select(normal, normalize(perturbed), dot(perturbed, perturbed) > 1e-12);
+}
+
+// Cook-Torrance with a GGX distribution, Smith geometry and Schlick Fresnel
+fn shade_pbr(in: VertexOutput, view_dir: vec3<f32>) -> vec3<f32> {
+    let base_color = textureSample(diffuse_texture, diffuse_sampler, in.tex_coords).rgb;
+    let metallic_texel = textureSample(metallic_texture, diffuse_sampler, in.tex_coords);
+    let roughness_texel = textureSample(roughness_texture, diffuse_sampler, in.tex_coords);
+    let normal_texel = textureSample(normal_texture, diffuse_sampler, in.tex_coords).xyz;
+    let occlusion = textureSample(occlusion_texture, diffuse_sampler, in.tex_coords).r;
+
+    let albedo = select(in.color, material.diffuse.rgb, material.diffuse.w > 0.5) * base_color;
+    let metallic = clamp(material.pbr.x * metallic_texel.b, 0.0, 1.0);
+    // Very smooth surfaces turn lights into invisible points
+    let roughness = clamp(material.pbr.y * roughness_texel.g, 0.04, 1.0);
+    let map = (normal_texel * 2.0 - 1.0) * vec3<f32>(material.pbr.z, material.pbr.z, 1.0);
+    let normal = perturb_normal(normalize(in.normal), in.world_position, in.tex_coords, map);
+
+    let alpha = roughness * roughness;
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+    let n_dot_v = max(dot(normal, view_dir), 1e-4);
+    var ambient = vec3<f32>(0.0);
+    var direct = vec3<f32>(0.0);
+    for (var i = 0u; i < lights.count; i = i + 1u) {
+        let light = lights.lights[i];
+        let light_color = light.color.xyz * light.intensity;
+        ambient += light.ambient_strength * light_color;
+
+        let light_dir = normalize(light.position.xyz - in.world_position * light.position.w);
+        let half_dir = normalize(light_dir + view_dir);
+        let n_dot_l = max(dot(normal, light_dir), 0.0);
+        let n_dot_h = max(dot(normal, half_dir), 0.0);
+        let h_dot_v = max(dot(half_dir, view_dir), 0.0);
+
+        let d_denom = n_dot_h * n_dot_h * (alpha * alpha - 1.0) + 1.0;
+        let distribution = alpha * alpha / (PI * d_denom * d_denom);
+        let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+        let geometry = n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+        let fresnel = f0 + (1.0 - f0) * pow(1.0 - h_dot_v, 5.0);
+
+        let specular = distribution * geometry * fresnel / max(4.0 * n_dot_v * n_dot_l, 1e-4);
+        let diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo / PI;
+        // Scaled by PI so a white lambertian surface matches the Phong diffuse
+        direct += (diffuse + specular) * light_color * n_dot_l * PI;
+    }
+
+    if (lights.environment_intensity > 0.0) {
+        // Split-sum image-based lighting with an analytic fit of the
+        // environment BRDF (Karis, "Physically Based Shading on Mobile")
+        let c0 = vec4<f32>(-1.0, -0.0275, -0.572, 0.022);
+        let c1 = vec4<f32>(1.0, 0.0425, 1.04, -0.04);
+        let r = roughness * c0 + c1;
+        let a004 = min(r.x * r.x, exp2(-9.28 * n_dot_v)) * r.x + r.y;
+        let scale_bias = vec2<f32>(-1.04, 1.04) * a004 + r.zw;
+        let specular_color = f0 * scale_bias.x + scale_bias.y;
+        let diffuse = environment_irradiance(normal) * albedo * (1.0 - metallic);
+        let specular = environment_specular(reflect(-view_dir, normal), roughness) * specular_color;
+        return direct + (diffuse + specular) * occlusion;
+    }
+
+    // No environment to reflect, so metals take the ambient term in their own color
+    let ambient_color = mix(albedo, f0, metallic);
+    return direct + ambient * ambient_color * occlusion;
+}
+
+// Directions in the environment maps, which turn with the environment
+fn environment_direction(direction: vec3<f32>) -> vec3<f32> {
+    let c = cos(lights.environment_rotation);
+    let s = sin(lights.environment_rotation);
+    return vec3<f32>(c * direction.x + s * direction.z, direction.y, -s * direction.x + c * direction.z);
+}
+
+fn environment_irradiance(normal: vec3<f32>) -> vec3<f32> {
+    let irradiance = textureSampleLevel(irradiance_texture, environment_sampler, environment_direction(normal), 0.0).rgb;
+    return irradiance * lights.environment_intensity;
+}
+
+fn environment_specular(direction: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let lod = roughness * (lights.specular_levels - 1.0);
+    let radiance = textureSampleLevel(specular_texture, environment_sampler, environment_direction(direction), lod).rgb;
+    return radiance * lights.environment_intensity;
+} 
+// Shading modes showing a property of the surface instead of lighting it.
+// The colors are meant as displayed, so they're decoded to linear first.
+fn false_color(in: VertexOutput, view_dir: vec3<f32>) -> vec3<f32> {
+    let normal = normalize(in.normal);
+    if (camera.shading_mode == 1u) {
+        return pow(normal * 0.5 + 0.5, vec3<f32>(2.2));
+    }
+    var value = in.world_position.y;
+    if (camera.shading_mode == 3u) {
+        value = distance(in.world_position, camera.centroid);
+    }
+    let t = clamp((value - camera.value_range.x) / max(camera.value_range.y - camera.value_range.x, 1e-6), 0.0, 1.0);
+    // A little shading from the camera keeps the shape readable
+    return pow(colormap(t), vec3<f32>(2.2)) * (0.6 + 0.4 * abs(dot(normal, view_dir)));
+}
+
+fn colormap(t: f32) -> vec3<f32> {
+    switch camera.colormap {
+        // Polynomial fits of matplotlib's viridis and Google's turbo
+        case 0u: {
+            let c0 = vec3<f32>(0.2777273, 0.0054073, 0.3340998);
+            let c1 = vec3<f32>(0.1050930, 1.4046134, 1.3845901);
+            let c2 = vec3<f32>(-0.3308618, 0.2148476, 0.0950952);
+            let c3 = vec3<f32>(-4.6342305, -5.7991007, -19.3324409);
+            let c4 = vec3<f32>(6.2282699, 14.1799334, 56.6905526);
+            let c5 = vec3<f32>(4.7763850, -13.7451454, -65.3530326);
+            let c6 = vec3<f32>(-5.4354559, 4.6458526, 26.3124352);
+            return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6)))));
+        }
+        case 1u: {
+            let v4 = vec4<f32>(1.0, t, t * t, t * t * t);
+            let v2 = v4.zw * v4.z;
+            return vec3<f32>(
+                dot(v4, vec4<f32>(0.13572138, 4.61539260, -42.66032258, 132.13108234)) + dot(v2, vec2<f32>(-152.94239396, 59.28637943)),
+                dot(v4, vec4<f32>(0.09140261, 2.19418839, 4.84296658, -14.18503333)) + dot(v2, vec2<f32>(4.27729857, 2.82956604)),
+                dot(v4, vec4<f32>(0.10667330, 12.64194608, -60.58204836, 110.36276771)) + dot(v2, vec2<f32>(-89.90310912, 27.34824973)),
+            );
+        }
+        // Hue from blue to red
+        case 2u: {
+            let hue = (1.0 - t) * 0.66 * 6.0;
+            let rgb = clamp(abs((hue + vec3<f32>(0.0, 4.0, 2.0)) % 6.0 - 3.0) - 1.0, vec3<f32>(0.0), vec3<f32>(1.0));
+            return 0.95 * mix(vec3<f32>(1.0), rgb, 0.85);
+        }
+        default: {
+            return vec3<f32>(t);
+        }
+    }
+}
@@ -2,30 +2,90 @@ struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) color: vec3<f32>,
+    @location(4) target_position: vec3<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
+    @location(1) world_position: vec3<f32>,
 }
 
 struct CameraUniforms {
     view_projection: mat4x4<f32>,
+    view_matrix: mat4x4<f32>,
+    camera_position: vec3<f32>,
+    morph_weight: f32,
+    clip_plane: vec4<f32>,
+    // w is 1 where the line color replaces the vertex colors
+    line_color: vec4<f32>,
+    pixel_size: vec2<f32>,
+    line_width: f32,
+    srgb_vertex_colors: f32,
+}
+
+struct ObjectUniforms {
+    model: mat4x4<f32>,
+    normal_matrix: mat4x4<f32>,
+    object_id: u32,
+    // Index into the model's materials, 0xffffffff for none
+    material_index: u32,
 }
 
 @group(0) @binding(0) var<uniform> camera: CameraUniforms;
+@group(2) @binding(0) var<uniform> object: ObjectUniforms;
 
 @vertex
 fn vs_main(
     model: VertexInput,
+    @builtin(instance_index) instance: u32,
 ) -> VertexOutput {
+    let position = object.model * vec4<f32>(mix(model.position, model.target_position, camera.morph_weight), 1.0);
+    return line_vertex(position, model.color, instance);
+}
+
+@vertex
+fn vs_quantized(
+    model: QuantizedVertexInput,
+    @builtin(instance_index) instance: u32,
+) -> VertexOutput {
+    let position = object.model * vec4<f32>(model.position.xyz, 1.0);
+    return line_vertex(position, model.color.rgb, instance);
+}
+
+fn line_vertex(position: vec4<f32>, color: vec3<f32>, instance: u32) -> VertexOutput {
     var out: VertexOutput;
-    out.color = model.color;
-    out.clip_position = camera.view_projection * vec4<f32>(model.position, 1.0);
+    out.color = select(color, pow(color, vec3<f32>(2.2)), camera.srgb_vertex_colors > 0.5);
+    out.world_position = position.xyz;
+    out.clip_position = camera.view_projection * position;
+    // Wide lines are drawn once per pixel of their width, a row of copies
+    // shifted sideways and then a column shifted up, which widens lines of
+    // any direction
+    let width = u32(camera.line_width);
+    let step = f32(instance % width) - f32(width - 1u) * 0.5;
+    let offset = select(vec2<f32>(step, 0.0), vec2<f32>(0.0, step), instance >= width);
+    out.clip_position += vec4<f32>(offset * camera.pixel_size * out.clip_position.w, 0.0, 0.0);
     return out;
 }
 
+// Whether the section plane cuts away a point
+fn clipped(world_position: vec3<f32>) -> bool {
+    return dot(camera.clip_plane.xyz, world_position) + camera.clip_plane.w > 0.0;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(in.color, 1.0);
-} 
\ No newline at end of file
+    if (clipped(in.world_position)) {
+        discard;
+    }
+    return vec4<f32>(select(in.color, camera.line_color.rgb, camera.line_color.w > 0.5), 1.0);
+}
+
+// Edges drawn over the shaded mesh in hidden-line mode
+@fragment
+fn fs_edges(in: VertexOutput) -> @location(0) vec4<f32> {
+    if (clipped(in.world_position)) {
+        discard;
+    }
+    return vec4<f32>(camera.line_color.rgb, 1.0);
+}
@@ -0,0 +1,346 @@
+use glam::Vec3;
+use std::collections::HashMap;
+
+use crate::mesh::{Mesh, Vertex};
+
+/// Where vertex normals come from when a model is loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalMode {
+    /// The file's normals, averaged from the faces where it has none.
+    #[default]
+    FromFile,
+    /// Averaged over the faces meeting at each position, across UV seams
+    /// and split vertices, except where they meet at a sharp angle.
+    Smooth,
+    /// Each face's own normal, with vertices duplicated per face.
+    Flat,
+}
+
+impl NormalMode {
+    pub const ALL: [NormalMode; 3] = [NormalMode::FromFile, NormalMode::Smooth, NormalMode::Flat];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            NormalMode::FromFile => "From file",
+            NormalMode::Smooth => "Smooth",
+            NormalMode::Flat => "Flat",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadingOptions {
+    pub normals: NormalMode,
+    /// Degrees; faces meeting at a sharper angle keep a hard edge when
+    /// smoothing.
+    pub smoothing_angle: f32,
+}
+
+impl Default for ShadingOptions {
+    fn default() -> Self {
+        Self {
+            normals: NormalMode::FromFile,
+            smoothing_angle: 30.0,
+        }
+    }
+}
+
+/// Replaces the normals of a freshly loaded `mesh` as `options` ask. Each
+/// corner gets its own normal, and vertices are split wherever corners
+/// sharing one end up with different normals. Draw ranges are shaded on
+/// their own, so separate objects never smooth into each other.
+pub fn apply(mesh: &mut Mesh, options: ShadingOptions) {
+    mesh.normals = options.normals;
+    if options.normals == NormalMode::FromFile || mesh.indices.is_empty() {
+        return;
+    }
+    // Flat shading only lets a face's own normal through
+    let min_cos = match options.normals {
+        NormalMode::Smooth => options.smoothing_angle.clamp(0.0, 180.0).to_radians().cos(),
+        _ => f32::INFINITY,
+    };
+
+    let mut vertices = Vec::with_capacity(mesh.vertices.len());
+    let mut indices = mesh.indices.clone();
+    // Corners with the same vertex and normal still share one
+    let mut split: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+    for range in mesh.draw_ranges.iter().filter(|range| range.count > 0) {
+        let corners = &mut indices[range.start as usize..(range.start + range.count) as usize];
+        let position = |index: u32| Vec3::from(mesh.vertices[index as usize].position);
+        // Area-weighted, as the cross product's length is twice the area
+        let face_normals: Vec<Vec3> = corners
+            .chunks_exact(3)
+            .map(|triangle| (position(triangle[1]) - position(triangle[0])).cross(position(triangle[2]) - position(triangle[0])))
+            .collect();
+        let mut faces_at: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+        if min_cos.is_finite() {
+            for (corner, &index) in corners.iter().enumerate() {
+                faces_at.entry(position(index).to_array().map(f32::to_bits)).or_default().push(corner / 3);
+            }
+        }
+
+        for (corner, index) in corners.iter_mut().enumerate() {
+            let face = corner / 3;
+            let own = face_normals[face].normalize_or_zero();
+            let sum = match faces_at.get(&position(*index).to_array().map(f32::to_bits)) {
+                Some(faces) => faces
+                    .iter()
+                    .map(|&other| face_normals[other])
+                    .filter(|normal| normal.normalize_or_zero().dot(own) >= min_cos)
+                    .sum(),
+                None => own,
+            };
+            let vertex = mesh.vertices[*index as usize];
+            let normal = sum.try_normalize().map_or(vertex.normal, Into::into);
+            *index = *split.entry((*index, normal.map(f32::to_bits))).or_insert_with(|| {
+                vertices.push(Vertex { normal, ..vertex });
+                vertices.len() as u32 - 1
+            });
+        }
+    }
+    mesh.vertices = vertices;
+    mesh.indices = indices;
+}
+
+/// Gives `target`, a morph target loaded like `base` and in its index
+/// order, the vertex layout `apply` made for `base`, with normals from the
+/// target's own faces. Leaves it alone when `base` kept the file's normals.
+pub fn follow(target: &mut Mesh, base: &Mesh) {
+    if base.normals == NormalMode::FromFile || target.indices.len() != base.indices.len() {
+        return;
+    }
+    let mut vertices = base.vertices.clone();
+    let mut sums = vec![Vec3::ZERO; vertices.len()];
+    for (target_triangle, base_triangle) in target.indices.chunks_exact(3).zip(base.indices.chunks_exact(3)) {
+        let [a, b, c] = [0, 1, 2].map(|corner| Vec3::from(target.vertices[target_triangle[corner] as usize].position));
+        let face_normal = (b - a).cross(c - a);
+        for (&target_index, &base_index) in target_triangle.iter().zip(base_triangle) {
+            vertices[base_index as usize].position = target.vertices[target_index as usize].position;
+            sums[base_index as usize] += face_normal;
+        }
+    }
+    for (vertex, sum) in vertices.iter_mut().zip(sums) {
+        vertex.normal = sum.try_normalize().map_or(vertex.normal, Into::into);
+    }
+    target.vertices = vertices;
+    target.indices = base.indices.clone();
+    target.normals = base.normals;
+}
+
+/// How vertex colors from the file are read. Exporters disagree: some write
+/// the sRGB values a paint tool shows, others the linear ones it shades with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VertexColorSpace {
+    #[default]
+    Linear,
+    Srgb,
+}
+
+impl VertexColorSpace {
+    pub const ALL: [VertexColorSpace; 2] = [VertexColorSpace::Linear, VertexColorSpace::Srgb];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VertexColorSpace::Linear => "Linear",
+            VertexColorSpace::Srgb => "sRGB",
+        }
+    }
+}
+
+/// Lines drawn in wireframe and hidden-line display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineStyle {
+    /// Hidden-line edges always use it, the wireframe unless it shows vertex
+    /// colors.
+    pub color: [f32; 3],
+    pub vertex_colors: bool,
+    /// Pixels, drawn as that many one-pixel lines side by side.
+    pub width: u32,
+}
+
+impl LineStyle {
+    pub const MAX_WIDTH: u32 = 8;
+
+    /// Copies of each line to draw: a row and a column of `width` each,
+    /// which the wireframe shader offsets by the instance index.
+    pub fn instances(&self) -> u32 {
+        match self.width.clamp(1, Self::MAX_WIDTH) {
+            1 => 1,
+            width => 2 * width,
+        }
+    }
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        Self {
+            color: [0.02, 0.02, 0.02],
+            vertex_colors: true,
+            width: 1,
+        }
+    }
+}
+
+/// What shaded surfaces show: the lit model, or one of its geometric
+/// properties as false color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShadingMode {
+    #[default]
+    Lit,
+    /// World-space normal mapped to RGB.
+    Normal,
+    /// World Y through the colormap, bottom to top of the model.
+    Height,
+    /// Distance from the model's centroid through the colormap.
+    Distance,
+}
+
+impl ShadingMode {
+    pub const ALL: [ShadingMode; 4] = [ShadingMode::Lit, ShadingMode::Normal, ShadingMode::Height, ShadingMode::Distance];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ShadingMode::Lit => "Lit",
+            ShadingMode::Normal => "Normal",
+            ShadingMode::Height => "Height",
+            ShadingMode::Distance => "Distance from centroid",
+        }
+    }
+
+    pub fn uses_colormap(self) -> bool {
+        matches!(self, ShadingMode::Height | ShadingMode::Distance)
+    }
+}
+
+/// Color ramps for the height and distance modes, evaluated in the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    #[default]
+    Viridis,
+    Turbo,
+    /// Blue to red, like the analysis overlays.
+    Rainbow,
+    Grayscale,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 4] = [Colormap::Viridis, Colormap::Turbo, Colormap::Rainbow, Colormap::Grayscale];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Colormap::Viridis => "Viridis",
+            Colormap::Turbo => "Turbo",
+            Colormap::Rainbow => "Rainbow",
+            Colormap::Grayscale => "Grayscale",
+        }
+    }
+}
+
+/// Average vertex position of `mesh` and the largest distance of a vertex
+/// from it, the range of the distance mode.
+pub fn centroid(mesh: &Mesh) -> (Vec3, f32) {
+    if mesh.vertices.is_empty() {
+        return (Vec3::ZERO, 0.0);
+    }
+    let sum: Vec3 = mesh.vertices.iter().map(|vertex| Vec3::from(vertex.position)).sum();
+    let centroid = sum / mesh.vertices.len() as f32;
+    let radius = mesh.vertices.iter().map(|vertex| Vec3::from(vertex.position).distance(centroid)).fold(0.0, f32::max);
+    (centroid, radius)
+}
+
+/// Which side of the triangles is skipped when drawing shaded surfaces.
+/// Front faces wind counter-clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CullMode {
+    /// Double-sided: both sides are drawn, lit from the side facing the camera.
+    None,
+    #[default]
+    Back,
+    Front,
+}
+
+impl CullMode {
+    pub const ALL: [CullMode; 3] = [CullMode::None, CullMode::Back, CullMode::Front];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CullMode::None => "None (double-sided)",
+            CullMode::Back => "Back",
+            CullMode::Front => "Front",
+        }
+    }
+
+    pub fn face(self) -> Option<wgpu::Face> {
+        match self {
+            CullMode::None => None,
+            CullMode::Back => Some(wgpu::Face::Back),
+            CullMode::Front => Some(wgpu::Face::Front),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::MeshData;
+
+    // Two triangles folded along the x axis, their normals `angle` degrees apart
+    fn fold(angle: f32) -> Mesh {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let mut mesh = Mesh::new();
+        mesh.append(MeshData {
+            positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.5, 1.0, 0.0], [0.5, -cos, sin]],
+            triangles: vec![[0, 1, 2], [1, 0, 3]],
+            ..Default::default()
+        });
+        mesh
+    }
+
+    fn shade(mesh: &mut Mesh, normals: NormalMode, smoothing_angle: f32) {
+        apply(mesh, ShadingOptions { normals, smoothing_angle });
+    }
+
+    fn corner_normals(mesh: &Mesh) -> Vec<Vec3> {
+        mesh.indices.iter().map(|&index| Vec3::from(mesh.vertices[index as usize].normal)).collect()
+    }
+
+    #[test]
+    fn splits_vertices_at_sharp_edges() {
+        let mut mesh = fold(90.0);
+        shade(&mut mesh, NormalMode::Smooth, 30.0);
+        assert_eq!(mesh.vertices.len(), 6);
+        let normals = corner_normals(&mesh);
+        assert!(normals[..3].iter().all(|normal| normal.abs_diff_eq(Vec3::Z, 1e-5)));
+        assert!(normals[3..].iter().all(|normal| normal.abs_diff_eq(Vec3::Y, 1e-5)));
+    }
+
+    #[test]
+    fn smooths_edges_below_the_angle() {
+        let mut mesh = fold(20.0);
+        shade(&mut mesh, NormalMode::Smooth, 30.0);
+        // The two shared corners get the average of both faces
+        assert_eq!(mesh.vertices.len(), 4);
+        let expected = (Vec3::Z + Vec3::new(0.0, 20f32.to_radians().sin(), 20f32.to_radians().cos())).normalize();
+        let normals = corner_normals(&mesh);
+        assert!(normals[0].abs_diff_eq(expected, 1e-5) && normals[1].abs_diff_eq(expected, 1e-5));
+        assert!(normals[2].abs_diff_eq(Vec3::Z, 1e-5));
+
+        // A smaller angle keeps the edge hard
+        let mut mesh = fold(20.0);
+        shade(&mut mesh, NormalMode::Smooth, 10.0);
+        assert_eq!(mesh.vertices.len(), 6);
+    }
+
+    #[test]
+    fn flat_ignores_the_smoothing_angle() {
+        let mut mesh = fold(20.0);
+        shade(&mut mesh, NormalMode::Flat, 180.0);
+        assert_eq!(mesh.vertices.len(), 6);
+        assert!(corner_normals(&mesh)[..3].iter().all(|normal| normal.abs_diff_eq(Vec3::Z, 1e-5)));
+
+        // Corners of coplanar faces still share their vertex
+        let mut mesh = fold(0.0);
+        shade(&mut mesh, NormalMode::Flat, 180.0);
+        assert_eq!(mesh.vertices.len(), 4);
+    }
+}
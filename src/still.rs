@@ -0,0 +1,251 @@
+use glam::Vec2;
+use wgpu::util::DeviceExt;
+
+use crate::postprocess::HDR_FORMAT;
+use crate::shaders::create_shader_module;
+
+// Full float precision, so hundreds of small steps still average cleanly
+const HISTORY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct StillParams {
+    weight: f32,
+    _pad: [f32; 3],
+}
+
+/// Progressive supersampling for high quality stills: while the view holds
+/// still, each frame is rendered with a different sub-pixel offset into
+/// `sample_view` and folded into a running average, which is also written to
+/// the HDR target for display. Any change to what the frames show starts the
+/// average over.
+pub struct StillAccumulator {
+    width: u32,
+    height: u32,
+    sample_view: wgpu::TextureView,
+    // Ping-ponged averages; the resolve reads one and writes the other
+    history_views: [wgpu::TextureView; 2],
+    bind_groups: [wgpu::BindGroup; 2],
+    params_buffer: wgpu::Buffer,
+    pipeline: wgpu::RenderPipeline,
+    current: usize,
+    samples: u32,
+    // What the accumulated frames show, to notice when it changes
+    signature: Vec<u8>,
+}
+
+impl StillAccumulator {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let create_view = |label, format, usage| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING;
+        let sample_view = create_view("Still Sample Texture", HDR_FORMAT, usage);
+        let history_views = [
+            create_view("Still History Texture", HISTORY_FORMAT, usage),
+            create_view("Still History Texture", HISTORY_FORMAT, usage),
+        ];
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Still Params Buffer"),
+            contents: bytemuck::cast_slice(&[StillParams { weight: 1.0, _pad: [0.0; 3] }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Still Bind Group Layout"),
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_groups = [0, 1].map(|history| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Still Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&sample_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&history_views[history]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        let shader = create_shader_module(device, "Still Shader", include_str!("shaders/still.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Still Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Still Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: HISTORY_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    Some(wgpu::ColorTargetState {
+                        format: HDR_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            width,
+            height,
+            sample_view,
+            history_views,
+            bind_groups,
+            params_buffer,
+            pipeline,
+            current: 0,
+            samples: 0,
+            signature: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Samples averaged so far.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn reset(&mut self) {
+        self.samples = 0;
+    }
+
+    /// Starts over unless `signature` describes the same view as the samples
+    /// taken so far.
+    pub fn follow(&mut self, signature: Vec<u8>) {
+        if signature != self.signature {
+            self.signature = signature;
+            self.reset();
+        }
+    }
+
+    /// Offset to add to the projection for the next sample, in normalized
+    /// device coordinates: a Halton (2, 3) point within the pixel, so the
+    /// samples cover it evenly however many are taken.
+    pub fn jitter(&self) -> Vec2 {
+        let index = self.samples + 1;
+        let pixel = Vec2::new(halton(index, 2), halton(index, 3)) - 0.5;
+        pixel * 2.0 / Vec2::new(self.width as f32, self.height as f32)
+    }
+
+    /// Where the next sample is rendered.
+    pub fn sample_view(&self) -> &wgpu::TextureView {
+        &self.sample_view
+    }
+
+    /// Folds the sample just rendered into the average and writes the result
+    /// to `hdr_view`. The weight is written through the queue, so each sample
+    /// needs its own submit.
+    pub fn encode_resolve(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, hdr_view: &wgpu::TextureView) {
+        self.samples += 1;
+        let params = StillParams {
+            weight: 1.0 / self.samples as f32,
+            _pad: [0.0; 3],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+        let next = 1 - self.current;
+        let attachment = |view| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        };
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Still Resolve Pass"),
+                color_attachments: &[attachment(&self.history_views[next]), attachment(hdr_view)],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+        self.current = next;
+    }
+}
+
+// Element `index` of the van der Corput sequence in `base`
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
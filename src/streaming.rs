@@ -0,0 +1,227 @@
+use glam::{Mat4, Vec3};
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+use crate::cluster::{Cluster, Clusters};
+use crate::mesh::{self, Mesh, Vertex};
+
+/// GPU memory for model geometry; clustered models needing more are streamed.
+pub const DEFAULT_GEOMETRY_BUDGET_MB: u32 = 1024;
+
+const PROXY_COLOR: [f32; 3] = [0.45, 0.45, 0.5];
+// Indices of a cluster's box proxy, 6 per face
+const PROXY_INDICES: u32 = 36;
+// Edge indices of a box proxy: its faces don't share vertices, so each keeps
+// its 4 sides and diagonal, and the boxes' edges follow each other in order
+const PROXY_EDGE_INDICES: u32 = 60;
+
+struct ResidentCluster {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    edge_buffer: wgpu::Buffer,
+    num_edge_indices: u32,
+}
+
+/// Keeps the clusters of a model that doesn't fit the geometry budget on
+/// the GPU by camera proximity, each in its own small buffers. Clusters that
+/// aren't resident are drawn as their bounding boxes.
+pub struct ClusterStreamer {
+    pub budget: usize,
+    resident: Vec<Option<ResidentCluster>>,
+    resident_bytes: usize,
+    // GPU bytes of each cluster once resident
+    cluster_bytes: Vec<usize>,
+    proxies: Mesh,
+}
+
+impl ClusterStreamer {
+    pub fn new(device: &wgpu::Device, mesh: &Mesh, clusters: &Clusters, budget: usize) -> Self {
+        // Counts the distinct vertices of each cluster, stamping each vertex
+        // with the last cluster that used it
+        let mut last_cluster = vec![usize::MAX; mesh.vertices.len()];
+        let cluster_bytes = clusters
+            .iter()
+            .enumerate()
+            .map(|(i, cluster)| {
+                let indices = cluster_indices(mesh, cluster);
+                let mut vertex_count = 0;
+                for &index in indices {
+                    if last_cluster[index as usize] != i {
+                        last_cluster[index as usize] = i;
+                        vertex_count += 1;
+                    }
+                }
+                // Edges take about as many indices as the triangles
+                vertex_count * std::mem::size_of::<Vertex>() + std::mem::size_of_val(indices) * 2
+            })
+            .collect();
+
+        let mut proxies = Mesh::new();
+        for cluster in clusters.iter() {
+            push_box(&mut proxies, cluster.bounds);
+        }
+        proxies.create_buffers(device);
+
+        Self {
+            budget,
+            resident: clusters.iter().map(|_| None).collect(),
+            resident_bytes: 0,
+            cluster_bytes,
+            proxies,
+        }
+    }
+
+    /// Keeps the first clusters of `order` that fit the budget, drops the
+    /// others and uploads missing ones until `upload_budget` bytes have been
+    /// spent.
+    pub fn update(&mut self, device: &wgpu::Device, mesh: &Mesh, clusters: &Clusters, order: &[usize], mut upload_budget: usize) {
+        let mut wanted = vec![false; self.resident.len()];
+        let mut planned = 0;
+        for &i in order {
+            if planned + self.cluster_bytes[i] > self.budget {
+                break;
+            }
+            planned += self.cluster_bytes[i];
+            wanted[i] = true;
+        }
+
+        for (i, resident) in self.resident.iter_mut().enumerate() {
+            if !wanted[i] && resident.take().is_some() {
+                self.resident_bytes -= self.cluster_bytes[i];
+            }
+        }
+        for &i in order {
+            if upload_budget == 0 || !wanted[i] {
+                break;
+            }
+            if self.resident[i].is_none() {
+                self.resident[i] = Some(upload_cluster(device, mesh, clusters.get(i)));
+                self.resident_bytes += self.cluster_bytes[i];
+                upload_budget = upload_budget.saturating_sub(self.cluster_bytes[i]);
+            }
+        }
+    }
+
+    /// Vertex buffer, triangle or (with `edges`) edge index buffer, and
+    /// index count of cluster `i` when resident.
+    pub fn resident(&self, i: usize, edges: bool) -> Option<(&wgpu::Buffer, &wgpu::Buffer, u32)> {
+        self.resident[i].as_ref().map(|resident| {
+            if edges {
+                (&resident.vertex_buffer, &resident.edge_buffer, resident.num_edge_indices)
+            } else {
+                (&resident.vertex_buffer, &resident.index_buffer, resident.num_indices)
+            }
+        })
+    }
+
+    /// The vertex and triangle or edge index buffer holding one box per cluster.
+    pub fn proxy_buffers(&self, edges: bool) -> Option<(&wgpu::Buffer, &wgpu::Buffer)> {
+        let indices = if edges { self.proxies.get_edge_buffer() } else { self.proxies.get_index_buffer() };
+        self.proxies.get_vertex_buffer().zip(indices)
+    }
+
+    /// Indices of cluster `i`'s box in the proxy buffers.
+    pub fn proxy_indices(i: usize, edges: bool) -> std::ops::Range<u32> {
+        let count = if edges { PROXY_EDGE_INDICES } else { PROXY_INDICES };
+        i as u32 * count..(i as u32 + 1) * count
+    }
+
+    pub fn resident_count(&self) -> usize {
+        self.resident.iter().filter(|resident| resident.is_some()).count()
+    }
+
+    pub fn resident_bytes(&self) -> usize {
+        self.resident_bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.resident.len()
+    }
+}
+
+/// Clusters in streaming priority: the visible ones first, each group
+/// nearest to `eye` first.
+pub fn priority_order(clusters: &Clusters, transforms: &[Mat4], visibility: &[bool], eye: Vec3) -> Vec<usize> {
+    let mut order: Vec<(bool, f32, usize)> = clusters
+        .iter()
+        .enumerate()
+        .map(|(i, cluster)| {
+            let transform = transforms.get(cluster.object).copied().unwrap_or(Mat4::IDENTITY);
+            let (min, max) = cluster.bounds;
+            let center = transform.transform_point3((min + max) * 0.5);
+            let radius = transform.transform_vector3((max - min) * 0.5).length();
+            let hidden = !visibility.get(i).copied().unwrap_or(true);
+            (hidden, (center.distance(eye) - radius).max(0.0), i)
+        })
+        .collect();
+    order.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    order.into_iter().map(|(_, _, i)| i).collect()
+}
+
+fn cluster_indices<'a>(mesh: &'a Mesh, cluster: &Cluster) -> &'a [u32] {
+    &mesh.indices[cluster.start as usize..(cluster.start + cluster.count) as usize]
+}
+
+// Copies the cluster's triangles into buffers of their own, renumbering the
+// vertices it uses from 0
+fn upload_cluster(device: &wgpu::Device, mesh: &Mesh, cluster: &Cluster) -> ResidentCluster {
+    let mut remap = HashMap::new();
+    let mut vertices = Vec::new();
+    let indices: Vec<u32> = cluster_indices(mesh, cluster)
+        .iter()
+        .map(|&index| {
+            *remap.entry(index).or_insert_with(|| {
+                vertices.push(mesh.vertices[index as usize]);
+                vertices.len() as u32 - 1
+            })
+        })
+        .collect();
+    let edges = mesh::unique_edges(&indices);
+    ResidentCluster {
+        vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cluster Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        }),
+        index_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cluster Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        }),
+        num_indices: indices.len() as u32,
+        edge_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cluster Edge Buffer"),
+            contents: bytemuck::cast_slice(&edges),
+            usage: wgpu::BufferUsages::INDEX,
+        }),
+        num_edge_indices: edges.len() as u32,
+    }
+}
+
+// Appends an outward-facing box with flat normals
+fn push_box(mesh: &mut Mesh, (min, max): (Vec3, Vec3)) {
+    let center = (min + max) * 0.5;
+    let half = (max - min) * 0.5;
+    for axis in 0..3 {
+        for sign in [1.0, -1.0] {
+            let normal = Vec3::AXES[axis] * sign;
+            // u x v points along the normal, so the corners wind counter-clockwise seen from outside
+            let (mut u, mut v) = (Vec3::AXES[(axis + 1) % 3], Vec3::AXES[(axis + 2) % 3]);
+            if sign < 0.0 {
+                std::mem::swap(&mut u, &mut v);
+            }
+            let base = mesh.vertices.len() as u32;
+            for (du, dv) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                let position = center + (normal + u * du + v * dv) * half;
+                mesh.vertices.push(Vertex {
+                    position: position.into(),
+                    normal: normal.into(),
+                    color: PROXY_COLOR,
+                    tex_coords: [0.0, 0.0],
+                });
+            }
+            mesh.indices.extend([0, 1, 2, 0, 2, 3].map(|corner| base + corner));
+        }
+    }
+}
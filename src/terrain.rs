@@ -0,0 +1,117 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+use crate::mesh::MeshData;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainOptions {
+    /// Grid samples along the longer image edge, capped at the image size.
+    pub resolution: u32,
+    /// Height of a white pixel as a fraction of the terrain's width.
+    pub vertical_scale: f32,
+}
+
+impl Default for TerrainOptions {
+    fn default() -> Self {
+        Self {
+            resolution: 512,
+            vertical_scale: 0.2,
+        }
+    }
+}
+
+pub fn is_heightmap(path: &Path) -> bool {
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    extension.as_deref() == Some("png")
+}
+
+/// Builds a grid mesh from a grayscale image, one unit per source pixel,
+/// centered on the origin with heights along +Y. Colored images are
+/// converted to luminance; 16-bit images keep their full precision.
+pub fn load(path: &Path, options: TerrainOptions) -> Result<MeshData> {
+    let (width, height, samples) = load_heights(path)?;
+    if width < 2 || height < 2 {
+        bail!("Height map {:?} is too small ({}x{})", path, width, height);
+    }
+
+    let longest = width.max(height);
+    let resolution = options.resolution.clamp(2, longest);
+    let columns = ((width as u64 * resolution as u64 / longest as u64) as u32).max(2);
+    let rows = ((height as u64 * resolution as u64 / longest as u64) as u32).max(2);
+    let vertical_scale = options.vertical_scale * (width - 1) as f32;
+
+    // Bilinear lookup in source pixel coordinates
+    let sample = |x: f32, y: f32| {
+        let x0 = (x.floor() as u32).min(width - 2);
+        let y0 = (y.floor() as u32).min(height - 2);
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let at = |x: u32, y: u32| samples[(y * width + x) as usize];
+        let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1, y0) * fx;
+        let bottom = at(x0, y0 + 1) * (1.0 - fx) + at(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    };
+
+    let mut positions = Vec::with_capacity((columns * rows) as usize);
+    for row in 0..rows {
+        let y = row as f32 * (height - 1) as f32 / (rows - 1) as f32;
+        for column in 0..columns {
+            let x = column as f32 * (width - 1) as f32 / (columns - 1) as f32;
+            positions.push([
+                x - (width - 1) as f32 * 0.5,
+                sample(x, y) * vertical_scale,
+                y - (height - 1) as f32 * 0.5,
+            ]);
+        }
+    }
+
+    let mut triangles = Vec::with_capacity(((columns - 1) * (rows - 1) * 2) as usize);
+    for row in 0..rows - 1 {
+        for column in 0..columns - 1 {
+            let a = row * columns + column;
+            let b = a + columns;
+            triangles.push([a, b, a + 1]);
+            triangles.push([a + 1, b, b + 1]);
+        }
+    }
+
+    Ok(MeshData {
+        name: path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "terrain".to_string()),
+        positions,
+        triangles,
+        ..Default::default()
+    })
+}
+
+// Returns heights normalized to 0..1
+fn load_heights(path: &Path) -> Result<(u32, u32, Vec<f32>)> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+
+    let values: Vec<f32> = match info.bit_depth {
+        png::BitDepth::Sixteen => buffer
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as f32 / 65535.0)
+            .collect(),
+        _ => buffer.iter().map(|&value| value as f32 / 255.0).collect(),
+    };
+    let heights = match info.color_type {
+        png::ColorType::Grayscale => values,
+        png::ColorType::GrayscaleAlpha => values.chunks_exact(2).map(|pixel| pixel[0]).collect(),
+        png::ColorType::Rgb => values.chunks_exact(3).map(luminance).collect(),
+        png::ColorType::Rgba => values.chunks_exact(4).map(luminance).collect(),
+        png::ColorType::Indexed => bail!("Indexed PNG was not expanded"),
+    };
+    Ok((info.width, info.height, heights))
+}
+
+fn luminance(pixel: &[f32]) -> f32 {
+    0.2126 * pixel[0] + 0.7152 * pixel[1] + 0.0722 * pixel[2]
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+/// GPU memory for cached textures; past it, unused ones are evicted.
+pub const DEFAULT_TEXTURE_BUDGET_MB: u32 = 512;
+
+/// Identifies an image by the file holding it (the model, for images
+/// embedded in it) and that file's modification time, so a rewritten file
+/// is decoded again.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextureKey {
+    path: PathBuf,
+    embedded: Option<String>,
+    modified: Option<SystemTime>,
+    // Uploaded as data (normal, metallic, ...) rather than sRGB color
+    linear: bool,
+}
+
+impl TextureKey {
+    pub fn file(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            embedded: None,
+            modified: modified_time(path),
+            linear: false,
+        }
+    }
+
+    /// Image `name` embedded in the model file `model_path`, e.g. a GLB.
+    pub fn embedded(model_path: &Path, name: &str) -> Self {
+        Self {
+            embedded: Some(name.to_string()),
+            ..Self::file(model_path)
+        }
+    }
+
+    /// The same image read as linear data, which is a texture of its own.
+    pub fn linear(self) -> Self {
+        Self { linear: true, ..self }
+    }
+
+    /// The image file, or the model file for embedded images.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+struct CachedTexture {
+    texture: Rc<wgpu::Texture>,
+    bytes: usize,
+    last_used: u64,
+}
+
+/// Decoded and uploaded material textures, kept across model loads so
+/// switching back and forth between models doesn't decode their images
+/// again. Textures no material holds are evicted least recently used first
+/// once the cache grows past its budget.
+pub struct TextureCache {
+    entries: HashMap<TextureKey, CachedTexture>,
+    pub budget: usize,
+    clock: u64,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl TextureCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget,
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &TextureKey) -> Option<Rc<wgpu::Texture>> {
+        self.clock += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                self.hits += 1;
+                Some(entry.texture.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: TextureKey, texture: Rc<wgpu::Texture>) {
+        self.clock += 1;
+        let bytes = texture.width() as usize * texture.height() as usize * 4;
+        self.entries.insert(
+            key,
+            CachedTexture {
+                texture,
+                bytes,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    /// Forgets `texture`, e.g. one whose upload was cut short.
+    pub fn remove(&mut self, texture: &Rc<wgpu::Texture>) {
+        self.entries.retain(|_, entry| !Rc::ptr_eq(&entry.texture, texture));
+    }
+
+    /// Evicts unused textures, least recently used first, until the cache
+    /// fits its budget. Textures still in use stay even over budget.
+    pub fn trim(&mut self) {
+        let mut unused: Vec<(u64, TextureKey)> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| Rc::strong_count(&entry.texture) == 1)
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect();
+        unused.sort_unstable_by_key(|(last_used, _)| *last_used);
+        let mut unused = unused.into_iter();
+        while self.bytes() > self.budget {
+            let Some((_, key)) = unused.next() else {
+                break;
+            };
+            self.entries.remove(&key);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.entries.values().map(|entry| entry.bytes).sum()
+    }
+
+    /// Bytes of the textures no material holds.
+    pub fn unused_bytes(&self) -> usize {
+        self.entries
+            .values()
+            .filter(|entry| Rc::strong_count(&entry.texture) == 1)
+            .map(|entry| entry.bytes)
+            .sum()
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,136 @@
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use crate::material::{self, TextureSlot};
+use crate::texture_cache::TextureKey;
+
+const MAX_WORKERS: usize = 4;
+
+/// Where the image of a texture comes from.
+pub enum TextureSource {
+    File(PathBuf),
+    // Image bytes embedded in the model, e.g. in a GLB
+    Embedded(Vec<u8>),
+}
+
+/// A material texture to decode, handed back with the result so it can be
+/// installed once it's done.
+pub struct TextureRequest {
+    /// Index of the material in the loaded model.
+    pub material: usize,
+    pub name: String,
+    /// The texture as the material references it, for warnings.
+    pub texture: String,
+    pub key: Option<TextureKey>,
+    /// The map of the material the texture is for.
+    pub slot: TextureSlot,
+    pub source: TextureSource,
+    pub max_dimension: u32,
+}
+
+/// A finished request with its mip levels, largest first.
+pub struct DecodedTexture {
+    pub request: TextureRequest,
+    pub levels: Result<Vec<(u32, u32, Vec<u8>)>>,
+}
+
+/// Worker threads decoding material textures and building their mip levels
+/// off the render thread. Requests made before the last `cancel` are
+/// skipped, and their results dropped.
+pub struct TextureDecoder {
+    requests: Sender<(u64, TextureRequest)>,
+    results: Receiver<(u64, DecodedTexture)>,
+    generation: Arc<AtomicU64>,
+    // Requests of the current generation not handed back yet
+    in_flight: usize,
+}
+
+impl TextureDecoder {
+    pub fn new() -> Self {
+        let (requests, queue) = mpsc::channel::<(u64, TextureRequest)>();
+        let (finished, results) = mpsc::channel();
+        let queue = Arc::new(Mutex::new(queue));
+        let generation = Arc::new(AtomicU64::new(0));
+        let workers = std::thread::available_parallelism().map_or(1, usize::from).clamp(1, MAX_WORKERS);
+        for _ in 0..workers {
+            let queue = queue.clone();
+            let finished = finished.clone();
+            let current = generation.clone();
+            std::thread::spawn(move || loop {
+                // The lock is only held while waiting for the next request
+                let next = queue.lock().ok().and_then(|queue| queue.recv().ok());
+                let Some((generation, request)) = next else {
+                    return;
+                };
+                if generation != current.load(Ordering::Relaxed) {
+                    continue;
+                }
+                let levels = decode(&request);
+                if finished.send((generation, DecodedTexture { request, levels })).is_err() {
+                    return;
+                }
+            });
+        }
+        Self {
+            requests,
+            results,
+            generation,
+            in_flight: 0,
+        }
+    }
+
+    pub fn decode(&mut self, request: TextureRequest) {
+        if self.requests.send((self.generation.load(Ordering::Relaxed), request)).is_ok() {
+            self.in_flight += 1;
+        }
+    }
+
+    /// Drops every request made so far, e.g. when the model is replaced.
+    pub fn cancel(&mut self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        self.in_flight = 0;
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// Textures finished since the last call, without waiting.
+    pub fn poll(&mut self) -> Vec<DecodedTexture> {
+        let mut decoded = Vec::new();
+        while let Ok(result) = self.results.try_recv() {
+            self.accept(result, &mut decoded);
+        }
+        decoded
+    }
+
+    /// Waits for every request still in flight.
+    pub fn finish(&mut self) -> Vec<DecodedTexture> {
+        let mut decoded = self.poll();
+        while self.in_flight > 0 {
+            match self.results.recv() {
+                Ok(result) => self.accept(result, &mut decoded),
+                Err(_) => self.in_flight = 0,
+            }
+        }
+        decoded
+    }
+
+    fn accept(&mut self, (generation, texture): (u64, DecodedTexture), decoded: &mut Vec<DecodedTexture>) {
+        if generation == self.generation.load(Ordering::Relaxed) {
+            self.in_flight -= 1;
+            decoded.push(texture);
+        }
+    }
+}
+
+fn decode(request: &TextureRequest) -> Result<Vec<(u32, u32, Vec<u8>)>> {
+    let image = match &request.source {
+        TextureSource::File(path) => material::load_rgba(path)?,
+        TextureSource::Embedded(bytes) => material::decode_rgba(bytes)?,
+    };
+    Ok(material::mip_chain(material::fit_to_dimension(image, request.max_dimension)))
+}
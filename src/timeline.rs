@@ -0,0 +1,211 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::camera::CameraState;
+
+/// Frame-based playback clock shared by everything animated in the viewer:
+/// OBJ sequences, camera paths and the turntable.
+pub struct Timeline {
+    pub frame_count: u32,
+    pub fps: f32,
+    pub playing: bool,
+    pub looping: bool,
+    frame: u32,
+    // Seconds into the current frame
+    accumulated: f32,
+    last_tick: Option<Instant>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self {
+            frame_count: 120,
+            fps: 30.0,
+            playing: false,
+            looping: true,
+            frame: 0,
+            accumulated: 0.0,
+            last_tick: None,
+        }
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn set_frame(&mut self, frame: u32) {
+        self.frame = frame.min(self.frame_count.saturating_sub(1));
+        self.accumulated = 0.0;
+    }
+
+    /// Position in the timeline as 0..1, where 1 would be one frame past the end.
+    pub fn progress(&self, frame: u32) -> f32 {
+        frame as f32 / self.frame_count.max(1) as f32
+    }
+
+    /// Advances playback by the wall time since the last call. Returns
+    /// whether the current frame changed.
+    pub fn tick(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = self.last_tick.map_or(0.0, |last| (now - last).as_secs_f32());
+        self.last_tick = Some(now);
+        if !self.playing || self.fps <= 0.0 {
+            return false;
+        }
+
+        self.accumulated += elapsed;
+        let frame_time = 1.0 / self.fps;
+        let start = self.frame;
+        while self.accumulated >= frame_time {
+            self.accumulated -= frame_time;
+            if self.frame + 1 < self.frame_count {
+                self.frame += 1;
+            } else if self.looping {
+                self.frame = 0;
+            } else {
+                self.playing = false;
+                self.accumulated = 0.0;
+                break;
+            }
+        }
+        self.frame != start
+    }
+
+    /// Transport controls. Returns whether the user moved to another frame.
+    pub fn show(&mut self, ui: &mut egui::Ui) -> bool {
+        let start = self.frame;
+        ui.horizontal(|ui| {
+            if ui.button("⏮").on_hover_text("First frame").clicked() {
+                self.set_frame(0);
+            }
+            let label = if self.playing { "⏸ Pause" } else { "▶ Play" };
+            if ui.button(label).clicked() {
+                self.playing = !self.playing;
+                if self.playing && !self.looping && self.frame + 1 >= self.frame_count {
+                    self.set_frame(0);
+                }
+            }
+            if ui.button("⏭").on_hover_text("Last frame").clicked() {
+                self.set_frame(self.frame_count);
+            }
+            ui.checkbox(&mut self.looping, "Loop");
+        });
+
+        let mut frame = self.frame;
+        let last = self.frame_count.saturating_sub(1);
+        if ui.add(egui::Slider::new(&mut frame, 0..=last).text("Frame")).changed() {
+            self.set_frame(frame);
+        }
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut self.fps).clamp_range(1.0..=120.0).suffix(" fps"));
+            ui.add(egui::DragValue::new(&mut self.frame_count).clamp_range(1..=100_000).suffix(" frames"));
+        });
+        self.frame = self.frame.min(self.frame_count.saturating_sub(1));
+        self.frame != start
+    }
+}
+
+/// Numbered model files played back one per frame, e.g. `wave_0001.obj`,
+/// `wave_0002.obj`, ...
+pub struct FrameSequence {
+    pub frames: Vec<PathBuf>,
+}
+
+impl FrameSequence {
+    /// Collects every file next to `path` that shares its name prefix and
+    /// extension and ends in a frame number, ordered by that number.
+    pub fn discover(path: &Path) -> Result<Self> {
+        let (prefix, _) = split_frame_number(path).context("File name doesn't end in a frame number")?;
+        let extension = path.extension().map(|ext| ext.to_ascii_lowercase());
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+
+        let mut frames = Vec::new();
+        for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to list {:?}", dir))? {
+            let candidate = entry?.path();
+            if candidate.extension().map(|ext| ext.to_ascii_lowercase()) != extension {
+                continue;
+            }
+            if let Some((candidate_prefix, number)) = split_frame_number(&candidate) {
+                if candidate_prefix == prefix {
+                    frames.push((number, candidate));
+                }
+            }
+        }
+        if frames.len() < 2 {
+            bail!("No other numbered frames found next to {:?}", path);
+        }
+        frames.sort();
+        Ok(Self {
+            frames: frames.into_iter().map(|(_, path)| path).collect(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn path(&self, frame: u32) -> &Path {
+        &self.frames[frame as usize % self.frames.len()]
+    }
+}
+
+fn split_frame_number(path: &Path) -> Option<(String, u64)> {
+    let stem = path.file_stem()?.to_str()?;
+    let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let number = stem[prefix.len()..].parse().ok()?;
+    Some((prefix.to_string(), number))
+}
+
+/// Camera keyframes, interpolated linearly between keys and held before
+/// the first and after the last one.
+#[derive(Clone, Default)]
+pub struct CameraPath {
+    keys: Vec<(u32, CameraState)>,
+}
+
+impl CameraPath {
+    pub fn set_key(&mut self, frame: u32, state: CameraState) {
+        match self.keys.binary_search_by_key(&frame, |(key, _)| *key) {
+            Ok(index) => self.keys[index].1 = state,
+            Err(index) => self.keys.insert(index, (frame, state)),
+        }
+    }
+
+    pub fn remove_key(&mut self, frame: u32) {
+        self.keys.retain(|(key, _)| *key != frame);
+    }
+
+    pub fn has_key(&self, frame: u32) -> bool {
+        self.keys.iter().any(|(key, _)| *key == frame)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    pub fn keys(&self) -> &[(u32, CameraState)] {
+        &self.keys
+    }
+
+    pub fn sample(&self, frame: u32) -> Option<CameraState> {
+        let next = self.keys.iter().position(|(key, _)| *key >= frame);
+        match next {
+            None => self.keys.last().map(|(_, state)| *state),
+            Some(0) => self.keys.first().map(|(_, state)| *state),
+            Some(index) => {
+                let (start, from) = self.keys[index - 1];
+                let (end, to) = self.keys[index];
+                Some(from.lerp(&to, (frame - start) as f32 / (end - start) as f32))
+            }
+        }
+    }
+}
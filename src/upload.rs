@@ -0,0 +1,56 @@
+/// Bytes of deferred buffer and texture data written to the GPU per frame.
+/// Enough for a mid-sized model in a frame or two without stalling the
+/// frame when a large one is loaded.
+pub const DEFAULT_UPLOAD_BUDGET: usize = 16 << 20;
+
+/// Writes the next part of `data` into `buffer`, starting at `*written` and
+/// taking at most `*budget` bytes, which are taken off the budget. Returns
+/// true once all of `data` has been written.
+pub fn write_buffer_chunk(queue: &wgpu::Queue, buffer: &wgpu::Buffer, data: &[u8], written: &mut usize, budget: &mut usize) -> bool {
+    let align = wgpu::COPY_BUFFER_ALIGNMENT as usize;
+    let remaining = data.len() - *written;
+    // Buffer writes must stay 4-byte aligned; vertex and index data always are
+    let size = remaining.min(*budget / align * align);
+    if size > 0 {
+        queue.write_buffer(buffer, *written as u64, &data[*written..*written + size]);
+        *written += size;
+        *budget -= size;
+    }
+    *written == data.len()
+}
+
+/// Writes the next rows of a tightly packed RGBA8 image into mip level
+/// `mip_level` of `texture`, starting at row `*rows_written` and staying
+/// within `*budget` bytes. At least one row is written while there's budget
+/// left. Returns true once every row has been written.
+pub fn write_texture_rows(queue: &wgpu::Queue, texture: &wgpu::Texture, mip_level: u32, rgba: &[u8], rows_written: &mut u32, budget: &mut usize) -> bool {
+    let (width, height) = ((texture.width() >> mip_level).max(1), (texture.height() >> mip_level).max(1));
+    let row_bytes = width as usize * 4;
+    if *budget == 0 || *rows_written >= height {
+        return *rows_written >= height;
+    }
+    let rows = ((*budget / row_bytes).max(1) as u32).min(height - *rows_written);
+    let start = *rows_written as usize * row_bytes;
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level,
+            origin: wgpu::Origin3d { x: 0, y: *rows_written, z: 0 },
+            aspect: wgpu::TextureAspect::All,
+        },
+        &rgba[start..start + rows as usize * row_bytes],
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * 4),
+            rows_per_image: Some(rows),
+        },
+        wgpu::Extent3d {
+            width,
+            height: rows,
+            depth_or_array_layers: 1,
+        },
+    );
+    *rows_written += rows;
+    *budget = budget.saturating_sub(rows as usize * row_bytes);
+    *rows_written == height
+}
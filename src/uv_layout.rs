@@ -0,0 +1,95 @@
+use egui::{Color32, Pos2, Rect, Sense, Stroke};
+
+use crate::mesh::Mesh;
+
+// Edges beyond this many triangles are skipped to keep the panel interactive
+const MAX_DRAWN_TRIANGLES: usize = 50_000;
+
+/// Draws the mesh's UV layout as a wireframe in texture space. Clicking a
+/// face selects it; the selection is highlighted here and in the 3D view.
+pub fn show(ctx: &egui::Context, mesh: &Mesh, selected_face: &mut Option<usize>) {
+    egui::Window::new("UV Layout")
+        .default_open(false)
+        .resizable(true)
+        .default_size([320.0, 340.0])
+        .show(ctx, |ui| {
+            let side = ui.available_width().min(ui.available_height() - 20.0).max(64.0);
+            let (response, painter) = ui.allocate_painter(egui::vec2(side, side), Sense::click());
+            let rect = response.rect;
+
+            let (uv_min, uv_max) = uv_bounds(mesh);
+            let extent = (uv_max - uv_min).max_element().max(f32::EPSILON);
+            // Texture V points up, screen Y points down
+            let to_screen = |uv: [f32; 2]| {
+                Pos2::new(
+                    rect.left() + (uv[0] - uv_min.x) / extent * rect.width(),
+                    rect.bottom() - (uv[1] - uv_min.y) / extent * rect.height(),
+                )
+            };
+            let to_uv = |pos: Pos2| {
+                glam::Vec2::new(
+                    uv_min.x + (pos.x - rect.left()) / rect.width() * extent,
+                    uv_min.y + (rect.bottom() - pos.y) / rect.height() * extent,
+                )
+            };
+
+            painter.rect_filled(rect, 0.0, Color32::from_gray(24));
+            painter.rect_stroke(
+                Rect::from_two_pos(to_screen([0.0, 0.0]), to_screen([1.0, 1.0])),
+                0.0,
+                Stroke::new(1.0, Color32::from_gray(90)),
+            );
+
+            let stroke = Stroke::new(0.5, Color32::from_rgb(120, 200, 255));
+            for triangle in mesh.indices.chunks_exact(3).take(MAX_DRAWN_TRIANGLES) {
+                let [a, b, c] = [0, 1, 2].map(|i| to_screen(mesh.vertices[triangle[i] as usize].tex_coords));
+                painter.line_segment([a, b], stroke);
+                painter.line_segment([b, c], stroke);
+                painter.line_segment([c, a], stroke);
+            }
+
+            if let Some(face) = *selected_face {
+                if let Some(triangle) = mesh.indices.chunks_exact(3).nth(face) {
+                    let points = [0, 1, 2].map(|i| to_screen(mesh.vertices[triangle[i] as usize].tex_coords)).to_vec();
+                    painter.add(egui::Shape::convex_polygon(
+                        points,
+                        Color32::from_rgba_unmultiplied(255, 140, 0, 160),
+                        Stroke::new(1.5, Color32::from_rgb(255, 140, 0)),
+                    ));
+                }
+            }
+
+            if let Some(pos) = response.interact_pointer_pos().filter(|_| response.clicked()) {
+                *selected_face = face_at(mesh, to_uv(pos));
+            }
+
+            if mesh.triangle_count() > MAX_DRAWN_TRIANGLES {
+                ui.label(format!("Showing {} of {} faces", MAX_DRAWN_TRIANGLES, mesh.triangle_count()));
+            } else {
+                match *selected_face {
+                    Some(face) => ui.label(format!("Selected face {}", face)),
+                    None => ui.label("Click a face to select it"),
+                };
+            }
+        });
+}
+
+/// Bounds of all texture coordinates, always including the unit square.
+fn uv_bounds(mesh: &Mesh) -> (glam::Vec2, glam::Vec2) {
+    mesh.vertices.iter().fold((glam::Vec2::ZERO, glam::Vec2::ONE), |(min, max), vertex| {
+        let uv = glam::Vec2::from(vertex.tex_coords);
+        (min.min(uv), max.max(uv))
+    })
+}
+
+fn face_at(mesh: &Mesh, point: glam::Vec2) -> Option<usize> {
+    mesh.indices.chunks_exact(3).position(|triangle| {
+        let [a, b, c] = [0, 1, 2].map(|i| glam::Vec2::from(mesh.vertices[triangle[i] as usize].tex_coords));
+        let d1 = (b - a).perp_dot(point - a);
+        let d2 = (c - b).perp_dot(point - b);
+        let d3 = (a - c).perp_dot(point - c);
+        let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+        let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+        !(has_negative && has_positive)
+    })
+}
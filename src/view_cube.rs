@@ -0,0 +1,130 @@
+use glam::{Mat3, Vec3};
+
+use crate::camera::Camera;
+
+// Half the cube's edge on screen
+const HALF_SIZE_POINTS: f32 = 32.0;
+// Gap between the cube and the corner of the viewport
+const MARGIN_POINTS: f32 = 18.0;
+// Where a face's edge and corner strips start, as a fraction of its half size
+const EDGE: f32 = 0.6;
+const FACE_COLOR: egui::Color32 = egui::Color32::from_rgba_premultiplied(70, 74, 82, 220);
+const HOVER_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 210, 90);
+const OUTLINE_COLOR: egui::Color32 = egui::Color32::from_gray(25);
+
+// Faces with their outward normal and the two axes spanning them; the
+// labels follow the view presets, which look from +Z for the front
+const FACES: [(Vec3, Vec3, Vec3, &str); 6] = [
+    (Vec3::X, Vec3::Z, Vec3::Y, "Right"),
+    (Vec3::NEG_X, Vec3::Z, Vec3::Y, "Left"),
+    (Vec3::Y, Vec3::X, Vec3::Z, "Top"),
+    (Vec3::NEG_Y, Vec3::X, Vec3::Z, "Bottom"),
+    (Vec3::Z, Vec3::X, Vec3::Y, "Front"),
+    (Vec3::NEG_Z, Vec3::X, Vec3::Y, "Back"),
+];
+
+// One of the 3x3 cells a face is split into, with the direction clicking it
+// looks from: the face's normal, plus the neighbouring faces' for edge and
+// corner cells
+struct Cell {
+    direction: Vec3,
+    corners: [egui::Pos2; 4],
+}
+
+/// Orientation cube in the top right corner of the viewport, turned with
+/// the camera. Clicking a face, edge or corner looks at the target from
+/// that side, keeping the distance.
+pub struct ViewCube {
+    pub enabled: bool,
+    hovered: Option<Vec3>,
+}
+
+impl ViewCube {
+    pub fn new() -> Self {
+        Self { enabled: true, hovered: None }
+    }
+
+    /// Whether a left press goes to the cube rather than the camera.
+    pub fn is_active(&self) -> bool {
+        self.hovered.is_some()
+    }
+
+    /// Forgets the hovered cell until the next `show`.
+    pub fn release(&mut self) {
+        self.hovered = None;
+    }
+
+    /// Paints the cube for `camera` in the corner of `rect`. Returns the
+    /// direction to look from when the cube was clicked.
+    pub fn show(&mut self, ctx: &egui::Context, camera: &Camera, rect: egui::Rect) -> Option<Vec3> {
+        if !self.enabled {
+            self.release();
+            return None;
+        }
+        let center = egui::pos2(rect.max.x - MARGIN_POINTS - HALF_SIZE_POINTS * 1.8, rect.min.y + MARGIN_POINTS + HALF_SIZE_POINTS * 1.8);
+        let rotation = Mat3::from_mat4(camera.view_matrix());
+        let to_screen = |point: Vec3| {
+            let view = rotation * point;
+            center + egui::vec2(view.x, -view.y) * HALF_SIZE_POINTS
+        };
+
+        // Only faces towards the camera; on a cube they never overlap
+        let mut cells = Vec::new();
+        let mut labels = Vec::new();
+        for (normal, u, v, label) in FACES {
+            let facing = (rotation * normal).z;
+            if facing <= 0.0 {
+                continue;
+            }
+            let band = |i: i32| match i {
+                -1 => (-1.0, -EDGE),
+                0 => (-EDGE, EDGE),
+                _ => (EDGE, 1.0),
+            };
+            for i in -1..=1 {
+                for j in -1..=1 {
+                    let ((u0, u1), (v0, v1)) = (band(i), band(j));
+                    cells.push(Cell {
+                        direction: normal + u * i as f32 + v * j as f32,
+                        corners: [(u0, v0), (u1, v0), (u1, v1), (u0, v1)].map(|(a, b)| to_screen(normal + u * a + v * b)),
+                    });
+                }
+            }
+            // Labels seen nearly edge-on would only be clutter
+            if facing > 0.3 {
+                labels.push((to_screen(normal), label));
+            }
+        }
+
+        let (pointer, clicked) = ctx.input(|input| (input.pointer.hover_pos(), input.pointer.primary_clicked()));
+        self.hovered = pointer
+            .filter(|_| !ctx.is_pointer_over_area())
+            .and_then(|pointer| cells.iter().find(|cell| contains(&cell.corners, pointer)))
+            .map(|cell| cell.direction);
+
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        for cell in &cells {
+            let fill = if self.hovered == Some(cell.direction) { HOVER_COLOR } else { FACE_COLOR };
+            painter.add(egui::Shape::convex_polygon(cell.corners.to_vec(), fill, egui::Stroke::new(0.5, OUTLINE_COLOR)));
+        }
+        for (position, label) in labels {
+            painter.text(position, egui::Align2::CENTER_CENTER, label, egui::FontId::proportional(11.0), egui::Color32::WHITE);
+        }
+
+        self.hovered.filter(|_| clicked)
+    }
+}
+
+// Whether `point` lies inside the convex quad `corners`, wound either way
+fn contains(corners: &[egui::Pos2; 4], point: egui::Pos2) -> bool {
+    let sides = (0..4).map(|i| {
+        let (a, b) = (corners[i], corners[(i + 1) % 4]);
+        (b - a).x * (point - a).y - (b - a).y * (point - a).x
+    });
+    let (mut positive, mut negative) = (false, false);
+    for side in sides {
+        positive |= side > 0.0;
+        negative |= side < 0.0;
+    }
+    !(positive && negative)
+}
@@ -0,0 +1,51 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::camera::CameraState;
+use crate::light::Light;
+
+const VIEW_STATE_VERSION: u32 = 1;
+
+/// Camera and lighting snapshot that can be shared as a JSON file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewState {
+    pub version: u32,
+    pub camera: CameraState,
+    pub lights: Vec<Light>,
+}
+
+impl ViewState {
+    pub fn new(camera: CameraState, lights: Vec<Light>) -> Self {
+        Self {
+            version: VIEW_STATE_VERSION,
+            camera,
+            lights,
+        }
+    }
+
+    /// The view as one line of JSON, for pasting into bug reports and back
+    /// with `from_text`.
+    pub fn to_text(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    pub fn from_text(text: &str) -> Result<Self> {
+        let state: Self = serde_json::from_str(text.trim()).context("Invalid view text")?;
+        if state.version > VIEW_STATE_VERSION {
+            anyhow::bail!("View version {} is newer than supported ({})", state.version, VIEW_STATE_VERSION);
+        }
+        Ok(state)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {:?}", path))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        Self::from_text(&json).with_context(|| format!("Invalid view file {:?}", path))
+    }
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+
+use crate::camera::{Camera, ViewPreset};
+use crate::camera_controller::OrbitController;
+
+/// How the window is split into viewports, each with its own camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewportLayout {
+    Single,
+    SideBySide,
+    Quad,
+}
+
+impl ViewportLayout {
+    pub const ALL: [ViewportLayout; 3] = [ViewportLayout::Single, ViewportLayout::SideBySide, ViewportLayout::Quad];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ViewportLayout::Single => "Single",
+            ViewportLayout::SideBySide => "Two viewports",
+            ViewportLayout::Quad => "Four viewports",
+        }
+    }
+
+    /// Viewport rectangles as `[x, y, width, height]` fractions of the
+    /// window; the first one belongs to the main camera.
+    pub fn rects(self) -> &'static [[f32; 4]] {
+        match self {
+            ViewportLayout::Single => &[[0.0, 0.0, 1.0, 1.0]],
+            ViewportLayout::SideBySide => &[[0.0, 0.0, 0.5, 1.0], [0.5, 0.0, 0.5, 1.0]],
+            ViewportLayout::Quad => &[
+                [0.0, 0.0, 0.5, 0.5],
+                [0.5, 0.0, 0.5, 0.5],
+                [0.0, 0.5, 0.5, 0.5],
+                [0.5, 0.5, 0.5, 0.5],
+            ],
+        }
+    }
+
+    /// Index of the viewport containing the window fraction `(x, y)`.
+    pub fn viewport_at(self, x: f32, y: f32) -> usize {
+        self.rects()
+            .iter()
+            .position(|rect| x >= rect[0] && x < rect[0] + rect[2] && y >= rect[1] && y < rect[1] + rect[3])
+            .unwrap_or(0)
+    }
+}
+
+/// How the main viewport draws the mesh, cycled with W.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayMode {
+    Shaded,
+    Wireframe,
+    // Shaded, with the edges drawn over it
+    HiddenLine,
+}
+
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 3] = [DisplayMode::Shaded, DisplayMode::Wireframe, DisplayMode::HiddenLine];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DisplayMode::Shaded => "Shaded",
+            DisplayMode::Wireframe => "Wireframe",
+            DisplayMode::HiddenLine => "Hidden line",
+        }
+    }
+
+    pub fn next(self) -> DisplayMode {
+        match self {
+            DisplayMode::Shaded => DisplayMode::Wireframe,
+            DisplayMode::Wireframe => DisplayMode::HiddenLine,
+            DisplayMode::HiddenLine => DisplayMode::Shaded,
+        }
+    }
+}
+
+/// What a secondary viewport draws; the main viewport follows the display
+/// mode and the Analysis color mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    Shaded,
+    Wireframe,
+    HiddenLine,
+    ColorMode,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 4] = [RenderMode::Shaded, RenderMode::Wireframe, RenderMode::HiddenLine, RenderMode::ColorMode];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RenderMode::Shaded => "Shaded",
+            RenderMode::Wireframe => "Wireframe",
+            RenderMode::HiddenLine => "Hidden line",
+            RenderMode::ColorMode => "Color mode",
+        }
+    }
+
+    /// How the mesh is drawn; the color mode is shaded with the Analysis
+    /// colors.
+    pub fn display_mode(self) -> DisplayMode {
+        match self {
+            RenderMode::Shaded | RenderMode::ColorMode => DisplayMode::Shaded,
+            RenderMode::Wireframe => DisplayMode::Wireframe,
+            RenderMode::HiddenLine => DisplayMode::HiddenLine,
+        }
+    }
+}
+
+/// A secondary viewport of a split layout.
+pub struct Viewport {
+    pub camera: Camera,
+    pub controller: OrbitController,
+    pub mode: RenderMode,
+    preset: ViewPreset,
+}
+
+impl Viewport {
+    /// The three viewports after the main one, looking from the top, the
+    /// right and the front.
+    pub fn secondary(aspect_ratio: f32) -> Vec<Viewport> {
+        [
+            (ViewPreset::Top, RenderMode::Wireframe),
+            (ViewPreset::Right, RenderMode::ColorMode),
+            (ViewPreset::Front, RenderMode::Shaded),
+        ]
+        .into_iter()
+        .map(|(preset, mode)| {
+            let mut camera = Camera::new(aspect_ratio);
+            camera.set_view_preset(preset);
+            Viewport { camera, controller: OrbitController::default(), mode, preset }
+        })
+        .collect()
+    }
+
+    /// Points the camera back along its preset direction and fits the model.
+    pub fn fit(&mut self, model_bounds: (glam::Vec3, glam::Vec3)) {
+        self.camera.set_view_preset(self.preset);
+        self.camera.auto_fit_to_model(model_bounds);
+    }
+}
@@ -0,0 +1,496 @@
+use anyhow::{bail, Context, Result};
+use glam::{Mat3, Mat4, Quat, Vec3};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::mesh::MeshData;
+use crate::xml::{self, Element};
+
+/// Scene graph node shared by the X3D (XML) and VRML97 (text) encodings.
+/// Non-node fields are kept as raw tokens; node-valued fields become children.
+#[derive(Debug, Clone, Default)]
+struct Node {
+    kind: String,
+    def: Option<String>,
+    use_name: Option<String>,
+    fields: HashMap<String, Vec<String>>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn floats(&self, field: &str) -> Vec<f32> {
+        self.fields
+            .get(field)
+            .map(|tokens| tokens.iter().filter_map(|token| token.parse().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    fn ints(&self, field: &str) -> Vec<i64> {
+        self.fields
+            .get(field)
+            .map(|tokens| tokens.iter().filter_map(|token| token.parse().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    fn vec3(&self, field: &str) -> Option<Vec3> {
+        match self.floats(field)[..] {
+            [x, y, z, ..] => Some(Vec3::new(x, y, z)),
+            _ => None,
+        }
+    }
+
+    fn flag(&self, field: &str, default: bool) -> bool {
+        match self.fields.get(field).and_then(|tokens| tokens.first()) {
+            Some(value) => value.eq_ignore_ascii_case("true"),
+            None => default,
+        }
+    }
+
+    fn child(&self, kind: &str) -> Option<&Node> {
+        self.children.iter().find(|child| child.kind == kind)
+    }
+}
+
+pub fn load_x3d(path: &Path) -> Result<Vec<MeshData>> {
+    parse_x3d(&std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?)
+}
+
+pub fn load_vrml(path: &Path) -> Result<Vec<MeshData>> {
+    parse_vrml(&std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?)
+}
+
+fn parse_x3d(source: &str) -> Result<Vec<MeshData>> {
+    let root = xml::parse(source).context("Invalid XML")?;
+    if root.name != "X3D" {
+        bail!("Not an X3D document (root element <{}>)", root.name);
+    }
+    let scene = root.child("Scene").context("X3D document without <Scene>")?;
+    collect_shapes(&from_xml(scene))
+}
+
+fn parse_vrml(source: &str) -> Result<Vec<MeshData>> {
+    if !source.starts_with("#VRML V2.0") {
+        bail!("Only VRML 2.0 (VRML97) files are supported");
+    }
+    let mut parser = VrmlParser {
+        tokens: tokenize(source),
+        position: 0,
+    };
+    let mut scene = Node {
+        kind: "Scene".to_string(),
+        ..Default::default()
+    };
+    while parser.peek().is_some() {
+        scene.children.push(parser.node().context("Invalid VRML")?);
+    }
+    collect_shapes(&scene)
+}
+
+fn from_xml(element: &Element) -> Node {
+    let mut node = Node {
+        kind: element.name.clone(),
+        ..Default::default()
+    };
+    for (key, value) in &element.attributes {
+        match key.as_str() {
+            "DEF" => node.def = Some(value.clone()),
+            "USE" => node.use_name = Some(value.clone()),
+            _ => {
+                let tokens = value
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .filter(|token| !token.is_empty())
+                    .map(|token| token.trim_matches('"').to_string())
+                    .collect();
+                node.fields.insert(key.clone(), tokens);
+            }
+        }
+    }
+    node.children = element.children.iter().map(from_xml).collect();
+    node
+}
+
+fn tokenize(source: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            '#' => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            '{' | '}' | '[' | ']' => {
+                tokens.push(c.to_string());
+                chars.next();
+            }
+            '"' => {
+                chars.next();
+                let mut string = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => string.extend(chars.next()),
+                        '"' => break,
+                        _ => string.push(c),
+                    }
+                }
+                tokens.push(string);
+            }
+            c if c.is_whitespace() || c == ',' => {
+                chars.next();
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|&c| !(c.is_whitespace() || "{}[],#\"".contains(c))) {
+                    word.push(c);
+                }
+                tokens.push(word);
+            }
+        }
+    }
+    tokens
+}
+
+struct VrmlParser {
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl VrmlParser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Result<String> {
+        let token = self.tokens.get(self.position).cloned().context("Unexpected end of file")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<()> {
+        let token = self.next()?;
+        if token != expected {
+            bail!("Expected '{}' but found '{}'", expected, token);
+        }
+        Ok(())
+    }
+
+    fn node(&mut self) -> Result<Node> {
+        let mut token = self.next()?;
+        match token.as_str() {
+            "USE" => {
+                return Ok(Node {
+                    kind: "USE".to_string(),
+                    use_name: Some(self.next()?),
+                    ..Default::default()
+                })
+            }
+            "NULL" => return Ok(Node::default()),
+            "PROTO" | "EXTERNPROTO" | "ROUTE" => bail!("{} is not supported", token),
+            _ => {}
+        }
+
+        let mut def = None;
+        if token == "DEF" {
+            def = Some(self.next()?);
+            token = self.next()?;
+        }
+
+        let mut node = Node {
+            kind: token,
+            def,
+            ..Default::default()
+        };
+        self.expect("{")?;
+        while self.peek() != Some("}") {
+            let field = self.next()?;
+            match self.peek() {
+                Some("[") => {
+                    self.next()?;
+                    let mut values = Vec::new();
+                    while self.peek() != Some("]") {
+                        if self.is_node_start() {
+                            node.children.push(self.node()?);
+                        } else {
+                            values.push(self.next()?);
+                        }
+                    }
+                    self.next()?;
+                    node.fields.insert(field, values);
+                }
+                _ if self.is_node_start() => node.children.push(self.node()?),
+                _ => {
+                    // Single-valued field: take tokens up to the next field name
+                    let mut values = vec![self.next()?];
+                    while let Some(token) = self.peek() {
+                        if !token.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '.') {
+                            break;
+                        }
+                        values.push(self.next()?);
+                    }
+                    node.fields.insert(field, values);
+                }
+            }
+        }
+        self.next()?;
+        Ok(node)
+    }
+
+    // A node starts with a type name followed by '{', or with DEF/USE
+    fn is_node_start(&self) -> bool {
+        match self.peek() {
+            Some("DEF") | Some("USE") | Some("NULL") => true,
+            Some(token) if token.starts_with(|c: char| c.is_ascii_uppercase()) => {
+                self.tokens.get(self.position + 1).map(String::as_str) == Some("{")
+            }
+            _ => false,
+        }
+    }
+}
+
+struct Collector<'a> {
+    definitions: HashMap<String, &'a Node>,
+    parts: Vec<MeshData>,
+}
+
+fn collect_shapes(scene: &Node) -> Result<Vec<MeshData>> {
+    let mut collector = Collector {
+        definitions: HashMap::new(),
+        parts: Vec::new(),
+    };
+    collector.visit(scene, Mat4::IDENTITY)?;
+    Ok(collector.parts)
+}
+
+impl<'a> Collector<'a> {
+    fn resolve(&mut self, node: &'a Node) -> &'a Node {
+        if let Some(name) = &node.def {
+            self.definitions.insert(name.clone(), node);
+        }
+        match node.use_name.as_ref().and_then(|name| self.definitions.get(name)) {
+            Some(definition) => definition,
+            None => node,
+        }
+    }
+
+    fn visit(&mut self, node: &'a Node, transform: Mat4) -> Result<()> {
+        let node = self.resolve(node);
+        match node.kind.as_str() {
+            "Transform" => {
+                let transform = transform * local_transform(node);
+                for child in &node.children {
+                    self.visit(child, transform)?;
+                }
+            }
+            "Shape" => self.shape(node, transform)?,
+            _ => {
+                for child in &node.children {
+                    self.visit(child, transform)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn shape(&mut self, shape: &'a Node, transform: Mat4) -> Result<()> {
+        let diffuse = shape
+            .child("Appearance")
+            .map(|appearance| self.resolve(appearance))
+            .and_then(|appearance| appearance.child("Material"))
+            .map(|material| self.resolve(material))
+            .and_then(|material| material.vec3("diffuseColor"));
+
+        for child in &shape.children {
+            let geometry = self.resolve(child);
+            let part = match geometry.kind.as_str() {
+                "IndexedFaceSet" => self.indexed_face_set(geometry)?,
+                "IndexedTriangleSet" => self.indexed_triangle_set(geometry)?,
+                _ => continue,
+            };
+            let Some(mut part) = part else {
+                continue;
+            };
+
+            let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+            for position in &mut part.positions {
+                *position = transform.transform_point3(Vec3::from(*position)).into();
+            }
+            if let Some(normals) = &mut part.normals {
+                for normal in normals {
+                    *normal = (normal_matrix * Vec3::from(*normal)).normalize_or_zero().into();
+                }
+            }
+            if part.vertex_colors.is_none() && part.face_colors.is_none() {
+                if let Some(diffuse) = diffuse {
+                    part.vertex_colors = Some(vec![diffuse.into(); part.positions.len()]);
+                }
+            }
+            part.name = shape
+                .def
+                .clone()
+                .or_else(|| geometry.def.clone())
+                .unwrap_or_else(|| format!("Shape {}", self.parts.len()));
+            self.parts.push(part);
+        }
+        Ok(())
+    }
+
+    fn points(&mut self, geometry: &'a Node, kind: &str, field: &str) -> Option<Vec<[f32; 3]>> {
+        let node = geometry.children.iter().map(|child| self.resolve(child)).find(|child| child.kind == kind)?;
+        Some(node.floats(field).chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect())
+    }
+
+    fn indexed_face_set(&mut self, geometry: &'a Node) -> Result<Option<MeshData>> {
+        let Some(positions) = self.points(geometry, "Coordinate", "point") else {
+            return Ok(None);
+        };
+        let colors = self.points(geometry, "Color", "color");
+        let color_per_vertex = geometry.flag("colorPerVertex", true);
+        let color_index = geometry.ints("colorIndex");
+
+        let mut triangles = Vec::new();
+        let mut face_colors = Vec::new();
+        let mut vertex_colors = colors.as_ref().map(|_| vec![[0.8, 0.8, 0.8]; positions.len()]);
+        let coord_index = geometry.ints("coordIndex");
+        let mut corner = 0;
+        for (face, polygon) in coord_index.split(|&index| index < 0).enumerate() {
+            let polygon_start = corner;
+            corner += polygon.len() + 1;
+            if polygon.iter().any(|&index| index as usize >= positions.len()) {
+                bail!("Coordinate index out of range in face {}", face);
+            }
+            // Fan triangulation of convex polygons
+            for i in 1..polygon.len().saturating_sub(1) {
+                triangles.push([polygon[0] as u32, polygon[i] as u32, polygon[i + 1] as u32]);
+                if let (Some(colors), false) = (&colors, color_per_vertex) {
+                    let index = color_index.get(face).map(|&index| index as usize).unwrap_or(face);
+                    face_colors.push(colors.get(index).copied().unwrap_or([0.8, 0.8, 0.8]));
+                }
+            }
+            if let (Some(colors), Some(vertex_colors), true) = (&colors, &mut vertex_colors, color_per_vertex) {
+                for (offset, &index) in polygon.iter().enumerate() {
+                    let color = color_index.get(polygon_start + offset).map(|&i| i as usize).unwrap_or(index as usize);
+                    if let Some(color) = colors.get(color) {
+                        vertex_colors[index as usize] = *color;
+                    }
+                }
+            }
+        }
+
+        let has_face_colors = colors.is_some() && !color_per_vertex;
+        Ok(Some(MeshData {
+            name: String::new(),
+            normals: None,
+            vertex_colors: if color_per_vertex { vertex_colors } else { None },
+            face_colors: has_face_colors.then_some(face_colors),
+            positions,
+            triangles,
+            ..Default::default()
+        }))
+    }
+
+    fn indexed_triangle_set(&mut self, geometry: &'a Node) -> Result<Option<MeshData>> {
+        let Some(positions) = self.points(geometry, "Coordinate", "point") else {
+            return Ok(None);
+        };
+        let index = geometry.ints("index");
+        if index.iter().any(|&i| i < 0 || i as usize >= positions.len()) {
+            bail!("Triangle index out of range");
+        }
+        let vertex_colors = self
+            .points(geometry, "Color", "color")
+            .filter(|colors| colors.len() >= positions.len());
+        Ok(Some(MeshData {
+            name: String::new(),
+            triangles: index.chunks_exact(3).map(|t| [t[0] as u32, t[1] as u32, t[2] as u32]).collect(),
+            normals: None,
+            vertex_colors,
+            face_colors: None,
+            positions,
+            ..Default::default()
+        }))
+    }
+}
+
+// T * C * R * SR * S * -SR * -C, as defined for X3D/VRML Transform nodes
+fn local_transform(node: &Node) -> Mat4 {
+    let rotation = |field: &str| match node.floats(field)[..] {
+        [x, y, z, angle, ..] => Vec3::new(x, y, z)
+            .try_normalize()
+            .map(|axis| Quat::from_axis_angle(axis, angle))
+            .unwrap_or(Quat::IDENTITY),
+        _ => Quat::IDENTITY,
+    };
+    let translation = node.vec3("translation").unwrap_or(Vec3::ZERO);
+    let center = node.vec3("center").unwrap_or(Vec3::ZERO);
+    let scale = node.vec3("scale").unwrap_or(Vec3::ONE);
+    let scale_orientation = rotation("scaleOrientation");
+
+    Mat4::from_translation(translation + center)
+        * Mat4::from_quat(rotation("rotation"))
+        * Mat4::from_quat(scale_orientation)
+        * Mat4::from_scale(scale)
+        * Mat4::from_quat(scale_orientation.inverse())
+        * Mat4::from_translation(-center)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A red unit quad moved along X, as X3D with `coord_index` as its faces
+    fn quad_x3d(coord_index: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <X3D profile="Interchange" version="3.3">
+                <Scene>
+                    <Transform translation="2 0 0">
+                        <Shape DEF="Quad">
+                            <Appearance><Material diffuseColor="1 0 0"/></Appearance>
+                            <IndexedFaceSet coordIndex="{}">
+                                <Coordinate point="0 0 0, 1 0 0, 1 1 0, 0 1 0"/>
+                            </IndexedFaceSet>
+                        </Shape>
+                    </Transform>
+                </Scene>
+            </X3D>"#,
+            coord_index
+        )
+    }
+
+    #[test]
+    fn loads_x3d_shapes() {
+        let parts = parse_x3d(&quad_x3d("0 1 2 3 -1")).unwrap();
+        let [part] = &parts[..] else { panic!("expected one part, got {}", parts.len()) };
+        assert_eq!(part.name, "Quad");
+        assert_eq!(part.positions, [[2.0, 0.0, 0.0], [3.0, 0.0, 0.0], [3.0, 1.0, 0.0], [2.0, 1.0, 0.0]]);
+        assert_eq!(part.triangles, [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(part.vertex_colors, Some(vec![[1.0, 0.0, 0.0]; 4]));
+    }
+
+    #[test]
+    fn rejects_out_of_range_coordinates() {
+        let error = parse_x3d(&quad_x3d("0 1 4 -1")).unwrap_err();
+        assert!(error.to_string().contains("Coordinate index out of range in face 0"), "{}", error);
+    }
+
+    #[test]
+    fn loads_vrml_with_reused_geometry() {
+        let vrml = r#"#VRML V2.0 utf8
+            # Two instances of one triangle set, the second moved up
+            Transform { children [ Shape { geometry DEF Tri IndexedTriangleSet {
+                index [ 0 1 2 ]
+                coord Coordinate { point [ 0 0 0, 1 0 0, 0 1 0 ] }
+            } } ] }
+            Transform { translation 0 0 5 children [ Shape { geometry USE Tri } ] }
+        "#;
+        let parts = parse_vrml(vrml).unwrap();
+        let [first, second] = &parts[..] else { panic!("expected two parts, got {}", parts.len()) };
+        assert_eq!((first.name.as_str(), second.name.as_str()), ("Tri", "Tri"));
+        assert_eq!(first.triangles, [[0, 1, 2]]);
+        assert_eq!(second.positions[1], [1.0, 0.0, 5.0]);
+    }
+
+    #[test]
+    fn rejects_other_vrml_versions() {
+        let error = parse_vrml("#VRML V1.0 ascii\nSeparator {}\n").unwrap_err();
+        assert!(error.to_string().contains("Only VRML 2.0"), "{}", error);
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+/// Minimal in-memory XML tree for the XML-based model importers.
+#[derive(Debug, Default)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+impl Element {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Text of the child element `name`, trimmed.
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+}
+
+pub fn parse(source: &str) -> Result<Element> {
+    let mut reader = Reader::from_str(source);
+    reader.config_mut().trim_text(true);
+
+    // The bottom of the stack collects the document's top-level elements
+    let mut stack = vec![Element::default()];
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => stack.push(element(&start)?),
+            Event::Empty(start) => {
+                let element = element(&start)?;
+                stack.last_mut().context("Unbalanced XML")?.children.push(element);
+            }
+            Event::End(_) => {
+                let element = stack.pop().context("Unbalanced XML")?;
+                stack.last_mut().context("Unbalanced XML")?.children.push(element);
+            }
+            Event::Text(text) => {
+                stack.last_mut().context("Unbalanced XML")?.text.push_str(&text.unescape()?);
+            }
+            Event::CData(data) => {
+                stack.last_mut().context("Unbalanced XML")?.text.push_str(&String::from_utf8_lossy(&data));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    let mut document = stack.pop().context("Unbalanced XML")?;
+    anyhow::ensure!(stack.is_empty(), "Unclosed XML element");
+    document.children.pop().context("Empty XML document")
+}
+
+fn element(start: &BytesStart) -> Result<Element> {
+    let mut attributes = Vec::new();
+    for attribute in start.attributes() {
+        let attribute = attribute?;
+        attributes.push((
+            String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+            attribute.unescape_value()?.into_owned(),
+        ));
+    }
+    Ok(Element {
+        name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+        attributes,
+        ..Default::default()
+    })
+}
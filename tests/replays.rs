@@ -0,0 +1,29 @@
+//! Visual regression tests: each replay in `tests/replays` is played
+//! headlessly and its frames compared with the reference PNGs in the folder
+//! of the same name. They need a GPU adapter and small differences between
+//! drivers can still add up, so they only run when asked for:
+//! `cargo test -- --ignored`. A software adapter such as Mesa's llvmpipe or
+//! lavapipe, or WARP on Windows, is enough; without any adapter they fail.
+//! Set `DOTOBJVIEWER_BLESS=1` as well to record the references after an
+//! intended rendering change.
+
+use std::path::Path;
+
+fn check(name: &str) {
+    assert!(
+        dotobjviewer::replay::adapter_available(),
+        "Replay {} needs a GPU adapter; install a software one (Mesa llvmpipe/lavapipe, WARP) to run it",
+        name
+    );
+    let replays = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/replays");
+    let failures = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    if let Err(e) = dotobjviewer::replay::check(&replays.join(format!("{}.json", name)), &replays.join(name), &failures) {
+        panic!("{:#}", e);
+    }
+}
+
+#[test]
+#[ignore = "needs a GPU adapter; run with --ignored"]
+fn cube_orbit() {
+    check("cube_orbit");
+}